@@ -0,0 +1,391 @@
+//! Content classifier for text entries
+//!
+//! Tags copied text with what it looks like (URL, e-mail, color, code...) so
+//! the popup can show a matching icon/preview and search can filter with
+//! `kind:url`. Only cheap heuristics: it runs on every insert.
+
+use crate::types::TextKind;
+
+/// Longest single-line text checked for URL/e-mail/path/etc.
+const MAX_TOKEN_LEN: usize = 2048;
+
+/// Distinct signals a text needs before it is taken as code
+const MIN_CODE_SIGNALS: usize = 2;
+
+/// Substrings that hint at each language, checked in this order (ties go to
+/// the first language)
+const LANGUAGE_SIGNALS: &[(&str, &[&str])] = &[
+    (
+        "rust",
+        &[
+            "fn ", "let mut ", "impl ", "pub fn ", "println!", "use std", "&self", "match ",
+            "Option<", "Result<", "::new(", "#[derive",
+        ],
+    ),
+    (
+        "python",
+        &[
+            "def ",
+            "import ",
+            "self.",
+            "elif ",
+            "print(",
+            "__init__",
+            "lambda ",
+            "):\n",
+            "None:",
+            "    return ",
+        ],
+    ),
+    (
+        "javascript",
+        &[
+            "const ",
+            "function ",
+            "=> ",
+            "console.log",
+            "require(",
+            "export ",
+            "document.",
+            "===",
+            "await ",
+            "let ",
+        ],
+    ),
+    (
+        "go",
+        &[
+            "func ",
+            "package ",
+            ":= ",
+            "fmt.",
+            "go func",
+            "chan ",
+            "defer ",
+            "err != nil",
+        ],
+    ),
+    (
+        "c",
+        &[
+            "#include", "int main", "printf(", "malloc(", "std::", "nullptr", "void ", "->",
+        ],
+    ),
+    (
+        "java",
+        &[
+            "public class",
+            "public static",
+            "System.out",
+            "@Override",
+            "private final",
+            "import java.",
+        ],
+    ),
+    (
+        "shell",
+        &[
+            "#!/bin/",
+            "sudo ",
+            "echo ",
+            "$(",
+            "| grep",
+            "&& ",
+            " --",
+            "apt ",
+            "git ",
+            "cargo ",
+            "npm ",
+            "export PATH",
+        ],
+    ),
+    (
+        "sql",
+        &[
+            "SELECT ",
+            "FROM ",
+            "WHERE ",
+            "INSERT INTO",
+            "UPDATE ",
+            "CREATE TABLE",
+            "JOIN ",
+            "GROUP BY",
+            "ORDER BY",
+            "DELETE FROM",
+        ],
+    ),
+    (
+        "html",
+        &[
+            "<div",
+            "</",
+            "<html",
+            "<span",
+            "<a ",
+            "<p>",
+            "class=\"",
+            "<!DOCTYPE",
+            "href=\"",
+        ],
+    ),
+    (
+        "css",
+        &[
+            "color:",
+            "margin:",
+            "padding:",
+            "display:",
+            "font-size:",
+            "px;",
+            "@media",
+            "background:",
+            "border:",
+        ],
+    ),
+];
+
+/// Kind of `text`, plus the guessed language when it is code
+pub fn classify(text: &str) -> (TextKind, Option<&'static str>) {
+    let text = text.trim();
+    if text.is_empty() {
+        return (TextKind::Plain, None);
+    }
+
+    if !text.contains('\n') && text.len() <= MAX_TOKEN_LEN {
+        let kind = if is_url(text) {
+            Some(TextKind::Url)
+        } else if is_email(text) {
+            Some(TextKind::Email)
+        } else if is_color(text) {
+            Some(TextKind::Color)
+        } else if is_number(text) {
+            Some(TextKind::Number)
+        } else if is_phone(text) {
+            Some(TextKind::Phone)
+        } else if is_path(text) {
+            Some(TextKind::Path)
+        } else {
+            None
+        };
+        if let Some(kind) = kind {
+            return (kind, None);
+        }
+    }
+
+    if is_json(text) {
+        return (TextKind::Json, None);
+    }
+
+    match guess_language(text) {
+        Some(language) => (TextKind::Code, Some(language)),
+        None => (TextKind::Plain, None),
+    }
+}
+
+fn is_url(text: &str) -> bool {
+    if text.contains(char::is_whitespace) {
+        return false;
+    }
+    let lower = text.to_ascii_lowercase();
+    ["http://", "https://", "ftp://", "file://", "www."]
+        .iter()
+        .any(|prefix| lower.len() > prefix.len() && lower.starts_with(prefix))
+}
+
+fn is_email(text: &str) -> bool {
+    let Some((local, domain)) = text.split_once('@') else {
+        return false;
+    };
+    let local_ok = !local.is_empty()
+        && local
+            .chars()
+            .all(|c| c.is_alphanumeric() || "._%+-".contains(c));
+    let labels: Vec<&str> = domain.split('.').collect();
+    let domain_ok = labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+        })
+        && labels
+            .last()
+            .is_some_and(|tld| tld.len() >= 2 && tld.chars().all(char::is_alphabetic));
+    local_ok && domain_ok
+}
+
+/// `#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa`, `rgb(...)` and `rgba(...)`
+fn is_color(text: &str) -> bool {
+    if let Some(hex) = text.strip_prefix('#') {
+        return matches!(hex.len(), 3 | 4 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit());
+    }
+
+    let lower = text.to_ascii_lowercase();
+    let Some(inner) = lower
+        .strip_prefix("rgba(")
+        .or_else(|| lower.strip_prefix("rgb("))
+        .and_then(|rest| rest.strip_suffix(')'))
+    else {
+        return false;
+    };
+    let components: Vec<&str> = inner
+        .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .collect();
+    (3..=4).contains(&components.len())
+        && components
+            .iter()
+            .all(|part| part.trim_end_matches('%').parse::<f32>().is_ok())
+}
+
+/// Integers and decimals, with `.`/`,` as decimal or thousands separators
+fn is_number(text: &str) -> bool {
+    let digits = text.strip_prefix(['-', '+']).unwrap_or(text);
+    let starts_and_ends_with_digit = digits.chars().next().is_some_and(|c| c.is_ascii_digit())
+        && digits.chars().last().is_some_and(|c| c.is_ascii_digit());
+    starts_and_ends_with_digit
+        && digits
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '.' || c == ',')
+        && !digits.contains("..")
+        && !digits.contains(",,")
+}
+
+/// Phone numbers with separators, e.g. "+55 11 98765-4321" or "(11) 3456-7890"
+///
+/// Plain digit strings are numbers; dates like 2024-01-15 are rejected by
+/// requiring a '+' prefix, parentheses or a last group of 4+ digits.
+fn is_phone(text: &str) -> bool {
+    if !text
+        .chars()
+        .all(|c| c.is_ascii_digit() || " -.()+".contains(c))
+    {
+        return false;
+    }
+    let digit_count = text.chars().filter(char::is_ascii_digit).count();
+    if !(8..=15).contains(&digit_count) {
+        return false;
+    }
+
+    let groups: Vec<&str> = text
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|group| !group.is_empty())
+        .collect();
+    groups.len() >= 2
+        && (text.starts_with('+')
+            || text.contains('(')
+            || groups.last().is_some_and(|group| group.len() >= 4))
+}
+
+/// Unix (absolute, home or relative) and Windows paths
+fn is_path(text: &str) -> bool {
+    if text.contains("://") {
+        return false;
+    }
+    if let Some(rest) = text.strip_prefix('/') {
+        // "/" alone, "// comment" and "/ something" are not paths
+        return rest
+            .chars()
+            .next()
+            .is_some_and(|c| c != '/' && !c.is_whitespace());
+    }
+    if ["~/", "./", "../", "\\\\"]
+        .iter()
+        .any(|prefix| text.len() > prefix.len() && text.starts_with(prefix))
+    {
+        return true;
+    }
+
+    // C:\Users or C:/Users
+    let bytes = text.as_bytes();
+    bytes.len() > 3
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && (bytes[2] == b'\\' || bytes[2] == b'/')
+}
+
+/// JSON objects and arrays (bare strings/numbers are left to the other kinds)
+fn is_json(text: &str) -> bool {
+    let wrapped = (text.starts_with('{') && text.ends_with('}'))
+        || (text.starts_with('[') && text.ends_with(']'));
+    wrapped && serde_json::from_str::<serde_json::Value>(text).is_ok()
+}
+
+/// Language whose signals match most, if it reaches `MIN_CODE_SIGNALS`
+fn guess_language(text: &str) -> Option<&'static str> {
+    let mut best: Option<(&'static str, usize)> = None;
+    for (language, signals) in LANGUAGE_SIGNALS {
+        let score = signals
+            .iter()
+            .filter(|signal| text.contains(*signal))
+            .count();
+        if score >= MIN_CODE_SIGNALS && best.is_none_or(|(_, best_score)| score > best_score) {
+            best = Some((language, score));
+        }
+    }
+    best.map(|(language, _)| language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(text: &str) -> TextKind {
+        classify(text).0
+    }
+
+    #[test]
+    fn test_single_token_kinds() {
+        assert_eq!(kind("https://example.com/path?q=1"), TextKind::Url);
+        assert_eq!(kind("www.rust-lang.org"), TextKind::Url);
+        assert_eq!(kind("fulano.silva@example.com.br"), TextKind::Email);
+        assert_eq!(kind("#1e90ff"), TextKind::Color);
+        assert_eq!(kind("rgba(30, 144, 255, 0.5)"), TextKind::Color);
+        assert_eq!(kind("/home/user/Documentos/notas.txt"), TextKind::Path);
+        assert_eq!(kind("C:\\Users\\fulano"), TextKind::Path);
+        assert_eq!(kind("  ~/.config/clippit\n"), TextKind::Path);
+    }
+
+    #[test]
+    fn test_numbers_phones_and_dates() {
+        assert_eq!(kind("42"), TextKind::Number);
+        assert_eq!(kind("-1.234,56"), TextKind::Number);
+        assert_eq!(kind("11987654321"), TextKind::Number);
+        assert_eq!(kind("+55 11 98765-4321"), TextKind::Phone);
+        assert_eq!(kind("(11) 3456-7890"), TextKind::Phone);
+        // A date has separators but is not a phone number
+        assert_eq!(kind("2024-01-15"), TextKind::Plain);
+    }
+
+    #[test]
+    fn test_json_and_code() {
+        assert_eq!(
+            kind("{\"name\": \"clippit\", \"tags\": [1, 2]}"),
+            TextKind::Json
+        );
+        assert_eq!(kind("[1, 2, 3]"), TextKind::Json);
+        assert_eq!(kind("{not json}"), TextKind::Plain);
+
+        let rust = "fn main() {\n    let mut total = 0;\n    println!(\"{}\", total);\n}";
+        assert_eq!(classify(rust), (TextKind::Code, Some("rust")));
+
+        let python = "def soma(a, b):\n    return a + b\n\nprint(soma(1, 2))";
+        assert_eq!(classify(python), (TextKind::Code, Some("python")));
+
+        let sql = "SELECT id, content_text FROM clipboard_history WHERE id = 1";
+        assert_eq!(classify(sql), (TextKind::Code, Some("sql")));
+
+        assert_eq!(
+            classify("sudo apt install tesseract-ocr"),
+            (TextKind::Code, Some("shell"))
+        );
+    }
+
+    #[test]
+    fn test_prose_is_plain() {
+        assert_eq!(kind(""), TextKind::Plain);
+        assert_eq!(kind("Reunião amanhã às 10h na sala 3"), TextKind::Plain);
+        assert_eq!(
+            kind("Please send the report from last week to the team."),
+            TextKind::Plain
+        );
+        assert_eq!(kind("Olá @fulano, tudo bem?"), TextKind::Plain);
+    }
+}
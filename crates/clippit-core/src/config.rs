@@ -15,6 +15,13 @@ pub struct Config {
     pub autocomplete: AutocompleteConfig,
     #[serde(default)]
     pub ocr: OCRConfig,
+    #[serde(default)]
+    pub snippets: SnippetsConfig,
+    #[serde(default)]
+    pub http_api: HttpApiConfig,
+    /// Capture rules (`[[rules]]`), evaluated in order for every new entry
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<CaptureRule>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,6 +37,24 @@ pub struct GeneralConfig {
 
     #[serde(default = "default_max_image_size")]
     pub max_image_size: usize,
+
+    /// Images larger than this (width or height, in pixels) are downscaled before saving
+    #[serde(default = "default_max_image_dimension")]
+    pub max_image_dimension: u32,
+
+    /// Images with more pixels than this are refused before being decoded
+    /// (protects against decompression bombs)
+    #[serde(default = "default_max_image_pixels")]
+    pub max_image_pixels: u64,
+
+    /// Images of the same size whose perceptual hashes differ by at most this many
+    /// bits (0-64) are treated as duplicates; 0 disables near-duplicate detection
+    #[serde(default = "default_similar_image_distance")]
+    pub similar_image_distance: u32,
+
+    /// Clipboard backend: "auto", "wayland", "x11" or "polling"
+    #[serde(default = "default_clipboard_backend")]
+    pub clipboard_backend: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -125,6 +150,9 @@ pub struct FeaturesConfig {
 
     #[serde(default = "default_true")]
     pub enable_ocr: bool,
+
+    #[serde(default = "default_true")]
+    pub decode_barcodes: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -220,6 +248,18 @@ pub struct OCRConfig {
     /// Timeout para processamento OCR (segundos)
     #[serde(default = "default_ocr_timeout")]
     pub timeout_seconds: u64,
+
+    /// Número máximo de imagens processadas ao mesmo tempo
+    #[serde(default = "default_ocr_max_concurrent_jobs")]
+    pub max_concurrent_jobs: usize,
+
+    /// Motor de OCR: "auto", "tesseract" ou "ocrs" (Rust puro)
+    #[serde(default = "default_ocr_engine")]
+    pub engine: String,
+
+    /// Diretório com os modelos do ocrs (text-detection.rten, text-recognition.rten)
+    #[serde(default)]
+    pub model_dir: Option<String>,
 }
 
 impl Default for OCRConfig {
@@ -227,10 +267,98 @@ impl Default for OCRConfig {
         Self {
             languages: default_ocr_languages(),
             timeout_seconds: default_ocr_timeout(),
+            max_concurrent_jobs: default_ocr_max_concurrent_jobs(),
+            engine: default_ocr_engine(),
+            model_dir: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SnippetsConfig {
+    /// Cola o snippet no app em foco ao escolher no popup (xdotool no X11,
+    /// wtype no Wayland) e posiciona o cursor em `{cursor}`; desligado, o
+    /// snippet só vai para o clipboard
+    #[serde(default = "default_false")]
+    pub paste_on_select: bool,
+}
+
+/// `[http_api]`: REST API on 127.0.0.1 for browser extensions and editor
+/// plugins, with a bearer token kept in `http-token` next to the config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpApiConfig {
+    #[serde(default = "default_false")]
+    pub enabled: bool,
+
+    #[serde(default = "default_http_api_port")]
+    pub port: u16,
+}
+
+impl Default for HttpApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_http_api_port(),
         }
     }
 }
 
+fn default_http_api_port() -> u16 {
+    7399
+}
+
+/// A `[[rules]]` entry: when every condition set matches a new entry, the
+/// actions are applied (see `rules::RuleSet`)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CaptureRule {
+    /// Shown in logs and in the dry-run output
+    pub name: String,
+
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    // ----- Conditions (all that are set must match) -----
+    /// Regex searched in the text (text entries only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+
+    /// "text", "image" or a text kind ("url", "email", "code"...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+
+    /// Part of the window class of the app that copied (case-insensitive);
+    /// never matches when the clipboard backend can't tell the app
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_app: Option<String>,
+
+    /// Content size in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_size: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<usize>,
+
+    // ----- Actions -----
+    /// Don't save the entry at all
+    #[serde(default)]
+    pub drop: bool,
+
+    /// Replacement for every `pattern` match (`$1` refers to groups)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rewrite: Option<String>,
+
+    /// Tags added to the entry
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+
+    /// Delete the entry this many seconds after it was copied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_seconds: Option<u64>,
+
+    /// Don't run OCR on the image
+    #[serde(default)]
+    pub skip_ocr: bool,
+}
+
 // Default functions
 fn default_max_history() -> usize {
     100
@@ -244,6 +372,18 @@ fn default_max_text_size() -> usize {
 fn default_max_image_size() -> usize {
     50 * 1024 * 1024
 }
+fn default_max_image_dimension() -> u32 {
+    2048
+}
+fn default_max_image_pixels() -> u64 {
+    50_000_000
+}
+fn default_similar_image_distance() -> u32 {
+    4
+}
+fn default_clipboard_backend() -> String {
+    "auto".to_string()
+}
 fn default_hotkey_modifier() -> String {
     "super".to_string()
 }
@@ -344,6 +484,12 @@ fn default_ocr_languages() -> String {
 fn default_ocr_timeout() -> u64 {
     5
 }
+fn default_ocr_max_concurrent_jobs() -> usize {
+    1
+}
+fn default_ocr_engine() -> String {
+    "auto".to_string()
+}
 
 impl Default for Config {
     fn default() -> Self {
@@ -353,6 +499,10 @@ impl Default for Config {
                 poll_interval_ms: default_poll_interval(),
                 max_text_size: default_max_text_size(),
                 max_image_size: default_max_image_size(),
+                max_image_dimension: default_max_image_dimension(),
+                max_image_pixels: default_max_image_pixels(),
+                similar_image_distance: default_similar_image_distance(),
+                clipboard_backend: default_clipboard_backend(),
             },
             hotkeys: HotkeyConfig {
                 show_history_modifier: default_hotkey_modifier(),
@@ -399,6 +549,7 @@ impl Default for Config {
                 capture_files: false,
                 sync_enabled: false,
                 enable_ocr: true,
+                decode_barcodes: true,
             },
             privacy: PrivacyConfig {
                 ignore_sensitive_apps: true,
@@ -418,6 +569,9 @@ impl Default for Config {
             },
             autocomplete: AutocompleteConfig::default(),
             ocr: OCRConfig::default(),
+            snippets: SnippetsConfig::default(),
+            http_api: HttpApiConfig::default(),
+            rules: Vec::new(),
         }
     }
 }
@@ -483,6 +637,11 @@ impl Config {
         Ok(())
     }
 
+    /// Bearer token of the HTTP API (`[http_api]`)
+    pub fn http_token_path() -> PathBuf {
+        Self::config_path().with_file_name("http-token")
+    }
+
     pub fn config_path() -> PathBuf {
         let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
         path.push("clippit");
@@ -510,4 +669,22 @@ mod tests {
         let parsed: Config = toml::from_str(&toml_str).unwrap();
         assert_eq!(parsed.general.max_history_items, 100);
     }
+
+    #[test]
+    fn test_rules_roundtrip() {
+        let rule: CaptureRule =
+            toml::from_str("name = \"otp\"\npattern = '^\\d{6}$'\nttl_seconds = 120").unwrap();
+        assert!(rule.enabled);
+        assert!(!rule.drop);
+
+        let mut config = Config::default();
+        config.rules.push(rule);
+
+        // Rules are serialized after the other sections, as [[rules]] tables
+        let toml_str = toml::to_string(&config).unwrap();
+        assert!(toml_str.contains("[[rules]]"));
+        let parsed: Config = toml::from_str(&toml_str).unwrap();
+        assert_eq!(parsed.rules, config.rules);
+        assert_eq!(parsed.rules[0].pattern.as_deref(), Some("^\\d{6}$"));
+    }
 }
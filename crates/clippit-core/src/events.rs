@@ -0,0 +1,70 @@
+//! Change notifications from `HistoryManager`
+//!
+//! Every mutation emits a `HistoryEvent` to the receivers handed out by
+//! `EventBus::subscribe`, so the daemon can push live updates to clients.
+//! Receivers that were dropped are forgotten on the next emit.
+
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HistoryEvent {
+    EntryAdded {
+        id: i64,
+    },
+    /// OCR/barcode text or tags of an entry changed
+    EntryUpdated {
+        id: i64,
+    },
+    /// Deleted by hand, pruned or expired
+    EntryDeleted {
+        ids: Vec<i64>,
+    },
+    HistoryCleared,
+    CaptureStateChanged {
+        paused: bool,
+    },
+}
+
+/// Fan-out of `HistoryEvent`s; clones share the same subscribers
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<Sender<HistoryEvent>>>>,
+}
+
+impl EventBus {
+    pub fn subscribe(&self) -> Receiver<HistoryEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    /// Sends to every live subscriber (never blocks)
+    pub fn emit(&self, event: HistoryEvent) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.lock().unwrap().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dropped_subscribers_are_forgotten() {
+        let bus = EventBus::default();
+        let kept = bus.subscribe();
+        let dropped = bus.clone().subscribe();
+        drop(dropped);
+
+        bus.emit(HistoryEvent::HistoryCleared);
+        assert_eq!(bus.subscriber_count(), 1);
+        assert_eq!(kept.try_recv().unwrap(), HistoryEvent::HistoryCleared);
+    }
+}
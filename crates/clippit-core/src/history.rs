@@ -1,16 +1,37 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use chrono::{Duration, Utc};
 use sha2::{Digest, Sha256};
 use std::path::PathBuf;
 use tracing::{info, warn};
 
+use crate::classifier;
+use crate::events::{EventBus, HistoryEvent};
+use crate::ocr;
+use crate::phash;
+use crate::rules::RuleSet;
+use crate::snippets::{ExpandedSnippet, Snippet, SnippetContext};
 use crate::storage::Storage;
-use crate::types::{ClipboardEntry, ContentType};
+use crate::types::{
+    BoundingBox, ClipboardEntry, ContentType, HistoryStats, OcrJob, OcrJobStatus, OcrQueueStats,
+    OcrWord, Tag, TextKind,
+};
 use crate::validator::ContentValidator;
 
 pub struct HistoryManager {
     storage: Storage,
     last_hash: Option<String>,
     max_entries: usize,
+    validator: ContentValidator,
+    /// Max Hamming distance for two images to count as duplicates (None = disabled)
+    similarity_threshold: Option<u32>,
+    /// `[[rules]]` from the config, applied to every new entry
+    rules: RuleSet,
+    /// Subscribers to changes of the history (daemon event stream)
+    events: EventBus,
+    /// While true, `add_entry` drops everything (capture paused from a client)
+    capture_paused: bool,
+    /// Text on the clipboard right now, as last reported by the clipboard source
+    clipboard_text: Option<String>,
 }
 
 impl HistoryManager {
@@ -20,6 +41,12 @@ impl HistoryManager {
             storage,
             last_hash: None,
             max_entries,
+            validator: ContentValidator::default(),
+            similarity_threshold: None,
+            rules: RuleSet::default(),
+            events: EventBus::default(),
+            capture_paused: false,
+            clipboard_text: None,
         })
     }
 
@@ -29,9 +56,71 @@ impl HistoryManager {
             storage,
             last_hash: None,
             max_entries,
+            validator: ContentValidator::default(),
+            similarity_threshold: None,
+            rules: RuleSet::default(),
+            events: EventBus::default(),
+            capture_paused: false,
+            clipboard_text: None,
         })
     }
 
+    /// Bus where every change to the history is announced
+    pub fn events(&self) -> EventBus {
+        self.events.clone()
+    }
+
+    pub fn is_capture_paused(&self) -> bool {
+        self.capture_paused
+    }
+
+    /// Pauses/resumes capture; new entries are dropped while paused
+    pub fn set_capture_paused(&mut self, paused: bool) {
+        if self.capture_paused == paused {
+            return;
+        }
+        self.capture_paused = paused;
+        info!("Capture {}", if paused { "paused" } else { "resumed" });
+        self.events
+            .emit(HistoryEvent::CaptureStateChanged { paused });
+    }
+
+    /// Records the live clipboard text (None when it holds no text), used by
+    /// `{clipboard}` in snippets; captured or not, it is what a paste would give
+    pub fn set_clipboard_text(&mut self, text: Option<String>) {
+        self.clipboard_text = text;
+    }
+
+    /// Replaces the content limits (see `ContentValidator::from_config`)
+    pub fn set_validator(&mut self, validator: ContentValidator) {
+        self.validator = validator;
+    }
+
+    /// Enables near-duplicate detection for images (see `general.similar_image_distance`)
+    pub fn set_similarity_threshold(&mut self, threshold: Option<u32>) {
+        self.similarity_threshold = threshold;
+    }
+
+    /// Replaces the capture rules (see `RuleSet::compile`)
+    pub fn set_rules(&mut self, rules: RuleSet) {
+        self.rules = rules;
+    }
+
+    /// Same screenshot captured again with small changes (same size, close dHash)
+    fn is_near_duplicate(&self, entry: &ClipboardEntry, existing: &ClipboardEntry) -> bool {
+        let (Some(threshold), Some(hash), Some(existing_hash)) = (
+            self.similarity_threshold,
+            entry.image_phash,
+            existing.image_phash,
+        ) else {
+            return false;
+        };
+
+        entry.image_width == existing.image_width
+            && entry.image_height == existing.image_height
+            && phash::hamming_distance(hash, existing_hash) <= threshold
+    }
+
     fn compute_hash(entry: &ClipboardEntry) -> String {
         let mut hasher = Sha256::new();
 
@@ -59,11 +148,19 @@ impl HistoryManager {
     }
 
     pub fn add_entry(&mut self, mut entry: ClipboardEntry) -> Result<Option<i64>> {
+        if self.capture_paused {
+            return Ok(None);
+        }
+
         // Validate content
         match &entry.content_type {
             ContentType::Text => {
                 if let Some(text) = &entry.content_text {
-                    ContentValidator::validate_text(text)?;
+                    self.validator.validate_text(text)?;
+
+                    let (kind, language) = classifier::classify(text);
+                    entry.text_kind = Some(kind);
+                    entry.code_language = language.map(str::to_string);
                 } else {
                     warn!("Text entry without content_text");
                     return Ok(None);
@@ -73,7 +170,7 @@ impl HistoryManager {
                 // For file-based images, we only have image_path (no content_data)
                 // For legacy images, we might have content_data
                 if let Some(data) = &entry.content_data {
-                    ContentValidator::validate_image(data)?;
+                    self.validator.validate_image(data)?;
                 } else if entry.image_path.is_none() {
                     // Only warn if BOTH content_data AND image_path are missing
                     warn!("Image entry without content_data or image_path");
@@ -83,6 +180,28 @@ impl HistoryManager {
             }
         }
 
+        // Capture rules: drop, rewrite, tag, TTL, skip OCR
+        let outcome = self.rules.apply(&mut entry);
+        if !outcome.matched.is_empty() {
+            info!("Capture rules matched: {}", outcome.matched.join(", "));
+        }
+        if outcome.drop {
+            info!("Dropping entry (capture rule)");
+            return Ok(None);
+        }
+        if let Some(text) = &outcome.rewritten_text {
+            if text.trim().is_empty() {
+                info!("Dropping entry (capture rule rewrote it to nothing)");
+                return Ok(None);
+            }
+            let (kind, language) = classifier::classify(text);
+            entry.text_kind = Some(kind);
+            entry.code_language = language.map(str::to_string);
+        }
+        if let Some(ttl) = outcome.ttl_seconds {
+            entry.expires_at = Some(entry.timestamp + Duration::seconds(ttl as i64));
+        }
+
         // Check for duplicates nos últimos 10 itens do histórico
         let hash = Self::compute_hash(&entry);
 
@@ -94,34 +213,51 @@ impl HistoryManager {
                 info!("Skipping duplicate entry (already exists in history)");
                 return Ok(None);
             }
+            if self.is_near_duplicate(&entry, &existing) {
+                info!(
+                    "Skipping near-duplicate image (similar to entry {})",
+                    existing.id
+                );
+                return Ok(None);
+            }
         }
 
         // Insert into storage
         let id = self.storage.insert(&entry)?;
         entry.id = id;
 
+        if !outcome.tags.is_empty() {
+            self.storage.add_tags(id, &outcome.tags)?;
+        }
+        if outcome.skip_ocr && matches!(entry.content_type, ContentType::Image) {
+            self.storage
+                .skip_ocr_job(id, &format!("capture rule: {}", outcome.matched.join(", ")))?;
+        }
+
         // Update last hash
         self.last_hash = Some(hash);
 
         // Prune old entries if necessary
         let count = self.storage.count()?;
         if count > self.max_entries {
-            let pruned = self.storage.prune_old(self.max_entries)?;
-            info!("Pruned {} old entries", pruned);
+            let pruned = self.storage.prune_old_ids(self.max_entries)?;
+            info!("Pruned {} old entries", pruned.len());
+            self.emit_deleted(pruned);
         }
 
         info!("Added entry with id {}", id);
+        self.events.emit(HistoryEvent::EntryAdded { id });
         Ok(Some(id))
     }
 
     pub fn get_recent(&self, limit: usize) -> Result<Vec<ClipboardEntry>> {
-        self.storage.get_recent(limit)
+        self.with_tags(self.storage.get_recent(limit)?)
     }
 
     /// Get recent entries without loading image data (metadata only)
     /// Optimized for fast listing in UI
     pub fn get_recent_metadata(&self, limit: usize) -> Result<Vec<ClipboardEntry>> {
-        self.storage.get_recent_metadata(limit)
+        self.with_tags(self.storage.get_recent_metadata(limit)?)
     }
 
     /// Get recent entries with offset (for infinite scroll)
@@ -130,37 +266,437 @@ impl HistoryManager {
         limit: usize,
         offset: usize,
     ) -> Result<Vec<ClipboardEntry>> {
-        self.storage.get_recent_metadata_with_offset(limit, offset)
+        self.with_tags(
+            self.storage
+                .get_recent_metadata_with_offset(limit, offset)?,
+        )
     }
 
     pub fn get_by_id(&self, id: i64) -> Result<Option<ClipboardEntry>> {
-        self.storage.get_by_id(id)
+        let Some(mut entry) = self.storage.get_by_id(id)? else {
+            return Ok(None);
+        };
+        entry.tags = self.storage.get_tags(id)?;
+        Ok(Some(entry))
+    }
+
+    /// Fills `tags` of a page of entries
+    fn with_tags(&self, mut entries: Vec<ClipboardEntry>) -> Result<Vec<ClipboardEntry>> {
+        let ids: Vec<i64> = entries.iter().map(|entry| entry.id).collect();
+        let mut tags = self.storage.tags_of_entries(&ids)?;
+        for entry in &mut entries {
+            entry.tags = tags.remove(&entry.id).unwrap_or_default();
+        }
+        Ok(entries)
     }
 
     pub fn prune_old(&self) -> Result<usize> {
-        self.storage.prune_old(self.max_entries)
+        let pruned = self.storage.prune_old_ids(self.max_entries)?;
+        let count = pruned.len();
+        self.emit_deleted(pruned);
+        Ok(count)
     }
 
     pub fn count(&self) -> Result<usize> {
         self.storage.count()
     }
 
+    /// Whether some entry points to this image file (files are named by hash,
+    /// so an exact duplicate shares the file of the entry it duplicates)
+    pub fn is_image_path_used(&self, image_path: &str) -> Result<bool> {
+        self.storage.is_image_path_used(image_path)
+    }
+
     pub fn delete_by_id(&self, id: i64) -> Result<bool> {
-        self.storage.delete_by_id(id)
+        let deleted = self.storage.delete_by_id(id)?;
+        if deleted {
+            self.emit_deleted(vec![id]);
+        }
+        Ok(deleted)
+    }
+
+    /// Deletes several entries; returns the ids that existed
+    pub fn delete_by_ids(&self, ids: &[i64]) -> Result<Vec<i64>> {
+        let mut deleted = Vec::new();
+        for &id in ids {
+            if self.storage.delete_by_id(id)? {
+                deleted.push(id);
+            }
+        }
+        self.emit_deleted(deleted.clone());
+        Ok(deleted)
     }
 
     pub fn clear(&self) -> Result<usize> {
-        self.storage.clear()
+        let count = self.storage.clear()?;
+        self.events.emit(HistoryEvent::HistoryCleared);
+        Ok(count)
+    }
+
+    /// Replaces the text of a text entry (classified again); false when there
+    /// is no such entry
+    pub fn update_text(&self, id: i64, text: &str) -> Result<bool> {
+        self.validator.validate_text(text)?;
+        if !self.storage.update_text(id, text)? {
+            return Ok(false);
+        }
+        let (kind, language) = classifier::classify(text);
+        self.storage.update_text_kind(id, kind, language)?;
+        self.events.emit(HistoryEvent::EntryUpdated { id });
+        Ok(true)
+    }
+
+    pub fn stats(&self) -> Result<HistoryStats> {
+        self.storage.stats()
+    }
+
+    fn emit_deleted(&self, ids: Vec<i64>) {
+        if !ids.is_empty() {
+            self.events.emit(HistoryEvent::EntryDeleted { ids });
+        }
     }
 
     /// Search in ALL history (no limit) - returns metadata only for images
+    ///
+    /// `kind:url` (or any `TextKind` name) tokens restrict the results to text
+    /// entries of that kind; several `kind:` tokens match any of them.
+    /// `tag:sql` (or `tag:"Cliente X"`) tokens keep entries having every one
+    /// of those tags.
     pub fn search(&self, query: &str) -> Result<Vec<ClipboardEntry>> {
-        self.storage.search(query)
+        let filter = SearchFilter::parse(query);
+        if filter.kinds.is_empty() && filter.tags.is_empty() {
+            return self.with_tags(self.storage.search(query)?);
+        }
+
+        let entries = if !filter.text.is_empty() {
+            self.storage.search(&filter.text)?
+        } else if !filter.tags.is_empty() {
+            self.storage.get_by_tags(&filter.tags)?
+        } else {
+            self.storage.get_by_text_kinds(&filter.kinds)?
+        };
+        Ok(self
+            .with_tags(entries)?
+            .into_iter()
+            .filter(|entry| filter.matches(entry))
+            .collect())
+    }
+
+    /// Deletes entries whose capture-rule TTL ran out
+    pub fn delete_expired(&self) -> Result<usize> {
+        let expired = self.storage.delete_expired(Utc::now())?;
+        let count = expired.len();
+        self.emit_deleted(expired);
+        Ok(count)
+    }
+
+    pub fn get_tags(&self, id: i64) -> Result<Vec<String>> {
+        self.storage.get_tags(id)
+    }
+
+    pub fn list_tags(&self) -> Result<Vec<Tag>> {
+        self.storage.list_tags()
+    }
+
+    /// Creates an empty tag (or returns the one with that name)
+    pub fn create_tag(&self, name: &str) -> Result<Tag> {
+        let id = self.storage.ensure_tag(name)?;
+        self.find_tag(id)
+    }
+
+    pub fn rename_tag(&self, id: i64, name: &str) -> Result<Tag> {
+        if !self.storage.rename_tag(id, name)? {
+            return Err(anyhow!("Tag with id {} not found", id));
+        }
+        self.find_tag(id)
+    }
+
+    pub fn delete_tag(&self, id: i64) -> Result<bool> {
+        self.storage.delete_tag(id)
+    }
+
+    pub fn add_entry_tag(&self, entry_id: i64, tag: &str) -> Result<()> {
+        self.ensure_entry_exists(entry_id)?;
+        self.storage.add_tags(entry_id, &[tag.to_string()])?;
+        self.events
+            .emit(HistoryEvent::EntryUpdated { id: entry_id });
+        Ok(())
+    }
+
+    pub fn remove_entry_tag(&self, entry_id: i64, tag: &str) -> Result<bool> {
+        let removed = self.storage.remove_tag(entry_id, tag)?;
+        if removed {
+            self.events
+                .emit(HistoryEvent::EntryUpdated { id: entry_id });
+        }
+        Ok(removed)
+    }
+
+    /// Replaces the tags of an entry (the tag editor saves this way)
+    pub fn set_entry_tags(&self, entry_id: i64, tags: &[String]) -> Result<()> {
+        self.ensure_entry_exists(entry_id)?;
+        self.storage.set_tags(entry_id, tags)?;
+        self.events
+            .emit(HistoryEvent::EntryUpdated { id: entry_id });
+        Ok(())
+    }
+
+    fn find_tag(&self, id: i64) -> Result<Tag> {
+        self.storage
+            .list_tags()?
+            .into_iter()
+            .find(|tag| tag.id == id)
+            .ok_or_else(|| anyhow!("Tag with id {} not found", id))
+    }
+
+    fn ensure_entry_exists(&self, entry_id: i64) -> Result<()> {
+        match self.storage.get_by_id(entry_id)? {
+            Some(_) => Ok(()),
+            None => Err(anyhow!("Entry with id {} not found", entry_id)),
+        }
+    }
+
+    pub fn list_snippets(&self) -> Result<Vec<Snippet>> {
+        self.storage.list_snippets()
+    }
+
+    pub fn get_snippet(&self, id: i64) -> Result<Option<Snippet>> {
+        self.storage.get_snippet(id)
+    }
+
+    pub fn create_snippet(
+        &self,
+        title: &str,
+        body: &str,
+        keyword: Option<&str>,
+    ) -> Result<Snippet> {
+        let mut snippet = Snippet::new(title, body, keyword)?;
+        snippet.id = self.storage.insert_snippet(&snippet)?;
+        Ok(snippet)
+    }
+
+    pub fn update_snippet(
+        &self,
+        id: i64,
+        title: &str,
+        body: &str,
+        keyword: Option<&str>,
+    ) -> Result<Snippet> {
+        let existing = self
+            .storage
+            .get_snippet(id)?
+            .ok_or_else(|| anyhow!("Snippet with id {} not found", id))?;
+        let snippet = Snippet {
+            id,
+            created_at: existing.created_at,
+            ..Snippet::new(title, body, keyword)?
+        };
+        self.storage.update_snippet(&snippet)?;
+        Ok(snippet)
+    }
+
+    pub fn delete_snippet(&self, id: i64) -> Result<bool> {
+        self.storage.delete_snippet(id)
+    }
+
+    /// Snippet body with placeholders filled in; `{clipboard}` is the live
+    /// clipboard text (empty when the clipboard holds none)
+    pub fn expand_snippet(&self, id: i64) -> Result<ExpandedSnippet> {
+        let snippet = self
+            .storage
+            .get_snippet(id)?
+            .ok_or_else(|| anyhow!("Snippet with id {} not found", id))?;
+        Ok(snippet.expand(&SnippetContext::now(self.clipboard_text.clone())))
+    }
+
+    /// Classifies text entries saved before the classifier existed
+    pub fn classify_existing_entries(&self) -> Result<usize> {
+        let entries = self.storage.unclassified_text_entries()?;
+        for (id, text) in &entries {
+            let (kind, language) = classifier::classify(text);
+            self.storage.update_text_kind(*id, kind, language)?;
+        }
+        Ok(entries.len())
+    }
+
+    /// Images visually similar to entry `id`, closest first, with their distance
+    pub fn find_similar_images(
+        &self,
+        id: i64,
+        max_distance: u32,
+        limit: usize,
+    ) -> Result<Vec<(ClipboardEntry, u32)>> {
+        let target = self
+            .storage
+            .get_by_id(id)?
+            .ok_or_else(|| anyhow!("Entry with id {} not found", id))?;
+        let target_hash = target
+            .image_phash
+            .ok_or_else(|| anyhow!("Entry {} has no perceptual hash", id))?;
+
+        let mut matches: Vec<(i64, u32)> = self
+            .storage
+            .get_image_hashes()?
+            .into_iter()
+            .filter(|(other_id, _)| *other_id != id)
+            .map(|(other_id, hash)| (other_id, phash::hamming_distance(target_hash, hash)))
+            .filter(|(_, distance)| *distance <= max_distance)
+            .collect();
+        // Stable sort keeps newest first among equal distances
+        matches.sort_by_key(|(_, distance)| *distance);
+
+        let mut result = Vec::new();
+        for (other_id, distance) in matches.into_iter().take(limit) {
+            if let Some(entry) = self.storage.get_by_id(other_id)? {
+                result.push((entry, distance));
+            }
+        }
+        Ok(result)
     }
 
     /// Update OCR text for an existing entry (used by OCR processor)
     pub fn update_ocr_text(&self, id: i64, ocr_text: &str) -> Result<()> {
-        self.storage.update_ocr_text(id, ocr_text)
+        self.storage.update_ocr_text(id, ocr_text)?;
+        self.events.emit(HistoryEvent::EntryUpdated { id });
+        Ok(())
+    }
+
+    /// Stores decoded QR/barcode payloads (one per line), searchable like OCR text
+    pub fn update_barcode_text(&self, id: i64, barcode_text: &str) -> Result<()> {
+        self.storage.update_barcode_text(id, barcode_text)?;
+        self.events.emit(HistoryEvent::EntryUpdated { id });
+        Ok(())
+    }
+
+    /// Stores the word boxes of an OCR run, replacing previous ones
+    pub fn save_ocr_words(&self, id: i64, words: &[OcrWord]) -> Result<()> {
+        self.storage.save_ocr_words(id, words)
+    }
+
+    pub fn get_ocr_words(&self, id: i64) -> Result<Vec<OcrWord>> {
+        self.storage.get_ocr_words(id)
+    }
+
+    /// Text of the OCR words inside `region` (image pixels)
+    ///
+    /// None when the entry has no word boxes (OCR not run yet, or run before
+    /// word boxes were stored), Some("") when the region has no words.
+    pub fn ocr_text_in_region(&self, id: i64, region: &BoundingBox) -> Result<Option<String>> {
+        let words = self.storage.get_ocr_words(id)?;
+        if words.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(ocr::text_in_region(&words, region)))
+    }
+
+    /// Queues an image for OCR (see `Storage::enqueue_ocr_job`)
+    pub fn enqueue_ocr_job(&self, entry_id: i64) -> Result<bool> {
+        self.storage.enqueue_ocr_job(entry_id)
+    }
+
+    pub fn enqueue_ocr_backfill(&self, include_failed: bool) -> Result<usize> {
+        self.storage.enqueue_ocr_backfill(include_failed)
+    }
+
+    pub fn pending_ocr_jobs(&self, limit: usize) -> Result<Vec<OcrJob>> {
+        self.storage.pending_ocr_jobs(limit)
+    }
+
+    pub fn set_ocr_job_status(
+        &self,
+        entry_id: i64,
+        status: OcrJobStatus,
+        error: Option<&str>,
+    ) -> Result<()> {
+        self.storage.set_ocr_job_status(entry_id, status, error)
+    }
+
+    pub fn get_ocr_job_status(&self, entry_id: i64) -> Result<Option<OcrJobStatus>> {
+        self.storage.get_ocr_job_status(entry_id)
+    }
+
+    pub fn reset_running_ocr_jobs(&self) -> Result<usize> {
+        self.storage.reset_running_ocr_jobs()
+    }
+
+    pub fn ocr_queue_stats(&self) -> Result<OcrQueueStats> {
+        self.storage.ocr_queue_stats()
+    }
+}
+
+/// Search query split into `kind:`/`tag:` filters and the remaining text
+#[derive(Debug, Default, PartialEq)]
+struct SearchFilter {
+    kinds: Vec<TextKind>,
+    tags: Vec<String>,
+    text: String,
+}
+
+impl SearchFilter {
+    /// Unknown kinds stay in the text; `tag:"two words"` may span words
+    fn parse(query: &str) -> Self {
+        let mut filter = SearchFilter::default();
+        let mut words = Vec::new();
+        let mut tokens = query.split_whitespace();
+        while let Some(word) = tokens.next() {
+            if let Some(kind) = word.strip_prefix("kind:").and_then(TextKind::parse) {
+                filter.kinds.push(kind);
+                continue;
+            }
+
+            let Some(tag) = word.strip_prefix("tag:").filter(|tag| !tag.is_empty()) else {
+                words.push(word);
+                continue;
+            };
+            let tag = match tag.strip_prefix('"') {
+                Some(quoted) => read_quoted(quoted, &mut tokens),
+                None => tag.to_string(),
+            };
+            if !tag.trim().is_empty() {
+                filter.tags.push(tag);
+            }
+        }
+        filter.text = words.join(" ");
+        filter
+    }
+
+    fn matches(&self, entry: &ClipboardEntry) -> bool {
+        let kind_ok = self.kinds.is_empty()
+            || entry
+                .text_kind
+                .is_some_and(|kind| self.kinds.contains(&kind));
+        let tags_ok = self.tags.iter().all(|tag| {
+            entry
+                .tags
+                .iter()
+                .any(|entry_tag| entry_tag.eq_ignore_ascii_case(tag.trim()))
+        });
+        kind_ok && tags_ok
+    }
+}
+
+/// Rest of a quoted tag starting at `first`, up to the closing `"`
+///
+/// `\"` and `\\` stand for a quote and a backslash inside the name.
+fn read_quoted<'a>(first: &'a str, tokens: &mut impl Iterator<Item = &'a str>) -> String {
+    let mut tag = String::new();
+    let mut part = first;
+    loop {
+        let mut chars = part.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => tag.extend(chars.next()),
+                '"' => return tag,
+                c => tag.push(c),
+            }
+        }
+        match tokens.next() {
+            Some(next) => {
+                tag.push(' ');
+                part = next;
+            }
+            None => return tag,
+        }
     }
 }
 
@@ -201,4 +737,261 @@ mod tests {
 
         assert_eq!(manager.count().unwrap(), 5);
     }
+
+    fn image_entry(path: &str, phash: u64) -> ClipboardEntry {
+        let mut entry = ClipboardEntry::new_image_with_dimensions(path.to_string(), None, 800, 600);
+        entry.image_phash = Some(phash);
+        entry
+    }
+
+    #[test]
+    fn test_near_duplicate_images_are_skipped() {
+        let mut manager = HistoryManager::new_in_memory(100).unwrap();
+        manager.set_similarity_threshold(Some(4));
+
+        let first = manager
+            .add_entry(image_entry("/tmp/a.png", 0xF0F0))
+            .unwrap();
+        let near = manager
+            .add_entry(image_entry("/tmp/b.png", 0xF0F1))
+            .unwrap();
+        let far = manager
+            .add_entry(image_entry("/tmp/c.png", !0xF0F0))
+            .unwrap();
+
+        assert!(first.is_some());
+        assert!(near.is_none());
+        assert!(far.is_some());
+
+        // Same hash but a different size is a different image
+        let mut resized = image_entry("/tmp/d.png", 0xF0F0);
+        resized.image_width = Some(400);
+        assert!(manager.add_entry(resized).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_near_duplicates_kept_when_disabled() {
+        let mut manager = HistoryManager::new_in_memory(100).unwrap();
+
+        manager
+            .add_entry(image_entry("/tmp/a.png", 0xF0F0))
+            .unwrap();
+        let near = manager
+            .add_entry(image_entry("/tmp/b.png", 0xF0F1))
+            .unwrap();
+
+        assert!(near.is_some());
+    }
+
+    #[test]
+    fn test_find_similar_images() {
+        let mut manager = HistoryManager::new_in_memory(100).unwrap();
+        let target = manager
+            .add_entry(image_entry("/tmp/a.png", 0b0000))
+            .unwrap()
+            .unwrap();
+        let close = manager
+            .add_entry(image_entry("/tmp/b.png", 0b0001))
+            .unwrap()
+            .unwrap();
+        let farther = manager
+            .add_entry(image_entry("/tmp/c.png", 0b0111))
+            .unwrap()
+            .unwrap();
+        manager
+            .add_entry(image_entry("/tmp/d.png", u64::MAX))
+            .unwrap();
+        manager
+            .add_entry(ClipboardEntry::new_text("not an image".to_string()))
+            .unwrap();
+
+        let similar = manager.find_similar_images(target, 10, 10).unwrap();
+        let ids: Vec<_> = similar.iter().map(|(e, d)| (e.id, *d)).collect();
+        assert_eq!(ids, vec![(close, 1), (farther, 3)]);
+
+        let limited = manager.find_similar_images(target, 10, 1).unwrap();
+        assert_eq!(limited.len(), 1);
+
+        assert!(manager.find_similar_images(9999, 10, 10).is_err());
+    }
+
+    #[test]
+    fn test_kind_filter_in_search() {
+        let mut manager = HistoryManager::new_in_memory(100).unwrap();
+        let url = manager
+            .add_entry(ClipboardEntry::new_text(
+                "https://docs.rs/clippit".to_string(),
+            ))
+            .unwrap()
+            .unwrap();
+        manager
+            .add_entry(ClipboardEntry::new_text("docs do projeto".to_string()))
+            .unwrap();
+        let color = manager
+            .add_entry(ClipboardEntry::new_text("#ff8800".to_string()))
+            .unwrap()
+            .unwrap();
+
+        let stored = manager.get_by_id(url).unwrap().unwrap();
+        assert_eq!(stored.text_kind, Some(TextKind::Url));
+
+        let ids = |query: &str| -> Vec<i64> {
+            manager
+                .search(query)
+                .unwrap()
+                .iter()
+                .map(|entry| entry.id)
+                .collect()
+        };
+        assert_eq!(ids("kind:url"), vec![url]);
+        assert_eq!(ids("docs kind:url"), vec![url]);
+        assert_eq!(ids("kind:url kind:color"), vec![color, url]);
+        assert_eq!(ids("docs").len(), 2);
+    }
+
+    #[test]
+    fn test_capture_rules_in_add_entry() {
+        let rules: Vec<crate::config::CaptureRule> = [
+            "name = \"token\"\npattern = '^tok_'\ndrop = true",
+            "name = \"otp\"\npattern = '^\\d{6}$'\nttl_seconds = 120\ntags = [\"otp\"]",
+            "name = \"screens\"\nkind = \"image\"\nskip_ocr = true",
+        ]
+        .iter()
+        .map(|rule| toml::from_str(rule).unwrap())
+        .collect();
+
+        let mut manager = HistoryManager::new_in_memory(100).unwrap();
+        manager.set_rules(RuleSet::compile(&rules).unwrap());
+
+        let dropped = manager
+            .add_entry(ClipboardEntry::new_text("tok_secret".to_string()))
+            .unwrap();
+        assert!(dropped.is_none());
+        assert_eq!(manager.count().unwrap(), 0);
+
+        let otp = manager
+            .add_entry(ClipboardEntry::new_text("482913".to_string()))
+            .unwrap()
+            .unwrap();
+        let stored = manager.get_by_id(otp).unwrap().unwrap();
+        let ttl = stored.expires_at.unwrap() - stored.timestamp;
+        assert_eq!(ttl.num_seconds(), 120);
+        assert_eq!(manager.get_tags(otp).unwrap(), vec!["otp"]);
+
+        let image = manager
+            .add_entry(image_entry("/tmp/rules.png", 0))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            manager.get_ocr_job_status(image).unwrap(),
+            Some(OcrJobStatus::Skipped)
+        );
+        assert!(!manager.enqueue_ocr_job(image).unwrap());
+
+        // Not expired yet; a cleanup in 3 minutes removes the code
+        assert_eq!(manager.delete_expired().unwrap(), 0);
+        let later = Utc::now() + Duration::minutes(3);
+        assert_eq!(manager.storage.delete_expired(later).unwrap(), vec![otp]);
+        assert!(manager.get_by_id(otp).unwrap().is_none());
+        assert!(manager.get_by_id(image).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_tag_filter_in_search() {
+        let mut manager = HistoryManager::new_in_memory(100).unwrap();
+        let add = |manager: &mut HistoryManager, text: &str| {
+            manager
+                .add_entry(ClipboardEntry::new_text(text.to_string()))
+                .unwrap()
+                .unwrap()
+        };
+        let query = add(&mut manager, "SELECT * FROM pedidos WHERE cliente = 42");
+        let url = add(&mut manager, "https://cliente-x.example.com/pedidos");
+        add(&mut manager, "pedidos antigos");
+
+        manager
+            .set_entry_tags(
+                query,
+                &["SQL snippets".to_string(), "Cliente X".to_string()],
+            )
+            .unwrap();
+        manager.add_entry_tag(url, "cliente x").unwrap();
+        assert!(manager.add_entry_tag(999, "x").is_err());
+
+        let ids = |query: &str| -> Vec<i64> {
+            manager
+                .search(query)
+                .unwrap()
+                .iter()
+                .map(|entry| entry.id)
+                .collect()
+        };
+        assert_eq!(ids("tag:\"Cliente X\""), vec![url, query]);
+        assert_eq!(ids("tag:\"sql snippets\" tag:\"cliente x\""), vec![query]);
+        assert_eq!(ids("pedidos tag:\"Cliente X\" kind:url"), vec![url]);
+
+        assert_eq!(ids("pedidos").len(), 3);
+
+        let listed = manager.get_recent_metadata(10).unwrap();
+        assert_eq!(listed[2].tags, vec!["Cliente X", "SQL snippets"]);
+        // Tag names are case-insensitive: the existing spelling is kept
+        assert_eq!(
+            manager.get_by_id(url).unwrap().unwrap().tags,
+            vec!["Cliente X"]
+        );
+
+        // Quotes and backslashes are escaped inside a quoted tag
+        manager.add_entry_tag(url, r#"Ana "B" \ C"#).unwrap();
+        assert_eq!(ids(r#"tag:"ana \"b\" \\ c""#), vec![url]);
+        assert_eq!(ids(r#"tag:"Cliente X" tag:"Ana \"B\" \\ C""#), vec![url]);
+    }
+
+    #[test]
+    fn test_mutations_emit_events() {
+        let mut manager = HistoryManager::new_in_memory(2).unwrap();
+        let events = manager.events().subscribe();
+        let add = |manager: &mut HistoryManager, text: &str| {
+            manager
+                .add_entry(ClipboardEntry::new_text(text.to_string()))
+                .unwrap()
+        };
+
+        let first = add(&mut manager, "um").unwrap();
+        let second = add(&mut manager, "dois").unwrap();
+        let third = add(&mut manager, "três").unwrap();
+        manager.add_entry_tag(second, "x").unwrap();
+        manager.delete_by_id(third).unwrap();
+        assert!(manager.update_text(second, "https://example.com").unwrap());
+        assert_eq!(
+            manager.get_by_id(second).unwrap().unwrap().text_kind,
+            Some(TextKind::Url)
+        );
+        assert_eq!(
+            manager.delete_by_ids(&[third, second]).unwrap(),
+            vec![second]
+        );
+
+        manager.set_capture_paused(true);
+        assert_eq!(add(&mut manager, "ignorado"), None);
+        manager.set_capture_paused(false);
+        manager.clear().unwrap();
+
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![
+                HistoryEvent::EntryAdded { id: first },
+                HistoryEvent::EntryAdded { id: second },
+                // Over max_entries: the oldest goes first
+                HistoryEvent::EntryDeleted { ids: vec![first] },
+                HistoryEvent::EntryAdded { id: third },
+                HistoryEvent::EntryUpdated { id: second },
+                HistoryEvent::EntryDeleted { ids: vec![third] },
+                HistoryEvent::EntryUpdated { id: second },
+                HistoryEvent::EntryDeleted { ids: vec![second] },
+                HistoryEvent::CaptureStateChanged { paused: true },
+                HistoryEvent::CaptureStateChanged { paused: false },
+                HistoryEvent::HistoryCleared,
+            ]
+        );
+    }
 }
@@ -0,0 +1,290 @@
+//! Image formats accepted in the history and privacy cleanup of saved images
+//!
+//! Images are stored in the format the source application offered them
+//! (JPEG screenshots stay JPEG), but EXIF/XMP metadata - which may contain GPS
+//! coordinates, camera serials, etc. - is removed before the file hits the disk.
+
+use anyhow::{anyhow, Result};
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use img_parts::jpeg::{markers, Jpeg};
+use img_parts::png::Png;
+use img_parts::webp::{WebP, CHUNK_XMP};
+use img_parts::{Bytes, ImageEXIF};
+use std::io::Cursor;
+
+/// Formats the history accepts, in no particular order
+pub const SUPPORTED_FORMATS: &[ImageFormat] = &[
+    ImageFormat::Png,
+    ImageFormat::Jpeg,
+    ImageFormat::WebP,
+    ImageFormat::Gif,
+    ImageFormat::Bmp,
+    ImageFormat::Tiff,
+];
+
+/// SVG is kept as-is (vector); it has no `ImageFormat`, so it's handled by MIME type
+pub const SVG_MIME_TYPE: &str = "image/svg+xml";
+
+/// PNG text chunks (XMP is stored in iTXt) and the EXIF chunk
+const PNG_METADATA_CHUNKS: &[[u8; 4]] = &[*b"eXIf", *b"tEXt", *b"zTXt", *b"iTXt", *b"tIME"];
+
+/// JPEG quality used when an image has to be re-encoded
+const JPEG_QUALITY: u8 = 90;
+
+pub fn is_supported(format: ImageFormat) -> bool {
+    SUPPORTED_FORMATS.contains(&format)
+}
+
+/// Maps a clipboard MIME type to a supported format
+pub fn format_from_mime(mime_type: &str) -> Option<ImageFormat> {
+    let format = match mime_type.to_ascii_lowercase().as_str() {
+        // Aliases some apps still announce
+        "image/jpg" | "image/pjpeg" => Some(ImageFormat::Jpeg),
+        "image/x-bmp" | "image/x-ms-bmp" => Some(ImageFormat::Bmp),
+        other => ImageFormat::from_mime_type(other),
+    }?;
+
+    is_supported(format).then_some(format)
+}
+
+pub fn mime_type(format: ImageFormat) -> &'static str {
+    format.to_mime_type()
+}
+
+/// File extension used when saving (without the dot)
+pub fn extension(format: ImageFormat) -> &'static str {
+    format.extensions_str().first().copied().unwrap_or("img")
+}
+
+pub fn is_svg_mime(mime_type: &str) -> bool {
+    mime_type.eq_ignore_ascii_case(SVG_MIME_TYPE)
+}
+
+/// Sniffs SVG documents (optional BOM, XML prolog, comments or doctype before `<svg`)
+pub fn is_svg(data: &[u8]) -> bool {
+    let head = &data[..data.len().min(4096)];
+    let Ok(text) = std::str::from_utf8(head).or_else(|e| {
+        // The 4KB cut may split a multi-byte character
+        std::str::from_utf8(&head[..e.valid_up_to()])
+    }) else {
+        return false;
+    };
+
+    text.trim_start_matches('\u{feff}')
+        .trim_start()
+        .starts_with('<')
+        && text.contains("<svg")
+}
+
+/// Whether an image format is animated-capable and must not be re-encoded
+pub fn is_animated(data: &[u8], format: ImageFormat) -> bool {
+    use image::AnimationDecoder;
+
+    match format {
+        ImageFormat::Gif => image::codecs::gif::GifDecoder::new(Cursor::new(data))
+            .map(|decoder| decoder.into_frames().take(2).count() > 1)
+            .unwrap_or(false),
+        ImageFormat::WebP => image::codecs::webp::WebPDecoder::new(Cursor::new(data))
+            .map(|decoder| decoder.has_animation())
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Detects the format from the file header
+pub fn detect_format(data: &[u8]) -> Option<ImageFormat> {
+    image::guess_format(data).ok()
+}
+
+/// Removes EXIF/XMP metadata, keeping the pixels untouched when possible
+///
+/// JPEG, PNG and WebP are cleaned at the container level (no re-encoding, the ICC
+/// profile is kept). JPEG/WebP images that rely on the EXIF orientation tag are
+/// rotated first so they don't show up sideways once the tag is gone. TIFF keeps
+/// its metadata in the same IFD as the pixels, so it is re-encoded. GIF and BMP
+/// have no EXIF and are returned as-is.
+pub fn strip_metadata(data: &[u8], format: ImageFormat) -> Result<Vec<u8>> {
+    match format {
+        ImageFormat::Jpeg | ImageFormat::WebP if needs_orientation_fix(data, format) => {
+            reencode(data, format)
+        }
+        ImageFormat::Jpeg => {
+            let mut jpeg = Jpeg::from_bytes(Bytes::copy_from_slice(data))
+                .map_err(|e| anyhow!("Invalid JPEG: {}", e))?;
+            // APP1 holds both EXIF and XMP
+            jpeg.remove_segments_by_marker(markers::APP1);
+            jpeg.remove_segments_by_marker(markers::COM);
+            Ok(jpeg.encoder().bytes().to_vec())
+        }
+        ImageFormat::Png => {
+            let mut png = Png::from_bytes(Bytes::copy_from_slice(data))
+                .map_err(|e| anyhow!("Invalid PNG: {}", e))?;
+            png.chunks_mut()
+                .retain(|chunk| !PNG_METADATA_CHUNKS.contains(&chunk.kind()));
+            Ok(png.encoder().bytes().to_vec())
+        }
+        ImageFormat::WebP => {
+            let mut webp = WebP::from_bytes(Bytes::copy_from_slice(data))
+                .map_err(|e| anyhow!("Invalid WebP: {}", e))?;
+            webp.remove_chunks_by_id(CHUNK_XMP);
+            // Also updates the VP8X feature flags
+            webp.set_exif(None);
+            Ok(webp.encoder().bytes().to_vec())
+        }
+        ImageFormat::Tiff => reencode(data, format),
+        _ => Ok(data.to_vec()),
+    }
+}
+
+fn needs_orientation_fix(data: &[u8], format: ImageFormat) -> bool {
+    let mut reader = ImageReader::new(Cursor::new(data));
+    reader.set_format(format);
+
+    reader
+        .into_decoder()
+        .and_then(|mut decoder| decoder.orientation())
+        .map(|orientation| orientation != Orientation::NoTransforms)
+        .unwrap_or(false)
+}
+
+/// Decodes and encodes again in the same format; the encoders don't write metadata
+fn reencode(data: &[u8], format: ImageFormat) -> Result<Vec<u8>> {
+    let mut reader = ImageReader::new(Cursor::new(data));
+    reader.set_format(format);
+
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut img = DynamicImage::from_decoder(decoder)?;
+    img.apply_orientation(orientation);
+
+    encode(&img, format)
+}
+
+/// Encodes an image in `format`, converting the color type when the encoder needs it
+pub fn encode(img: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+
+    match format {
+        ImageFormat::Jpeg => {
+            // JPEG has no alpha channel
+            let rgb = DynamicImage::ImageRgb8(img.to_rgb8());
+            let encoder =
+                image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buf, JPEG_QUALITY);
+            rgb.write_with_encoder(encoder)?;
+        }
+        ImageFormat::WebP | ImageFormat::Gif => {
+            DynamicImage::ImageRgba8(img.to_rgba8())
+                .write_to(&mut Cursor::new(&mut buf), format)?;
+        }
+        _ => img.write_to(&mut Cursor::new(&mut buf), format)?,
+    }
+
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use img_parts::jpeg::JpegSegment;
+
+    fn sample(format: ImageFormat) -> Vec<u8> {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            16,
+            8,
+            image::Rgba([10, 20, 30, 255]),
+        ));
+        encode(&img, format).unwrap()
+    }
+
+    /// Minimal EXIF block with a GPS IFD pointer, enough to be recognized as EXIF
+    fn fake_exif() -> Bytes {
+        Bytes::from_static(b"Exif\0\0MM\0\x2a\0\0\0\x08\0\x01\x88\x25\0\x04\0\0\0\x01\0\0\0\0GPS")
+    }
+
+    #[test]
+    fn test_format_from_mime() {
+        assert_eq!(format_from_mime("image/png"), Some(ImageFormat::Png));
+        assert_eq!(format_from_mime("image/jpg"), Some(ImageFormat::Jpeg));
+        assert_eq!(format_from_mime("IMAGE/WEBP"), Some(ImageFormat::WebP));
+        assert_eq!(format_from_mime("image/tiff"), Some(ImageFormat::Tiff));
+        assert_eq!(format_from_mime("image/x-icon"), None);
+        assert_eq!(format_from_mime("text/plain"), None);
+    }
+
+    #[test]
+    fn test_roundtrip_all_supported_formats() {
+        for &format in SUPPORTED_FORMATS {
+            let data = sample(format);
+            assert_eq!(detect_format(&data), Some(format), "{:?}", format);
+
+            let stripped = strip_metadata(&data, format).unwrap();
+            let img = image::load_from_memory(&stripped).unwrap();
+            assert_eq!((img.width(), img.height()), (16, 8), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn test_strip_jpeg_exif() {
+        let mut jpeg = Jpeg::from_bytes(sample(ImageFormat::Jpeg).into()).unwrap();
+        jpeg.segments_mut().insert(
+            1,
+            JpegSegment::new_with_contents(markers::APP1, fake_exif()),
+        );
+        let with_exif = jpeg.encoder().bytes().to_vec();
+        assert!(with_exif.windows(3).any(|w| w == b"GPS"));
+
+        let stripped = strip_metadata(&with_exif, ImageFormat::Jpeg).unwrap();
+
+        assert!(!stripped.windows(3).any(|w| w == b"GPS"));
+        assert!(image::load_from_memory(&stripped).is_ok());
+    }
+
+    #[test]
+    fn test_svg_detection() {
+        assert!(is_svg(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>"));
+        assert!(is_svg(
+            b"\xef\xbb\xbf<?xml version=\"1.0\"?>\n<!-- Inkscape -->\n<svg width=\"10\"/>"
+        ));
+        assert!(!is_svg(b"plain text mentioning <svg> later"));
+        assert!(!is_svg(&sample(ImageFormat::Png)));
+        assert!(is_svg_mime("image/SVG+xml"));
+    }
+
+    #[test]
+    fn test_animated_gif_detection() {
+        use image::codecs::gif::GifEncoder;
+        use image::{Delay, Frame};
+
+        let frame = |v: u8| {
+            Frame::from_parts(
+                image::RgbaImage::from_pixel(4, 4, image::Rgba([v, v, v, 255])),
+                0,
+                0,
+                Delay::from_numer_denom_ms(100, 1),
+            )
+        };
+        let mut animated = Vec::new();
+        GifEncoder::new(&mut animated)
+            .encode_frames(vec![frame(0), frame(255)])
+            .unwrap();
+
+        assert!(is_animated(&animated, ImageFormat::Gif));
+        assert!(!is_animated(&sample(ImageFormat::Gif), ImageFormat::Gif));
+        assert!(!is_animated(&sample(ImageFormat::Png), ImageFormat::Png));
+    }
+
+    #[test]
+    fn test_strip_png_text_chunks() {
+        let mut png = Png::from_bytes(sample(ImageFormat::Png).into()).unwrap();
+        let chunk =
+            img_parts::png::PngChunk::new(*b"tEXt", Bytes::from_static(b"GPS\0-23.5,-46.6"));
+        png.chunks_mut().insert(1, chunk);
+        let with_text = png.encoder().bytes().to_vec();
+
+        let stripped = strip_metadata(&with_text, ImageFormat::Png).unwrap();
+
+        assert!(!stripped.windows(3).any(|w| w == b"GPS"));
+        assert!(image::load_from_memory(&stripped).is_ok());
+    }
+}
@@ -1,12 +1,29 @@
+pub mod classifier;
 pub mod config;
+pub mod events;
 pub mod history;
+pub mod imaging;
+pub mod ocr;
+pub mod phash;
+pub mod qr;
+pub mod rules;
+pub mod snippets;
 pub mod storage;
+pub mod transform;
 pub mod types;
 pub mod validator;
 
-pub use config::Config;
+pub use config::{CaptureRule, Config, HttpApiConfig};
+pub use events::{EventBus, HistoryEvent};
 pub use history::HistoryManager;
-pub use types::{ClipboardEntry, ContentType};
+pub use qr::{QrEcLevel, QrImage};
+pub use rules::{RuleOutcome, RuleSet};
+pub use snippets::{ExpandedSnippet, Snippet, SnippetContext};
+pub use transform::Transform;
+pub use types::{
+    BoundingBox, ClipboardEntry, ContentType, HistoryStats, OcrJob, OcrJobStatus, OcrQueueStats,
+    OcrWord, Tag, TextKind,
+};
 pub use validator::ContentValidator;
 
 // Initialize i18n
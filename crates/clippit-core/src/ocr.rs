@@ -0,0 +1,112 @@
+//! Helpers for OCR word boxes
+//!
+//! Words are stored with their bounding boxes so a region of a screenshot (e.g.
+//! one table cell) can be turned back into text without running OCR again.
+
+use crate::types::{BoundingBox, OcrWord};
+
+impl BoundingBox {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn center(&self) -> (u32, u32) {
+        (self.x + self.width / 2, self.y + self.height / 2)
+    }
+
+    pub fn contains_point(&self, (px, py): (u32, u32)) -> bool {
+        px >= self.x && px <= self.x + self.width && py >= self.y && py <= self.y + self.height
+    }
+}
+
+/// Text of the words whose center falls inside `region`, in reading order
+///
+/// Words are grouped into lines when their vertical ranges overlap by at least
+/// half of the smaller height; lines are joined with '\n', words with spaces.
+pub fn text_in_region(words: &[OcrWord], region: &BoundingBox) -> String {
+    let mut selected: Vec<&OcrWord> = words
+        .iter()
+        .filter(|word| region.contains_point(word.bbox.center()))
+        .collect();
+    selected.sort_by_key(|word| (word.bbox.y, word.bbox.x));
+
+    let mut lines: Vec<Vec<&OcrWord>> = Vec::new();
+    for word in selected {
+        match lines.last_mut() {
+            Some(line) if same_line(line[0], word) => line.push(word),
+            _ => lines.push(vec![word]),
+        }
+    }
+
+    lines
+        .into_iter()
+        .map(|mut line| {
+            line.sort_by_key(|word| word.bbox.x);
+            line.iter()
+                .map(|word| word.text.as_str())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn same_line(a: &OcrWord, b: &OcrWord) -> bool {
+    let top = a.bbox.y.max(b.bbox.y);
+    let bottom = (a.bbox.y + a.bbox.height).min(b.bbox.y + b.bbox.height);
+    let overlap = bottom.saturating_sub(top);
+    overlap * 2 >= a.bbox.height.min(b.bbox.height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, x: u32, y: u32, width: u32) -> OcrWord {
+        OcrWord {
+            text: text.to_string(),
+            bbox: BoundingBox::new(x, y, width, 20),
+            confidence: Some(0.9),
+        }
+    }
+
+    /// 2x2 table: | Nome  | Valor |
+    ///            | Total | 42,00 |
+    fn table() -> Vec<OcrWord> {
+        vec![
+            word("Valor", 200, 10, 60),
+            word("Nome", 10, 12, 50),
+            word("Total", 10, 50, 50),
+            word("R$", 200, 48, 25),
+            word("42,00", 230, 50, 50),
+        ]
+    }
+
+    #[test]
+    fn test_single_cell() {
+        let region = BoundingBox::new(190, 40, 120, 40);
+        assert_eq!(text_in_region(&table(), &region), "R$ 42,00");
+    }
+
+    #[test]
+    fn test_whole_table_keeps_reading_order() {
+        let region = BoundingBox::new(0, 0, 400, 100);
+        assert_eq!(
+            text_in_region(&table(), &region),
+            "Nome Valor\nTotal R$ 42,00"
+        );
+    }
+
+    #[test]
+    fn test_words_cut_by_the_region_edge() {
+        // "Valor" center is at x=230: a selection ending at x=220 leaves it out
+        let region = BoundingBox::new(0, 0, 220, 35);
+        assert_eq!(text_in_region(&table(), &region), "Nome");
+        assert_eq!(text_in_region(&[], &region), "");
+    }
+}
@@ -0,0 +1,93 @@
+//! Perceptual hashing (dHash) for near-duplicate image detection
+//!
+//! Unlike the SHA-256 used for exact duplicates, a dHash changes only a few bits
+//! when the image changes slightly (a re-captured screenshot, a blinking cursor,
+//! re-encoding), so similarity is the Hamming distance between two hashes.
+
+use image::imageops::FilterType;
+use image::DynamicImage;
+
+/// Default distance used by "find similar images" queries
+pub const DEFAULT_SIMILAR_DISTANCE: u32 = 10;
+
+/// Difference hash: 64 bits comparing horizontally adjacent pixels of a 9x8 grayscale
+pub fn dhash(img: &DynamicImage) -> u64 {
+    let small = img.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = small.get_pixel(x, y)[0];
+            let right = small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | u64::from(left > right);
+        }
+    }
+    hash
+}
+
+/// Number of differing bits (0 = same hash, 64 = opposite)
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    /// Horizontal gradient with a dark square, similar to a window screenshot
+    fn screenshot(square_x: u32) -> DynamicImage {
+        let mut img = RgbaImage::from_fn(320, 200, |x, _| {
+            let v = (x * 255 / 320) as u8;
+            Rgba([v, v, v, 255])
+        });
+        for y in 50..150 {
+            for x in square_x..square_x + 80 {
+                img.put_pixel(x, y, Rgba([0, 0, 0, 255]));
+            }
+        }
+        DynamicImage::ImageRgba8(img)
+    }
+
+    #[test]
+    fn test_one_pixel_difference_is_near_duplicate() {
+        let original = screenshot(100);
+        let mut changed = original.to_rgba8();
+        changed.put_pixel(10, 10, Rgba([255, 0, 0, 255]));
+
+        let distance =
+            hamming_distance(dhash(&original), dhash(&DynamicImage::ImageRgba8(changed)));
+        assert!(distance <= 2, "distance = {}", distance);
+    }
+
+    #[test]
+    fn test_resized_copy_is_similar() {
+        let original = screenshot(100);
+        let smaller = original.resize(160, 100, FilterType::Lanczos3);
+
+        let distance = hamming_distance(dhash(&original), dhash(&smaller));
+        assert!(
+            distance <= DEFAULT_SIMILAR_DISTANCE,
+            "distance = {}",
+            distance
+        );
+    }
+
+    #[test]
+    fn test_different_images_are_far_apart() {
+        let original = screenshot(100);
+        let distance = hamming_distance(dhash(&original), dhash(&original.fliph()));
+        assert!(
+            distance > DEFAULT_SIMILAR_DISTANCE,
+            "distance = {}",
+            distance
+        );
+    }
+
+    #[test]
+    fn test_hamming_distance() {
+        assert_eq!(hamming_distance(0, 0), 0);
+        assert_eq!(hamming_distance(0b1011, 0b0001), 2);
+        assert_eq!(hamming_distance(0, u64::MAX), 64);
+    }
+}
@@ -0,0 +1,194 @@
+//! QR code generation for text entries
+//!
+//! Used to move a copied URL or password to a phone: the popup shows the code
+//! in a window and `clippit-daemon --qr <id>` writes it as SVG.
+
+use anyhow::{anyhow, Result};
+use image::{GrayImage, ImageFormat, Luma};
+use qrcode::{Color, EcLevel, QrCode};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+
+/// Longest text turned into a QR code. Every level fits more (up to 2953 bytes
+/// with `Low`), but such codes are too dense to scan from a laptop screen.
+pub const MAX_QR_TEXT_BYTES: usize = 1024;
+
+/// Blank border around the code, in modules (required by the spec)
+const QUIET_ZONE: usize = 4;
+
+/// How much of the code can be damaged and still be read
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum QrEcLevel {
+    Low,
+    #[default]
+    Medium,
+    Quartile,
+    High,
+}
+
+impl QrEcLevel {
+    pub const ALL: [QrEcLevel; 4] = [
+        QrEcLevel::Low,
+        QrEcLevel::Medium,
+        QrEcLevel::Quartile,
+        QrEcLevel::High,
+    ];
+
+    /// Accepts the usual one-letter names ("L", "M", "Q", "H") and the full ones
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "l" | "low" => Some(QrEcLevel::Low),
+            "m" | "medium" => Some(QrEcLevel::Medium),
+            "q" | "quartile" => Some(QrEcLevel::Quartile),
+            "h" | "high" => Some(QrEcLevel::High),
+            _ => None,
+        }
+    }
+
+    /// Share of the code that can be recovered
+    pub fn recovery_percent(&self) -> u8 {
+        match self {
+            QrEcLevel::Low => 7,
+            QrEcLevel::Medium => 15,
+            QrEcLevel::Quartile => 25,
+            QrEcLevel::High => 30,
+        }
+    }
+
+    fn to_qrcode(self) -> EcLevel {
+        match self {
+            QrEcLevel::Low => EcLevel::L,
+            QrEcLevel::Medium => EcLevel::M,
+            QrEcLevel::Quartile => EcLevel::Q,
+            QrEcLevel::High => EcLevel::H,
+        }
+    }
+}
+
+/// Encoded QR code: a square of dark/light modules
+#[derive(Debug, Clone)]
+pub struct QrImage {
+    width: usize,
+    modules: Vec<bool>,
+}
+
+impl QrImage {
+    /// Encodes `text`, refusing texts longer than `MAX_QR_TEXT_BYTES`
+    pub fn generate(text: &str, level: QrEcLevel) -> Result<Self> {
+        if text.is_empty() {
+            return Err(anyhow!("Nothing to encode: the text is empty"));
+        }
+        if text.len() > MAX_QR_TEXT_BYTES {
+            return Err(anyhow!(
+                "Text too long for a QR code: {} bytes (max {})",
+                text.len(),
+                MAX_QR_TEXT_BYTES
+            ));
+        }
+
+        let code = QrCode::with_error_correction_level(text, level.to_qrcode())
+            .map_err(|e| anyhow!("Failed to encode QR code: {}", e))?;
+
+        Ok(Self {
+            width: code.width(),
+            modules: code
+                .to_colors()
+                .into_iter()
+                .map(|color| color == Color::Dark)
+                .collect(),
+        })
+    }
+
+    /// Modules per side, without the quiet zone
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.width + x]
+    }
+
+    /// Black on white PNG, `scale` pixels per module, with the quiet zone
+    pub fn to_png(&self, scale: u32) -> Result<Vec<u8>> {
+        let scale = scale.max(1);
+        let size = (self.width + 2 * QUIET_ZONE) as u32 * scale;
+
+        let image = GrayImage::from_fn(size, size, |x, y| {
+            let (mx, my) = ((x / scale) as usize, (y / scale) as usize);
+            let inside = (QUIET_ZONE..QUIET_ZONE + self.width).contains(&mx)
+                && (QUIET_ZONE..QUIET_ZONE + self.width).contains(&my);
+            if inside && self.is_dark(mx - QUIET_ZONE, my - QUIET_ZONE) {
+                Luma([0])
+            } else {
+                Luma([255])
+            }
+        });
+
+        let mut png = Vec::new();
+        image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+        Ok(png)
+    }
+
+    /// Scalable SVG, one unit per module, with the quiet zone
+    pub fn to_svg(&self) -> String {
+        let size = self.width + 2 * QUIET_ZONE;
+        let mut path = String::new();
+        for y in 0..self.width {
+            for x in 0..self.width {
+                if self.is_dark(x, y) {
+                    path.push_str(&format!("M{} {}h1v1h-1z", x + QUIET_ZONE, y + QUIET_ZONE));
+                }
+            }
+        }
+
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {size} {size}\" \
+             shape-rendering=\"crispEdges\">\
+             <rect width=\"{size}\" height=\"{size}\" fill=\"#fff\"/>\
+             <path d=\"{path}\" fill=\"#000\"/></svg>"
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_and_render() {
+        let qr = QrImage::generate("https://example.com", QrEcLevel::Medium).unwrap();
+        assert_eq!(qr.width(), 25); // Version 2
+
+        let png = qr.to_png(4).unwrap();
+        let image = image::load_from_memory(&png).unwrap();
+        assert_eq!(image.width(), (25 + 8) * 4);
+
+        let svg = qr.to_svg();
+        assert!(svg.starts_with("<svg") && svg.contains("viewBox=\"0 0 33 33\""));
+    }
+
+    #[test]
+    fn test_higher_level_needs_bigger_code() {
+        let text = "senha-super-secreta-123";
+        let low = QrImage::generate(text, QrEcLevel::Low).unwrap();
+        let high = QrImage::generate(text, QrEcLevel::High).unwrap();
+        assert!(high.width() > low.width());
+    }
+
+    #[test]
+    fn test_size_guard() {
+        let long = "a".repeat(MAX_QR_TEXT_BYTES + 1);
+        let err = QrImage::generate(&long, QrEcLevel::Low).unwrap_err();
+        assert!(err.to_string().contains("too long"));
+        assert!(QrImage::generate("", QrEcLevel::Low).is_err());
+        assert!(QrImage::generate(&"a".repeat(MAX_QR_TEXT_BYTES), QrEcLevel::High).is_ok());
+    }
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(QrEcLevel::parse("H"), Some(QrEcLevel::High));
+        assert_eq!(QrEcLevel::parse("quartile"), Some(QrEcLevel::Quartile));
+        assert_eq!(QrEcLevel::parse("x"), None);
+        assert_eq!(QrEcLevel::default(), QrEcLevel::Medium);
+    }
+}
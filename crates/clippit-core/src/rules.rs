@@ -0,0 +1,365 @@
+//! Capture rules engine
+//!
+//! `[[rules]]` in the config decide what happens to a new entry before it is
+//! saved: drop it (internal tokens), rewrite it (strip `utm_*` from URLs), tag
+//! it, expire it (one-time codes) or skip OCR. Rules run in order; a rewrite is
+//! seen by the rules after it, and a drop stops the evaluation.
+
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+
+use crate::classifier;
+use crate::config::CaptureRule;
+use crate::types::{ClipboardEntry, ContentType, TextKind};
+
+/// What a kind condition accepts
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum KindFilter {
+    Text,
+    Image,
+    TextKind(TextKind),
+}
+
+impl KindFilter {
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "text" => Some(KindFilter::Text),
+            "image" => Some(KindFilter::Image),
+            other => TextKind::parse(other).map(KindFilter::TextKind),
+        }
+    }
+
+    fn matches(&self, entry: &ClipboardEntry) -> bool {
+        match (self, &entry.content_type) {
+            (KindFilter::Text, ContentType::Text) => true,
+            (KindFilter::Image, ContentType::Image) => true,
+            (KindFilter::TextKind(kind), ContentType::Text) => entry.text_kind == Some(*kind),
+            _ => false,
+        }
+    }
+}
+
+/// A rule with its regex compiled and its kind parsed
+#[derive(Debug, Clone)]
+struct CompiledRule {
+    rule: CaptureRule,
+    pattern: Option<Regex>,
+    kind: Option<KindFilter>,
+}
+
+impl CompiledRule {
+    fn compile(rule: &CaptureRule) -> Result<Self> {
+        let pattern = rule
+            .pattern
+            .as_deref()
+            .map(Regex::new)
+            .transpose()
+            .with_context(|| format!("Rule '{}': invalid pattern", rule.name))?;
+        let kind = rule
+            .kind
+            .as_deref()
+            .map(|kind| {
+                KindFilter::parse(kind)
+                    .ok_or_else(|| anyhow!("Rule '{}': unknown kind '{}'", rule.name, kind))
+            })
+            .transpose()?;
+        if rule.rewrite.is_some() && pattern.is_none() {
+            return Err(anyhow!(
+                "Rule '{}': rewrite needs a pattern to replace",
+                rule.name
+            ));
+        }
+
+        Ok(Self {
+            rule: rule.clone(),
+            pattern,
+            kind,
+        })
+    }
+
+    fn matches(&self, entry: &ClipboardEntry) -> bool {
+        if let Some(pattern) = &self.pattern {
+            let Some(text) = text_of(entry) else {
+                return false;
+            };
+            if !pattern.is_match(text) {
+                return false;
+            }
+        }
+
+        if let Some(kind) = &self.kind {
+            if !kind.matches(entry) {
+                return false;
+            }
+        }
+
+        if let Some(app) = &self.rule.source_app {
+            let matches_app = entry
+                .source_app
+                .as_deref()
+                .is_some_and(|source| source.to_lowercase().contains(&app.to_lowercase()));
+            if !matches_app {
+                return false;
+            }
+        }
+
+        if self.rule.min_size.is_some() || self.rule.max_size.is_some() {
+            let size = entry_size(entry);
+            if self.rule.min_size.is_some_and(|min| size < min)
+                || self.rule.max_size.is_some_and(|max| size > max)
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Compiled `[[rules]]`, ready to run on every new entry
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    rules: Vec<CompiledRule>,
+}
+
+/// Everything the matching rules asked for
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RuleOutcome {
+    /// Names of the rules that fired, in order
+    pub matched: Vec<String>,
+    pub drop: bool,
+    /// New text when a rule rewrote it
+    pub rewritten_text: Option<String>,
+    pub tags: Vec<String>,
+    /// Shortest TTL among the matching rules
+    pub ttl_seconds: Option<u64>,
+    pub skip_ocr: bool,
+}
+
+impl RuleSet {
+    /// Fails on the first rule with an invalid regex or kind
+    pub fn compile(rules: &[CaptureRule]) -> Result<Self> {
+        Ok(Self {
+            rules: rules
+                .iter()
+                .filter(|rule| rule.enabled)
+                .map(CompiledRule::compile)
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Rules that would fire for `text` copied from `source_app`, without
+    /// saving anything (used by the dry-run IPC command)
+    pub fn dry_run(&self, text: &str, source_app: Option<&str>) -> RuleOutcome {
+        let mut entry = ClipboardEntry::new_text(text.to_string());
+        entry.text_kind = Some(classifier::classify(text).0);
+        entry.source_app = source_app.map(str::to_string);
+        self.apply(&mut entry)
+    }
+
+    /// Runs the rules on `entry`; rewrites are applied to `entry` itself
+    pub fn apply(&self, entry: &mut ClipboardEntry) -> RuleOutcome {
+        let mut outcome = RuleOutcome::default();
+
+        for compiled in &self.rules {
+            if !compiled.matches(entry) {
+                continue;
+            }
+            let rule = &compiled.rule;
+            outcome.matched.push(rule.name.clone());
+
+            if rule.drop {
+                outcome.drop = true;
+                break;
+            }
+
+            if let (Some(pattern), Some(replacement)) = (&compiled.pattern, &rule.rewrite) {
+                if let Some(text) = entry.content_text.take() {
+                    let rewritten = pattern.replace_all(&text, replacement.as_str()).to_string();
+                    if rewritten != text {
+                        outcome.rewritten_text = Some(rewritten.clone());
+                    }
+                    entry.content_text = Some(rewritten);
+                }
+            }
+
+            for tag in &rule.tags {
+                if !outcome.tags.contains(tag) {
+                    outcome.tags.push(tag.clone());
+                }
+            }
+
+            if let Some(ttl) = rule.ttl_seconds {
+                outcome.ttl_seconds = Some(outcome.ttl_seconds.map_or(ttl, |t| t.min(ttl)));
+            }
+
+            outcome.skip_ocr |= rule.skip_ocr;
+        }
+
+        outcome
+    }
+}
+
+fn text_of(entry: &ClipboardEntry) -> Option<&str> {
+    match entry.content_type {
+        ContentType::Text => entry.content_text.as_deref(),
+        ContentType::Image => None,
+    }
+}
+
+/// Text length, or image size (file on disk, or legacy inline data)
+fn entry_size(entry: &ClipboardEntry) -> usize {
+    match entry.content_type {
+        ContentType::Text => entry.content_text.as_ref().map_or(0, String::len),
+        ContentType::Image => entry
+            .content_data
+            .as_ref()
+            .map(Vec::len)
+            .or_else(|| {
+                let path = entry.image_path.as_ref()?;
+                Some(std::fs::metadata(path).ok()?.len() as usize)
+            })
+            .unwrap_or(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(toml_rules: &str) -> RuleSet {
+        #[derive(serde::Deserialize)]
+        struct Rules {
+            rules: Vec<CaptureRule>,
+        }
+        let parsed: Rules = toml::from_str(toml_rules).unwrap();
+        RuleSet::compile(&parsed.rules).unwrap()
+    }
+
+    fn text(value: &str) -> ClipboardEntry {
+        let mut entry = ClipboardEntry::new_text(value.to_string());
+        entry.text_kind = Some(classifier::classify(value).0);
+        entry
+    }
+
+    #[test]
+    fn test_drop_internal_tokens() {
+        let rules = rules(
+            r#"
+            [[rules]]
+            name = "internal token"
+            pattern = '^tok_[a-z0-9]{16}$'
+            drop = true
+
+            [[rules]]
+            name = "never reached"
+            tags = ["x"]
+            "#,
+        );
+
+        let outcome = rules.apply(&mut text("tok_abcdef0123456789"));
+        assert!(outcome.drop);
+        assert_eq!(outcome.matched, vec!["internal token"]);
+
+        let outcome = rules.apply(&mut text("tok_short"));
+        assert!(!outcome.drop);
+        assert_eq!(outcome.matched, vec!["never reached"]);
+    }
+
+    #[test]
+    fn test_rewrite_strips_utm_params() {
+        let rules = rules(
+            r#"
+            [[rules]]
+            name = "strip utm"
+            kind = "url"
+            pattern = '[?&]utm_[^&#]*'
+            rewrite = ""
+
+            [[rules]]
+            name = "tag links"
+            kind = "url"
+            tags = ["link"]
+            "#,
+        );
+
+        let mut entry = text("https://example.com/post?utm_source=x&utm_medium=y");
+        let outcome = rules.apply(&mut entry);
+        assert_eq!(
+            entry.content_text.as_deref(),
+            Some("https://example.com/post")
+        );
+        assert_eq!(
+            outcome.rewritten_text.as_deref(),
+            Some("https://example.com/post")
+        );
+        assert_eq!(outcome.tags, vec!["link"]);
+
+        // Not a URL: the kind condition fails
+        let mut entry = text("see ?utm_source=x in the docs");
+        let outcome = rules.apply(&mut entry);
+        assert!(outcome.matched.is_empty());
+        assert_eq!(
+            entry.content_text.as_deref(),
+            Some("see ?utm_source=x in the docs")
+        );
+    }
+
+    #[test]
+    fn test_ttl_size_and_app_conditions() {
+        let rules = rules(
+            r#"
+            [[rules]]
+            name = "otp"
+            pattern = '^\d{6}$'
+            ttl_seconds = 120
+
+            [[rules]]
+            name = "short numbers"
+            kind = "number"
+            max_size = 8
+            ttl_seconds = 600
+
+            [[rules]]
+            name = "terminal"
+            source_app = "Terminal"
+            skip_ocr = true
+            tags = ["term"]
+            "#,
+        );
+
+        let outcome = rules.apply(&mut text("123456"));
+        assert_eq!(outcome.matched, vec!["otp", "short numbers"]);
+        assert_eq!(outcome.ttl_seconds, Some(120));
+
+        let outcome = rules.apply(&mut text("1234567890"));
+        assert!(outcome.matched.is_empty());
+
+        let mut entry = text("ls -la");
+        entry.source_app = Some("gnome-terminal-server".to_string());
+        let outcome = rules.apply(&mut entry);
+        assert_eq!(outcome.matched, vec!["terminal"]);
+        assert!(outcome.skip_ocr);
+        assert_eq!(outcome.tags, vec!["term"]);
+    }
+
+    #[test]
+    fn test_invalid_rules_are_reported() {
+        let rule = |extra: &str| -> CaptureRule {
+            toml::from_str(&format!("name = \"bad\"\n{}", extra)).unwrap()
+        };
+
+        let err = RuleSet::compile(&[rule("pattern = '('")]).unwrap_err();
+        assert!(err.to_string().contains("Rule 'bad'"));
+        assert!(RuleSet::compile(&[rule("kind = \"video\"")]).is_err());
+        assert!(RuleSet::compile(&[rule("rewrite = \"x\"")]).is_err());
+
+        // Disabled rules are not compiled
+        let disabled = rule("enabled = false\npattern = '('");
+        assert!(RuleSet::compile(&[disabled]).unwrap().is_empty());
+    }
+}
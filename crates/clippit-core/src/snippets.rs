@@ -0,0 +1,210 @@
+//! Snippets library
+//!
+//! Recurring texts (signatures, templates) kept apart from the capture
+//! history: pruning and "clear history" never touch them. Bodies may contain
+//! placeholders, expanded when the snippet is pasted:
+//!
+//! - `{date}` / `{time}`: local date (`dd/mm/yyyy`) and time (`HH:MM`)
+//! - `{clipboard}`: text currently on the clipboard
+//! - `{cursor}`: where the caret should end up
+//!
+//! `{{` and `}}` produce literal braces; unknown placeholders are kept as-is.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Longest keyword accepted
+pub const MAX_KEYWORD_LEN: usize = 32;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Snippet {
+    pub id: i64,
+    pub title: String,
+    pub body: String,
+    /// Short unique word to find the snippet (e.g. `;sig`)
+    pub keyword: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Snippet {
+    pub fn new(title: &str, body: &str, keyword: Option<&str>) -> Result<Self> {
+        let now = Utc::now();
+        Ok(Self {
+            id: 0,
+            title: normalize_title(title)?,
+            body: body.to_string(),
+            keyword: normalize_keyword(keyword)?,
+            created_at: now,
+            updated_at: now,
+        })
+    }
+
+    /// Body with the placeholders filled in
+    pub fn expand(&self, context: &SnippetContext) -> ExpandedSnippet {
+        expand(&self.body, context)
+    }
+}
+
+/// Values for the placeholders
+#[derive(Debug, Clone)]
+pub struct SnippetContext {
+    pub now: DateTime<Local>,
+    pub clipboard: Option<String>,
+}
+
+impl SnippetContext {
+    pub fn now(clipboard: Option<String>) -> Self {
+        Self {
+            now: Local::now(),
+            clipboard,
+        }
+    }
+}
+
+/// Snippet text ready to paste
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpandedSnippet {
+    pub text: String,
+    /// Character index of the first `{cursor}`, if any
+    pub cursor: Option<usize>,
+}
+
+impl ExpandedSnippet {
+    /// Left-arrow presses that bring the caret from the end to `{cursor}`
+    pub fn chars_after_cursor(&self) -> usize {
+        self.cursor
+            .map_or(0, |cursor| self.text.chars().count().saturating_sub(cursor))
+    }
+}
+
+pub fn expand(body: &str, context: &SnippetContext) -> ExpandedSnippet {
+    let mut text = String::with_capacity(body.len());
+    let mut cursor = None;
+    let mut rest = body;
+
+    while let Some(brace) = rest.find(['{', '}']) {
+        text.push_str(&rest[..brace]);
+        rest = &rest[brace..];
+
+        if let Some(after) = rest.strip_prefix("{{") {
+            text.push('{');
+            rest = after;
+            continue;
+        }
+        if let Some(after) = rest.strip_prefix("}}") {
+            text.push('}');
+            rest = after;
+            continue;
+        }
+
+        // A name runs up to the next brace; no placeholder contains one
+        let name = rest.strip_prefix('{').and_then(|inner| {
+            let end = inner.find(['{', '}'])?;
+            inner[end..].starts_with('}').then(|| &inner[..end])
+        });
+        let value = match name {
+            Some("date") => Some(context.now.format("%d/%m/%Y").to_string()),
+            Some("time") => Some(context.now.format("%H:%M").to_string()),
+            Some("clipboard") => Some(context.clipboard.clone().unwrap_or_default()),
+            Some("cursor") => {
+                if cursor.is_none() {
+                    cursor = Some(text.chars().count());
+                }
+                Some(String::new())
+            }
+            _ => None,
+        };
+        match (name, value) {
+            (Some(name), Some(value)) => {
+                text.push_str(&value);
+                // Skip the braces and the name
+                rest = &rest[name.len() + 2..];
+            }
+            _ => {
+                text.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+    text.push_str(rest);
+
+    ExpandedSnippet { text, cursor }
+}
+
+fn normalize_title(title: &str) -> Result<String> {
+    let title = title.trim();
+    if title.is_empty() {
+        return Err(anyhow!("Snippet title is empty"));
+    }
+    Ok(title.to_string())
+}
+
+/// Empty keywords become None; keywords can't contain spaces
+fn normalize_keyword(keyword: Option<&str>) -> Result<Option<String>> {
+    let Some(keyword) = keyword.map(str::trim).filter(|k| !k.is_empty()) else {
+        return Ok(None);
+    };
+    if keyword.contains(char::is_whitespace) {
+        return Err(anyhow!("Snippet keyword can't contain spaces"));
+    }
+    if keyword.chars().count() > MAX_KEYWORD_LEN {
+        return Err(anyhow!(
+            "Snippet keyword is longer than {} characters",
+            MAX_KEYWORD_LEN
+        ));
+    }
+    Ok(Some(keyword.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn context() -> SnippetContext {
+        SnippetContext {
+            now: Local.with_ymd_and_hms(2024, 3, 9, 14, 5, 0).unwrap(),
+            clipboard: Some("PED-1234".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let expanded = expand("Pedido {clipboard} em {date} às {time}", &context());
+        assert_eq!(expanded.text, "Pedido PED-1234 em 09/03/2024 às 14:05");
+        assert_eq!(expanded.cursor, None);
+
+        let expanded = expand("Olá {cursor},\n\nAtenciosamente", &context());
+        assert_eq!(expanded.text, "Olá ,\n\nAtenciosamente");
+        assert_eq!(expanded.cursor, Some(4));
+        assert_eq!(expanded.chars_after_cursor(), 17);
+    }
+
+    #[test]
+    fn test_literal_braces_and_unknown_placeholders() {
+        let expanded = expand("fn x() {{ {name} }} {date", &context());
+        assert_eq!(expanded.text, "fn x() { {name} } {date");
+
+        let expanded = expand("}date} {x {time}} {{cursor}}", &context());
+        assert_eq!(expanded.text, "}date} {x 14:05} {cursor}");
+        assert_eq!(expanded.cursor, None);
+
+        let empty_clipboard = SnippetContext {
+            clipboard: None,
+            ..context()
+        };
+        assert_eq!(expand("[{clipboard}]", &empty_clipboard).text, "[]");
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(Snippet::new("  ", "body", None).is_err());
+        assert!(Snippet::new("Assinatura", "body", Some("; sig")).is_err());
+
+        let snippet = Snippet::new(" Assinatura ", "body", Some("  ")).unwrap();
+        assert_eq!(snippet.title, "Assinatura");
+        assert_eq!(snippet.keyword, None);
+    }
+}
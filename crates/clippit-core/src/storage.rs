@@ -1,7 +1,12 @@
-use crate::types::{ClipboardEntry, ContentType};
-use anyhow::Result;
+use crate::snippets::Snippet;
+use crate::types::{
+    BoundingBox, ClipboardEntry, ContentType, HistoryStats, OcrJob, OcrJobStatus, OcrQueueStats,
+    OcrWord, Tag, TextKind,
+};
+use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
 use std::path::Path;
 use std::time::Duration;
 
@@ -72,16 +77,136 @@ impl Storage {
         );
 
         // Migration: Add ocr_text column if it doesn't exist (for OCR feature)
+        let _ = self
+            .conn
+            .execute("ALTER TABLE clipboard_history ADD COLUMN ocr_text TEXT", []);
+
+        // Migration: Add mime_type column if it doesn't exist (original image format)
+        let _ = self.conn.execute(
+            "ALTER TABLE clipboard_history ADD COLUMN mime_type TEXT",
+            [],
+        );
+
+        // Migration: Add image_phash column if it doesn't exist (near-duplicate images)
+        let _ = self.conn.execute(
+            "ALTER TABLE clipboard_history ADD COLUMN image_phash INTEGER",
+            [],
+        );
+
+        // Migration: Add barcode_text column if it doesn't exist (QR/barcode payloads)
+        let _ = self.conn.execute(
+            "ALTER TABLE clipboard_history ADD COLUMN barcode_text TEXT",
+            [],
+        );
+
+        // Migration: Add text_kind/code_language columns if they don't exist (classifier)
+        let _ = self.conn.execute(
+            "ALTER TABLE clipboard_history ADD COLUMN text_kind TEXT",
+            [],
+        );
         let _ = self.conn.execute(
-            "ALTER TABLE clipboard_history ADD COLUMN ocr_text TEXT",
+            "ALTER TABLE clipboard_history ADD COLUMN code_language TEXT",
             [],
         );
 
-        // Check if FTS5 table has ocr_text column (migration)
+        // Migration: Add source_app/expires_at columns if they don't exist (capture rules)
+        let _ = self.conn.execute(
+            "ALTER TABLE clipboard_history ADD COLUMN source_app TEXT",
+            [],
+        );
+        let _ = self.conn.execute(
+            "ALTER TABLE clipboard_history ADD COLUMN expires_at TEXT",
+            [],
+        );
+
+        // Tags (named collections), linked many-to-many to entries
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS tags (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS entry_tags (
+                entry_id INTEGER NOT NULL,
+                tag_id INTEGER NOT NULL,
+                PRIMARY KEY (entry_id, tag_id)
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_entry_tags_tag ON entry_tags(tag_id)",
+            [],
+        )?;
+
+        // Snippets library: not part of the history, never pruned or cleared
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS snippets (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                title TEXT NOT NULL,
+                body TEXT NOT NULL,
+                keyword TEXT UNIQUE,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        // OCR job queue: one row per image, survives daemon restarts
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS ocr_jobs (
+                entry_id INTEGER PRIMARY KEY,
+                status TEXT NOT NULL,
+                attempts INTEGER NOT NULL DEFAULT 0,
+                error TEXT,
+                updated_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_ocr_jobs_status ON ocr_jobs(status)",
+            [],
+        )?;
+
+        // OCR word boxes, used to copy the text of a region of an image
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS ocr_words (
+                entry_id INTEGER NOT NULL,
+                position INTEGER NOT NULL,
+                text TEXT NOT NULL,
+                x INTEGER NOT NULL,
+                y INTEGER NOT NULL,
+                width INTEGER NOT NULL,
+                height INTEGER NOT NULL,
+                confidence REAL,
+                PRIMARY KEY (entry_id, position)
+            )",
+            [],
+        )?;
+
+        // OCR jobs, words and tags of deleted/pruned entries go away with them
+        self.conn
+            .execute("DROP TRIGGER IF EXISTS clipboard_history_ocr_ad", [])?;
+        self.conn.execute(
+            "CREATE TRIGGER clipboard_history_ocr_ad
+             AFTER DELETE ON clipboard_history BEGIN
+                 DELETE FROM ocr_jobs WHERE entry_id = old.id;
+                 DELETE FROM ocr_words WHERE entry_id = old.id;
+                 DELETE FROM entry_tags WHERE entry_id = old.id;
+             END",
+            [],
+        )?;
+
+        // Check if FTS5 table has the newest column, barcode_text (migration)
         let needs_fts_migration = self
             .conn
             .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('clipboard_history_fts') WHERE name='ocr_text'",
+                "SELECT COUNT(*) FROM pragma_table_info('clipboard_history_fts') WHERE name='barcode_text'",
                 [],
                 |row| row.get::<_, i64>(0),
             )
@@ -89,19 +214,27 @@ impl Storage {
             == 0;
 
         if needs_fts_migration {
-            // Drop and recreate FTS5 table with ocr_text
-            let _ = self.conn.execute("DROP TABLE IF EXISTS clipboard_history_fts", []);
-            let _ = self.conn.execute("DROP TRIGGER IF EXISTS clipboard_history_ai", []);
-            let _ = self.conn.execute("DROP TRIGGER IF EXISTS clipboard_history_au", []);
-            let _ = self.conn.execute("DROP TRIGGER IF EXISTS clipboard_history_ad", []);
+            // Drop and recreate FTS5 table with ocr_text and barcode_text
+            let _ = self
+                .conn
+                .execute("DROP TABLE IF EXISTS clipboard_history_fts", []);
+            let _ = self
+                .conn
+                .execute("DROP TRIGGER IF EXISTS clipboard_history_ai", []);
+            let _ = self
+                .conn
+                .execute("DROP TRIGGER IF EXISTS clipboard_history_au", []);
+            let _ = self
+                .conn
+                .execute("DROP TRIGGER IF EXISTS clipboard_history_ad", []);
         }
 
-        // Create FTS5 virtual table for fast text search (includes ocr_text)
+        // Create FTS5 virtual table for fast text search (includes ocr_text and barcode_text)
         // NOTE: NOT using external content to avoid trigger complexity and "malformed database" errors
         // Data is duplicated in FTS5 table, but this is the recommended approach for reliability
         let _ = self.conn.execute(
             "CREATE VIRTUAL TABLE IF NOT EXISTS clipboard_history_fts
-             USING fts5(content_text, ocr_text)",
+             USING fts5(content_text, ocr_text, barcode_text)",
             [],
         );
 
@@ -110,8 +243,9 @@ impl Storage {
         let _ = self.conn.execute(
             "CREATE TRIGGER IF NOT EXISTS clipboard_history_ai
              AFTER INSERT ON clipboard_history BEGIN
-                 INSERT INTO clipboard_history_fts(rowid, content_text, ocr_text)
-                 VALUES (new.id, COALESCE(new.content_text, ''), COALESCE(new.ocr_text, ''));
+                 INSERT INTO clipboard_history_fts(rowid, content_text, ocr_text, barcode_text)
+                 VALUES (new.id, COALESCE(new.content_text, ''), COALESCE(new.ocr_text, ''),
+                         COALESCE(new.barcode_text, ''));
              END",
             [],
         );
@@ -123,8 +257,9 @@ impl Storage {
             "CREATE TRIGGER IF NOT EXISTS clipboard_history_au
              AFTER UPDATE ON clipboard_history BEGIN
                  DELETE FROM clipboard_history_fts WHERE rowid = old.id;
-                 INSERT INTO clipboard_history_fts(rowid, content_text, ocr_text)
-                 VALUES (new.id, COALESCE(new.content_text, ''), COALESCE(new.ocr_text, ''));
+                 INSERT INTO clipboard_history_fts(rowid, content_text, ocr_text, barcode_text)
+                 VALUES (new.id, COALESCE(new.content_text, ''), COALESCE(new.ocr_text, ''),
+                         COALESCE(new.barcode_text, ''));
              END",
             [],
         );
@@ -154,9 +289,12 @@ impl Storage {
             if fts_cnt == 0 && main_cnt > 0 {
                 // Rebuild FTS index from existing data (includes ocr_text)
                 let _ = self.conn.execute(
-                    "INSERT INTO clipboard_history_fts(rowid, content_text, ocr_text)
-                     SELECT id, content_text, ocr_text FROM clipboard_history 
-                     WHERE content_text IS NOT NULL OR ocr_text IS NOT NULL",
+                    "INSERT INTO clipboard_history_fts(rowid, content_text, ocr_text, barcode_text)
+                     SELECT id, COALESCE(content_text, ''), COALESCE(ocr_text, ''),
+                            COALESCE(barcode_text, '')
+                     FROM clipboard_history
+                     WHERE content_text IS NOT NULL OR ocr_text IS NOT NULL
+                        OR barcode_text IS NOT NULL",
                     [],
                 );
             }
@@ -174,8 +312,8 @@ impl Storage {
         let timestamp = entry.timestamp.to_rfc3339();
 
         self.conn.execute(
-            "INSERT INTO clipboard_history (content_type, content_text, content_data, image_path, thumbnail_data, image_width, image_height, ocr_text, timestamp)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            "INSERT INTO clipboard_history (content_type, content_text, content_data, image_path, thumbnail_data, image_width, image_height, ocr_text, timestamp, mime_type, image_phash, barcode_text, text_kind, code_language, source_app, expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
             params![
                 content_type_str,
                 entry.content_text,
@@ -186,46 +324,39 @@ impl Storage {
                 entry.image_height,
                 entry.ocr_text,
                 timestamp,
+                entry.mime_type,
+                // SQLite integers are signed; the bits are stored as-is
+                entry.image_phash.map(|hash| hash as i64),
+                entry.barcode_text,
+                entry.text_kind.map(|kind| kind.as_str()),
+                entry.code_language,
+                entry.source_app,
+                entry.expires_at.map(|at| at.to_rfc3339()),
             ],
         )?;
 
         Ok(self.conn.last_insert_rowid())
     }
 
+    /// Whether some entry points to this image file
+    pub fn is_image_path_used(&self, image_path: &str) -> Result<bool> {
+        let used = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM clipboard_history WHERE image_path = ?1)",
+            [image_path],
+            |row| row.get(0),
+        )?;
+        Ok(used)
+    }
+
     pub fn get_recent(&self, limit: usize) -> Result<Vec<ClipboardEntry>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, content_type, content_text, content_data, image_path, thumbnail_data, image_width, image_height, ocr_text, timestamp
+            "SELECT id, content_type, content_text, content_data, image_path, thumbnail_data, image_width, image_height, ocr_text, timestamp, mime_type, image_phash, barcode_text, text_kind, code_language, source_app, expires_at
              FROM clipboard_history
              ORDER BY timestamp DESC
              LIMIT ?1",
         )?;
 
-        let entries = stmt.query_map([limit], |row| {
-            let content_type_str: String = row.get(1)?;
-            let content_type = match content_type_str.as_str() {
-                "text" => ContentType::Text,
-                "image" => ContentType::Image,
-                _ => ContentType::Text,
-            };
-
-            let timestamp_str: String = row.get(9)?;
-            let timestamp = DateTime::parse_from_rfc3339(&timestamp_str)
-                .unwrap_or_else(|_| Utc::now().into())
-                .with_timezone(&Utc);
-
-            Ok(ClipboardEntry {
-                id: row.get(0)?,
-                content_type,
-                content_text: row.get(2)?,
-                content_data: row.get(3)?,
-                image_path: row.get(4)?,
-                thumbnail_data: row.get(5)?,
-                image_width: row.get(6)?,
-                image_height: row.get(7)?,
-                ocr_text: row.get(8)?,
-                timestamp,
-            })
-        })?;
+        let entries = stmt.query_map([limit], entry_from_row)?;
 
         let mut result = Vec::new();
         for entry in entries {
@@ -249,38 +380,20 @@ impl Storage {
                     image_width,
                     image_height,
                     ocr_text,
-                    timestamp
+                    timestamp,
+                    mime_type,
+                    image_phash,
+                    barcode_text,
+                    text_kind,
+                    code_language,
+                    source_app,
+                    expires_at
              FROM clipboard_history
              ORDER BY timestamp DESC
              LIMIT ?1",
         )?;
 
-        let entries = stmt.query_map([limit], |row| {
-            let content_type_str: String = row.get(1)?;
-            let content_type = match content_type_str.as_str() {
-                "text" => ContentType::Text,
-                "image" => ContentType::Image,
-                _ => ContentType::Text,
-            };
-
-            let timestamp_str: String = row.get(9)?;
-            let timestamp = DateTime::parse_from_rfc3339(&timestamp_str)
-                .unwrap_or_else(|_| Utc::now().into())
-                .with_timezone(&Utc);
-
-            Ok(ClipboardEntry {
-                id: row.get(0)?,
-                content_type,
-                content_text: row.get(2)?,
-                content_data: row.get(3)?, // Will be None for images due to CASE statement
-                image_path: row.get(4)?,
-                thumbnail_data: row.get(5)?, // Contains thumbnail for images
-                image_width: row.get(6)?,
-                image_height: row.get(7)?,
-                ocr_text: row.get(8)?,
-                timestamp,
-            })
-        })?;
+        let entries = stmt.query_map([limit], entry_from_row)?;
 
         let mut result = Vec::new();
         for entry in entries {
@@ -307,38 +420,20 @@ impl Storage {
                     image_width,
                     image_height,
                     ocr_text,
-                    timestamp
+                    timestamp,
+                    mime_type,
+                    image_phash,
+                    barcode_text,
+                    text_kind,
+                    code_language,
+                    source_app,
+                    expires_at
              FROM clipboard_history
              ORDER BY timestamp DESC
              LIMIT ?1 OFFSET ?2",
         )?;
 
-        let entries = stmt.query_map([limit, offset], |row| {
-            let content_type_str: String = row.get(1)?;
-            let content_type = match content_type_str.as_str() {
-                "text" => ContentType::Text,
-                "image" => ContentType::Image,
-                _ => ContentType::Text,
-            };
-
-            let timestamp_str: String = row.get(9)?;
-            let timestamp = DateTime::parse_from_rfc3339(&timestamp_str)
-                .unwrap_or_else(|_| Utc::now().into())
-                .with_timezone(&Utc);
-
-            Ok(ClipboardEntry {
-                id: row.get(0)?,
-                content_type,
-                content_text: row.get(2)?,
-                content_data: row.get(3)?,
-                image_path: row.get(4)?,
-                thumbnail_data: row.get(5)?,
-                image_width: row.get(6)?,
-                image_height: row.get(7)?,
-                ocr_text: row.get(8)?,
-                timestamp,
-            })
-        })?;
+        let entries = stmt.query_map([limit, offset], entry_from_row)?;
 
         let mut result = Vec::new();
         for entry in entries {
@@ -350,7 +445,7 @@ impl Storage {
 
     pub fn get_by_id(&self, id: i64) -> Result<Option<ClipboardEntry>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, content_type, content_text, content_data, image_path, thumbnail_data, image_width, image_height, ocr_text, timestamp
+            "SELECT id, content_type, content_text, content_data, image_path, thumbnail_data, image_width, image_height, ocr_text, timestamp, mime_type, image_phash, barcode_text, text_kind, code_language, source_app, expires_at
              FROM clipboard_history
              WHERE id = ?1",
         )?;
@@ -358,30 +453,7 @@ impl Storage {
         let mut rows = stmt.query([id])?;
 
         if let Some(row) = rows.next()? {
-            let content_type_str: String = row.get(1)?;
-            let content_type = match content_type_str.as_str() {
-                "text" => ContentType::Text,
-                "image" => ContentType::Image,
-                _ => ContentType::Text,
-            };
-
-            let timestamp_str: String = row.get(9)?;
-            let timestamp = DateTime::parse_from_rfc3339(&timestamp_str)
-                .unwrap_or_else(|_| Utc::now().into())
-                .with_timezone(&Utc);
-
-            Ok(Some(ClipboardEntry {
-                id: row.get(0)?,
-                content_type,
-                content_text: row.get(2)?,
-                content_data: row.get(3)?,
-                image_path: row.get(4)?,
-                thumbnail_data: row.get(5)?,
-                image_width: row.get(6)?,
-                image_height: row.get(7)?,
-                ocr_text: row.get(8)?,
-                timestamp,
-            }))
+            Ok(Some(entry_from_row(row)?))
         } else {
             Ok(None)
         }
@@ -397,42 +469,60 @@ impl Storage {
     }
 
     pub fn prune_old(&self, keep_count: usize) -> Result<usize> {
+        Ok(self.prune_old_ids(keep_count)?.len())
+    }
+
+    /// Deletes the oldest entries beyond `keep_count`, returning their ids
+    pub fn prune_old_ids(&self, keep_count: usize) -> Result<Vec<i64>> {
         let count = self.count()?;
 
         if count <= keep_count {
-            return Ok(0);
+            return Ok(Vec::new());
         }
 
         let to_delete = count - keep_count;
 
-        let deleted = self.conn.execute(
-            "DELETE FROM clipboard_history
-             WHERE id IN (
-                 SELECT id FROM clipboard_history
-                 ORDER BY timestamp ASC
-                 LIMIT ?1
-             )",
-            [to_delete],
+        let mut stmt = self.conn.prepare(
+            "SELECT id FROM clipboard_history
+             ORDER BY timestamp ASC
+             LIMIT ?1",
         )?;
+        let ids = stmt
+            .query_map([to_delete], |row| row.get::<_, i64>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
 
-        Ok(deleted)
+        for id in &ids {
+            self.conn
+                .execute("DELETE FROM clipboard_history WHERE id = ?1", [id])?;
+        }
+
+        Ok(ids)
     }
 
     pub fn delete_by_id(&self, id: i64) -> Result<bool> {
         // First, get the entry to check if it has an image file to delete
-        if let Some(entry) = self.get_by_id(id)? {
-            if let Some(image_path) = entry.image_path {
-                // Try to delete the image file (ignore errors if file doesn't exist)
-                let _ = std::fs::remove_file(&image_path);
-            }
-        }
+        let image_path = self.get_by_id(id)?.and_then(|entry| entry.image_path);
 
         let deleted = self
             .conn
             .execute("DELETE FROM clipboard_history WHERE id = ?1", [id])?;
+        if let Some(image_path) = image_path {
+            self.remove_image_if_unused(&image_path)?;
+        }
         Ok(deleted > 0)
     }
 
+    /// Deletes an image file once no entry points to it anymore
+    ///
+    /// Files are named by content hash, so duplicates can share one.
+    fn remove_image_if_unused(&self, image_path: &str) -> Result<()> {
+        if !self.is_image_path_used(image_path)? {
+            // Ignore errors if the file doesn't exist
+            let _ = std::fs::remove_file(image_path);
+        }
+        Ok(())
+    }
+
     pub fn clear(&self) -> Result<usize> {
         // Get all image paths before deleting
         let entries = self.get_recent(10000)?; // Get all entries (max 10k)
@@ -448,6 +538,71 @@ impl Storage {
         Ok(deleted)
     }
 
+    /// Replaces the text of a text entry; false when there is no such entry
+    pub fn update_text(&self, id: i64, text: &str) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE clipboard_history SET content_text = ?1
+             WHERE id = ?2 AND content_type = 'text'",
+            params![text, id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    pub fn stats(&self) -> Result<HistoryStats> {
+        let (total, texts, images, oldest, newest) = self.conn.query_row(
+            "SELECT COUNT(*),
+                    COALESCE(SUM(content_type = 'text'), 0),
+                    COALESCE(SUM(content_type = 'image'), 0),
+                    MIN(timestamp),
+                    MAX(timestamp)
+             FROM clipboard_history",
+            [],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                ))
+            },
+        )?;
+        let tag_count: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM tags", [], |row| row.get(0))?;
+        let database_bytes: i64 = self.conn.query_row(
+            "SELECT (page_count - freelist_count) * page_size
+             FROM pragma_page_count(), pragma_freelist_count(), pragma_page_size()",
+            [],
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT image_path FROM clipboard_history WHERE image_path IS NOT NULL")?;
+        let image_bytes = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .filter_map(|path| std::fs::metadata(path.ok()?).ok())
+            .map(|metadata| metadata.len())
+            .sum();
+
+        let parse = |timestamp: Option<String>| {
+            timestamp
+                .and_then(|t| DateTime::parse_from_rfc3339(&t).ok())
+                .map(|t| t.with_timezone(&Utc))
+        };
+        Ok(HistoryStats {
+            total_entries: total as usize,
+            text_entries: texts as usize,
+            image_entries: images as usize,
+            tag_count: tag_count as usize,
+            database_bytes: database_bytes.max(0) as u64,
+            image_bytes,
+            oldest: parse(oldest),
+            newest: parse(newest),
+        })
+    }
+
     /// Search in ALL history entries (no limit) - metadata only for images
     /// Uses FTS5 for fast text search when possible
     pub fn search(&self, query: &str) -> Result<Vec<ClipboardEntry>> {
@@ -458,7 +613,7 @@ impl Storage {
         if use_fts {
             // Fast FTS5 search with prefix matching
             // Add * to each word for prefix search: "lingua" → "lingua*"
-            // FTS5 will search in ALL columns (content_text AND ocr_text)
+            // FTS5 will search in ALL columns (content_text, ocr_text and barcode_text)
             let fts_query = query
                 .split_whitespace()
                 .map(|word| format!("{}*", word))
@@ -476,7 +631,14 @@ impl Storage {
                         h.image_width,
                         h.image_height,
                         h.ocr_text,
-                        h.timestamp
+                        h.timestamp,
+                        h.mime_type,
+                        h.image_phash,
+                        h.barcode_text,
+                        h.text_kind,
+                        h.code_language,
+                        h.source_app,
+                        h.expires_at
                  FROM clipboard_history h
                  INNER JOIN clipboard_history_fts fts ON h.id = fts.rowid
                  WHERE clipboard_history_fts MATCH ?1
@@ -484,32 +646,7 @@ impl Storage {
             )?;
 
             let entries = stmt
-                .query_map(params![&fts_query], |row| {
-                    let content_type_str: String = row.get(1)?;
-                    let content_type = match content_type_str.as_str() {
-                        "text" => ContentType::Text,
-                        "image" => ContentType::Image,
-                        _ => ContentType::Text,
-                    };
-
-                    let timestamp_str: String = row.get(9)?;
-                    let timestamp = DateTime::parse_from_rfc3339(&timestamp_str)
-                        .unwrap_or_else(|_| Utc::now().into())
-                        .with_timezone(&Utc);
-
-                    Ok(ClipboardEntry {
-                        id: row.get(0)?,
-                        content_type,
-                        content_text: row.get(2)?,
-                        content_data: row.get(3)?,
-                        image_path: row.get(4)?,
-                        thumbnail_data: row.get(5)?,
-                        image_width: row.get(6)?,
-                        image_height: row.get(7)?,
-                        ocr_text: row.get(8)?,
-                        timestamp,
-                    })
-                })?
+                .query_map(params![&fts_query], entry_from_row)?
                 .collect::<Result<Vec<_>, _>>()?;
 
             Ok(entries)
@@ -528,47 +665,594 @@ impl Storage {
                         image_width,
                         image_height,
                         ocr_text,
-                        timestamp
+                        timestamp,
+                        mime_type,
+                        image_phash,
+                        barcode_text,
+                        text_kind,
+                        code_language,
+                        source_app,
+                        expires_at
                  FROM clipboard_history
                  WHERE content_text LIKE ?1
                     OR image_path LIKE ?1
                     OR ocr_text LIKE ?1
+                    OR barcode_text LIKE ?1
                  ORDER BY timestamp DESC",
             )?;
 
             let entries = stmt
-                .query_map([&search_pattern], |row| {
-                    let content_type_str: String = row.get(1)?;
-                    let content_type = match content_type_str.as_str() {
-                        "text" => ContentType::Text,
-                        "image" => ContentType::Image,
-                        _ => ContentType::Text,
-                    };
-
-                    let timestamp_str: String = row.get(9)?;
-                    let timestamp = DateTime::parse_from_rfc3339(&timestamp_str)
-                        .unwrap_or_else(|_| Utc::now().into())
-                        .with_timezone(&Utc);
-
-                    Ok(ClipboardEntry {
-                        id: row.get(0)?,
-                        content_type,
-                        content_text: row.get(2)?,
-                        content_data: row.get(3)?, // Will be None for images due to CASE
-                        image_path: row.get(4)?,
-                        thumbnail_data: row.get(5)?,
-                        image_width: row.get(6)?,
-                        image_height: row.get(7)?,
-                        ocr_text: row.get(8)?,
-                        timestamp,
-                    })
-                })?
+                .query_map([&search_pattern], entry_from_row)?
                 .collect::<Result<Vec<_>, _>>()?;
 
             Ok(entries)
         }
     }
 
+    /// Perceptual hashes of all images that have one, newest first
+    pub fn get_image_hashes(&self) -> Result<Vec<(i64, u64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, image_phash FROM clipboard_history
+             WHERE content_type = 'image' AND image_phash IS NOT NULL
+             ORDER BY timestamp DESC",
+        )?;
+
+        let hashes = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)? as u64))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(hashes)
+    }
+
+    /// Adds an image to the OCR queue; returns false if it already has a job
+    pub fn enqueue_ocr_job(&self, entry_id: i64) -> Result<bool> {
+        let inserted = self.conn.execute(
+            "INSERT OR IGNORE INTO ocr_jobs (entry_id, status, updated_at)
+             VALUES (?1, ?2, ?3)",
+            params![
+                entry_id,
+                OcrJobStatus::Pending.as_str(),
+                Utc::now().to_rfc3339()
+            ],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// Queues every image without OCR text that has no job yet
+    ///
+    /// SVGs are left out: their text is in the document, and the OCR engines
+    /// only read raster images. With `include_failed`, failed jobs are retried
+    /// as well.
+    pub fn enqueue_ocr_backfill(&self, include_failed: bool) -> Result<usize> {
+        let now = Utc::now().to_rfc3339();
+        let mut queued = self.conn.execute(
+            "INSERT OR IGNORE INTO ocr_jobs (entry_id, status, updated_at)
+             SELECT id, ?1, ?2 FROM clipboard_history
+             WHERE content_type = 'image' AND image_path IS NOT NULL
+               AND (mime_type IS NULL OR mime_type != 'image/svg+xml')
+               AND (ocr_text IS NULL OR ocr_text = '')",
+            params![OcrJobStatus::Pending.as_str(), now],
+        )?;
+
+        if include_failed {
+            queued += self.conn.execute(
+                "UPDATE ocr_jobs SET status = ?1, error = NULL, updated_at = ?2
+                 WHERE status = ?3
+                   AND entry_id NOT IN (
+                       SELECT id FROM clipboard_history WHERE mime_type = 'image/svg+xml'
+                   )",
+                params![
+                    OcrJobStatus::Pending.as_str(),
+                    now,
+                    OcrJobStatus::Failed.as_str()
+                ],
+            )?;
+        }
+
+        Ok(queued)
+    }
+
+    /// Oldest pending jobs first
+    pub fn pending_ocr_jobs(&self, limit: usize) -> Result<Vec<OcrJob>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT j.entry_id, h.image_path, j.attempts
+             FROM ocr_jobs j JOIN clipboard_history h ON h.id = j.entry_id
+             WHERE j.status = ?1 AND h.image_path IS NOT NULL
+             ORDER BY j.entry_id ASC
+             LIMIT ?2",
+        )?;
+
+        let jobs = stmt
+            .query_map(params![OcrJobStatus::Pending.as_str(), limit], |row| {
+                Ok(OcrJob {
+                    entry_id: row.get(0)?,
+                    image_path: row.get(1)?,
+                    attempts: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(jobs)
+    }
+
+    /// Moves a job to `status`; starting a job counts as an attempt
+    pub fn set_ocr_job_status(
+        &self,
+        entry_id: i64,
+        status: OcrJobStatus,
+        error: Option<&str>,
+    ) -> Result<()> {
+        let attempt = i64::from(status == OcrJobStatus::Running);
+        self.conn.execute(
+            "UPDATE ocr_jobs
+             SET status = ?1, error = ?2, attempts = attempts + ?3, updated_at = ?4
+             WHERE entry_id = ?5",
+            params![
+                status.as_str(),
+                error,
+                attempt,
+                Utc::now().to_rfc3339(),
+                entry_id
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_ocr_job_status(&self, entry_id: i64) -> Result<Option<OcrJobStatus>> {
+        let status = self
+            .conn
+            .query_row(
+                "SELECT status FROM ocr_jobs WHERE entry_id = ?1",
+                [entry_id],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+        Ok(status.and_then(|s| OcrJobStatus::parse(&s)))
+    }
+
+    /// Jobs left running by a daemon that stopped mid-OCR go back to pending
+    pub fn reset_running_ocr_jobs(&self) -> Result<usize> {
+        let reset = self.conn.execute(
+            "UPDATE ocr_jobs SET status = ?1 WHERE status = ?2",
+            params![
+                OcrJobStatus::Pending.as_str(),
+                OcrJobStatus::Running.as_str()
+            ],
+        )?;
+        Ok(reset)
+    }
+
+    pub fn ocr_queue_stats(&self) -> Result<OcrQueueStats> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT status, COUNT(*) FROM ocr_jobs GROUP BY status")?;
+        let mut stats = OcrQueueStats::default();
+
+        for row in stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize))
+        })? {
+            let (status, count) = row?;
+            match OcrJobStatus::parse(&status) {
+                Some(OcrJobStatus::Pending) => stats.pending = count,
+                Some(OcrJobStatus::Running) => stats.running = count,
+                Some(OcrJobStatus::Done) => stats.done = count,
+                Some(OcrJobStatus::Failed) => stats.failed = count,
+                Some(OcrJobStatus::Skipped) | None => {}
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// Replaces the OCR word boxes of an entry
+    pub fn save_ocr_words(&self, entry_id: i64, words: &[OcrWord]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM ocr_words WHERE entry_id = ?1", [entry_id])?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO ocr_words
+                 (entry_id, position, text, x, y, width, height, confidence)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for (position, word) in words.iter().enumerate() {
+                stmt.execute(params![
+                    entry_id,
+                    position as i64,
+                    word.text,
+                    word.bbox.x,
+                    word.bbox.y,
+                    word.bbox.width,
+                    word.bbox.height,
+                    word.confidence,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn get_ocr_words(&self, entry_id: i64) -> Result<Vec<OcrWord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT text, x, y, width, height, confidence FROM ocr_words
+             WHERE entry_id = ?1 ORDER BY position ASC",
+        )?;
+
+        let words = stmt
+            .query_map([entry_id], |row| {
+                Ok(OcrWord {
+                    text: row.get(0)?,
+                    bbox: BoundingBox {
+                        x: row.get(1)?,
+                        y: row.get(2)?,
+                        width: row.get(3)?,
+                        height: row.get(4)?,
+                    },
+                    confidence: row.get(5)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(words)
+    }
+
+    /// Stores the decoded QR/barcode payloads of an image
+    pub fn update_barcode_text(&self, id: i64, barcode_text: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE clipboard_history SET barcode_text = ?1 WHERE id = ?2",
+            params![barcode_text, id],
+        )?;
+        Ok(())
+    }
+
+    /// Stores the classifier result of a text entry
+    pub fn update_text_kind(
+        &self,
+        id: i64,
+        kind: TextKind,
+        code_language: Option<&str>,
+    ) -> Result<()> {
+        self.conn.execute(
+            "UPDATE clipboard_history SET text_kind = ?1, code_language = ?2 WHERE id = ?3",
+            params![kind.as_str(), code_language, id],
+        )?;
+        Ok(())
+    }
+
+    /// Text entries saved before the classifier existed: (id, text)
+    pub fn unclassified_text_entries(&self) -> Result<Vec<(i64, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, content_text FROM clipboard_history
+             WHERE content_type = 'text' AND text_kind IS NULL AND content_text IS NOT NULL",
+        )?;
+
+        let entries = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(entries)
+    }
+
+    /// All entries of the given kinds, newest first (metadata only for images)
+    pub fn get_by_text_kinds(&self, kinds: &[TextKind]) -> Result<Vec<ClipboardEntry>> {
+        if kinds.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let placeholders = (1..=kinds.len())
+            .map(|i| format!("?{}", i))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, content_type, content_text, content_data, image_path, thumbnail_data, image_width, image_height, ocr_text, timestamp, mime_type, image_phash, barcode_text, text_kind, code_language, source_app, expires_at
+             FROM clipboard_history
+             WHERE text_kind IN ({})
+             ORDER BY timestamp DESC",
+            placeholders
+        ))?;
+
+        let kinds: Vec<&str> = kinds.iter().map(|kind| kind.as_str()).collect();
+        let entries = stmt
+            .query_map(rusqlite::params_from_iter(kinds), entry_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(entries)
+    }
+
+    /// Marks an image so OCR never runs on it (queue and backfill skip it)
+    pub fn skip_ocr_job(&self, entry_id: i64, reason: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO ocr_jobs (entry_id, status, attempts, error, updated_at)
+             VALUES (?1, ?2, 0, ?3, ?4)",
+            params![
+                entry_id,
+                OcrJobStatus::Skipped.as_str(),
+                reason,
+                Utc::now().to_rfc3339()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Id of the tag called `name` (case-insensitive), created if needed
+    pub fn ensure_tag(&self, name: &str) -> Result<i64> {
+        let name = Tag::normalize_name(name)?;
+        self.conn.execute(
+            "INSERT OR IGNORE INTO tags (name, created_at) VALUES (?1, ?2)",
+            params![name, Utc::now().to_rfc3339()],
+        )?;
+        let id = self
+            .conn
+            .query_row("SELECT id FROM tags WHERE name = ?1", [&name], |row| {
+                row.get(0)
+            })?;
+        Ok(id)
+    }
+
+    /// All tags with how many entries they have, sorted by name
+    pub fn list_tags(&self) -> Result<Vec<Tag>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.id, t.name, COUNT(et.entry_id)
+             FROM tags t
+             LEFT JOIN entry_tags et ON et.tag_id = t.id
+             GROUP BY t.id
+             ORDER BY t.name",
+        )?;
+        let tags = stmt
+            .query_map([], |row| {
+                Ok(Tag {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    entry_count: row.get::<_, i64>(2)? as usize,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(tags)
+    }
+
+    /// Renames a tag; fails when another tag already has that name
+    pub fn rename_tag(&self, id: i64, name: &str) -> Result<bool> {
+        let name = Tag::normalize_name(name)?;
+        let existing: Option<i64> = self
+            .conn
+            .query_row("SELECT id FROM tags WHERE name = ?1", [&name], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        if existing.is_some_and(|existing| existing != id) {
+            return Err(anyhow!("Tag '{}' already exists", name));
+        }
+
+        let updated = self
+            .conn
+            .execute("UPDATE tags SET name = ?1 WHERE id = ?2", params![name, id])?;
+        Ok(updated > 0)
+    }
+
+    /// Deletes a tag; its entries stay in the history
+    pub fn delete_tag(&self, id: i64) -> Result<bool> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM entry_tags WHERE tag_id = ?1", [id])?;
+        let deleted = tx.execute("DELETE FROM tags WHERE id = ?1", [id])?;
+        tx.commit()?;
+        Ok(deleted > 0)
+    }
+
+    /// Adds tags to an entry, creating the ones that don't exist (existing
+    /// links are kept)
+    pub fn add_tags(&self, entry_id: i64, tags: &[String]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for tag in tags {
+            let tag_id = self.ensure_tag(tag)?;
+            tx.execute(
+                "INSERT OR IGNORE INTO entry_tags (entry_id, tag_id) VALUES (?1, ?2)",
+                params![entry_id, tag_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Removes a tag from an entry (the tag itself is kept)
+    pub fn remove_tag(&self, entry_id: i64, tag: &str) -> Result<bool> {
+        let removed = self.conn.execute(
+            "DELETE FROM entry_tags
+             WHERE entry_id = ?1 AND tag_id = (SELECT id FROM tags WHERE name = ?2)",
+            params![entry_id, tag.trim()],
+        )?;
+        Ok(removed > 0)
+    }
+
+    /// Replaces all tags of an entry
+    pub fn set_tags(&self, entry_id: i64, tags: &[String]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM entry_tags WHERE entry_id = ?1", [entry_id])?;
+        for tag in tags {
+            let tag_id = self.ensure_tag(tag)?;
+            tx.execute(
+                "INSERT OR IGNORE INTO entry_tags (entry_id, tag_id) VALUES (?1, ?2)",
+                params![entry_id, tag_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Tags of an entry, sorted
+    pub fn get_tags(&self, entry_id: i64) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.name FROM entry_tags et
+             INNER JOIN tags t ON t.id = et.tag_id
+             WHERE et.entry_id = ?1
+             ORDER BY t.name",
+        )?;
+        let tags = stmt
+            .query_map([entry_id], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(tags)
+    }
+
+    /// Tags of the given entries, each list sorted (one query per 500 ids);
+    /// untagged entries are left out
+    pub fn tags_of_entries(&self, entry_ids: &[i64]) -> Result<HashMap<i64, Vec<String>>> {
+        let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+        for ids in entry_ids.chunks(500) {
+            let placeholders = (1..=ids.len())
+                .map(|i| format!("?{}", i))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let mut stmt = self.conn.prepare(&format!(
+                "SELECT et.entry_id, t.name FROM entry_tags et
+                 INNER JOIN tags t ON t.id = et.tag_id
+                 WHERE et.entry_id IN ({})
+                 ORDER BY t.name",
+                placeholders
+            ))?;
+            let rows = stmt.query_map(rusqlite::params_from_iter(ids), |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?;
+            for row in rows {
+                let (entry_id, name) = row?;
+                tags.entry(entry_id).or_default().push(name);
+            }
+        }
+        Ok(tags)
+    }
+
+    /// Entries that have every one of `tags`, newest first (metadata only
+    /// for images)
+    pub fn get_by_tags(&self, tags: &[String]) -> Result<Vec<ClipboardEntry>> {
+        if tags.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let placeholders = (1..=tags.len())
+            .map(|i| format!("?{}", i))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, content_type, content_text,
+                    CASE
+                        WHEN content_type = 'image' THEN NULL
+                        ELSE content_data
+                    END as content_data,
+                    image_path, thumbnail_data, image_width, image_height, ocr_text, timestamp, mime_type, image_phash, barcode_text, text_kind, code_language, source_app, expires_at
+             FROM clipboard_history
+             WHERE id IN (
+                 SELECT et.entry_id FROM entry_tags et
+                 INNER JOIN tags t ON t.id = et.tag_id
+                 WHERE t.name IN ({})
+                 GROUP BY et.entry_id
+                 HAVING COUNT(DISTINCT t.id) = {}
+             )
+             ORDER BY timestamp DESC",
+            placeholders,
+            tags.len()
+        ))?;
+
+        let entries = stmt
+            .query_map(
+                rusqlite::params_from_iter(tags.iter().map(|tag| tag.trim())),
+                entry_from_row,
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(entries)
+    }
+
+    /// Deletes entries whose TTL ran out (and their image files)
+    pub fn delete_expired(&self, now: DateTime<Utc>) -> Result<Vec<i64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, image_path FROM clipboard_history
+             WHERE expires_at IS NOT NULL AND expires_at <= ?1",
+        )?;
+        let expired = stmt
+            .query_map([now.to_rfc3339()], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (id, image_path) in &expired {
+            self.conn
+                .execute("DELETE FROM clipboard_history WHERE id = ?1", [id])?;
+            if let Some(path) = image_path {
+                self.remove_image_if_unused(path)?;
+            }
+        }
+
+        Ok(expired.into_iter().map(|(id, _)| id).collect())
+    }
+
+    pub fn insert_snippet(&self, snippet: &Snippet) -> Result<i64> {
+        self.conn
+            .execute(
+                "INSERT INTO snippets (title, body, keyword, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    snippet.title,
+                    snippet.body,
+                    snippet.keyword,
+                    snippet.created_at.to_rfc3339(),
+                    snippet.updated_at.to_rfc3339()
+                ],
+            )
+            .map_err(|e| keyword_conflict(e, snippet))?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Saves title, body and keyword of an existing snippet
+    pub fn update_snippet(&self, snippet: &Snippet) -> Result<bool> {
+        let updated = self
+            .conn
+            .execute(
+                "UPDATE snippets SET title = ?1, body = ?2, keyword = ?3, updated_at = ?4
+                 WHERE id = ?5",
+                params![
+                    snippet.title,
+                    snippet.body,
+                    snippet.keyword,
+                    snippet.updated_at.to_rfc3339(),
+                    snippet.id
+                ],
+            )
+            .map_err(|e| keyword_conflict(e, snippet))?;
+        Ok(updated > 0)
+    }
+
+    pub fn delete_snippet(&self, id: i64) -> Result<bool> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM snippets WHERE id = ?1", [id])?;
+        Ok(deleted > 0)
+    }
+
+    /// All snippets, sorted by title
+    pub fn list_snippets(&self) -> Result<Vec<Snippet>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, title, body, keyword, created_at, updated_at
+             FROM snippets
+             ORDER BY title COLLATE NOCASE",
+        )?;
+        let snippets = stmt
+            .query_map([], snippet_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(snippets)
+    }
+
+    pub fn get_snippet(&self, id: i64) -> Result<Option<Snippet>> {
+        let snippet = self
+            .conn
+            .query_row(
+                "SELECT id, title, body, keyword, created_at, updated_at
+                 FROM snippets WHERE id = ?1",
+                [id],
+                snippet_from_row,
+            )
+            .optional()?;
+        Ok(snippet)
+    }
+
     /// Atualiza texto OCR de uma entrada existente (usado pelo OCR processor)
     pub fn update_ocr_text(&self, id: i64, ocr_text: &str) -> Result<()> {
         self.conn.execute(
@@ -579,6 +1263,78 @@ impl Storage {
     }
 }
 
+/// Maps a row selected with the standard column order
+/// (id, content_type, content_text, content_data, image_path, thumbnail_data,
+/// image_width, image_height, ocr_text, timestamp, mime_type, image_phash, barcode_text,
+/// text_kind, code_language, source_app, expires_at)
+fn entry_from_row(row: &rusqlite::Row) -> rusqlite::Result<ClipboardEntry> {
+    let content_type_str: String = row.get(1)?;
+    let content_type = match content_type_str.as_str() {
+        "text" => ContentType::Text,
+        "image" => ContentType::Image,
+        _ => ContentType::Text,
+    };
+
+    let timestamp_str: String = row.get(9)?;
+    let timestamp = DateTime::parse_from_rfc3339(&timestamp_str)
+        .unwrap_or_else(|_| Utc::now().into())
+        .with_timezone(&Utc);
+
+    Ok(ClipboardEntry {
+        id: row.get(0)?,
+        content_type,
+        content_text: row.get(2)?,
+        content_data: row.get(3)?,
+        image_path: row.get(4)?,
+        thumbnail_data: row.get(5)?,
+        image_width: row.get(6)?,
+        image_height: row.get(7)?,
+        ocr_text: row.get(8)?,
+        mime_type: row.get(10)?,
+        image_phash: row.get::<_, Option<i64>>(11)?.map(|hash| hash as u64),
+        barcode_text: row.get(12)?,
+        text_kind: row
+            .get::<_, Option<String>>(13)?
+            .and_then(|kind| TextKind::parse(&kind)),
+        code_language: row.get(14)?,
+        source_app: row.get(15)?,
+        expires_at: row
+            .get::<_, Option<String>>(16)?
+            .and_then(|at| DateTime::parse_from_rfc3339(&at).ok())
+            .map(|at| at.with_timezone(&Utc)),
+        tags: Vec::new(), // Filled by HistoryManager (see `tags_of_entries`)
+        timestamp,
+    })
+}
+
+fn snippet_from_row(row: &rusqlite::Row) -> rusqlite::Result<Snippet> {
+    let parse_time = |at: String| {
+        DateTime::parse_from_rfc3339(&at)
+            .map(|at| at.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now())
+    };
+    Ok(Snippet {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        body: row.get(2)?,
+        keyword: row.get(3)?,
+        created_at: parse_time(row.get(4)?),
+        updated_at: parse_time(row.get(5)?),
+    })
+}
+
+/// Readable error for the UNIQUE constraint on `keyword`
+fn keyword_conflict(error: rusqlite::Error, snippet: &Snippet) -> anyhow::Error {
+    match (&error, &snippet.keyword) {
+        (rusqlite::Error::SqliteFailure(e, _), Some(keyword))
+            if e.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            anyhow!("Keyword '{}' is already used by another snippet", keyword)
+        }
+        _ => error.into(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -589,6 +1345,45 @@ mod tests {
         assert_eq!(storage.count().unwrap(), 0);
     }
 
+    #[test]
+    fn test_update_text_and_stats() {
+        let storage = Storage::in_memory().unwrap();
+        assert_eq!(storage.stats().unwrap().total_entries, 0);
+        assert_eq!(storage.stats().unwrap().oldest, None);
+
+        let text = storage
+            .insert(&ClipboardEntry::new_text("old text".to_string()))
+            .unwrap();
+        let image = storage
+            .insert(&ClipboardEntry::new_image_with_dimensions(
+                "/nonexistent/shot.png".to_string(),
+                None,
+                10,
+                10,
+            ))
+            .unwrap();
+        storage.add_tags(text, &["work".to_string()]).unwrap();
+
+        assert!(storage.update_text(text, "new text").unwrap());
+        assert!(!storage.update_text(image, "not for images").unwrap());
+        assert_eq!(
+            storage.get_by_id(text).unwrap().unwrap().content_text,
+            Some("new text".to_string())
+        );
+        // The FTS index follows the update
+        assert_eq!(storage.search("new").unwrap().len(), 1);
+        assert!(storage.search("old").unwrap().is_empty());
+
+        let stats = storage.stats().unwrap();
+        assert_eq!(stats.total_entries, 2);
+        assert_eq!(stats.text_entries, 1);
+        assert_eq!(stats.image_entries, 1);
+        assert_eq!(stats.tag_count, 1);
+        assert_eq!(stats.image_bytes, 0); // The file doesn't exist
+        assert!(stats.database_bytes > 0);
+        assert!(stats.oldest.is_some() && stats.oldest <= stats.newest);
+    }
+
     #[test]
     fn test_insert_and_retrieve() {
         let storage = Storage::in_memory().unwrap();
@@ -601,6 +1396,59 @@ mod tests {
         assert_eq!(retrieved.content_text, Some("Test content".to_string()));
     }
 
+    #[test]
+    fn test_image_mime_type_roundtrip() {
+        let storage = Storage::in_memory().unwrap();
+        let mut entry = ClipboardEntry::new_image_with_dimensions(
+            "/tmp/shot.jpg".to_string(),
+            None,
+            1920,
+            1080,
+        );
+        entry.mime_type = Some("image/jpeg".to_string());
+
+        let id = storage.insert(&entry).unwrap();
+
+        let retrieved = storage.get_by_id(id).unwrap().unwrap();
+        assert_eq!(retrieved.mime_type.as_deref(), Some("image/jpeg"));
+        let listed = storage.get_recent_metadata(10).unwrap();
+        assert_eq!(listed[0].mime_type.as_deref(), Some("image/jpeg"));
+    }
+
+    #[test]
+    fn test_is_image_path_used() {
+        let storage = Storage::in_memory().unwrap();
+        let entry =
+            ClipboardEntry::new_image_with_dimensions("/tmp/used.png".to_string(), None, 10, 10);
+        storage.insert(&entry).unwrap();
+
+        assert!(storage.is_image_path_used("/tmp/used.png").unwrap());
+        assert!(!storage.is_image_path_used("/tmp/other.png").unwrap());
+    }
+
+    #[test]
+    fn test_expiring_a_duplicate_keeps_the_shared_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("abc123.png");
+        std::fs::write(&path, b"png").unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let storage = Storage::in_memory().unwrap();
+        let mut expiring = ClipboardEntry::new_image_with_dimensions(path.clone(), None, 1, 1);
+        expiring.expires_at = Some(Utc::now() - chrono::Duration::seconds(1));
+        let expiring = storage.insert(&expiring).unwrap();
+        let kept = storage
+            .insert(&ClipboardEntry::new_image_with_dimensions(path.clone(), None, 1, 1))
+            .unwrap();
+
+        assert_eq!(storage.delete_expired(Utc::now()).unwrap(), vec![expiring]);
+        assert!(std::path::Path::new(&path).exists());
+
+        // The last entry using it takes the file along
+        assert!(storage.delete_by_id(kept).unwrap());
+        assert!(!std::path::Path::new(&path).exists());
+    }
+
     #[test]
     fn test_prune_old() {
         let storage = Storage::in_memory().unwrap();
@@ -616,4 +1464,208 @@ mod tests {
         assert_eq!(deleted, 5);
         assert_eq!(storage.count().unwrap(), 5);
     }
+
+    #[test]
+    fn test_ocr_job_lifecycle() {
+        let storage = Storage::in_memory().unwrap();
+        let id = storage
+            .insert(&ClipboardEntry::new_image("/tmp/a.png".to_string(), None))
+            .unwrap();
+
+        assert!(storage.enqueue_ocr_job(id).unwrap());
+        assert!(!storage.enqueue_ocr_job(id).unwrap());
+        let jobs = storage.pending_ocr_jobs(10).unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].image_path, "/tmp/a.png");
+
+        storage
+            .set_ocr_job_status(id, OcrJobStatus::Running, None)
+            .unwrap();
+        assert!(storage.pending_ocr_jobs(10).unwrap().is_empty());
+
+        // Daemon restarted while the job was running
+        assert_eq!(storage.reset_running_ocr_jobs().unwrap(), 1);
+        assert_eq!(storage.pending_ocr_jobs(10).unwrap()[0].attempts, 1);
+
+        storage
+            .set_ocr_job_status(id, OcrJobStatus::Failed, Some("timeout"))
+            .unwrap();
+        assert_eq!(
+            storage.get_ocr_job_status(id).unwrap(),
+            Some(OcrJobStatus::Failed)
+        );
+
+        // Deleting the entry removes its job
+        storage.delete_by_id(id).unwrap();
+        assert_eq!(storage.get_ocr_job_status(id).unwrap(), None);
+    }
+
+    #[test]
+    fn test_ocr_backfill() {
+        let storage = Storage::in_memory().unwrap();
+        storage
+            .insert(&ClipboardEntry::new_text("text".to_string()))
+            .unwrap();
+        let without_ocr = storage
+            .insert(&ClipboardEntry::new_image("/tmp/a.png".to_string(), None))
+            .unwrap();
+        let with_ocr = storage
+            .insert(&ClipboardEntry::new_image("/tmp/b.png".to_string(), None))
+            .unwrap();
+        storage.update_ocr_text(with_ocr, "hello").unwrap();
+        let failed = storage
+            .insert(&ClipboardEntry::new_image("/tmp/c.png".to_string(), None))
+            .unwrap();
+        storage.enqueue_ocr_job(failed).unwrap();
+        storage
+            .set_ocr_job_status(failed, OcrJobStatus::Failed, Some("boom"))
+            .unwrap();
+        // Queued (and failed) before SVGs were left out of the backfill
+        let mut svg = ClipboardEntry::new_image("/tmp/d.svg".to_string(), None);
+        svg.mime_type = Some("image/svg+xml".to_string());
+        let svg = storage.insert(&svg).unwrap();
+        storage.enqueue_ocr_job(svg).unwrap();
+        storage
+            .set_ocr_job_status(svg, OcrJobStatus::Failed, Some("not a raster image"))
+            .unwrap();
+
+        assert_eq!(storage.enqueue_ocr_backfill(false).unwrap(), 1);
+        assert_eq!(
+            storage.get_ocr_job_status(without_ocr).unwrap(),
+            Some(OcrJobStatus::Pending)
+        );
+        assert_eq!(storage.enqueue_ocr_backfill(true).unwrap(), 1);
+
+        let stats = storage.ocr_queue_stats().unwrap();
+        assert_eq!((stats.pending, stats.failed), (2, 1));
+    }
+
+    #[test]
+    fn test_ocr_words_roundtrip() {
+        let storage = Storage::in_memory().unwrap();
+        let id = storage
+            .insert(&ClipboardEntry::new_image("/tmp/a.png".to_string(), None))
+            .unwrap();
+        let words = vec![
+            OcrWord {
+                text: "Olá".to_string(),
+                bbox: BoundingBox::new(1, 2, 30, 10),
+                confidence: Some(0.5),
+            },
+            OcrWord {
+                text: "mundo".to_string(),
+                bbox: BoundingBox::new(40, 2, 50, 10),
+                confidence: None,
+            },
+        ];
+
+        storage.save_ocr_words(id, &words).unwrap();
+        storage.save_ocr_words(id, &words).unwrap(); // Re-running OCR replaces them
+        assert_eq!(storage.get_ocr_words(id).unwrap(), words);
+
+        storage.delete_by_id(id).unwrap();
+        assert!(storage.get_ocr_words(id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_barcode_text_is_searchable() {
+        let storage = Storage::in_memory().unwrap();
+        let mut entry = ClipboardEntry::new_image("/tmp/qr.png".to_string(), None);
+        entry.barcode_text = Some("WIFI:S:CasaNet;T:WPA;P:segredo;;".to_string());
+        let with_code = storage.insert(&entry).unwrap();
+        let later = storage
+            .insert(&ClipboardEntry::new_image("/tmp/qr2.png".to_string(), None))
+            .unwrap();
+        storage
+            .update_barcode_text(later, "https://example.com/2fa")
+            .unwrap();
+
+        let found = storage.search("CasaNet").unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, with_code);
+        assert_eq!(
+            found[0].barcode_text.as_deref(),
+            Some("WIFI:S:CasaNet;T:WPA;P:segredo;;")
+        );
+        assert_eq!(storage.search("example").unwrap()[0].id, later);
+    }
+
+    #[test]
+    fn test_tags_crud() {
+        let storage = Storage::in_memory().unwrap();
+        let query = storage
+            .insert(&ClipboardEntry::new_text("SELECT 1".to_string()))
+            .unwrap();
+        let note = storage
+            .insert(&ClipboardEntry::new_text("ligar p/ cliente".to_string()))
+            .unwrap();
+
+        storage
+            .add_tags(query, &["SQL  snippets".to_string(), "Cliente X".to_string()])
+            .unwrap();
+        storage.add_tags(note, &["cliente x".to_string()]).unwrap();
+        assert_eq!(
+            storage.get_tags(query).unwrap(),
+            vec!["Cliente X", "SQL snippets"]
+        );
+
+        // Only the entries asked for
+        let page = storage.tags_of_entries(&[note]).unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[&note], vec!["Cliente X"]);
+
+        let tags = storage.list_tags().unwrap();
+        assert_eq!(tags.len(), 2);
+        assert_eq!((tags[0].name.as_str(), tags[0].entry_count), ("Cliente X", 2));
+
+        let both = storage
+            .get_by_tags(&["cliente x".to_string(), "sql snippets".to_string()])
+            .unwrap();
+        assert_eq!(both.len(), 1);
+        assert_eq!(both[0].id, query);
+        assert_eq!(
+            storage.get_by_tags(&["Cliente X".to_string()]).unwrap().len(),
+            2
+        );
+
+        assert!(storage.rename_tag(tags[1].id, "cliente x").is_err());
+        assert!(storage.rename_tag(tags[1].id, "SQL").unwrap());
+        assert!(storage.remove_tag(note, "Cliente X").unwrap());
+        storage.set_tags(query, &["SQL".to_string()]).unwrap();
+        assert_eq!(storage.get_tags(query).unwrap(), vec!["SQL"]);
+
+        // Deleting a tag keeps the entries; deleting an entry drops its links
+        assert!(storage.delete_tag(tags[0].id).unwrap());
+        assert_eq!(storage.count().unwrap(), 2);
+        storage.delete_by_id(query).unwrap();
+        assert_eq!(storage.list_tags().unwrap()[0].entry_count, 0);
+        assert!(storage.add_tags(note, &["  ".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_snippets_survive_history_clear() {
+        let storage = Storage::in_memory().unwrap();
+        storage
+            .insert(&ClipboardEntry::new_text("temp".to_string()))
+            .unwrap();
+        let mut snippet = Snippet::new("Assinatura", "Att,\n{cursor}", Some(";sig")).unwrap();
+        snippet.id = storage.insert_snippet(&snippet).unwrap();
+
+        let duplicate = Snippet::new("Outra", "x", Some(";sig")).unwrap();
+        let err = storage.insert_snippet(&duplicate).unwrap_err();
+        assert!(err.to_string().contains("already used"));
+
+        snippet.body = "Abraços".to_string();
+        assert!(storage.update_snippet(&snippet).unwrap());
+
+        storage.clear().unwrap();
+        storage.prune_old(0).unwrap();
+
+        let snippets = storage.list_snippets().unwrap();
+        assert_eq!(snippets.len(), 1);
+        assert_eq!(snippets[0].body, "Abraços");
+        assert_eq!(snippets[0].keyword.as_deref(), Some(";sig"));
+        assert!(storage.delete_snippet(snippet.id).unwrap());
+        assert!(storage.get_snippet(snippet.id).unwrap().is_none());
+    }
 }
@@ -0,0 +1,342 @@
+//! Text transforms for "Copy as…"
+//!
+//! Small conversions people otherwise open a terminal for (`jq`, `base64`,
+//! URL-encoding). Each transform has a stable id used over IPC, e.g.
+//! `json-pretty`.
+
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE, URL_SAFE_NO_PAD};
+use base64::Engine;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+
+use crate::classifier;
+
+/// Characters left as-is by URL encoding (RFC 3986 unreserved)
+const URL_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Transform {
+    Upper,
+    Lower,
+    Title,
+    Trim,
+    CollapseWhitespace,
+    NormalizeLineEndings,
+    JsonPretty,
+    JsonMinify,
+    Base64Encode,
+    Base64Decode,
+    UrlEncode,
+    UrlDecode,
+    StringLiteral,
+    MarkdownCodeBlock,
+}
+
+impl Transform {
+    /// Every transform, in the order shown in menus
+    pub const ALL: [Transform; 14] = [
+        Transform::Upper,
+        Transform::Lower,
+        Transform::Title,
+        Transform::Trim,
+        Transform::CollapseWhitespace,
+        Transform::NormalizeLineEndings,
+        Transform::JsonPretty,
+        Transform::JsonMinify,
+        Transform::Base64Encode,
+        Transform::Base64Decode,
+        Transform::UrlEncode,
+        Transform::UrlDecode,
+        Transform::StringLiteral,
+        Transform::MarkdownCodeBlock,
+    ];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Transform::Upper => "upper",
+            Transform::Lower => "lower",
+            Transform::Title => "title",
+            Transform::Trim => "trim",
+            Transform::CollapseWhitespace => "collapse-whitespace",
+            Transform::NormalizeLineEndings => "normalize-line-endings",
+            Transform::JsonPretty => "json-pretty",
+            Transform::JsonMinify => "json-minify",
+            Transform::Base64Encode => "base64-encode",
+            Transform::Base64Decode => "base64-decode",
+            Transform::UrlEncode => "url-encode",
+            Transform::UrlDecode => "url-decode",
+            Transform::StringLiteral => "string-literal",
+            Transform::MarkdownCodeBlock => "markdown-code-block",
+        }
+    }
+
+    pub fn parse(id: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|transform| transform.id() == id.trim().to_ascii_lowercase())
+    }
+
+    /// Transformed text; fails when the input doesn't fit (e.g. invalid JSON)
+    pub fn apply(&self, text: &str) -> Result<String> {
+        Ok(match self {
+            Transform::Upper => text.to_uppercase(),
+            Transform::Lower => text.to_lowercase(),
+            Transform::Title => title_case(text),
+            Transform::Trim => text.trim().to_string(),
+            Transform::CollapseWhitespace => text.split_whitespace().collect::<Vec<_>>().join(" "),
+            Transform::NormalizeLineEndings => text.replace("\r\n", "\n").replace('\r', "\n"),
+            Transform::JsonPretty => reformat_json(text, Some("  "))?,
+            Transform::JsonMinify => reformat_json(text, None)?,
+            Transform::Base64Encode => STANDARD.encode(text),
+            Transform::Base64Decode => base64_decode(text)?,
+            Transform::UrlEncode => utf8_percent_encode(text, URL_COMPONENT).to_string(),
+            Transform::UrlDecode => percent_decode_str(text.trim())
+                .decode_utf8()
+                .map_err(|_| anyhow!("Decoded URL is not valid UTF-8 text"))?
+                .into_owned(),
+            // A JSON string is also a valid literal in JS, Python and Rust
+            Transform::StringLiteral => serde_json::to_string(text)?,
+            Transform::MarkdownCodeBlock => markdown_code_block(text),
+        })
+    }
+
+    /// Results of every transform that works on `text` and actually changes it
+    pub fn apply_all(text: &str) -> Vec<(Transform, String)> {
+        Self::ALL
+            .into_iter()
+            .filter_map(|transform| {
+                let transformed = transform.apply(text).ok()?;
+                (transformed != text).then_some((transform, transformed))
+            })
+            .collect()
+    }
+}
+
+fn title_case(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut word_start = true;
+    for c in text.chars() {
+        if word_start {
+            result.extend(c.to_uppercase());
+        } else {
+            result.extend(c.to_lowercase());
+        }
+        word_start = c.is_whitespace();
+    }
+    result
+}
+
+/// Re-indents JSON (`indent`) or strips its whitespace (`None`)
+///
+/// Works on the tokens rather than a parsed `Value`, so key order, number
+/// spelling and string escapes come out exactly as they went in.
+fn reformat_json(text: &str, indent: Option<&str>) -> Result<String> {
+    let text = text.trim();
+    serde_json::from_str::<serde::de::IgnoredAny>(text)
+        .map_err(|e| anyhow!("Not valid JSON: {}", e))?;
+
+    let mut out = String::with_capacity(text.len());
+    let mut depth = 0;
+    let new_line = |out: &mut String, depth: usize| {
+        if let Some(indent) = indent {
+            out.push('\n');
+            for _ in 0..depth {
+                out.push_str(indent);
+            }
+        }
+    };
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                out.push(c);
+                while let Some(c) = chars.next() {
+                    out.push(c);
+                    match c {
+                        '\\' => out.extend(chars.next()),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            // Validated above, so whitespace here is between tokens
+            c if c.is_whitespace() => {}
+            '{' | '[' => {
+                out.push(c);
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                if let Some(close) = chars.next_if(|&c| c == '}' || c == ']') {
+                    out.push(close);
+                } else {
+                    depth += 1;
+                    new_line(&mut out, depth);
+                }
+            }
+            '}' | ']' => {
+                depth -= 1;
+                new_line(&mut out, depth);
+                out.push(c);
+            }
+            ',' => {
+                out.push(c);
+                new_line(&mut out, depth);
+            }
+            ':' => {
+                out.push(c);
+                if indent.is_some() {
+                    out.push(' ');
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+/// Accepts standard and URL-safe alphabets, with or without padding, ignoring
+/// line breaks (as wrapped by `base64` CLI)
+fn base64_decode(text: &str) -> Result<String> {
+    let compact: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if compact.is_empty() {
+        return Err(anyhow!("Nothing to decode"));
+    }
+
+    let bytes = STANDARD
+        .decode(&compact)
+        .or_else(|_| URL_SAFE.decode(&compact))
+        .or_else(|_| URL_SAFE_NO_PAD.decode(compact.trim_end_matches('=')))
+        .map_err(|_| anyhow!("Not valid base64"))?;
+    String::from_utf8(bytes).map_err(|_| anyhow!("Decoded base64 is binary, not text"))
+}
+
+/// Fenced block tagged with the guessed language; the fence is longer than
+/// any backtick run inside the text
+fn markdown_code_block(text: &str) -> String {
+    let longest_run = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    let language = classifier::classify(text).1.unwrap_or("");
+    format!(
+        "{fence}{language}\n{}\n{fence}",
+        text.trim_end_matches('\n')
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(transform: Transform, text: &str) -> String {
+        transform.apply(text).unwrap()
+    }
+
+    #[test]
+    fn test_case_and_whitespace() {
+        assert_eq!(apply(Transform::Upper, "ação"), "AÇÃO");
+        assert_eq!(
+            apply(Transform::Title, "olá MUNDO\tnovo"),
+            "Olá Mundo\tNovo"
+        );
+        assert_eq!(apply(Transform::Trim, "  x \n"), "x");
+        assert_eq!(
+            apply(Transform::CollapseWhitespace, " a \n\n b\tc "),
+            "a b c"
+        );
+        assert_eq!(
+            apply(Transform::NormalizeLineEndings, "a\r\nb\rc"),
+            "a\nb\nc"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let json = "{\"a\": [1, 2],\n \"b\": null}";
+        assert_eq!(
+            apply(Transform::JsonMinify, json),
+            "{\"a\":[1,2],\"b\":null}"
+        );
+        assert_eq!(
+            apply(Transform::JsonPretty, "{\"a\":1}"),
+            "{\n  \"a\": 1\n}"
+        );
+        assert!(Transform::JsonPretty.apply("{nope").is_err());
+        assert!(Transform::JsonMinify.apply("[1] 2").is_err());
+    }
+
+    #[test]
+    fn test_json_keeps_the_document() {
+        // Key order, big numbers, number spelling and escapes survive
+        let json = r#"{"b": 1, "a": {"z": 12345678901234567890123, "y": 1.10},
+            "c": ["\u00e9 \" }", [], {}]}"#;
+        assert_eq!(
+            apply(Transform::JsonMinify, json),
+            r#"{"b":1,"a":{"z":12345678901234567890123,"y":1.10},"c":["\u00e9 \" }",[],{}]}"#
+        );
+        assert_eq!(
+            apply(Transform::JsonPretty, json),
+            r#"{
+  "b": 1,
+  "a": {
+    "z": 12345678901234567890123,
+    "y": 1.10
+  },
+  "c": [
+    "\u00e9 \" }",
+    [],
+    {}
+  ]
+}"#
+        );
+    }
+
+    #[test]
+    fn test_encodings_roundtrip() {
+        let text = "senha=ç&x y/z";
+        let b64 = apply(Transform::Base64Encode, text);
+        assert_eq!(apply(Transform::Base64Decode, &b64), text);
+        assert_eq!(apply(Transform::Base64Decode, "aGk_Pw"), "hi??");
+        assert!(Transform::Base64Decode.apply("not base64!").is_err());
+
+        let url = apply(Transform::UrlEncode, text);
+        assert_eq!(url, "senha%3D%C3%A7%26x%20y%2Fz");
+        assert_eq!(apply(Transform::UrlDecode, &url), text);
+    }
+
+    #[test]
+    fn test_literal_and_code_block() {
+        assert_eq!(
+            apply(Transform::StringLiteral, "diz \"oi\"\n\\"),
+            "\"diz \\\"oi\\\"\\n\\\\\""
+        );
+
+        let code = "fn main() {\n    let mut x = 1;\n    println!(\"{}\", x);\n}\n";
+        assert_eq!(
+            apply(Transform::MarkdownCodeBlock, code),
+            format!("```rust\n{}\n```", code.trim_end())
+        );
+        assert_eq!(
+            apply(Transform::MarkdownCodeBlock, "use ``` here"),
+            "````\nuse ``` here\n````"
+        );
+    }
+
+    #[test]
+    fn test_registry() {
+        for transform in Transform::ALL {
+            assert_eq!(Transform::parse(transform.id()), Some(transform));
+        }
+        assert_eq!(Transform::parse("nope"), None);
+
+        let available: Vec<Transform> = Transform::apply_all("hello")
+            .into_iter()
+            .map(|(transform, _)| transform)
+            .collect();
+        assert!(available.contains(&Transform::Upper));
+        assert!(!available.contains(&Transform::Lower)); // unchanged
+        assert!(!available.contains(&Transform::JsonPretty)); // not JSON
+    }
+}
@@ -1,3 +1,4 @@
+use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -18,6 +19,22 @@ pub struct ClipboardEntry {
     pub image_width: Option<u32>,      // Image width in pixels (avoid loading full image)
     pub image_height: Option<u32>,     // Image height in pixels (avoid loading full image)
     pub ocr_text: Option<String>,      // OCR extracted text from images (processed in background)
+    #[serde(default)]
+    pub mime_type: Option<String>, // Original image format (e.g. image/jpeg), None for text/legacy
+    #[serde(default)]
+    pub image_phash: Option<u64>, // Perceptual hash (dHash) for near-duplicate detection
+    #[serde(default)]
+    pub barcode_text: Option<String>, // Decoded QR/barcode payloads, one per line
+    #[serde(default)]
+    pub text_kind: Option<TextKind>, // What a text entry looks like (URL, color, code...)
+    #[serde(default)]
+    pub code_language: Option<String>, // Guessed language when text_kind is Code
+    #[serde(default)]
+    pub source_app: Option<String>, // Window class of the app that copied, when known
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>, // Set by capture rules with a TTL
+    #[serde(default)]
+    pub tags: Vec<String>, // Collections the entry belongs to, sorted by name
     pub timestamp: DateTime<Utc>,
 }
 
@@ -33,6 +50,14 @@ impl ClipboardEntry {
             image_width: None,
             image_height: None,
             ocr_text: None,
+            mime_type: None,
+            image_phash: None,
+            barcode_text: None,
+            text_kind: None,
+            code_language: None,
+            source_app: None,
+            expires_at: None,
+            tags: Vec::new(),
             timestamp: Utc::now(),
         }
     }
@@ -48,6 +73,14 @@ impl ClipboardEntry {
             image_width: None,
             image_height: None,
             ocr_text: None, // Will be filled later by OCR processor
+            mime_type: None,
+            image_phash: None,
+            barcode_text: None,
+            text_kind: None,
+            code_language: None,
+            source_app: None,
+            expires_at: None,
+            tags: Vec::new(),
             timestamp: Utc::now(),
         }
     }
@@ -68,7 +101,172 @@ impl ClipboardEntry {
             image_width: Some(width),
             image_height: Some(height),
             ocr_text: None, // Will be filled later by OCR processor
+            mime_type: None,
+            image_phash: None,
+            barcode_text: None,
+            text_kind: None,
+            code_language: None,
+            source_app: None,
+            expires_at: None,
+            tags: Vec::new(),
             timestamp: Utc::now(),
         }
     }
 }
+
+/// Sub-kind of a text entry, set by `classifier::classify` on insert
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TextKind {
+    Plain,
+    Url,
+    Email,
+    Phone,
+    Path,
+    Color,
+    Json,
+    Code,
+    Number,
+}
+
+impl TextKind {
+    pub const ALL: [TextKind; 9] = [
+        TextKind::Plain,
+        TextKind::Url,
+        TextKind::Email,
+        TextKind::Phone,
+        TextKind::Path,
+        TextKind::Color,
+        TextKind::Json,
+        TextKind::Code,
+        TextKind::Number,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TextKind::Plain => "plain",
+            TextKind::Url => "url",
+            TextKind::Email => "email",
+            TextKind::Phone => "phone",
+            TextKind::Path => "path",
+            TextKind::Color => "color",
+            TextKind::Json => "json",
+            TextKind::Code => "code",
+            TextKind::Number => "number",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.to_ascii_lowercase();
+        Self::ALL.into_iter().find(|kind| kind.as_str() == value)
+    }
+}
+
+/// Named collection of entries ("SQL snippets", "Customer X")
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+    pub entry_count: usize,
+}
+
+impl Tag {
+    /// Longest tag name accepted
+    pub const MAX_NAME_LEN: usize = 64;
+
+    /// Trims and collapses whitespace; fails on empty or too long names
+    pub fn normalize_name(name: &str) -> Result<String> {
+        let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+        if name.is_empty() {
+            return Err(anyhow!("Tag name is empty"));
+        }
+        if name.chars().count() > Self::MAX_NAME_LEN {
+            return Err(anyhow!(
+                "Tag name is longer than {} characters",
+                Self::MAX_NAME_LEN
+            ));
+        }
+        Ok(name)
+    }
+}
+
+/// State of an entry in the OCR job queue (persisted in `ocr_jobs`)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum OcrJobStatus {
+    Pending,
+    Running,
+    Done,
+    Failed,
+    /// Not run on purpose (capture rule with `skip_ocr`)
+    Skipped,
+}
+
+impl OcrJobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OcrJobStatus::Pending => "pending",
+            OcrJobStatus::Running => "running",
+            OcrJobStatus::Done => "done",
+            OcrJobStatus::Failed => "failed",
+            OcrJobStatus::Skipped => "skipped",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(OcrJobStatus::Pending),
+            "running" => Some(OcrJobStatus::Running),
+            "done" => Some(OcrJobStatus::Done),
+            "failed" => Some(OcrJobStatus::Failed),
+            "skipped" => Some(OcrJobStatus::Skipped),
+            _ => None,
+        }
+    }
+}
+
+/// Image waiting for OCR
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcrJob {
+    pub entry_id: i64,
+    pub image_path: String,
+    pub attempts: u32,
+}
+
+/// Number of OCR jobs in each state
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct OcrQueueStats {
+    pub pending: usize,
+    pub running: usize,
+    pub done: usize,
+    pub failed: usize,
+}
+
+/// Size of the history, for the dashboard
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct HistoryStats {
+    pub total_entries: usize,
+    pub text_entries: usize,
+    pub image_entries: usize,
+    pub tag_count: usize,
+    pub database_bytes: u64, // Pages in use by the SQLite file
+    pub image_bytes: u64,    // Image files on disk
+    pub oldest: Option<DateTime<Utc>>,
+    pub newest: Option<DateTime<Utc>>,
+}
+
+/// Pixel rectangle in the original image
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct BoundingBox {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Word found by OCR
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OcrWord {
+    pub text: String,
+    pub bbox: BoundingBox,
+    /// 0.0 - 1.0; None when the engine doesn't report it
+    pub confidence: Option<f32>,
+}
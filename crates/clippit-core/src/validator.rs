@@ -1,22 +1,73 @@
 use anyhow::{anyhow, Result};
-use image::ImageFormat;
+use image::{DynamicImage, ImageFormat, ImageReader, Limits};
 use std::io::Cursor;
 
+use crate::config::Config;
+use crate::imaging;
+
 const MAX_TEXT_SIZE: usize = 10 * 1024 * 1024; // 10MB
 const MAX_IMAGE_SIZE: usize = 50 * 1024 * 1024; // 50MB
+const MAX_IMAGE_PIXELS: u64 = 50_000_000; // ~200MB as RGBA8
+
+/// Worst case bytes per decoded pixel (16-bit RGBA)
+const MAX_BYTES_PER_PIXEL: u64 = 8;
+
+/// Validates clipboard content against the size limits from `Config`
+///
+/// Images are checked in two steps so a small file can't expand into gigabytes
+/// (decompression bomb): dimensions are read from the header first, and the
+/// full decode runs with `image::Limits` derived from `max_image_pixels`.
+#[derive(Debug, Clone)]
+pub struct ContentValidator {
+    max_text_size: usize,
+    max_image_size: usize,
+    max_image_pixels: u64,
+}
 
-pub struct ContentValidator;
+impl Default for ContentValidator {
+    fn default() -> Self {
+        Self::new(MAX_TEXT_SIZE, MAX_IMAGE_SIZE)
+    }
+}
 
 impl ContentValidator {
-    pub fn validate_text(text: &str) -> Result<()> {
+    pub fn new(max_text_size: usize, max_image_size: usize) -> Self {
+        Self {
+            max_text_size,
+            max_image_size,
+            max_image_pixels: MAX_IMAGE_PIXELS,
+        }
+    }
+
+    pub fn with_max_image_pixels(mut self, max_image_pixels: u64) -> Self {
+        self.max_image_pixels = max_image_pixels;
+        self
+    }
+
+    /// Uses `general.max_text_size` and the stricter of `general.max_image_size`
+    /// and `privacy.max_image_size_mb`
+    pub fn from_config(config: &Config) -> Self {
+        let privacy_limit = (config.privacy.max_image_size_mb as usize) * 1024 * 1024;
+        Self::new(
+            config.general.max_text_size,
+            config.general.max_image_size.min(privacy_limit),
+        )
+        .with_max_image_pixels(config.general.max_image_pixels)
+    }
+
+    pub fn max_image_size(&self) -> usize {
+        self.max_image_size
+    }
+
+    pub fn validate_text(&self, text: &str) -> Result<()> {
         if text.is_empty() {
             return Err(anyhow!("Text content is empty"));
         }
 
-        if text.len() > MAX_TEXT_SIZE {
+        if text.len() > self.max_text_size {
             return Err(anyhow!(
                 "Text content exceeds maximum size of {} bytes",
-                MAX_TEXT_SIZE
+                self.max_text_size
             ));
         }
 
@@ -28,50 +79,262 @@ impl ContentValidator {
         Ok(())
     }
 
-    pub fn validate_image(data: &[u8]) -> Result<()> {
+    /// Checks size and format, returning the format detected from the header
+    pub fn validate_image(&self, data: &[u8]) -> Result<ImageFormat> {
         if data.is_empty() {
             return Err(anyhow!("Image data is empty"));
         }
 
-        if data.len() > MAX_IMAGE_SIZE {
+        if data.len() > self.max_image_size {
             return Err(anyhow!(
                 "Image data exceeds maximum size of {} bytes",
-                MAX_IMAGE_SIZE
+                self.max_image_size
             ));
         }
 
-        // Try to decode to validate format
-        let cursor = Cursor::new(data);
-        let format = image::ImageReader::new(cursor)
+        let format = match imaging::detect_format(data) {
+            Some(format) if imaging::is_supported(format) => format,
+            _ => {
+                return Err(anyhow!(
+                    "Unsupported image format. Supported: PNG, JPEG, WebP, GIF, BMP and TIFF"
+                ))
+            }
+        };
+
+        self.probe_dimensions(data)?;
+        Ok(format)
+    }
+
+    /// Checks size and that the data really is an SVG document
+    ///
+    /// SVG is never decoded at its declared size, only rasterized into a bounded
+    /// thumbnail, so the pixel limit doesn't apply here.
+    pub fn validate_svg(&self, data: &[u8]) -> Result<()> {
+        if data.is_empty() {
+            return Err(anyhow!("SVG data is empty"));
+        }
+
+        if data.len() > self.max_image_size {
+            return Err(anyhow!(
+                "SVG data exceeds maximum size of {} bytes",
+                self.max_image_size
+            ));
+        }
+
+        if !imaging::is_svg(data) {
+            return Err(anyhow!("Data is not an SVG document"));
+        }
+
+        Ok(())
+    }
+
+    /// Reads width/height from the header only and checks them against the pixel limit
+    pub fn probe_dimensions(&self, data: &[u8]) -> Result<(u32, u32)> {
+        let (width, height) = ImageReader::new(Cursor::new(data))
             .with_guessed_format()?
-            .format();
+            .into_dimensions()
+            .map_err(|e| anyhow!("Failed to read image header: {}", e))?;
 
-        match format {
-            Some(ImageFormat::Png) | Some(ImageFormat::Jpeg) => Ok(()),
-            _ => Err(anyhow!(
-                "Unsupported image format. Only PNG and JPEG are supported"
-            )),
+        self.check_dimensions(width, height)?;
+        Ok((width, height))
+    }
+
+    pub fn check_dimensions(&self, width: u32, height: u32) -> Result<()> {
+        let pixels = width as u64 * height as u64;
+        if pixels == 0 {
+            return Err(anyhow!("Image has no pixels ({}x{})", width, height));
         }
+
+        if pixels > self.max_image_pixels {
+            return Err(anyhow!(
+                "Image dimensions {}x{} exceed the limit of {} pixels",
+                width,
+                height,
+                self.max_image_pixels
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Decoder limits matching `max_image_pixels`
+    pub fn decode_limits(&self) -> Limits {
+        let mut limits = Limits::default();
+        limits.max_alloc = Some(self.max_image_pixels.saturating_mul(MAX_BYTES_PER_PIXEL));
+        limits
+    }
+
+    /// Fully decodes an image, refusing oversized ones before allocating the pixels
+    pub fn decode_image(&self, data: &[u8]) -> Result<DynamicImage> {
+        self.probe_dimensions(data)?;
+
+        let mut reader = ImageReader::new(Cursor::new(data)).with_guessed_format()?;
+        reader.limits(self.decode_limits());
+        reader
+            .decode()
+            .map_err(|e| anyhow!("Failed to decode image: {}", e))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use img_parts::jpeg::{markers, Jpeg};
+    use img_parts::png::{Png, PngChunk};
+    use img_parts::Bytes;
+
+    fn small_image(format: ImageFormat) -> Vec<u8> {
+        imaging::encode(&DynamicImage::new_rgba8(8, 8), format).unwrap()
+    }
+
+    /// Tiny PNG whose IHDR claims `width`x`height` (the IDAT stays 8x8)
+    fn png_bomb(width: u32, height: u32) -> Vec<u8> {
+        let mut png = Png::from_bytes(small_image(ImageFormat::Png).into()).unwrap();
+        let ihdr = png.chunk_by_type(*b"IHDR").unwrap().contents().to_vec();
+
+        let mut contents = Vec::with_capacity(ihdr.len());
+        contents.extend_from_slice(&width.to_be_bytes());
+        contents.extend_from_slice(&height.to_be_bytes());
+        contents.extend_from_slice(&ihdr[8..]);
+
+        png.chunks_mut()[0] = PngChunk::new(*b"IHDR", Bytes::from(contents));
+        png.encoder().bytes().to_vec()
+    }
+
+    /// Tiny JPEG whose SOF0 header claims 65000x65000
+    fn jpeg_bomb() -> Vec<u8> {
+        let mut jpeg = Jpeg::from_bytes(small_image(ImageFormat::Jpeg).into()).unwrap();
+        let segment = jpeg
+            .segments_mut()
+            .iter_mut()
+            .find(|s| s.marker() == markers::SOF0)
+            .unwrap();
+
+        // precision (1 byte), height (2), width (2), components...
+        let mut contents = segment.contents().to_vec();
+        contents[1..3].copy_from_slice(&65000u16.to_be_bytes());
+        contents[3..5].copy_from_slice(&65000u16.to_be_bytes());
+        *segment =
+            img_parts::jpeg::JpegSegment::new_with_contents(markers::SOF0, Bytes::from(contents));
+
+        jpeg.encoder().bytes().to_vec()
+    }
 
     #[test]
     fn test_validate_text_valid() {
-        assert!(ContentValidator::validate_text("Hello, world!").is_ok());
+        assert!(ContentValidator::default()
+            .validate_text("Hello, world!")
+            .is_ok());
     }
 
     #[test]
     fn test_validate_text_empty() {
-        assert!(ContentValidator::validate_text("").is_err());
+        assert!(ContentValidator::default().validate_text("").is_err());
     }
 
     #[test]
     fn test_validate_text_too_large() {
         let large_text = "a".repeat(MAX_TEXT_SIZE + 1);
-        assert!(ContentValidator::validate_text(&large_text).is_err());
+        assert!(ContentValidator::default()
+            .validate_text(&large_text)
+            .is_err());
+    }
+
+    #[test]
+    fn test_validate_image_formats() {
+        let validator = ContentValidator::default();
+        let img = image::DynamicImage::new_rgba8(4, 4);
+
+        for &format in imaging::SUPPORTED_FORMATS {
+            let data = imaging::encode(&img, format).unwrap();
+            assert_eq!(validator.validate_image(&data).unwrap(), format);
+        }
+
+        assert!(validator.validate_image(b"not an image").is_err());
+    }
+
+    #[test]
+    fn test_validate_svg() {
+        let validator = ContentValidator::new(1024, 64);
+        assert!(validator
+            .validate_svg(b"<svg width=\"4\" height=\"4\"/>")
+            .is_ok());
+        assert!(validator
+            .validate_svg(&small_image(ImageFormat::Png))
+            .is_err());
+        assert!(validator.validate_svg(&[b' '; 65]).is_err());
+    }
+
+    #[test]
+    fn test_image_limit_from_config() {
+        let mut config = Config::default();
+        config.privacy.max_image_size_mb = 1;
+        let validator = ContentValidator::from_config(&config);
+        assert_eq!(validator.max_image_size(), 1024 * 1024);
+
+        let too_large = vec![0u8; 1024 * 1024 + 1];
+        assert!(validator.validate_image(&too_large).is_err());
+
+        config.general.max_text_size = 4;
+        let validator = ContentValidator::from_config(&config);
+        assert!(validator.validate_text("12345").is_err());
+    }
+
+    #[test]
+    fn test_png_bomb_is_rejected_from_header() {
+        let bomb = png_bomb(100_000, 100_000);
+        assert!(bomb.len() < 1024);
+
+        let validator = ContentValidator::default();
+        assert_eq!(
+            ImageReader::new(Cursor::new(&bomb))
+                .with_guessed_format()
+                .unwrap()
+                .into_dimensions()
+                .unwrap(),
+            (100_000, 100_000)
+        );
+        assert!(validator.validate_image(&bomb).is_err());
+        assert!(validator.decode_image(&bomb).is_err());
+    }
+
+    #[test]
+    fn test_jpeg_bomb_is_rejected_from_header() {
+        let bomb = jpeg_bomb();
+        let validator = ContentValidator::default();
+
+        assert!(validator.probe_dimensions(&bomb).is_err());
+        assert!(validator.decode_image(&bomb).is_err());
+    }
+
+    #[test]
+    fn test_decode_limits_stop_allocation() {
+        // Highly compressible image: a few KB on disk, 16MB once decoded
+        let img = DynamicImage::new_rgba8(2000, 2000);
+        let data = imaging::encode(&img, ImageFormat::Png).unwrap();
+        assert!(data.len() < 100 * 1024);
+
+        let validator = ContentValidator::default().with_max_image_pixels(1000);
+
+        // Even if the header check were skipped, the decoder refuses to allocate
+        let mut reader = ImageReader::new(Cursor::new(&data))
+            .with_guessed_format()
+            .unwrap();
+        reader.limits(validator.decode_limits());
+        assert!(reader.decode().is_err());
+
+        assert!(validator.decode_image(&data).is_err());
+        assert!(ContentValidator::default().decode_image(&data).is_ok());
+    }
+
+    #[test]
+    fn test_pixel_limit_from_config() {
+        let mut config = Config::default();
+        config.general.max_image_pixels = 100;
+        let validator = ContentValidator::from_config(&config);
+
+        assert!(validator.check_dimensions(10, 10).is_ok());
+        assert!(validator.check_dimensions(11, 10).is_err());
+        assert!(validator.check_dimensions(0, 10).is_err());
     }
 }
@@ -0,0 +1,113 @@
+//! QR code and barcode decoding for captured images
+//!
+//! Screenshots of QR codes (Wi-Fi, 2FA setup, links) and product barcodes get
+//! their payloads stored in `barcode_text`, which is indexed by FTS like OCR
+//! text. Decoding uses rxing (pure Rust port of ZXing), compiled in with the
+//! `barcode` feature.
+
+use image::DynamicImage;
+
+/// Payload found in an image
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedCode {
+    /// e.g. "qrcode", "ean 13"
+    pub format: String,
+    pub text: String,
+}
+
+/// Finds every QR code / barcode in the image
+#[cfg(feature = "barcode")]
+pub fn decode(image: &DynamicImage) -> Vec<DecodedCode> {
+    let luma = image.to_luma8();
+    let (width, height) = luma.dimensions();
+
+    // rxing reports "nothing found" as an error
+    match rxing::helpers::detect_multiple_in_luma(luma.into_raw(), width, height) {
+        Ok(results) => results
+            .iter()
+            .map(|result| DecodedCode {
+                format: result.getBarcodeFormat().to_string(),
+                text: result.getText().to_string(),
+            })
+            .collect(),
+        Err(e) => {
+            tracing::debug!("No barcode found: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(not(feature = "barcode"))]
+pub fn decode(_image: &DynamicImage) -> Vec<DecodedCode> {
+    Vec::new()
+}
+
+/// Text stored in `barcode_text`: unique payloads in the order found, one per
+/// line; None when nothing was decoded
+pub fn payload_text(codes: &[DecodedCode]) -> Option<String> {
+    let mut payloads: Vec<&str> = Vec::new();
+    for code in codes {
+        let text = code.text.trim();
+        if !text.is_empty() && !payloads.contains(&text) {
+            payloads.push(text);
+        }
+    }
+
+    (!payloads.is_empty()).then(|| payloads.join("\n"))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    fn code(text: &str) -> DecodedCode {
+        DecodedCode {
+            format: "qrcode".to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    /// Renders `payload` as a QR code, 8px per module with the standard quiet zone
+    #[cfg(feature = "barcode")]
+    pub(crate) fn qr_image(payload: &str) -> image::GrayImage {
+        use image::{GrayImage, Luma};
+        use qrcode::{Color, QrCode};
+
+        let qr = QrCode::new(payload).unwrap();
+        let (modules, scale, quiet) = (qr.width() as u32, 8, 4);
+        let colors = qr.to_colors();
+
+        let size = (modules + 2 * quiet) * scale;
+        GrayImage::from_fn(size, size, |x, y| {
+            let (mx, my) = (x / scale, y / scale);
+            let inside =
+                (quiet..quiet + modules).contains(&mx) && (quiet..quiet + modules).contains(&my);
+            let dark =
+                inside && colors[((my - quiet) * modules + (mx - quiet)) as usize] == Color::Dark;
+            Luma([if dark { 0 } else { 255 }])
+        })
+    }
+
+    #[test]
+    fn test_payload_text_dedups_and_skips_empty() {
+        let codes = vec![
+            code("https://example.com"),
+            code("  "),
+            code("7891234567895"),
+            code("https://example.com"),
+        ];
+        assert_eq!(
+            payload_text(&codes).as_deref(),
+            Some("https://example.com\n7891234567895")
+        );
+        assert_eq!(payload_text(&[]), None);
+    }
+
+    #[cfg(feature = "barcode")]
+    #[test]
+    fn test_decodes_generated_qr_code() {
+        let payload = "WIFI:S:CasaNet;T:WPA;P:segredo123;;";
+        let codes = decode(&DynamicImage::ImageLuma8(qr_image(payload)));
+        assert_eq!(payload_text(&codes).as_deref(), Some(payload));
+    }
+}
@@ -0,0 +1,84 @@
+use anyhow::{anyhow, Result};
+use clippit_core::imaging;
+use image::{DynamicImage, ImageFormat, RgbaImage};
+use std::collections::VecDeque;
+
+use super::{read_selection, ClipboardImage, ClipboardSnapshot, ClipboardSource, Selection};
+
+/// Scripted source for pipeline tests: replays snapshots in order, then ends
+pub struct FakeSource {
+    script: VecDeque<ClipboardSnapshot>,
+}
+
+impl FakeSource {
+    pub fn new(script: Vec<ClipboardSnapshot>) -> Self {
+        Self {
+            script: script.into(),
+        }
+    }
+
+    pub fn text(text: &str) -> ClipboardSnapshot {
+        ClipboardSnapshot {
+            text: Some(text.to_string()),
+            image: None,
+            source_app: None,
+        }
+    }
+
+    /// Solid-color PNG
+    pub fn image(width: u32, height: u32, rgba: [u8; 4]) -> ClipboardSnapshot {
+        let img = RgbaImage::from_pixel(width, height, image::Rgba(rgba));
+        let data = imaging::encode(&DynamicImage::ImageRgba8(img), ImageFormat::Png).unwrap();
+        Self::encoded("image/png", data)
+    }
+
+    /// Image offered in its original encoding (e.g. `image/jpeg`)
+    pub fn encoded(mime_type: &str, data: Vec<u8>) -> ClipboardSnapshot {
+        ClipboardSnapshot {
+            text: None,
+            image: Some(ClipboardImage {
+                mime_type: mime_type.to_string(),
+                data,
+            }),
+            source_app: None,
+        }
+    }
+
+    /// What a backend reads from an owner offering `formats`, in that order
+    pub fn offered(formats: &[(&str, &[u8])]) -> ClipboardSnapshot {
+        let mut selection = FakeSelection(
+            formats
+                .iter()
+                .map(|(mime, data)| (mime.to_string(), data.to_vec()))
+                .collect(),
+        );
+        read_selection(&mut selection).unwrap()
+    }
+}
+
+/// Clipboard owner with fixed content for each format it offers
+struct FakeSelection(Vec<(String, Vec<u8>)>);
+
+impl Selection for FakeSelection {
+    fn mime_types(&mut self) -> Result<Vec<String>> {
+        Ok(self.0.iter().map(|(mime, _)| mime.clone()).collect())
+    }
+
+    fn read(&mut self, mime: &str) -> Result<Vec<u8>> {
+        self.0
+            .iter()
+            .find(|(offered, _)| offered == mime)
+            .map(|(_, data)| data.clone())
+            .ok_or_else(|| anyhow!("{} is not offered", mime))
+    }
+}
+
+impl ClipboardSource for FakeSource {
+    fn name(&self) -> &'static str {
+        "fake"
+    }
+
+    fn next_change(&mut self) -> Result<Option<ClipboardSnapshot>> {
+        Ok(self.script.pop_front())
+    }
+}
@@ -0,0 +1,167 @@
+//! Clipboard change sources
+//!
+//! The capture pipeline no longer polls the clipboard on a fixed timer. Instead it
+//! consumes a [`ClipboardSource`], which blocks until the clipboard owner changes and
+//! then hands back a [`ClipboardSnapshot`] with whatever content is available.
+//!
+//! Implementations:
+//! - [`WaylandSource`]: `wlr-data-control` selection events (wlroots, KDE, Hyprland...)
+//! - [`X11Source`]: XFixes `SelectionNotify` events
+//! - [`PollingSource`]: fallback that polls the X11 clipboard every `general.poll_interval_ms`
+//! - `FakeSource` (tests only): replays a scripted list of snapshots
+
+#[cfg(test)]
+pub mod fake;
+mod polling;
+mod wayland;
+mod x11;
+
+use anyhow::Result;
+use clippit_core::{imaging, Config};
+use std::time::Duration;
+use tracing::{info, warn};
+
+pub use polling::PollingSource;
+pub use wayland::WaylandSource;
+pub use x11::X11Source;
+
+/// Text MIME types in order of preference (X11 target names included)
+const TEXT_MIME_TYPES: &[&str] = &[
+    "text/plain;charset=utf-8",
+    "UTF8_STRING",
+    "text/plain",
+    "STRING",
+    "TEXT",
+];
+
+/// Upper bound for a single clipboard transfer
+const MAX_TRANSFER_SIZE: u64 = 64 * 1024 * 1024;
+
+/// How long to wait for the owning client to write the clipboard data
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Image as read from the clipboard
+///
+/// Always the file exactly as offered by the source application (e.g. a JPEG
+/// screenshot): sources never decode, so the capture pipeline can check the
+/// header against the limits before anything is allocated for the pixels.
+#[derive(Debug, Clone, PartialEq, Hash)]
+pub struct ClipboardImage {
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+/// Clipboard content captured after a change notification
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClipboardSnapshot {
+    pub text: Option<String>,
+    pub image: Option<ClipboardImage>,
+    /// Application that owns the selection, when the backend can tell (X11)
+    pub source_app: Option<String>,
+}
+
+impl ClipboardSnapshot {
+    pub fn is_empty(&self) -> bool {
+        self.text.is_none() && self.image.is_none()
+    }
+}
+
+/// A source of clipboard change events
+///
+/// Sources are blocking and run on a dedicated thread (see `monitor::start_monitor`).
+pub trait ClipboardSource: Send {
+    /// Short backend name used in logs
+    fn name(&self) -> &'static str;
+
+    /// Blocks until the clipboard changes and returns its new content.
+    ///
+    /// Returns `Ok(None)` when the source has no more changes to report
+    /// (only scripted sources end; real backends block forever).
+    fn next_change(&mut self) -> Result<Option<ClipboardSnapshot>>;
+}
+
+/// Clipboard content offered in several formats, read one format at a time
+trait Selection {
+    /// Formats the owner offers: MIME types, plus target names like
+    /// `UTF8_STRING` on X11
+    fn mime_types(&mut self) -> Result<Vec<String>>;
+
+    /// Content in `mime`, exactly as the owner sends it
+    fn read(&mut self, mime: &str) -> Result<Vec<u8>>;
+}
+
+/// Reads the text and the image of a selection
+///
+/// The image keeps the first supported format the owner offers, which is the
+/// one it actually holds (the others are usually conversions). It is never
+/// decoded here: the capture pipeline checks its header against the limits first.
+fn read_selection(selection: &mut dyn Selection) -> Result<ClipboardSnapshot> {
+    let mime_types = selection.mime_types()?;
+    let mut snapshot = ClipboardSnapshot::default();
+
+    if let Some(mime) = TEXT_MIME_TYPES
+        .iter()
+        .find(|m| mime_types.iter().any(|o| o == *m))
+    {
+        let data = selection.read(mime)?;
+        snapshot.text = String::from_utf8(data).ok().filter(|t| !t.is_empty());
+    }
+
+    let image_mime = mime_types
+        .iter()
+        .find(|m| imaging::format_from_mime(m).is_some() || imaging::is_svg_mime(m))
+        .cloned();
+
+    if let Some(mime_type) = image_mime {
+        let data = selection.read(&mime_type)?;
+        if data.is_empty() {
+            warn!("Clipboard owner sent an empty {} image", mime_type);
+        } else {
+            snapshot.image = Some(ClipboardImage { mime_type, data });
+        }
+    }
+
+    Ok(snapshot)
+}
+
+/// Opens the clipboard source selected by `general.clipboard_backend`
+///
+/// With `"auto"` the event-driven backends are tried first (Wayland data-control,
+/// then X11 XFixes) and the polling backend is used as the last resort.
+pub fn open(config: &Config) -> Result<Box<dyn ClipboardSource>> {
+    let poll_interval = Duration::from_millis(config.general.poll_interval_ms.max(10));
+
+    match config.general.clipboard_backend.to_lowercase().as_str() {
+        "wayland" => Ok(Box::new(WaylandSource::connect()?)),
+        "x11" => Ok(Box::new(X11Source::connect()?)),
+        "polling" => Ok(Box::new(PollingSource::new(poll_interval)?)),
+        other => {
+            if other != "auto" {
+                warn!(
+                    "Unknown clipboard backend '{}', using auto-detection",
+                    other
+                );
+            }
+
+            if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+                match WaylandSource::connect() {
+                    Ok(source) => return Ok(Box::new(source)),
+                    Err(e) => info!("Wayland data-control unavailable: {}", e),
+                }
+            }
+
+            if std::env::var_os("DISPLAY").is_some() {
+                match X11Source::connect() {
+                    Ok(source) => return Ok(Box::new(source)),
+                    Err(e) => info!("X11 XFixes unavailable: {}", e),
+                }
+            }
+
+            warn!(
+                "No event-driven clipboard backend available, polling every {:?}",
+                poll_interval
+            );
+            Ok(Box::new(PollingSource::new(poll_interval)?))
+        }
+    }
+}
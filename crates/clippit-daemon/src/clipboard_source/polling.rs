@@ -0,0 +1,69 @@
+use anyhow::Result;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+use tracing::debug;
+
+use super::x11::X11Selection;
+use super::{read_selection, ClipboardSnapshot, ClipboardSource};
+
+/// Fallback source that polls the X11 clipboard
+///
+/// Only used when neither Wayland data-control nor X11 XFixes is available
+/// (e.g. an X server without XFixes). Honors `general.poll_interval_ms`.
+pub struct PollingSource {
+    selection: X11Selection,
+    interval: Duration,
+    last_fingerprint: Option<u64>,
+}
+
+impl PollingSource {
+    pub fn new(interval: Duration) -> Result<Self> {
+        Ok(Self {
+            selection: X11Selection::connect()?,
+            interval,
+            last_fingerprint: None,
+        })
+    }
+
+    /// Current content; empty when nobody owns the clipboard or the owner fails
+    fn read(&mut self) -> ClipboardSnapshot {
+        if !self.selection.has_owner() {
+            return ClipboardSnapshot::default();
+        }
+        read_selection(&mut self.selection).unwrap_or_else(|e| {
+            debug!("Failed to read clipboard selection: {:#}", e);
+            ClipboardSnapshot::default()
+        })
+    }
+}
+
+/// Cheap change detection: hashes the raw content instead of encoding it
+fn fingerprint(snapshot: &ClipboardSnapshot) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    snapshot.text.hash(&mut hasher);
+    snapshot.image.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl ClipboardSource for PollingSource {
+    fn name(&self) -> &'static str {
+        "polling"
+    }
+
+    fn next_change(&mut self) -> Result<Option<ClipboardSnapshot>> {
+        loop {
+            let snapshot = self.read();
+
+            if !snapshot.is_empty() {
+                let current = fingerprint(&snapshot);
+                if self.last_fingerprint != Some(current) {
+                    self.last_fingerprint = Some(current);
+                    return Ok(Some(snapshot));
+                }
+            }
+
+            std::thread::sleep(self.interval);
+        }
+    }
+}
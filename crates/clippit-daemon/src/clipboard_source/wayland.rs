@@ -0,0 +1,237 @@
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::io::Read;
+use std::os::fd::AsFd;
+use std::sync::mpsc;
+use tracing::{debug, warn};
+use wayland_client::backend::ObjectId;
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::{wl_registry, wl_seat::WlSeat};
+use wayland_client::{event_created_child, Connection, Dispatch, EventQueue, Proxy, QueueHandle};
+use wayland_protocols_wlr::data_control::v1::client::{
+    zwlr_data_control_device_v1::{self, ZwlrDataControlDeviceV1},
+    zwlr_data_control_manager_v1::ZwlrDataControlManagerV1,
+    zwlr_data_control_offer_v1::{self, ZwlrDataControlOfferV1},
+};
+
+use super::{
+    read_selection, ClipboardSnapshot, ClipboardSource, Selection, MAX_TRANSFER_SIZE,
+    TRANSFER_TIMEOUT,
+};
+
+/// Wayland source using the `wlr-data-control` protocol
+///
+/// The compositor pushes a `selection` event every time the clipboard changes;
+/// content is only transferred for those changes.
+pub struct WaylandSource {
+    conn: Connection,
+    queue: EventQueue<State>,
+    state: State,
+    _device: ZwlrDataControlDeviceV1,
+}
+
+#[derive(Default)]
+struct State {
+    /// MIME types announced for each live offer
+    offers: HashMap<ObjectId, Vec<String>>,
+    selection: Option<ZwlrDataControlOfferV1>,
+    changed: bool,
+    finished: bool,
+}
+
+impl WaylandSource {
+    pub fn connect() -> Result<Self> {
+        let conn = Connection::connect_to_env().context("Failed to connect to Wayland")?;
+        let (globals, mut queue) = registry_queue_init::<State>(&conn)?;
+        let qh = queue.handle();
+
+        let seat: WlSeat = globals.bind(&qh, 1..=8, ())?;
+        let manager: ZwlrDataControlManagerV1 = globals
+            .bind(&qh, 1..=2, ())
+            .context("Compositor does not support wlr-data-control")?;
+        let device = manager.get_data_device(&seat, &qh, ());
+
+        // Receive the current selection so the first call reports it
+        let mut state = State::default();
+        queue.roundtrip(&mut state)?;
+
+        Ok(Self {
+            conn,
+            queue,
+            state,
+            _device: device,
+        })
+    }
+}
+
+/// One offer of the current selection
+struct Offer<'a> {
+    conn: &'a Connection,
+    offer: &'a ZwlrDataControlOfferV1,
+    mime_types: Vec<String>,
+}
+
+impl Selection for Offer<'_> {
+    fn mime_types(&mut self) -> Result<Vec<String>> {
+        Ok(self.mime_types.clone())
+    }
+
+    /// Asks the selection owner to write `mime` into a pipe and reads it back
+    fn read(&mut self, mime: &str) -> Result<Vec<u8>> {
+        let (reader, writer) = std::io::pipe()?;
+        self.offer.receive(mime.to_string(), writer.as_fd());
+        self.conn.flush()?;
+        drop(writer);
+
+        // The owner writes asynchronously; don't let a stuck client block the daemon
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut data = Vec::new();
+            let result = reader
+                .take(MAX_TRANSFER_SIZE)
+                .read_to_end(&mut data)
+                .map(|_| data);
+            let _ = tx.send(result);
+        });
+
+        rx.recv_timeout(TRANSFER_TIMEOUT)
+            .map_err(|_| anyhow!("Timed out reading {} from clipboard owner", mime))?
+            .map_err(Into::into)
+    }
+}
+
+impl ClipboardSource for WaylandSource {
+    fn name(&self) -> &'static str {
+        "wayland-data-control"
+    }
+
+    fn next_change(&mut self) -> Result<Option<ClipboardSnapshot>> {
+        loop {
+            if !self.state.changed {
+                self.queue.blocking_dispatch(&mut self.state)?;
+            }
+
+            if self.state.finished {
+                return Err(anyhow!(
+                    "Data control device was destroyed by the compositor"
+                ));
+            }
+
+            if !std::mem::take(&mut self.state.changed) {
+                continue;
+            }
+
+            let Some(offer) = self.state.selection.clone() else {
+                debug!("Clipboard cleared");
+                continue;
+            };
+
+            // A slow or broken clipboard owner only costs this selection; if the
+            // connection itself is gone, the next dispatch fails and ends the source
+            let mut selection = Offer {
+                conn: &self.conn,
+                offer: &offer,
+                mime_types: self
+                    .state
+                    .offers
+                    .get(&offer.id())
+                    .cloned()
+                    .unwrap_or_default(),
+            };
+            match read_selection(&mut selection) {
+                Ok(snapshot) if !snapshot.is_empty() => return Ok(Some(snapshot)),
+                Ok(_) => {}
+                Err(e) => warn!("⚠️  Failed to read clipboard selection: {:#}", e),
+            }
+        }
+    }
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for State {
+    fn event(
+        _: &mut Self,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WlSeat, ()> for State {
+    fn event(
+        _: &mut Self,
+        _: &WlSeat,
+        _: <WlSeat as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrDataControlManagerV1, ()> for State {
+    fn event(
+        _: &mut Self,
+        _: &ZwlrDataControlManagerV1,
+        _: <ZwlrDataControlManagerV1 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrDataControlDeviceV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrDataControlDeviceV1,
+        event: zwlr_data_control_device_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_data_control_device_v1::Event::DataOffer { id } => {
+                state.offers.insert(id.id(), Vec::new());
+            }
+            zwlr_data_control_device_v1::Event::Selection { id } => {
+                if let Some(previous) = state.selection.take() {
+                    state.offers.remove(&previous.id());
+                    previous.destroy();
+                }
+                state.selection = id;
+                state.changed = true;
+            }
+            zwlr_data_control_device_v1::Event::PrimarySelection { id: Some(offer) } => {
+                // Primary selection (middle click) is not captured
+                state.offers.remove(&offer.id());
+                offer.destroy();
+            }
+            zwlr_data_control_device_v1::Event::Finished => {
+                state.finished = true;
+            }
+            _ => {}
+        }
+    }
+
+    event_created_child!(State, ZwlrDataControlDeviceV1, [
+        zwlr_data_control_device_v1::EVT_DATA_OFFER_OPCODE => (ZwlrDataControlOfferV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrDataControlOfferV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        offer: &ZwlrDataControlOfferV1,
+        event: zwlr_data_control_offer_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let zwlr_data_control_offer_v1::Event::Offer { mime_type } = event {
+            state.offers.entry(offer.id()).or_default().push(mime_type);
+        }
+    }
+}
@@ -0,0 +1,316 @@
+use anyhow::{bail, Context, Result};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+use x11rb::connection::Connection;
+use x11rb::protocol::xfixes::{ConnectionExt as _, SelectionEventMask};
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ConnectionExt as _, CreateWindowAux, EventMask, GetPropertyReply, Property,
+    Window, WindowClass,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+use x11rb::{COPY_DEPTH_FROM_PARENT, CURRENT_TIME, NONE};
+
+use super::{
+    read_selection, ClipboardSnapshot, ClipboardSource, Selection, MAX_TRANSFER_SIZE,
+    TRANSFER_TIMEOUT,
+};
+
+/// X11 source driven by XFixes selection-owner notifications
+///
+/// The X server tells us when the CLIPBOARD owner changes, so we only read the
+/// clipboard when something was actually copied.
+pub struct X11Source {
+    selection: X11Selection,
+    primed: bool,
+}
+
+/// The CLIPBOARD selection, read in whatever targets its owner offers
+///
+/// Images come back exactly as the owner sends them (e.g. `image/jpeg`).
+pub struct X11Selection {
+    conn: RustConnection,
+    window: Window,
+    clipboard: Atom,
+    targets: Atom,
+    incr: Atom,
+    property: Atom, // Where owners put the converted data, on our window
+    /// Owner change seen while waiting for a transfer, reported next
+    pending_owner: Option<Window>,
+}
+
+impl X11Selection {
+    pub fn connect() -> Result<Self> {
+        let (conn, screen_num) = x11rb::connect(None).context("Failed to connect to X server")?;
+        let screen = &conn.setup().roots[screen_num];
+
+        // Owners deliver the data as a property of this window; large (INCR)
+        // transfers are paced by its property-change events
+        let window = conn.generate_id()?;
+        conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            screen.root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_ONLY,
+            0,
+            &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+        )?;
+
+        let intern = |name: &str| -> Result<Atom> {
+            Ok(conn.intern_atom(false, name.as_bytes())?.reply()?.atom)
+        };
+        let (clipboard, targets, incr, property) = (
+            intern("CLIPBOARD")?,
+            intern("TARGETS")?,
+            intern("INCR")?,
+            intern("CLIPPIT_SELECTION")?,
+        );
+        conn.flush()?;
+
+        Ok(Self {
+            conn,
+            window,
+            clipboard,
+            targets,
+            incr,
+            property,
+            pending_owner: None,
+        })
+    }
+
+    pub fn has_owner(&self) -> bool {
+        self.owner().is_ok_and(|owner| owner != NONE)
+    }
+
+    fn owner(&self) -> Result<Window> {
+        Ok(self
+            .conn
+            .get_selection_owner(self.clipboard)?
+            .reply()?
+            .owner)
+    }
+
+    /// Asks the owner to convert the selection to `target` and reads the result
+    fn convert(&mut self, target: Atom) -> Result<Vec<u8>> {
+        self.conn.convert_selection(
+            self.window,
+            self.clipboard,
+            target,
+            self.property,
+            CURRENT_TIME,
+        )?;
+        self.conn.flush()?;
+
+        loop {
+            if let Event::SelectionNotify(event) = self.next_event()? {
+                if event.requestor != self.window || event.target != target {
+                    continue;
+                }
+                if event.property == NONE {
+                    bail!("Clipboard owner can't convert to the requested format");
+                }
+                break;
+            }
+        }
+
+        let reply = self.take_property()?;
+        if reply.type_ != self.incr {
+            return Ok(reply.value);
+        }
+
+        // Deleting the INCR property (done by `take_property`) starts the
+        // transfer; each chunk deleted asks for the next, an empty one ends it
+        let mut data = Vec::new();
+        loop {
+            match self.next_event()? {
+                Event::PropertyNotify(event)
+                    if event.window == self.window
+                        && event.atom == self.property
+                        && event.state == Property::NEW_VALUE =>
+                {
+                    let chunk = self.take_property()?.value;
+                    if chunk.is_empty() {
+                        return Ok(data);
+                    }
+                    if (data.len() + chunk.len()) as u64 > MAX_TRANSFER_SIZE {
+                        bail!("Clipboard content is over {} bytes", MAX_TRANSFER_SIZE);
+                    }
+                    data.extend_from_slice(&chunk);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn take_property(&self) -> Result<GetPropertyReply> {
+        let reply = self
+            .conn
+            .get_property(
+                true,
+                self.window,
+                self.property,
+                AtomEnum::ANY,
+                0,
+                (MAX_TRANSFER_SIZE / 4) as u32,
+            )?
+            .reply()?;
+        if reply.bytes_after > 0 {
+            bail!("Clipboard content is over {} bytes", MAX_TRANSFER_SIZE);
+        }
+        Ok(reply)
+    }
+
+    /// Next event for a transfer; a stuck owner doesn't block the daemon
+    ///
+    /// Owner changes that arrive meanwhile are kept for `X11Source`.
+    fn next_event(&mut self) -> Result<Event> {
+        let deadline = Instant::now() + TRANSFER_TIMEOUT;
+        loop {
+            if let Some(event) = self.conn.poll_for_event()? {
+                if let Event::XfixesSelectionNotify(notify) = &event {
+                    self.pending_owner = Some(notify.owner);
+                }
+                return Ok(event);
+            }
+            if Instant::now() >= deadline {
+                bail!("Timed out waiting for the clipboard owner");
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+}
+
+impl Selection for X11Selection {
+    fn mime_types(&mut self) -> Result<Vec<String>> {
+        let targets: Vec<Atom> = self
+            .convert(self.targets)?
+            .chunks_exact(4)
+            .map(|atom| u32::from_ne_bytes([atom[0], atom[1], atom[2], atom[3]]))
+            .collect();
+
+        // Send every request before waiting for the first reply
+        let cookies = targets
+            .iter()
+            .map(|&atom| self.conn.get_atom_name(atom))
+            .collect::<Result<Vec<_>, _>>()?;
+        cookies
+            .into_iter()
+            .map(|cookie| Ok(String::from_utf8_lossy(&cookie.reply()?.name).into_owned()))
+            .collect()
+    }
+
+    fn read(&mut self, mime: &str) -> Result<Vec<u8>> {
+        let target = self.conn.intern_atom(false, mime.as_bytes())?.reply()?.atom;
+        self.convert(target)
+    }
+}
+
+impl X11Source {
+    pub fn connect() -> Result<Self> {
+        let selection = X11Selection::connect()?;
+        let conn = &selection.conn;
+
+        conn.xfixes_query_version(5, 0)?
+            .reply()
+            .context("XFixes extension not available")?;
+
+        conn.xfixes_select_selection_input(
+            selection.window,
+            selection.clipboard,
+            SelectionEventMask::SET_SELECTION_OWNER
+                | SelectionEventMask::SELECTION_WINDOW_DESTROY
+                | SelectionEventMask::SELECTION_CLIENT_CLOSE,
+        )?;
+        conn.flush()?;
+
+        Ok(Self {
+            selection,
+            primed: false,
+        })
+    }
+
+    /// WM_CLASS of the selection owner (`"instance\0class\0"`), used by
+    /// `source_app` capture rules
+    ///
+    /// Toolkits usually own the selection with a hidden toplevel that carries
+    /// the application's class; when it doesn't, the source stays unknown.
+    fn owner_class(&self, owner: Window) -> Option<String> {
+        let reply = self
+            .selection
+            .conn
+            .get_property(false, owner, AtomEnum::WM_CLASS, AtomEnum::STRING, 0, 256)
+            .ok()?
+            .reply()
+            .ok()?;
+        let mut parts = reply
+            .value
+            .split(|b| *b == 0)
+            .filter(|part| !part.is_empty());
+        let instance = parts.next();
+        let class = parts.next().or(instance)?;
+        Some(String::from_utf8_lossy(class).into_owned())
+    }
+
+    /// Content offered by `owner`, or `None` when there is nothing to report
+    ///
+    /// A slow or broken owner only costs this selection; a lost X connection
+    /// fails the next wait and ends the source.
+    fn read(&mut self, owner: Window) -> Option<ClipboardSnapshot> {
+        match read_selection(&mut self.selection) {
+            Ok(mut snapshot) if !snapshot.is_empty() => {
+                snapshot.source_app = self.owner_class(owner);
+                Some(snapshot)
+            }
+            Ok(_) => {
+                debug!("Selection owner changed but clipboard is empty");
+                None
+            }
+            Err(e) => {
+                warn!("⚠️  Failed to read clipboard selection: {:#}", e);
+                None
+            }
+        }
+    }
+}
+
+impl ClipboardSource for X11Source {
+    fn name(&self) -> &'static str {
+        "x11-xfixes"
+    }
+
+    fn next_change(&mut self) -> Result<Option<ClipboardSnapshot>> {
+        // Report what is already in the clipboard when the daemon starts
+        if !self.primed {
+            self.primed = true;
+            let owner = self.selection.owner()?;
+            if owner != NONE {
+                if let Some(snapshot) = self.read(owner) {
+                    return Ok(Some(snapshot));
+                }
+            }
+        }
+
+        loop {
+            let owner = match self.selection.pending_owner.take() {
+                Some(owner) => owner,
+                None => match self.selection.conn.wait_for_event()? {
+                    Event::XfixesSelectionNotify(event) => event.owner,
+                    _ => continue,
+                },
+            };
+
+            // Owner went away without a new selection, nothing to read
+            if owner == NONE {
+                continue;
+            }
+            if let Some(snapshot) = self.read(owner) {
+                return Ok(Some(snapshot));
+            }
+        }
+    }
+}
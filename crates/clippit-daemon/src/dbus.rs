@@ -0,0 +1,406 @@
+//! `org.clippit.History` on the session bus
+//!
+//! The same requests as the IPC socket, answered by `handle_ipc_message`, for
+//! desktop integrations that speak D-Bus (GNOME extensions, KDE widgets,
+//! `busctl`/`gdbus` in scripts). History events become signals, and pausing
+//! the capture is the `CapturePaused` property.
+//!
+//! ```text
+//! busctl --user call org.clippit.History /org/clippit/History \
+//!     org.clippit.History List uu 0 10
+//! ```
+
+use anyhow::Result;
+use clippit_ipc::{ContentType, ErrorCode, HistoryEntry, IpcEvent, IpcMessage, IpcResponse};
+use serde::{Deserialize, Serialize};
+use tokio::runtime::Handle;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+use zbus::object_server::SignalContext;
+use zbus::zvariant::Type;
+use zbus::{connection, Connection, DBusError};
+
+use crate::{Handler, RequestError};
+
+pub const BUS_NAME: &str = "org.clippit.History";
+pub const OBJECT_PATH: &str = "/org/clippit/History";
+
+/// Tag that `Pin` puts on an entry
+pub const PINNED_TAG: &str = "pinned";
+
+/// An entry as sent over D-Bus: `(xsssxas)`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+pub struct Entry {
+    pub id: i64,
+    pub content_type: String, // "text" or "image"
+    pub text: String,         // Text, or the OCR text of an image ("" when none)
+    pub image_path: String,   // "" for text and for images kept in the database
+    pub timestamp: i64,       // Unix seconds
+    pub tags: Vec<String>,
+}
+
+impl From<HistoryEntry> for Entry {
+    fn from(entry: HistoryEntry) -> Self {
+        let (content_type, text) = match entry.content_type {
+            ContentType::Text => ("text", entry.content_text),
+            ContentType::Image => ("image", entry.ocr_text),
+        };
+        Self {
+            id: entry.id,
+            content_type: content_type.to_string(),
+            text: text.unwrap_or_default(),
+            image_path: entry.image_path.unwrap_or_default(),
+            timestamp: entry.timestamp.timestamp(),
+            tags: entry.tags,
+        }
+    }
+}
+
+/// `org.clippit.History.Error.*`, one per IPC `ErrorCode`
+#[derive(Debug, DBusError)]
+#[zbus(prefix = "org.clippit.History.Error")]
+pub enum HistoryError {
+    #[zbus(error)]
+    ZBus(zbus::Error),
+    NotFound(String),
+    InvalidRequest(String),
+    StorageFailure(String),
+    Busy(String),
+    Unauthorized(String),
+    Unsupported(String),
+    Timeout(String),
+    Failed(String),
+}
+
+impl From<RequestError> for HistoryError {
+    fn from(RequestError { code, message }: RequestError) -> Self {
+        match code {
+            ErrorCode::NotFound => Self::NotFound(message),
+            ErrorCode::InvalidRequest => Self::InvalidRequest(message),
+            ErrorCode::StorageFailure => Self::StorageFailure(message),
+            ErrorCode::Busy => Self::Busy(message),
+            ErrorCode::Unauthorized => Self::Unauthorized(message),
+            ErrorCode::Unsupported => Self::Unsupported(message),
+            ErrorCode::Timeout => Self::Timeout(message),
+            ErrorCode::Internal => Self::Failed(message),
+        }
+    }
+}
+
+struct History {
+    handler: Handler,
+    runtime: Handle,
+    capture_paused: bool, // Kept in sync by `emit`
+}
+
+impl History {
+    /// Runs the handler off the D-Bus executor; `Error` responses become D-Bus errors
+    async fn request(&self, message: IpcMessage) -> Result<IpcResponse, HistoryError> {
+        Ok(crate::request(&self.runtime, &self.handler, message).await?)
+    }
+
+    async fn request_ok(&self, message: IpcMessage) -> Result<(), HistoryError> {
+        match self.request(message).await? {
+            IpcResponse::Ok => Ok(()),
+            other => Err(unexpected(other)),
+        }
+    }
+}
+
+fn unexpected(response: IpcResponse) -> HistoryError {
+    RequestError::unexpected(response).into()
+}
+
+#[zbus::interface(name = "org.clippit.History")]
+impl History {
+    /// Most recent entries first, images without their bytes
+    async fn list(&self, offset: u32, limit: u32) -> Result<Vec<Entry>, HistoryError> {
+        let message = IpcMessage::QueryHistoryMetadata {
+            limit: limit as usize,
+            offset: offset as usize,
+        };
+        match self.request(message).await? {
+            IpcResponse::HistoryMetadataResponse { entries } => {
+                Ok(entries.into_iter().map(Entry::from).collect())
+            }
+            other => Err(unexpected(other)),
+        }
+    }
+
+    async fn search(&self, query: String, limit: u32) -> Result<Vec<Entry>, HistoryError> {
+        let message = IpcMessage::SearchHistoryWithLimit {
+            query,
+            limit: limit as usize,
+        };
+        match self.request(message).await? {
+            IpcResponse::SearchHistoryWithLimitResponse { entries } => {
+                Ok(entries.into_iter().map(Entry::from).collect())
+            }
+            other => Err(unexpected(other)),
+        }
+    }
+
+    async fn get(&self, id: i64) -> Result<Entry, HistoryError> {
+        let message = IpcMessage::GetEntryData {
+            id,
+            skip_image_data: true,
+        };
+        match self.request(message).await? {
+            IpcResponse::EntryDataResponse { entry } => Ok(entry.into()),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Puts the entry on the clipboard
+    async fn select(&self, id: i64) -> Result<(), HistoryError> {
+        self.request_ok(IpcMessage::SelectItem { id }).await
+    }
+
+    async fn delete(&self, id: i64) -> Result<(), HistoryError> {
+        self.request_ok(IpcMessage::DeleteEntry { id }).await
+    }
+
+    /// Adds or removes the `pinned` tag
+    async fn pin(&self, id: i64, pinned: bool) -> Result<(), HistoryError> {
+        let tag = PINNED_TAG.to_string();
+        let message = if pinned {
+            IpcMessage::AddEntryTag { id, tag }
+        } else {
+            IpcMessage::RemoveEntryTag { id, tag }
+        };
+        self.request_ok(message).await
+    }
+
+    /// New clipboard content is ignored while true
+    #[zbus(property)]
+    async fn capture_paused(&self) -> bool {
+        self.capture_paused
+    }
+
+    #[zbus(property)]
+    async fn set_capture_paused(&mut self, paused: bool) -> Result<(), zbus::fdo::Error> {
+        self.request_ok(IpcMessage::SetCapturePaused { paused })
+            .await
+            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))?;
+        self.capture_paused = paused;
+        Ok(())
+    }
+
+    #[zbus(signal)]
+    async fn entry_added(ctxt: &SignalContext<'_>, entry: Entry) -> zbus::Result<()>;
+
+    /// Text, OCR or tags of an entry changed
+    #[zbus(signal)]
+    async fn entry_updated(ctxt: &SignalContext<'_>, entry: Entry) -> zbus::Result<()>;
+
+    /// Deleted by hand, pruned or expired
+    #[zbus(signal)]
+    async fn entry_removed(ctxt: &SignalContext<'_>, ids: Vec<i64>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn history_cleared(ctxt: &SignalContext<'_>) -> zbus::Result<()>;
+}
+
+/// Serves `org.clippit.History` on the session bus
+pub async fn serve_session(
+    handler: Handler,
+    events: broadcast::Receiver<IpcEvent>,
+) -> Result<Connection> {
+    serve(connection::Builder::session()?, handler, events).await
+}
+
+/// Serves `org.clippit.History` on the connection `builder` makes (the session
+/// bus, or a private bus in tests), emitting a signal for each of `events`
+///
+/// The service lives as long as the returned connection.
+pub async fn serve(
+    builder: connection::Builder<'_>,
+    handler: Handler,
+    mut events: broadcast::Receiver<IpcEvent>,
+) -> Result<Connection> {
+    let capture_paused = match handler(IpcMessage::GetCaptureState) {
+        IpcResponse::CaptureStateResponse { paused } => paused,
+        _ => false,
+    };
+    let history = History {
+        handler,
+        runtime: Handle::current(),
+        capture_paused,
+    };
+    let connection = builder
+        .name(BUS_NAME)?
+        .serve_at(OBJECT_PATH, history)?
+        .build()
+        .await?;
+    info!("🚌 D-Bus service {} ready", BUS_NAME);
+
+    let forwarded = connection.clone();
+    tokio::spawn(async move {
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("Missed {} history events for D-Bus signals", missed);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            if let Err(e) = emit(&forwarded, event).await {
+                warn!("Failed to emit D-Bus signal: {}", e);
+            }
+        }
+    });
+    Ok(connection)
+}
+
+async fn emit(connection: &Connection, event: IpcEvent) -> zbus::Result<()> {
+    let iface = connection
+        .object_server()
+        .interface::<_, History>(OBJECT_PATH)
+        .await?;
+    let ctxt = iface.signal_context();
+    match event {
+        IpcEvent::EntryAdded { entry } => History::entry_added(ctxt, entry.into()).await,
+        IpcEvent::EntryUpdated { entry } => History::entry_updated(ctxt, entry.into()).await,
+        IpcEvent::EntryDeleted { ids } => History::entry_removed(ctxt, ids).await,
+        IpcEvent::HistoryCleared => History::history_cleared(ctxt).await,
+        IpcEvent::CaptureStateChanged { paused } => {
+            let mut history = iface.get_mut().await;
+            if history.capture_paused == paused {
+                return Ok(()); // Set through the property, already announced
+            }
+            history.capture_paused = paused;
+            history.capture_paused_changed(ctxt).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+    use std::time::Duration;
+
+    use crate::test_support::recording_handler;
+
+    #[zbus::proxy(
+        interface = "org.clippit.History",
+        default_service = "org.clippit.History",
+        default_path = "/org/clippit/History"
+    )]
+    trait History {
+        fn list(&self, offset: u32, limit: u32) -> zbus::Result<Vec<Entry>>;
+        fn get(&self, id: i64) -> zbus::Result<Entry>;
+        fn pin(&self, id: i64, pinned: bool) -> zbus::Result<()>;
+        #[zbus(property)]
+        fn capture_paused(&self) -> zbus::Result<bool>;
+        #[zbus(property)]
+        fn set_capture_paused(&self, paused: bool) -> zbus::Result<()>;
+        #[zbus(signal)]
+        fn entry_removed(&self, ids: Vec<i64>) -> zbus::Result<()>;
+    }
+
+    /// A `dbus-daemon` of our own, killed on drop
+    struct PrivateBus {
+        process: Child,
+        address: String,
+    }
+
+    impl PrivateBus {
+        /// None when `dbus-daemon` isn't installed
+        fn start() -> Option<Self> {
+            let mut process = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .spawn()
+                .ok()?;
+            let mut address = String::new();
+            BufReader::new(process.stdout.take()?)
+                .read_line(&mut address)
+                .ok()?;
+            Some(Self {
+                process,
+                address: address.trim().to_string(),
+            })
+        }
+
+        fn connect(&self) -> connection::Builder<'static> {
+            connection::Builder::address(self.address.as_str()).unwrap()
+        }
+    }
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            let _ = self.process.kill();
+            let _ = self.process.wait();
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_history_over_private_bus() {
+        let Some(bus) = PrivateBus::start() else {
+            eprintln!("dbus-daemon not found, skipping");
+            return;
+        };
+
+        let (handler, received) = recording_handler();
+        let (events, event_receiver) = broadcast::channel(16);
+        let _service = serve(bus.connect(), handler, event_receiver).await.unwrap();
+
+        let client = bus.connect().build().await.unwrap();
+        let proxy = HistoryProxy::new(&client).await.unwrap();
+
+        let entries = proxy.list(0, 10).await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].text, "hello");
+        assert_eq!(entries[0].content_type, "text");
+
+        // IPC error codes come back as D-Bus error names
+        match proxy.get(9).await {
+            Err(zbus::Error::MethodError(name, message, _)) => {
+                assert_eq!(name.as_str(), "org.clippit.History.Error.NotFound");
+                assert_eq!(message.as_deref(), Some("Entry with id 9 not found"));
+            }
+            other => panic!("expected NotFound, got {:?}", other),
+        }
+
+        proxy.pin(2, true).await.unwrap();
+        proxy.set_capture_paused(true).await.unwrap();
+        {
+            let received = received.lock().unwrap();
+            assert!(received.iter().any(|message| matches!(
+                message,
+                IpcMessage::AddEntryTag { id: 2, tag } if tag == PINNED_TAG
+            )));
+            assert!(received
+                .iter()
+                .any(|message| matches!(message, IpcMessage::SetCapturePaused { paused: true })));
+        }
+
+        // History events become signals
+        let mut removed = proxy.receive_entry_removed().await.unwrap();
+        events
+            .send(IpcEvent::EntryDeleted { ids: vec![1, 2] })
+            .unwrap();
+        let signal = tokio::time::timeout(Duration::from_secs(5), removed.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(signal.args().unwrap().ids, vec![1, 2]);
+
+        // A pause from elsewhere (popup, IPC) updates the property
+        events
+            .send(IpcEvent::CaptureStateChanged { paused: false })
+            .unwrap();
+        let mut paused = proxy.receive_capture_paused_changed().await;
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while proxy.capture_paused().await.unwrap() {
+                paused.next().await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+}
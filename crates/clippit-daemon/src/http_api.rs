@@ -0,0 +1,475 @@
+//! Opt-in REST API on 127.0.0.1 (`[http_api]` in the config)
+//!
+//! For browser extensions and editor plugins, which can't open the Unix
+//! socket. Routes answer through `handle_ipc_message`, like the socket and
+//! D-Bus, and all need `Authorization: Bearer <token>`, with the token from
+//! `~/.config/clippit/http-token` (created on first start, mode 0600).
+//!
+//! - `GET /entries?limit=&offset=`: most recent first
+//! - `GET /entries/{id}`
+//! - `POST /entries` with `{"text": "..."}`: into the history and the clipboard
+//! - `GET /search?q=&limit=`
+//! - `GET /events`: server-sent events, an `entry` event per new entry
+//!
+//! Entries are the IPC `HistoryEntry` JSON without image bytes (`image_path`
+//! points to the file). Errors are `{"code": "NotFound", "message": "..."}`.
+
+use anyhow::{Context, Result};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use clippit_ipc::{ErrorCode, HistoryEntry, IpcEvent, IpcMessage, IpcResponse};
+use futures_util::Stream;
+use serde::Deserialize;
+use std::io::{Read, Write};
+use std::net::Ipv4Addr;
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::sync::Arc;
+use tokio::runtime::Handle;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+use crate::{Handler, RequestError};
+
+/// Most entries one request returns
+const MAX_LIMIT: usize = 1000;
+
+#[derive(Clone)]
+struct ApiState {
+    handler: Handler,
+    events: broadcast::Sender<IpcEvent>, // One receiver per `/events` client
+    token: Arc<str>,
+}
+
+/// Serves the API on `127.0.0.1:port` until the daemon exits
+pub async fn serve(
+    port: u16,
+    token: String,
+    handler: Handler,
+    events: broadcast::Sender<IpcEvent>,
+) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+        .await
+        .with_context(|| format!("Failed to listen on 127.0.0.1:{}", port))?;
+    info!("🌐 HTTP API on http://{}", listener.local_addr()?);
+
+    let state = ApiState {
+        handler,
+        events,
+        token: token.into(),
+    };
+    axum::serve(listener, router(state)).await?;
+    Ok(())
+}
+
+/// Token in `path`, or a new random one written there (readable by the user only)
+pub fn load_or_create_token(path: &std::path::Path) -> Result<String> {
+    if let Ok(token) = std::fs::read_to_string(path) {
+        let token = token.trim();
+        if !token.is_empty() {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+            return Ok(token.to_string());
+        }
+    }
+
+    let mut bytes = [0u8; 32];
+    std::fs::File::open("/dev/urandom")
+        .and_then(|mut random| random.read_exact(&mut bytes))
+        .context("Failed to generate the HTTP API token")?;
+    let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    writeln!(file, "{}", token)?;
+    info!("🔑 New HTTP API token in {}", path.display());
+    Ok(token)
+}
+
+fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/entries", get(list_entries).post(add_entry))
+        .route("/entries/:id", get(get_entry))
+        .route("/search", get(search))
+        .route("/events", get(events))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_token))
+        .with_state(state)
+}
+
+async fn require_token(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match token {
+        Some(token) if same_token(token, &state.token) => next.run(request).await,
+        _ => {
+            warn!(
+                target: "clippit_daemon::audit",
+                path = %request.uri().path(),
+                "Rejected HTTP API request without a valid token"
+            );
+            ApiError::new(ErrorCode::Unauthorized, "Missing or wrong bearer token").into_response()
+        }
+    }
+}
+
+/// Compares without stopping at the first difference
+fn same_token(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// An IPC error as an HTTP response
+#[derive(Debug)]
+struct ApiError {
+    code: ErrorCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn unexpected(response: IpcResponse) -> Self {
+        RequestError::unexpected(response).into()
+    }
+
+    fn status(&self) -> StatusCode {
+        match self.code {
+            ErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ErrorCode::InvalidRequest => StatusCode::BAD_REQUEST,
+            ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+            ErrorCode::Busy => StatusCode::SERVICE_UNAVAILABLE,
+            ErrorCode::Unsupported => StatusCode::NOT_IMPLEMENTED,
+            ErrorCode::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            ErrorCode::StorageFailure | ErrorCode::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<RequestError> for ApiError {
+    fn from(RequestError { code, message }: RequestError) -> Self {
+        Self { code, message }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({ "code": self.code, "message": self.message });
+        (self.status(), Json(body)).into_response()
+    }
+}
+
+/// Runs the handler off the async workers; `Error` responses become `ApiError`s
+async fn request(state: &ApiState, message: IpcMessage) -> Result<IpcResponse, ApiError> {
+    Ok(crate::request(&Handle::current(), &state.handler, message).await?)
+}
+
+/// Image bytes and thumbnails stay out of the JSON
+fn without_bytes(entry: HistoryEntry) -> HistoryEntry {
+    HistoryEntry {
+        content_data: None,
+        thumbnail_data: None,
+        ..entry
+    }
+}
+
+fn default_limit() -> usize {
+    50
+}
+
+#[derive(Deserialize)]
+struct ListQuery {
+    #[serde(default = "default_limit")]
+    limit: usize,
+    #[serde(default)]
+    offset: usize,
+}
+
+async fn list_entries(
+    State(state): State<ApiState>,
+    Query(query): Query<ListQuery>,
+) -> Result<Json<Vec<HistoryEntry>>, ApiError> {
+    let message = IpcMessage::QueryHistoryMetadata {
+        limit: query.limit.min(MAX_LIMIT),
+        offset: query.offset,
+    };
+    match request(&state, message).await? {
+        IpcResponse::HistoryMetadataResponse { entries } => {
+            Ok(Json(entries.into_iter().map(without_bytes).collect()))
+        }
+        other => Err(ApiError::unexpected(other)),
+    }
+}
+
+async fn get_entry(
+    State(state): State<ApiState>,
+    Path(id): Path<i64>,
+) -> Result<Json<HistoryEntry>, ApiError> {
+    let message = IpcMessage::GetEntryData {
+        id,
+        skip_image_data: true,
+    };
+    match request(&state, message).await? {
+        IpcResponse::EntryDataResponse { entry } => Ok(Json(without_bytes(entry))),
+        other => Err(ApiError::unexpected(other)),
+    }
+}
+
+#[derive(Deserialize)]
+struct NewEntry {
+    text: String,
+}
+
+/// 201 with the new id; 200 with `"id": null` when the history didn't keep it
+/// (capture paused, duplicate, dropped by a rule)
+async fn add_entry(
+    State(state): State<ApiState>,
+    Json(entry): Json<NewEntry>,
+) -> Result<Response, ApiError> {
+    let message = IpcMessage::AddEntry {
+        text: entry.text,
+        place_on_clipboard: true,
+    };
+    match request(&state, message).await? {
+        IpcResponse::EntryAddedResponse { id } => {
+            let status = match id {
+                Some(_) => StatusCode::CREATED,
+                None => StatusCode::OK,
+            };
+            Ok((status, Json(serde_json::json!({ "id": id }))).into_response())
+        }
+        other => Err(ApiError::unexpected(other)),
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    #[serde(default)]
+    q: String,
+    #[serde(default = "default_limit")]
+    limit: usize,
+}
+
+async fn search(
+    State(state): State<ApiState>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<Vec<HistoryEntry>>, ApiError> {
+    let message = IpcMessage::SearchHistoryWithLimit {
+        query: query.q,
+        limit: query.limit.min(MAX_LIMIT),
+    };
+    match request(&state, message).await? {
+        IpcResponse::SearchHistoryWithLimitResponse { entries } => {
+            Ok(Json(entries.into_iter().map(without_bytes).collect()))
+        }
+        other => Err(ApiError::unexpected(other)),
+    }
+}
+
+async fn events(
+    State(state): State<ApiState>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    // Dropped with the stream when the client disconnects
+    let receiver = state.events.subscribe();
+    let stream = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(IpcEvent::EntryAdded { entry }) => {
+                    let event = Event::default()
+                        .event("entry")
+                        .json_data(without_bytes(entry));
+                    return Some((event, receiver));
+                }
+                Ok(_) => {}
+                // Too far behind: the client reconnects and reloads
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("HTTP event stream missed {} events; closing it", missed);
+                    return None;
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use futures_util::StreamExt;
+    use std::sync::Mutex;
+    use tower::ServiceExt;
+
+    use crate::test_support::{recording_handler, text_entry};
+
+    const TOKEN: &str = "secret-token";
+
+    /// Router over a fake handler that records the messages it gets
+    fn test_router(events: broadcast::Sender<IpcEvent>) -> (Router, Arc<Mutex<Vec<IpcMessage>>>) {
+        let (handler, received) = recording_handler();
+        let state = ApiState {
+            handler,
+            events,
+            token: TOKEN.into(),
+        };
+        (router(state), received)
+    }
+
+    fn no_events() -> broadcast::Sender<IpcEvent> {
+        broadcast::channel(16).0
+    }
+
+    fn get(uri: &str, token: Option<&str>) -> axum::http::Request<Body> {
+        let mut request = axum::http::Request::get(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        request.body(Body::empty()).unwrap()
+    }
+
+    async fn json(response: Response) -> serde_json::Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_routes_need_the_token() {
+        let (router, received) = test_router(no_events());
+        for (uri, token) in [("/entries", None), ("/events", Some("wrong-token!"))] {
+            let response = router.clone().oneshot(get(uri, token)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert_eq!(json(response).await["code"], "Unauthorized");
+        }
+        assert!(received.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_entries_and_search() {
+        let (router, received) = test_router(no_events());
+
+        let response = router
+            .clone()
+            .oneshot(get("/entries?limit=5000", Some(TOKEN)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let entries = json(response).await;
+        assert_eq!(entries[0]["content_text"], "hello");
+        assert!(entries[0]["thumbnail_data"].is_null());
+
+        let response = router
+            .clone()
+            .oneshot(get("/search?q=needle", Some(TOKEN)))
+            .await
+            .unwrap();
+        assert_eq!(json(response).await[0]["content_text"], "needle");
+
+        // IPC error codes map to HTTP statuses
+        let response = router
+            .clone()
+            .oneshot(get("/entries/9", Some(TOKEN)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(json(response).await["code"], "NotFound");
+
+        let request = axum::http::Request::post("/entries")
+            .header(header::AUTHORIZATION, format!("Bearer {}", TOKEN))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"text":"from the browser"}"#))
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(json(response).await["id"], 5);
+
+        let received = received.lock().unwrap();
+        assert!(matches!(
+            received[0],
+            IpcMessage::QueryHistoryMetadata {
+                limit: MAX_LIMIT,
+                offset: 0
+            }
+        ));
+        assert!(received.iter().any(|message| matches!(
+            message,
+            IpcMessage::AddEntry { text, place_on_clipboard: true } if text == "from the browser"
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_new_entries_are_streamed() {
+        let (sender, _) = broadcast::channel(16);
+        let (router, _) = test_router(sender.clone());
+
+        let response = router.oneshot(get("/events", Some(TOKEN))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Only additions go out
+        sender
+            .send(IpcEvent::EntryDeleted { ids: vec![1] })
+            .unwrap();
+        sender
+            .send(IpcEvent::EntryAdded {
+                entry: text_entry(7, "new"),
+            })
+            .unwrap();
+
+        let mut body = response.into_body().into_data_stream();
+        let chunk = tokio::time::timeout(std::time::Duration::from_secs(5), body.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let chunk = String::from_utf8(chunk.to_vec()).unwrap();
+        assert!(chunk.starts_with("event: entry\ndata: {"), "{}", chunk);
+        assert!(chunk.contains(r#""id":7"#));
+
+        // A client that disconnects stops listening right away
+        assert_eq!(sender.receiver_count(), 1);
+        drop(body);
+        assert_eq!(sender.receiver_count(), 0);
+    }
+
+    #[test]
+    fn test_token_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("clippit").join("http-token");
+
+        let token = load_or_create_token(&path).unwrap();
+        assert_eq!(token.len(), 64);
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // Kept across restarts
+        assert_eq!(load_or_create_token(&path).unwrap(), token);
+        assert!(same_token(&token, &token));
+        assert!(!same_token(&token[1..], &token));
+    }
+}
@@ -1,16 +1,81 @@
 mod autocomplete_manager;
+mod barcode;
+mod clipboard_source;
+mod dbus;
 mod hotkey;
+mod http_api;
 mod monitor;
-mod ocr_processor;
+mod ocr;
+mod ocr_queue;
+mod svg;
+#[cfg(test)]
+mod test_support;
 mod typing_monitor;
 
 use anyhow::Result;
-use clippit_core::HistoryManager;
-use clippit_ipc::{ContentType, HistoryEntry, IpcMessage, IpcResponse, IpcServer};
+use clippit_core::{
+    phash, BoundingBox, ClipboardEntry, Config, HistoryEvent, HistoryManager, QrImage, RuleSet,
+    Transform,
+};
+use clippit_ipc::protocol::{capability, MAX_IMAGE_CHUNK};
+use clippit_ipc::{
+    ContentType, ErrorCode, HistoryEntry, HistoryStats, IpcClient, IpcEvent, IpcMessage,
+    IpcResponse, IpcServer, OcrQueueStatus, QrEcLevel, RuleTestResult, SimilarImage, SnippetInfo,
+    TagInfo, TextKind,
+};
+use std::io::{Read, Seek, SeekFrom};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
 use tokio::task;
-use tracing::{error, info};
+use tracing::{error, info, warn};
+
+use crate::ocr_queue::OcrQueue;
+
+/// Answers an IPC message (`handle_ipc_message`), for the socket, D-Bus and HTTP
+pub type Handler = Arc<dyn Fn(IpcMessage) -> IpcResponse + Send + Sync>;
+
+/// A request the handler refused or couldn't run, for D-Bus and HTTP to turn
+/// into their own errors
+#[derive(Debug)]
+pub struct RequestError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl RequestError {
+    /// The handler answered with a response the frontend didn't ask for
+    pub fn unexpected(response: IpcResponse) -> Self {
+        Self {
+            code: ErrorCode::Internal,
+            message: format!("Unexpected response: {:?}", response),
+        }
+    }
+}
+
+/// Runs the handler on a blocking thread of `runtime`, so the frontends'
+/// executors don't wait on the database; `Error` responses become `Err`
+pub async fn request(
+    runtime: &tokio::runtime::Handle,
+    handler: &Handler,
+    message: IpcMessage,
+) -> Result<IpcResponse, RequestError> {
+    let handler = Arc::clone(handler);
+    let response = runtime
+        .spawn_blocking(move || handler(message))
+        .await
+        .map_err(|e| RequestError {
+            code: ErrorCode::Internal,
+            message: format!("Request failed: {}", e),
+        })?;
+    match response {
+        IpcResponse::Error { message, code, .. } => Err(RequestError { code, message }),
+        response => Ok(response),
+    }
+}
+
+/// How often entries past their rule TTL are deleted
+const EXPIRED_CLEANUP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -21,9 +86,14 @@ async fn main() -> Result<()> {
         return Ok(());
     }
 
+    // QR code of a text entry, asked to the running daemon
+    if args.len() > 1 && args[1] == "--qr" {
+        return qr_command(&args[2..]);
+    }
+
     // Initialize logging
     tracing_subscriber::fmt()
-        .with_env_filter("clippit_daemon=info")
+        .with_env_filter("clippit_daemon=info,clippit_ipc=warn")
         .init();
 
     info!(
@@ -35,12 +105,45 @@ async fn main() -> Result<()> {
     let db_path = get_db_path();
     let history_manager = Arc::new(Mutex::new(HistoryManager::new(db_path, 100)?));
 
+    // Entries saved by older versions have no kind yet
+    match history_manager.lock().unwrap().classify_existing_entries() {
+        Ok(0) => {}
+        Ok(count) => info!("🏷️ Classified {} existing text entries", count),
+        Err(e) => error!("Failed to classify existing entries: {}", e),
+    }
+
+    // Start OCR queue worker (resumes jobs left pending by a previous run)
+    let config = Config::load().unwrap_or_default();
+
+    // Capture rules; the monitor reloads them when the config changes
+    match RuleSet::compile(&config.rules) {
+        Ok(rules) => history_manager.lock().unwrap().set_rules(rules),
+        Err(e) => error!("❌ Invalid capture rules: {}", e),
+    }
+
+    // Entries with a rule TTL are removed once they expire
+    let history_clone = Arc::clone(&history_manager);
+    task::spawn(async move {
+        let mut interval = tokio::time::interval(EXPIRED_CLEANUP_INTERVAL);
+        loop {
+            interval.tick().await;
+            match history_clone.lock().unwrap().delete_expired() {
+                Ok(0) => {}
+                Ok(count) => info!("⏳ Removed {} expired entries", count),
+                Err(e) => error!("Failed to remove expired entries: {}", e),
+            }
+        }
+    });
+    let ocr_queue = Arc::new(OcrQueue::from_config(Arc::clone(&history_manager), &config));
+    task::spawn(Arc::clone(&ocr_queue).run());
+
     // Clone for clipboard monitor
     let history_clone = Arc::clone(&history_manager);
+    let ocr_queue_clone = Arc::clone(&ocr_queue);
 
     // Start clipboard monitor
     let monitor_handle = task::spawn(async move {
-        if let Err(e) = monitor::start_monitor(history_clone).await {
+        if let Err(e) = monitor::start_monitor(history_clone, ocr_queue_clone).await {
             error!("FATAL: Clipboard monitor failed: {}", e);
             error!("This is a critical error - daemon cannot function without clipboard monitor");
             error!("Exiting to allow systemd to restart the service");
@@ -74,60 +177,163 @@ async fn main() -> Result<()> {
     // Clone for IPC server
     let history_clone = Arc::clone(&history_manager);
 
+    // IPC socket, D-Bus and HTTP answer the same requests
+    let handler: Handler =
+        Arc::new(move |message| handle_ipc_message(message, &history_clone, &ocr_queue));
+
+    // IPC subscribers, D-Bus signals and HTTP server-sent events
+    let events = forward_events(&history_manager);
+
+    // D-Bus service (optional: the daemon works without a session bus)
+    let _dbus_connection = match dbus::serve_session(Arc::clone(&handler), events.subscribe()).await
+    {
+        Ok(connection) => Some(connection),
+        Err(e) => {
+            warn!("⚠️ D-Bus service {} unavailable: {}", dbus::BUS_NAME, e);
+            None
+        }
+    };
+
+    // Local HTTP API (opt-in, `[http_api]`)
+    if config.http_api.enabled {
+        match http_api::load_or_create_token(&Config::http_token_path()) {
+            Ok(token) => {
+                let events = events.clone();
+                let handler = Arc::clone(&handler);
+                let port = config.http_api.port;
+                task::spawn(async move {
+                    if let Err(e) = http_api::serve(port, token, handler, events).await {
+                        error!("❌ HTTP API stopped: {:#}", e);
+                    }
+                });
+            }
+            Err(e) => error!("❌ HTTP API disabled: {:#}", e),
+        }
+    }
+
     // Start IPC server
-    let server = IpcServer::new(Box::new(move |message| {
-        handle_ipc_message(message, &history_clone)
-    }));
+    let server = IpcServer::new(Box::new(move |message| handler(message)))
+        .with_event_source(Box::new(move || events.subscribe()));
+
+    // Ctrl+C / systemctl stop: finish the requests in progress, then exit
+    let shutdown = server.shutdown_handle();
+    task::spawn(async move {
+        wait_for_stop_signal().await;
+        info!("🛑 Stop signal received, shutting down");
+        shutdown.shutdown();
+    });
 
     info!("All components started successfully");
 
-    // Run IPC server (blocks)
+    // Run IPC server (until a stop signal)
     if let Err(e) = server.start().await {
         error!("IPC server error: {}", e);
+        // Wait for other tasks
+        let _ = tokio::join!(monitor_handle, hotkey_handle);
+    }
+
+    Ok(())
+}
+
+async fn wait_for_stop_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+        }
+        Err(e) => {
+            error!("Failed to listen for SIGTERM: {}", e);
+            let _ = tokio::signal::ctrl_c().await;
+        }
     }
+}
 
-    // Wait for other tasks
-    let _ = tokio::join!(monitor_handle, hotkey_handle);
+/// `clippit-daemon --qr <id> [--ec L|M|Q|H] [--output FILE.svg]`
+///
+/// Prints the SVG to stdout when no output file is given.
+fn qr_command(args: &[String]) -> Result<()> {
+    let usage = "Usage: clippit-daemon --qr <id> [--ec L|M|Q|H] [--output FILE.svg]";
+    let mut id = None;
+    let mut ec_level = QrEcLevel::default();
+    let mut output = None;
 
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ec" => {
+                let value = args.next().map(String::as_str).unwrap_or_default();
+                ec_level = clippit_core::QrEcLevel::parse(value)
+                    .map(to_ipc_ec_level)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("Invalid error correction level '{}'\n{}", value, usage)
+                    })?;
+            }
+            "--output" | "-o" => {
+                output = Some(
+                    args.next()
+                        .ok_or_else(|| anyhow::anyhow!("Missing output file\n{}", usage))?,
+                );
+            }
+            value => {
+                id = Some(
+                    value
+                        .parse::<i64>()
+                        .map_err(|_| anyhow::anyhow!("Invalid entry id '{}'\n{}", value, usage))?,
+                );
+            }
+        }
+    }
+
+    let id = id.ok_or_else(|| anyhow::anyhow!(usage))?;
+    let svg = IpcClient::generate_qr_code(id, ec_level)?;
+
+    match output {
+        Some(path) => {
+            std::fs::write(path, svg)?;
+            eprintln!("✅ QR code of entry {} saved to {}", id, path);
+        }
+        None => println!("{}", svg),
+    }
     Ok(())
 }
 
 fn handle_ipc_message(
     message: IpcMessage,
     history_manager: &Arc<Mutex<HistoryManager>>,
+    ocr_queue: &OcrQueue,
 ) -> IpcResponse {
     match message {
+        IpcMessage::Hello {
+            client,
+            protocol_version,
+            capabilities,
+        } => {
+            info!("🤝 Hello from {} (protocol {})", client, protocol_version);
+            clippit_ipc::handshake::answer_hello(
+                &format!("clippit-daemon {}", env!("CARGO_PKG_VERSION")),
+                protocol_version,
+                &capabilities,
+                &daemon_capabilities(),
+            )
+        }
+
         IpcMessage::Ping => IpcResponse::Pong,
 
         IpcMessage::QueryHistory { limit } => {
             let manager = history_manager.lock().unwrap();
             match manager.get_recent(limit) {
                 Ok(entries) => {
-                    let ipc_entries: Vec<HistoryEntry> = entries
-                        .into_iter()
-                        .map(|e| HistoryEntry {
-                            id: e.id,
-                            content_type: match e.content_type {
-                                clippit_core::ContentType::Text => ContentType::Text,
-                                clippit_core::ContentType::Image => ContentType::Image,
-                            },
-                            content_text: e.content_text,
-                            content_data: e.content_data,
-                            image_path: e.image_path,
-                            thumbnail_data: e.thumbnail_data,
-                            image_width: e.image_width,
-                            image_height: e.image_height,
-                            ocr_text: e.ocr_text,
-                            timestamp: e.timestamp,
-                        })
-                        .collect();
+                    let ipc_entries: Vec<HistoryEntry> =
+                        entries.into_iter().map(to_ipc_entry).collect();
                     IpcResponse::HistoryResponse {
                         entries: ipc_entries,
                     }
                 }
-                Err(e) => IpcResponse::Error {
-                    message: format!("Failed to get history: {}", e),
-                },
+                Err(e) => error_response(ErrorCode::StorageFailure, "Failed to get history", e),
             }
         }
 
@@ -135,24 +341,8 @@ fn handle_ipc_message(
             let manager = history_manager.lock().unwrap();
             match manager.get_recent_metadata_with_offset(limit, offset) {
                 Ok(entries) => {
-                    let ipc_entries: Vec<HistoryEntry> = entries
-                        .into_iter()
-                        .map(|e| HistoryEntry {
-                            id: e.id,
-                            content_type: match e.content_type {
-                                clippit_core::ContentType::Text => ContentType::Text,
-                                clippit_core::ContentType::Image => ContentType::Image,
-                            },
-                            content_text: e.content_text,
-                            content_data: e.content_data, // Already None for images from get_recent_metadata
-                            image_path: e.image_path,     // Include image path
-                            thumbnail_data: e.thumbnail_data, // Include thumbnail data
-                            image_width: e.image_width,
-                            image_height: e.image_height,
-                            ocr_text: e.ocr_text,
-                            timestamp: e.timestamp,
-                        })
-                        .collect();
+                    let ipc_entries: Vec<HistoryEntry> =
+                        entries.into_iter().map(to_ipc_entry).collect();
                     info!(
                         "Returned {} metadata entries (images without data)",
                         ipc_entries.len()
@@ -161,9 +351,11 @@ fn handle_ipc_message(
                         entries: ipc_entries,
                     }
                 }
-                Err(e) => IpcResponse::Error {
-                    message: format!("Failed to get history metadata: {}", e),
-                },
+                Err(e) => error_response(
+                    ErrorCode::StorageFailure,
+                    "Failed to get history metadata",
+                    e,
+                ),
             }
         }
 
@@ -175,27 +367,11 @@ fn handle_ipc_message(
             } else {
                 manager.search(&query)
             };
-            
+
             match result {
                 Ok(entries) => {
-                    let ipc_entries: Vec<HistoryEntry> = entries
-                        .into_iter()
-                        .map(|e| HistoryEntry {
-                            id: e.id,
-                            content_type: match e.content_type {
-                                clippit_core::ContentType::Text => ContentType::Text,
-                                clippit_core::ContentType::Image => ContentType::Image,
-                            },
-                            content_text: e.content_text,
-                            content_data: e.content_data,
-                            image_path: e.image_path,
-                            thumbnail_data: e.thumbnail_data,
-                            image_width: e.image_width,
-                            image_height: e.image_height,
-                            ocr_text: e.ocr_text,
-                            timestamp: e.timestamp,
-                        })
-                        .collect();
+                    let ipc_entries: Vec<HistoryEntry> =
+                        entries.into_iter().map(to_ipc_entry).collect();
                     info!(
                         "Search '{}' returned {} results (NO LIMIT)",
                         query,
@@ -205,9 +381,7 @@ fn handle_ipc_message(
                         entries: ipc_entries,
                     }
                 }
-                Err(e) => IpcResponse::Error {
-                    message: format!("Failed to search history: {}", e),
-                },
+                Err(e) => error_response(ErrorCode::StorageFailure, "Failed to search history", e),
             }
         }
 
@@ -219,27 +393,13 @@ fn handle_ipc_message(
             } else {
                 manager.search(&query)
             };
-            
+
             match result {
                 Ok(entries) => {
                     let ipc_entries: Vec<HistoryEntry> = entries
                         .into_iter()
                         .take(limit) // Limit results (for search case)
-                        .map(|e| HistoryEntry {
-                            id: e.id,
-                            content_type: match e.content_type {
-                                clippit_core::ContentType::Text => ContentType::Text,
-                                clippit_core::ContentType::Image => ContentType::Image,
-                            },
-                            content_text: e.content_text,
-                            content_data: e.content_data,
-                            image_path: e.image_path,
-                            thumbnail_data: e.thumbnail_data,
-                            image_width: e.image_width,
-                            image_height: e.image_height,
-                            ocr_text: e.ocr_text,
-                            timestamp: e.timestamp,
-                        })
+                        .map(to_ipc_entry)
                         .collect();
                     info!(
                         "Search '{}' returned {} results (limited to {})",
@@ -251,21 +411,27 @@ fn handle_ipc_message(
                         entries: ipc_entries,
                     }
                 }
-                Err(e) => IpcResponse::Error {
-                    message: format!("Failed to search history: {}", e),
-                },
+                Err(e) => error_response(ErrorCode::StorageFailure, "Failed to search history", e),
             }
         }
 
-        IpcMessage::GetEntryData { id } => {
-            let manager = history_manager.lock().unwrap();
-            match manager.get_by_id(id) {
+        IpcMessage::GetEntryData {
+            id,
+            skip_image_data,
+        } => {
+            // The lock is released before the image file is read
+            let entry = history_manager.lock().unwrap().get_by_id(id);
+            match entry {
                 Ok(Some(mut entry)) => {
                     info!("📦 Preparing response for entry {}", id);
                     info!("   Content type: {:?}", entry.content_type);
 
-                    // If it's an image with a file path, read from disk
-                    if matches!(entry.content_type, clippit_core::ContentType::Image) {
+                    let is_image = matches!(entry.content_type, clippit_core::ContentType::Image);
+                    if is_image && skip_image_data {
+                        // The client fetches it with GetImageChunk
+                        entry.content_data = None;
+                    } else if is_image {
+                        // If it's an image with a file path, read from disk
                         if let Some(ref path) = entry.image_path {
                             info!("📂 Reading image from file: {}", path);
                             match std::fs::read(path) {
@@ -278,11 +444,12 @@ fn handle_ipc_message(
                                     entry.content_data = Some(data);
                                 }
                                 Err(e) => {
-                                    error!("❌ Failed to read image file: {}", e);
                                     // Return error if we can't read the file
-                                    return IpcResponse::Error {
-                                        message: format!("Failed to read image file: {}", e),
-                                    };
+                                    return error_response(
+                                        ErrorCode::StorageFailure,
+                                        "Failed to read image file",
+                                        e,
+                                    );
                                 }
                             }
                         }
@@ -296,50 +463,490 @@ fn handle_ipc_message(
                         );
                     }
 
-                    let ipc_entry = HistoryEntry {
-                        id: entry.id,
-                        content_type: match entry.content_type {
-                            clippit_core::ContentType::Text => ContentType::Text,
-                            clippit_core::ContentType::Image => ContentType::Image,
-                        },
-                        content_text: entry.content_text,
-                        content_data: entry.content_data, // Full data included (backwards compat)
-                        image_path: entry.image_path,     // Include image path
-                        thumbnail_data: entry.thumbnail_data, // Include thumbnail data
-                        image_width: entry.image_width,
-                        image_height: entry.image_height,
-                        ocr_text: entry.ocr_text,
-                        timestamp: entry.timestamp,
-                    };
+                    let ipc_entry = to_ipc_entry(entry);
                     info!("✅ Returned full data for entry {}", id);
                     IpcResponse::EntryDataResponse { entry: ipc_entry }
                 }
-                Ok(None) => IpcResponse::Error {
-                    message: format!("Entry with id {} not found", id),
-                },
-                Err(e) => IpcResponse::Error {
-                    message: format!("Failed to get entry: {}", e),
-                },
+                Ok(None) => IpcResponse::error(
+                    ErrorCode::NotFound,
+                    format!("Entry with id {} not found", id),
+                ),
+                Err(e) => error_response(ErrorCode::StorageFailure, "Failed to get entry", e),
+            }
+        }
+
+        IpcMessage::GetImageChunk { id, offset, length } => {
+            let entry = match history_manager.lock().unwrap().get_by_id(id) {
+                Ok(Some(entry)) => entry,
+                Ok(None) => {
+                    return IpcResponse::error(
+                        ErrorCode::NotFound,
+                        format!("Entry with id {} not found", id),
+                    )
+                }
+                Err(e) => {
+                    return error_response(ErrorCode::StorageFailure, "Failed to get entry", e)
+                }
+            };
+            match read_image_chunk(&entry, offset, length.min(MAX_IMAGE_CHUNK)) {
+                Ok((data, total_size)) => IpcResponse::ImageChunkResponse { data, total_size },
+                Err(e) => error_response(
+                    ErrorCode::StorageFailure,
+                    &format!("Failed to read image {}", id),
+                    e,
+                ),
             }
         }
 
         IpcMessage::SelectItem { id } => {
+            let entry = history_manager.lock().unwrap().get_by_id(id);
+            match entry {
+                Ok(Some(mut entry)) => {
+                    // Images saved to disk are read back for the clipboard
+                    if entry.content_data.is_none() {
+                        if let Some(path) = &entry.image_path {
+                            match std::fs::read(path) {
+                                Ok(data) => entry.content_data = Some(data),
+                                Err(e) => {
+                                    return error_response(
+                                        ErrorCode::StorageFailure,
+                                        "Failed to read image file",
+                                        e,
+                                    )
+                                }
+                            }
+                        }
+                    }
+                    match monitor::set_clipboard_content(&entry) {
+                        Ok(()) => {
+                            info!("📋 Item {} placed on the clipboard", id);
+                            IpcResponse::Ok
+                        }
+                        Err(e) => error_response(ErrorCode::Internal, "Failed to set clipboard", e),
+                    }
+                }
+                Ok(None) => IpcResponse::error(
+                    ErrorCode::NotFound,
+                    format!("Entry with id {} not found", id),
+                ),
+                Err(e) => error_response(ErrorCode::StorageFailure, "Failed to get entry", e),
+            }
+        }
+
+        IpcMessage::DeleteEntry { id } => match history_manager.lock().unwrap().delete_by_id(id) {
+            Ok(true) => {
+                info!("🗑️ Entry {} deleted", id);
+                IpcResponse::Ok
+            }
+            Ok(false) => IpcResponse::error(
+                ErrorCode::NotFound,
+                format!("Entry with id {} not found", id),
+            ),
+            Err(e) => error_response(ErrorCode::StorageFailure, "Failed to delete entry", e),
+        },
+
+        IpcMessage::DeleteEntries { ids } => {
+            match history_manager.lock().unwrap().delete_by_ids(&ids) {
+                Ok(deleted) => {
+                    info!("🗑️ {} of {} entries deleted", deleted.len(), ids.len());
+                    IpcResponse::EntriesDeletedResponse { ids: deleted }
+                }
+                Err(e) => error_response(ErrorCode::StorageFailure, "Failed to delete entries", e),
+            }
+        }
+
+        IpcMessage::AddEntry {
+            text,
+            place_on_clipboard,
+        } => {
+            let entry = ClipboardEntry::new_text(text);
+            let added = history_manager.lock().unwrap().add_entry(entry.clone());
+            match added {
+                Ok(id) => {
+                    // The monitor then sees it as a duplicate
+                    if place_on_clipboard {
+                        if let Err(e) = monitor::set_clipboard_content(&entry) {
+                            return error_response(
+                                ErrorCode::Internal,
+                                "Failed to set clipboard",
+                                e,
+                            );
+                        }
+                    }
+                    IpcResponse::EntryAddedResponse { id }
+                }
+                Err(e) => error_response(ErrorCode::InvalidRequest, "Failed to add entry", e),
+            }
+        }
+
+        IpcMessage::ClearHistory => match history_manager.lock().unwrap().clear() {
+            Ok(deleted) => {
+                info!("🗑️ History cleared ({} entries)", deleted);
+                IpcResponse::HistoryClearedResponse { deleted }
+            }
+            Err(e) => error_response(ErrorCode::StorageFailure, "Failed to clear history", e),
+        },
+
+        IpcMessage::UpdateEntryText { id, text } => {
             let manager = history_manager.lock().unwrap();
-            match manager.get_by_id(id) {
-                Ok(Some(_entry)) => {
-                    // Clipboard copy is handled by popup with arboard
-                    info!("Item {} selected (clipboard copy handled by popup)", id);
-                    IpcResponse::Ok
+            match manager.update_text(id, &text) {
+                Ok(true) => match manager.get_by_id(id) {
+                    Ok(Some(entry)) => IpcResponse::EntryDataResponse {
+                        entry: to_ipc_entry(entry),
+                    },
+                    Ok(None) => IpcResponse::error(
+                        ErrorCode::NotFound,
+                        format!("Entry with id {} not found", id),
+                    ),
+                    Err(e) => error_response(ErrorCode::StorageFailure, "Failed to get entry", e),
+                },
+                Ok(false) => IpcResponse::error(
+                    ErrorCode::NotFound,
+                    format!("Text entry with id {} not found", id),
+                ),
+                Err(e) => error_response(ErrorCode::InvalidRequest, "Failed to update entry", e),
+            }
+        }
+
+        IpcMessage::CountEntries => match history_manager.lock().unwrap().count() {
+            Ok(count) => IpcResponse::CountResponse { count },
+            Err(e) => error_response(ErrorCode::StorageFailure, "Failed to count entries", e),
+        },
+
+        IpcMessage::GetStats => match history_manager.lock().unwrap().stats() {
+            Ok(stats) => IpcResponse::StatsResponse {
+                stats: to_ipc_stats(stats),
+            },
+            Err(e) => error_response(ErrorCode::StorageFailure, "Failed to get stats", e),
+        },
+
+        IpcMessage::FindSimilarImages {
+            id,
+            max_distance,
+            limit,
+        } => {
+            let manager = history_manager.lock().unwrap();
+            let max_distance = max_distance.unwrap_or(phash::DEFAULT_SIMILAR_DISTANCE);
+            match manager.find_similar_images(id, max_distance, limit) {
+                Ok(similar) => {
+                    info!(
+                        "🔎 Found {} images similar to entry {} (distance <= {})",
+                        similar.len(),
+                        id,
+                        max_distance
+                    );
+                    IpcResponse::SimilarImagesResponse {
+                        images: similar
+                            .into_iter()
+                            .map(|(entry, distance)| SimilarImage {
+                                entry: to_ipc_entry(entry),
+                                distance,
+                            })
+                            .collect(),
+                    }
                 }
-                Ok(None) => IpcResponse::Error {
-                    message: format!("Entry with id {} not found", id),
+                Err(e) => error_response(
+                    ErrorCode::StorageFailure,
+                    "Failed to find similar images",
+                    e,
+                ),
+            }
+        }
+
+        IpcMessage::BackfillOcr { include_failed } => match ocr_queue.backfill(include_failed) {
+            Ok(queued) => IpcResponse::OcrBackfillResponse { queued },
+            Err(e) => error_response(ErrorCode::StorageFailure, "Failed to queue OCR backfill", e),
+        },
+
+        IpcMessage::GetOcrStatus => match ocr_queue.stats() {
+            Ok(stats) => IpcResponse::OcrStatusResponse {
+                status: OcrQueueStatus {
+                    pending: stats.pending,
+                    running: stats.running,
+                    done: stats.done,
+                    failed: stats.failed,
                 },
-                Err(e) => IpcResponse::Error {
-                    message: format!("Failed to get entry: {}", e),
+            },
+            Err(e) => error_response(ErrorCode::StorageFailure, "Failed to get OCR status", e),
+        },
+
+        IpcMessage::GetOcrRegionText { id, region } => {
+            let region = BoundingBox {
+                x: region.x,
+                y: region.y,
+                width: region.width,
+                height: region.height,
+            };
+            match history_manager
+                .lock()
+                .unwrap()
+                .ocr_text_in_region(id, &region)
+            {
+                Ok(text) => IpcResponse::OcrRegionTextResponse { text },
+                Err(e) => error_response(
+                    ErrorCode::StorageFailure,
+                    "Failed to get OCR region text",
+                    e,
+                ),
+            }
+        }
+
+        IpcMessage::GenerateQrCode { id, ec_level } => {
+            let text = match history_manager.lock().unwrap().get_by_id(id) {
+                Ok(Some(entry)) => entry.content_text,
+                Ok(None) => {
+                    return IpcResponse::error(
+                        ErrorCode::NotFound,
+                        format!("Entry {} not found", id),
+                    )
+                }
+                Err(e) => {
+                    return error_response(ErrorCode::StorageFailure, "Failed to get entry", e)
+                }
+            };
+
+            let Some(text) = text else {
+                return IpcResponse::error(
+                    ErrorCode::InvalidRequest,
+                    format!("Entry {} has no text to encode", id),
+                );
+            };
+
+            match QrImage::generate(&text, to_core_ec_level(ec_level)) {
+                Ok(qr) => IpcResponse::QrCodeResponse {
+                    svg: qr.to_svg(),
+                    modules: qr.width(),
                 },
+                Err(e) => IpcResponse::error(ErrorCode::InvalidRequest, e.to_string()),
+            }
+        }
+
+        IpcMessage::TransformText {
+            id,
+            transform,
+            place_on_clipboard,
+        } => {
+            let Some(transform) = Transform::parse(&transform) else {
+                let known: Vec<&str> = Transform::ALL.iter().map(|t| t.id()).collect();
+                return IpcResponse::error(
+                    ErrorCode::InvalidRequest,
+                    format!(
+                        "Unknown transform '{}' (available: {})",
+                        transform,
+                        known.join(", ")
+                    ),
+                );
+            };
+
+            let text = match history_manager.lock().unwrap().get_by_id(id) {
+                Ok(Some(entry)) => entry.content_text,
+                Ok(None) => {
+                    return IpcResponse::error(
+                        ErrorCode::NotFound,
+                        format!("Entry {} not found", id),
+                    )
+                }
+                Err(e) => {
+                    return error_response(ErrorCode::StorageFailure, "Failed to get entry", e)
+                }
+            };
+            let Some(text) = text else {
+                return IpcResponse::error(
+                    ErrorCode::InvalidRequest,
+                    format!("Entry {} has no text to transform", id),
+                );
+            };
+
+            let transformed = match transform.apply(&text) {
+                Ok(transformed) => transformed,
+                Err(e) => {
+                    return IpcResponse::error(
+                        ErrorCode::InvalidRequest,
+                        format!("Cannot apply '{}': {}", transform.id(), e),
+                    )
+                }
+            };
+
+            if place_on_clipboard {
+                // The monitor picks it up as a new history entry
+                if let Err(e) =
+                    monitor::set_clipboard_content(&ClipboardEntry::new_text(transformed.clone()))
+                {
+                    return error_response(ErrorCode::Internal, "Failed to set clipboard", e);
+                }
+                info!("✨ Entry {} copied as {}", id, transform.id());
             }
+
+            IpcResponse::TransformedTextResponse { text: transformed }
         }
 
+        IpcMessage::TestRules { text, source_app } => {
+            let config = Config::load().unwrap_or_default();
+            let rules = match RuleSet::compile(&config.rules) {
+                Ok(rules) => rules,
+                Err(e) => {
+                    return IpcResponse::error(
+                        ErrorCode::InvalidRequest,
+                        format!("Invalid capture rules: {}", e),
+                    )
+                }
+            };
+
+            let outcome = rules.dry_run(&text, source_app.as_deref());
+            IpcResponse::RuleTestResponse {
+                result: RuleTestResult {
+                    matched: outcome.matched,
+                    dropped: outcome.drop,
+                    rewritten_text: outcome.rewritten_text,
+                    tags: outcome.tags,
+                    ttl_seconds: outcome.ttl_seconds,
+                    skip_ocr: outcome.skip_ocr,
+                },
+            }
+        }
+
+        // ========== TAGS ==========
+        IpcMessage::ListTags => match history_manager.lock().unwrap().list_tags() {
+            Ok(tags) => IpcResponse::TagsResponse {
+                tags: tags.into_iter().map(to_ipc_tag).collect(),
+            },
+            Err(e) => error_response(ErrorCode::StorageFailure, "Failed to list tags", e),
+        },
+
+        IpcMessage::CreateTag { name } => match history_manager.lock().unwrap().create_tag(&name) {
+            Ok(tag) => IpcResponse::TagResponse {
+                tag: to_ipc_tag(tag),
+            },
+            Err(e) => error_response(ErrorCode::InvalidRequest, "Failed to create tag", e),
+        },
+
+        IpcMessage::RenameTag { id, name } => {
+            match history_manager.lock().unwrap().rename_tag(id, &name) {
+                Ok(tag) => IpcResponse::TagResponse {
+                    tag: to_ipc_tag(tag),
+                },
+                Err(e) => error_response(ErrorCode::InvalidRequest, "Failed to rename tag", e),
+            }
+        }
+
+        IpcMessage::DeleteTag { id } => match history_manager.lock().unwrap().delete_tag(id) {
+            Ok(true) => IpcResponse::Ok,
+            Ok(false) => IpcResponse::error(ErrorCode::NotFound, format!("Tag {} not found", id)),
+            Err(e) => error_response(ErrorCode::StorageFailure, "Failed to delete tag", e),
+        },
+
+        IpcMessage::AddEntryTag { id, tag } => {
+            match history_manager.lock().unwrap().add_entry_tag(id, &tag) {
+                Ok(()) => IpcResponse::Ok,
+                Err(e) => error_response(ErrorCode::InvalidRequest, "Failed to tag entry", e),
+            }
+        }
+
+        IpcMessage::RemoveEntryTag { id, tag } => {
+            match history_manager.lock().unwrap().remove_entry_tag(id, &tag) {
+                Ok(true) => IpcResponse::Ok,
+                Ok(false) => IpcResponse::error(
+                    ErrorCode::NotFound,
+                    format!("Entry {} has no tag '{}'", id, tag),
+                ),
+                Err(e) => error_response(ErrorCode::InvalidRequest, "Failed to untag entry", e),
+            }
+        }
+
+        IpcMessage::SetEntryTags { id, tags } => {
+            match history_manager.lock().unwrap().set_entry_tags(id, &tags) {
+                Ok(()) => IpcResponse::Ok,
+                Err(e) => error_response(ErrorCode::InvalidRequest, "Failed to set entry tags", e),
+            }
+        }
+
+        // ========== SNIPPETS ==========
+        IpcMessage::ListSnippets => match history_manager.lock().unwrap().list_snippets() {
+            Ok(snippets) => IpcResponse::SnippetsResponse {
+                snippets: snippets.into_iter().map(to_ipc_snippet).collect(),
+            },
+            Err(e) => error_response(ErrorCode::StorageFailure, "Failed to list snippets", e),
+        },
+
+        IpcMessage::CreateSnippet {
+            title,
+            body,
+            keyword,
+        } => {
+            let result =
+                history_manager
+                    .lock()
+                    .unwrap()
+                    .create_snippet(&title, &body, keyword.as_deref());
+            match result {
+                Ok(snippet) => IpcResponse::SnippetResponse {
+                    snippet: to_ipc_snippet(snippet),
+                },
+                Err(e) => error_response(ErrorCode::InvalidRequest, "Failed to create snippet", e),
+            }
+        }
+
+        IpcMessage::UpdateSnippet {
+            id,
+            title,
+            body,
+            keyword,
+        } => {
+            let result = history_manager.lock().unwrap().update_snippet(
+                id,
+                &title,
+                &body,
+                keyword.as_deref(),
+            );
+            match result {
+                Ok(snippet) => IpcResponse::SnippetResponse {
+                    snippet: to_ipc_snippet(snippet),
+                },
+                Err(e) => error_response(ErrorCode::InvalidRequest, "Failed to update snippet", e),
+            }
+        }
+
+        IpcMessage::DeleteSnippet { id } => {
+            match history_manager.lock().unwrap().delete_snippet(id) {
+                Ok(true) => IpcResponse::Ok,
+                Ok(false) => {
+                    IpcResponse::error(ErrorCode::NotFound, format!("Snippet {} not found", id))
+                }
+                Err(e) => error_response(ErrorCode::StorageFailure, "Failed to delete snippet", e),
+            }
+        }
+
+        IpcMessage::ExpandSnippet { id } => {
+            match history_manager.lock().unwrap().expand_snippet(id) {
+                Ok(expanded) => IpcResponse::ExpandedSnippetResponse {
+                    text: expanded.text,
+                    cursor: expanded.cursor,
+                },
+                Err(e) => error_response(ErrorCode::InvalidRequest, "Failed to expand snippet", e),
+            }
+        }
+
+        // The IPC server streams events itself; this is only reached by mistake
+        IpcMessage::Subscribe { .. } => IpcResponse::error(
+            ErrorCode::InvalidRequest,
+            "Subscribe needs a connection of its own".to_string(),
+        ),
+
+        // Answered by the IPC server itself
+        IpcMessage::UseFraming { .. } => IpcResponse::error(
+            ErrorCode::InvalidRequest,
+            "UseFraming is handled by the IPC server".to_string(),
+        ),
+
+        IpcMessage::SetCapturePaused { paused } => {
+            history_manager.lock().unwrap().set_capture_paused(paused);
+            IpcResponse::Ok
+        }
+
+        IpcMessage::GetCaptureState => IpcResponse::CaptureStateResponse {
+            paused: history_manager.lock().unwrap().is_capture_paused(),
+        },
+
         IpcMessage::ShowPopup => {
             // This is handled by the UI, daemon just acknowledges
             IpcResponse::Ok
@@ -383,6 +990,213 @@ fn handle_ipc_message(
     }
 }
 
+/// History events in their IPC form, for every subscribed client
+///
+/// One thread turns each core event into its IPC form (loading the entry for
+/// added/updated ones) and broadcasts it. Subscribers hold a receiver each,
+/// dropped as soon as their client is gone.
+fn forward_events(history_manager: &Arc<Mutex<HistoryManager>>) -> broadcast::Sender<IpcEvent> {
+    let events = history_manager.lock().unwrap().events().subscribe();
+    let history_manager = Arc::clone(history_manager);
+    let (sender, _) = broadcast::channel(clippit_ipc::server::EVENT_BUFFER);
+    let forwarded = sender.clone();
+
+    std::thread::spawn(move || {
+        for event in events {
+            // Nobody listening: don't load entries for nothing
+            if forwarded.receiver_count() == 0 {
+                continue;
+            }
+            if let Some(event) = to_ipc_event(event, &history_manager) {
+                let _ = forwarded.send(event);
+            }
+        }
+    });
+    sender
+}
+
+/// None when the entry is already gone (deleted right after the change)
+fn to_ipc_event(event: HistoryEvent, history_manager: &Mutex<HistoryManager>) -> Option<IpcEvent> {
+    let load = |id| {
+        let entry = history_manager.lock().unwrap().get_by_id(id).ok()??;
+        // Like `QueryHistoryMetadata`: thumbnail only, image data on demand
+        let entry = to_ipc_entry(entry);
+        Some(match entry.content_type {
+            ContentType::Image => HistoryEntry {
+                content_data: None,
+                ..entry
+            },
+            ContentType::Text => entry,
+        })
+    };
+
+    Some(match event {
+        HistoryEvent::EntryAdded { id } => IpcEvent::EntryAdded { entry: load(id)? },
+        HistoryEvent::EntryUpdated { id } => IpcEvent::EntryUpdated { entry: load(id)? },
+        HistoryEvent::EntryDeleted { ids } => IpcEvent::EntryDeleted { ids },
+        HistoryEvent::HistoryCleared => IpcEvent::HistoryCleared,
+        HistoryEvent::CaptureStateChanged { paused } => IpcEvent::CaptureStateChanged { paused },
+    })
+}
+
+/// Up to `length` bytes of an image entry from `offset`, and its total size
+///
+/// Images on disk are read in place (only the chunk is loaded); older entries
+/// keep the bytes in the database.
+fn read_image_chunk(entry: &ClipboardEntry, offset: u64, length: u64) -> Result<(Vec<u8>, u64)> {
+    if !matches!(entry.content_type, clippit_core::ContentType::Image) {
+        anyhow::bail!("Entry {} is not an image", entry.id);
+    }
+
+    if let Some(path) = &entry.image_path {
+        let mut file = std::fs::File::open(path)?;
+        let total_size = file.metadata()?.len();
+        file.seek(SeekFrom::Start(offset.min(total_size)))?;
+        let mut data = Vec::new();
+        file.take(length).read_to_end(&mut data)?;
+        return Ok((data, total_size));
+    }
+
+    let bytes = entry.content_data.as_deref().unwrap_or_default();
+    let start = (offset as usize).min(bytes.len());
+    let end = start.saturating_add(length as usize).min(bytes.len());
+    Ok((bytes[start..end].to_vec(), bytes.len() as u64))
+}
+
+/// Optional IPC features this build of the daemon serves
+fn daemon_capabilities() -> Vec<&'static str> {
+    let ocr_built = cfg!(any(feature = "tesseract", feature = "ocrs"));
+    capability::ALL
+        .iter()
+        .copied()
+        .filter(|&c| c != capability::OCR || ocr_built)
+        .collect()
+}
+
+fn to_ipc_ec_level(level: clippit_core::QrEcLevel) -> QrEcLevel {
+    match level {
+        clippit_core::QrEcLevel::Low => QrEcLevel::Low,
+        clippit_core::QrEcLevel::Medium => QrEcLevel::Medium,
+        clippit_core::QrEcLevel::Quartile => QrEcLevel::Quartile,
+        clippit_core::QrEcLevel::High => QrEcLevel::High,
+    }
+}
+
+fn to_core_ec_level(level: QrEcLevel) -> clippit_core::QrEcLevel {
+    match level {
+        QrEcLevel::Low => clippit_core::QrEcLevel::Low,
+        QrEcLevel::Medium => clippit_core::QrEcLevel::Medium,
+        QrEcLevel::Quartile => clippit_core::QrEcLevel::Quartile,
+        QrEcLevel::High => clippit_core::QrEcLevel::High,
+    }
+}
+
+/// Converts a stored entry into its IPC representation
+fn to_ipc_entry(entry: ClipboardEntry) -> HistoryEntry {
+    HistoryEntry {
+        id: entry.id,
+        content_type: match entry.content_type {
+            clippit_core::ContentType::Text => ContentType::Text,
+            clippit_core::ContentType::Image => ContentType::Image,
+        },
+        content_text: entry.content_text,
+        content_data: entry.content_data,
+        image_path: entry.image_path,
+        thumbnail_data: entry.thumbnail_data,
+        image_width: entry.image_width,
+        image_height: entry.image_height,
+        ocr_text: entry.ocr_text,
+        timestamp: entry.timestamp,
+        mime_type: entry.mime_type,
+        barcode_text: entry.barcode_text,
+        text_kind: entry.text_kind.map(to_ipc_text_kind),
+        code_language: entry.code_language,
+        tags: entry.tags,
+    }
+}
+
+/// `Error` response for a failed request
+///
+/// The code comes from the cause when it tells more (locked database, missing
+/// file, timeout); otherwise it is `fallback`.
+fn error_response(
+    fallback: ErrorCode,
+    context: &str,
+    error: impl Into<anyhow::Error>,
+) -> IpcResponse {
+    let error = error.into();
+    let code = error
+        .chain()
+        .find_map(|cause| {
+            if let Some(e) = cause.downcast_ref::<rusqlite::Error>() {
+                return Some(match e.sqlite_error_code() {
+                    Some(
+                        rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked,
+                    ) => ErrorCode::Busy,
+                    _ => ErrorCode::StorageFailure,
+                });
+            }
+            match cause.downcast_ref::<std::io::Error>()?.kind() {
+                std::io::ErrorKind::NotFound => Some(ErrorCode::NotFound),
+                std::io::ErrorKind::TimedOut => Some(ErrorCode::Timeout),
+                _ => Some(ErrorCode::StorageFailure),
+            }
+        })
+        .unwrap_or(fallback);
+
+    error!("❌ {}: {:#} ({:?})", context, error, code);
+    IpcResponse::Error {
+        message: format!("{}: {}", context, error),
+        code,
+        details: Some(error.root_cause().to_string()),
+    }
+}
+
+fn to_ipc_stats(stats: clippit_core::HistoryStats) -> HistoryStats {
+    HistoryStats {
+        total_entries: stats.total_entries,
+        text_entries: stats.text_entries,
+        image_entries: stats.image_entries,
+        tag_count: stats.tag_count,
+        database_bytes: stats.database_bytes,
+        image_bytes: stats.image_bytes,
+        oldest: stats.oldest,
+        newest: stats.newest,
+    }
+}
+
+fn to_ipc_tag(tag: clippit_core::Tag) -> TagInfo {
+    TagInfo {
+        id: tag.id,
+        name: tag.name,
+        entry_count: tag.entry_count,
+    }
+}
+
+fn to_ipc_snippet(snippet: clippit_core::Snippet) -> SnippetInfo {
+    SnippetInfo {
+        id: snippet.id,
+        title: snippet.title,
+        body: snippet.body,
+        keyword: snippet.keyword,
+        updated_at: snippet.updated_at,
+    }
+}
+
+fn to_ipc_text_kind(kind: clippit_core::TextKind) -> TextKind {
+    match kind {
+        clippit_core::TextKind::Plain => TextKind::Plain,
+        clippit_core::TextKind::Url => TextKind::Url,
+        clippit_core::TextKind::Email => TextKind::Email,
+        clippit_core::TextKind::Phone => TextKind::Phone,
+        clippit_core::TextKind::Path => TextKind::Path,
+        clippit_core::TextKind::Color => TextKind::Color,
+        clippit_core::TextKind::Json => TextKind::Json,
+        clippit_core::TextKind::Code => TextKind::Code,
+        clippit_core::TextKind::Number => TextKind::Number,
+    }
+}
+
 fn get_db_path() -> PathBuf {
     let mut path = dirs::data_local_dir().unwrap_or_else(|| PathBuf::from("."));
     path.push("clippit");
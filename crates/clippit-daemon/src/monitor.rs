@@ -1,32 +1,44 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use arboard::{Clipboard, ImageData};
-use clippit_core::{ClipboardEntry, Config, HistoryManager};
-use dirs;
-use image::{self, DynamicImage, ImageFormat};
-use std::io::Cursor;
+use clippit_core::{
+    imaging, phash, CaptureRule, ClipboardEntry, Config, ContentValidator, HistoryManager, RuleSet,
+};
+use image::{self, ImageFormat};
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio::sync::mpsc;
 use tokio::time::sleep;
 use tracing::{error, info, warn};
 
-use crate::ocr_processor;
+use crate::barcode;
+use crate::clipboard_source::{self, ClipboardImage, ClipboardSnapshot};
+use crate::ocr_queue::OcrQueue;
+use crate::svg;
 
-pub async fn start_monitor(history_manager: Arc<Mutex<HistoryManager>>) -> Result<()> {
-    info!("Starting clipboard monitor (Wayland-native with arboard)...");
+pub async fn start_monitor(
+    history_manager: Arc<Mutex<HistoryManager>>,
+    ocr_queue: Arc<OcrQueue>,
+) -> Result<()> {
+    info!("Starting clipboard monitor (event-driven)...");
 
-    // Retry clipboard initialization with exponential backoff
+    // Retry source initialization with exponential backoff
     // This is crucial for boot scenarios where Wayland may not be ready
-    let mut clipboard = None;
+    let mut source = None;
     let mut retry_delay = Duration::from_millis(100);
 
     for attempt in 1..=10 {
-        match Clipboard::new() {
-            Ok(clip) => {
-                clipboard = Some(clip);
+        let config = Config::load().unwrap_or_default();
+        match clipboard_source::open(&config) {
+            Ok(s) => {
                 info!(
-                    "✅ Clipboard initialized successfully on attempt {}",
+                    "✅ Clipboard source '{}' initialized on attempt {}",
+                    s.name(),
                     attempt
                 );
+                source = Some(s);
                 break;
             }
             Err(e) => {
@@ -41,204 +53,386 @@ pub async fn start_monitor(history_manager: Arc<Mutex<HistoryManager>>) -> Resul
         }
     }
 
-    let mut clipboard = clipboard.ok_or_else(|| {
+    let mut source = source.ok_or_else(|| {
         anyhow::anyhow!(
             "Failed to initialize clipboard after 10 attempts. Wayland may not be ready."
         )
     })?;
 
-    let mut last_text: Option<String> = None;
-    let mut last_image_hash: Option<String> = None;
+    // Sources block, so they run on their own thread and feed the pipeline
+    let (tx, mut rx) = mpsc::channel::<ClipboardSnapshot>(16);
+    let source_thread = std::thread::spawn(move || -> Result<()> {
+        while let Some(snapshot) = source.next_change()? {
+            if tx.blocking_send(snapshot).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    });
+
+    let mut pipeline =
+        CapturePipeline::new(history_manager, images_dir()?).with_ocr_queue(ocr_queue);
 
-    loop {
-        // Load config for each iteration (to respect runtime changes)
+    while let Some(snapshot) = rx.recv().await {
+        // Load config for each change (to respect runtime changes)
         let config = Config::load().unwrap_or_default();
+        pipeline.process(snapshot, &config);
+    }
+
+    match source_thread.join() {
+        Ok(Ok(())) => Err(anyhow::anyhow!("Clipboard source stopped unexpectedly")),
+        Ok(Err(e)) => Err(e.context("Clipboard source failed")),
+        Err(_) => Err(anyhow::anyhow!("Clipboard source thread panicked")),
+    }
+}
 
-        // Try to get text first
-        match clipboard.get_text() {
-            Ok(text) => {
-                // Check if content changed
-                if last_text.as_ref() != Some(&text) {
-                    info!("Clipboard text changed, saving to history");
+/// Turns clipboard snapshots into history entries
+///
+/// Kept independent from the clipboard backend so it can be driven by a scripted
+/// source in tests.
+pub struct CapturePipeline {
+    history_manager: Arc<Mutex<HistoryManager>>,
+    images_dir: PathBuf,
+    last_text: Option<String>,
+    last_image_hash: Option<String>,
+    ocr_queue: Option<Arc<OcrQueue>>,
+    /// `[[rules]]` last handed to the history manager
+    rules: Vec<CaptureRule>,
+    /// Owner of the snapshot being processed
+    source_app: Option<String>,
+}
 
-                    let entry = ClipboardEntry::new_text(text.clone());
-                    let mut manager = history_manager.lock().unwrap();
+impl CapturePipeline {
+    pub fn new(history_manager: Arc<Mutex<HistoryManager>>, images_dir: PathBuf) -> Self {
+        Self {
+            history_manager,
+            images_dir,
+            last_text: None,
+            last_image_hash: None,
+            ocr_queue: None,
+            rules: Vec::new(),
+            source_app: None,
+        }
+    }
 
-                    match manager.add_entry(entry) {
-                        Ok(Some(id)) => {
-                            info!("Saved text entry with id {}", id);
-                            last_text = Some(text);
-                        }
-                        Ok(None) => {
-                            // Duplicate, skip
-                        }
-                        Err(e) => {
-                            error!("Failed to save entry: {}", e);
-                        }
-                    }
+    /// New images are queued here for OCR when `features.enable_ocr` is on
+    pub fn with_ocr_queue(mut self, ocr_queue: Arc<OcrQueue>) -> Self {
+        self.ocr_queue = Some(ocr_queue);
+        self
+    }
+
+    /// Stores the content of a snapshot, returning the ids of new entries
+    pub fn process(&mut self, snapshot: ClipboardSnapshot, config: &Config) -> Vec<i64> {
+        let mut added = Vec::new();
+
+        // Snippets paste the live clipboard, even when it isn't captured
+        self.history_manager
+            .lock()
+            .unwrap()
+            .set_clipboard_text(snapshot.text.clone());
+
+        // Paused: nothing is decoded or written, and the content isn't remembered
+        // so copying it again after resuming is still captured
+        if self.history_manager.lock().unwrap().is_capture_paused() {
+            return added;
+        }
+
+        self.history_manager
+            .lock()
+            .unwrap()
+            .set_validator(ContentValidator::from_config(config));
+        let distance = config.general.similar_image_distance;
+        self.history_manager
+            .lock()
+            .unwrap()
+            .set_similarity_threshold((distance > 0).then_some(distance));
+        self.update_rules(&config.rules);
+        self.source_app = snapshot.source_app;
+
+        if let Some(text) = snapshot.text {
+            if let Some(id) = self.capture_text(text) {
+                added.push(id);
+            }
+        }
+
+        if config.privacy.enable_image_capture {
+            if let Some(image) = snapshot.image {
+                if let Some(id) = self.capture_image(&image, config) {
+                    added.push(id);
                 }
             }
-            Err(_) => {
-                // No text in clipboard or error reading - this is normal
+        }
+
+        added
+    }
+
+    /// Recompiles `[[rules]]` when the config changed; a broken rule keeps the
+    /// previous set active
+    fn update_rules(&mut self, rules: &[CaptureRule]) {
+        if self.rules == rules {
+            return;
+        }
+        match RuleSet::compile(rules) {
+            Ok(compiled) => {
+                info!("📏 Loaded {} capture rule(s)", rules.len());
+                self.history_manager.lock().unwrap().set_rules(compiled);
+                self.rules = rules.to_vec();
+            }
+            Err(e) => error!("❌ Invalid capture rules, keeping the previous ones: {}", e),
+        }
+    }
+
+    fn capture_text(&mut self, text: String) -> Option<i64> {
+        // Check if content changed
+        if self.last_text.as_ref() == Some(&text) {
+            return None;
+        }
+
+        info!("Clipboard text changed, saving to history");
+
+        let mut entry = ClipboardEntry::new_text(text.clone());
+        entry.source_app = self.source_app.clone();
+        let mut manager = self.history_manager.lock().unwrap();
+        self.last_text = Some(text);
+
+        match manager.add_entry(entry) {
+            Ok(Some(id)) => {
+                info!("Saved text entry with id {}", id);
+                Some(id)
+            }
+            Ok(None) => None, // Duplicate or dropped by a rule
+            Err(e) => {
+                error!("Failed to save entry: {}", e);
+                None
             }
         }
+    }
 
-        // Try to get image if enabled
-        if config.privacy.enable_image_capture {
-            match clipboard.get_image() {
-                Ok(img_data) => {
-                    // Convert arboard::ImageData to Vec<u8> (PNG format)
-                    match convert_image_data_to_png(&img_data) {
-                        Ok(png_bytes) => {
-                            // Validate size (convert MB to bytes)
-                            let max_size_bytes =
-                                (config.privacy.max_image_size_mb as usize) * 1024 * 1024;
-
-                            if png_bytes.len() <= max_size_bytes {
-                                // Compute hash to avoid duplicates
-                                use sha2::{Digest, Sha256};
-                                let mut hasher = Sha256::new();
-                                hasher.update(&png_bytes);
-                                let current_hash = format!("{:x}", hasher.finalize());
-
-                                // Only save if different from last image
-                                if last_image_hash.as_ref() != Some(&current_hash) {
-                                    info!(
-                                        "📸 New image detected (PNG: {} bytes, hash: {}...), optimizing...",
-                                        png_bytes.len(),
-                                        &current_hash[..12]
-                                    );
-
-                                    // Optimize if needed (max 2048px)
-                                    match optimize_image(png_bytes.clone(), 2048) {
-                                        Ok((optimized, width, height)) => {
-                                            // Generate thumbnail (128x128)
-                                            let thumbnail = create_thumbnail(&optimized, 128).ok();
-
-                                            // Save image to file
-                                            match save_image_to_file(&optimized, &current_hash) {
-                                                Ok(image_path) => {
-                                                    info!(
-                                                        "💾 Saved image to: {} ({}x{})",
-                                                        image_path, width, height
-                                                    );
-
-                                                    // Clone image_path antes de mover para entry
-                                                    let image_path_for_ocr = image_path.clone();
-
-                                                    let entry =
-                                                        ClipboardEntry::new_image_with_dimensions(
-                                                            image_path, thumbnail, width, height,
-                                                        );
-                                                    let mut manager =
-                                                        history_manager.lock().unwrap();
-
-                                                    match manager.add_entry(entry) {
-                                                        Ok(Some(id)) => {
-                                                            info!("✅ Saved image entry with id {} (with thumbnail)", id);
-                                                            last_image_hash =
-                                                                Some(current_hash.clone());
-
-                                                            // Disparar OCR em background se habilitado
-                                                            if config.features.enable_ocr {
-                                                                let path_clone = image_path_for_ocr;
-                                                                let languages =
-                                                                    config.ocr.languages.clone();
-                                                                let db_path = dirs::data_local_dir()
-                                                                    .expect("Failed to get data directory")
-                                                                    .join("clippit")
-                                                                    .join("history.db")
-                                                                    .to_str()
-                                                                    .expect("Path to string failed")
-                                                                    .to_string();
-
-                                                                info!("🔍 Starting OCR processing in background for entry {}", id);
-
-                                                                tokio::spawn(async move {
-                                                                    ocr_processor::process_ocr_for_entry(
-                                                                        id,
-                                                                        path_clone,
-                                                                        languages,
-                                                                        db_path,
-                                                                    )
-                                                                    .await;
-                                                                });
-                                                            }
-                                                        }
-                                                        Ok(None) => {
-                                                            info!("⏭️  Image duplicate, skipped");
-                                                            // Update hash even for duplicates to avoid loop
-                                                            last_image_hash =
-                                                                Some(current_hash.clone());
-                                                        }
-                                                        Err(e) => {
-                                                            error!(
-                                                                "❌ Failed to save image entry: {}",
-                                                                e
-                                                            );
-                                                        }
-                                                    }
-                                                }
-                                                Err(e) => {
-                                                    error!("❌ Failed to save image file: {}", e);
-                                                }
-                                            }
-                                        }
-                                        Err(e) => {
-                                            error!("❌ Failed to optimize image: {}", e);
-                                        }
-                                    }
-                                } else {
-                                    // CRITICAL: Update hash even for duplicates to avoid infinite loop!
-                                    // Without this, daemon keeps processing same image forever
-                                    // Use trace level to avoid log spam (clipboard has same image constantly)
-                                    last_image_hash = Some(current_hash);
-                                }
-                            } else {
-                                warn!(
-                                    "⚠️  Image too large ({} MB > {} MB), skipping",
-                                    png_bytes.len() / 1024 / 1024,
-                                    config.privacy.max_image_size_mb
-                                );
+    fn capture_image(&mut self, image: &ClipboardImage, config: &Config) -> Option<i64> {
+        // Size, format and pixel limits come from the config (general + privacy sections)
+        let validator = ContentValidator::from_config(config);
+
+        // The original encoding is kept; SVG stays a document
+        if imaging::is_svg_mime(&image.mime_type) || imaging::is_svg(&image.data) {
+            return self.capture_svg(&image.data, &validator);
+        }
+        let data = image.data.clone();
+
+        // Header-only check first: dimensions are verified before anything is decoded
+        let format = match validator.validate_image(&data) {
+            Ok(format) => format,
+            Err(e) => {
+                warn!("⚠️  Skipping image ({} bytes): {}", data.len(), e);
+                return None;
+            }
+        };
+
+        // Compute hash to avoid duplicates
+        let current_hash = self.new_image_hash(&data)?;
+
+        info!(
+            "📸 New image detected ({}: {} bytes, hash: {}...), optimizing...",
+            imaging::mime_type(format),
+            data.len(),
+            &current_hash[..12]
+        );
+
+        // Optimize if needed (max dimension from config)
+        let optimized = match optimize_image(
+            data,
+            format,
+            config.general.max_image_dimension,
+            config.features.decode_barcodes,
+            &validator,
+        ) {
+            Ok(result) => result,
+            Err(e) => {
+                error!("❌ Failed to optimize image: {}", e);
+                return None;
+            }
+        };
+
+        // Remove EXIF/GPS before anything is written to disk
+        let (width, height) = (optimized.width, optimized.height);
+        let cleaned = match imaging::strip_metadata(&optimized.data, format) {
+            Ok(cleaned) => cleaned,
+            Err(e) => {
+                error!("❌ Failed to strip image metadata: {}", e);
+                return None;
+            }
+        };
+
+        // Generate thumbnail (128x128)
+        let thumbnail = create_thumbnail(&cleaned, 128, &validator).ok();
+
+        // Save image to file
+        let image_path = match save_image_to_file(&self.images_dir, &cleaned, &current_hash, format)
+        {
+            Ok(path) => path,
+            Err(e) => {
+                error!("❌ Failed to save image file: {}", e);
+                return None;
+            }
+        };
+        info!("💾 Saved image to: {} ({}x{})", image_path, width, height);
+
+        let mut entry =
+            ClipboardEntry::new_image_with_dimensions(image_path, thumbnail, width, height);
+        entry.mime_type = Some(imaging::mime_type(format).to_string());
+        entry.image_phash = Some(optimized.phash);
+        entry.barcode_text = optimized.barcode_text;
+
+        let id = self.add_image_entry(entry)?;
+
+        // Enfileirar OCR em background se habilitado
+        if config.features.enable_ocr {
+            if let Some(queue) = &self.ocr_queue {
+                if let Err(e) = queue.enqueue(id) {
+                    error!("❌ Failed to queue OCR for entry {}: {}", id, e);
+                }
+            }
+        }
+        Some(id)
+    }
+
+    /// SVG is saved as the original document; only the thumbnail is rasterized
+    fn capture_svg(&mut self, data: &[u8], validator: &ContentValidator) -> Option<i64> {
+        if let Err(e) = validator.validate_svg(data) {
+            warn!("⚠️  Skipping SVG ({} bytes): {}", data.len(), e);
+            return None;
+        }
+
+        let current_hash = self.new_image_hash(data)?;
+        info!(
+            "📐 New SVG detected ({} bytes, hash: {}...)",
+            data.len(),
+            &current_hash[..12]
+        );
+
+        let document = match svg::parse(data) {
+            Ok(document) => document,
+            Err(e) => {
+                error!("❌ Failed to parse SVG: {}", e);
+                return None;
+            }
+        };
+
+        let render = match document.render(128) {
+            Ok(render) => render,
+            Err(e) => {
+                error!("❌ Failed to render SVG: {}", e);
+                return None;
+            }
+        };
+        let mut thumbnail = Vec::new();
+        if let Err(e) = render.write_to(&mut Cursor::new(&mut thumbnail), ImageFormat::Png) {
+            warn!("Failed to encode SVG thumbnail: {}", e);
+        }
+
+        let filename = format!("{}.svg", current_hash);
+        let image_path = match save_file(&self.images_dir, &filename, data) {
+            Ok(path) => path,
+            Err(e) => {
+                error!("❌ Failed to save SVG file: {}", e);
+                return None;
+            }
+        };
+        info!(
+            "💾 Saved SVG to: {} ({}x{})",
+            image_path, document.width, document.height
+        );
+
+        let mut entry = ClipboardEntry::new_image_with_dimensions(
+            image_path,
+            (!thumbnail.is_empty()).then_some(thumbnail),
+            document.width,
+            document.height,
+        );
+        entry.mime_type = Some(imaging::SVG_MIME_TYPE.to_string());
+        entry.image_phash = Some(phash::dhash(&render));
+
+        // No OCR: the text of an SVG is already in the document
+        self.add_image_entry(entry)
+    }
+
+    /// SHA-256 of the image bytes, or None if it's the same image as last time
+    fn new_image_hash(&mut self, data: &[u8]) -> Option<String> {
+        use sha2::{Digest, Sha256};
+        let mut hasher = Sha256::new();
+        hasher.update(data);
+        let current_hash = format!("{:x}", hasher.finalize());
+
+        // Only save if different from last image
+        if self.last_image_hash.as_ref() == Some(&current_hash) {
+            return None;
+        }
+        self.last_image_hash = Some(current_hash.clone());
+        Some(current_hash)
+    }
+
+    fn add_image_entry(&self, mut entry: ClipboardEntry) -> Option<i64> {
+        entry.source_app = self.source_app.clone();
+        let image_path = entry.image_path.clone();
+        let mut manager = self.history_manager.lock().unwrap();
+
+        match manager.add_entry(entry) {
+            Ok(Some(id)) => {
+                info!("✅ Saved image entry with id {} (with thumbnail)", id);
+                Some(id)
+            }
+            Ok(None) => {
+                info!("⏭️  Image duplicate or dropped by a rule, skipped");
+                // The file was written before the decision; keep it only if an
+                // existing entry points to it
+                if let Some(path) = image_path {
+                    match manager.is_image_path_used(&path) {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            if let Err(e) = std::fs::remove_file(&path) {
+                                warn!("Failed to remove skipped image {}: {}", path, e);
                             }
                         }
-                        Err(e) => {
-                            warn!("Failed to convert image data to PNG: {}", e);
-                        }
+                        Err(e) => warn!("Failed to check image file {}: {}", path, e),
                     }
                 }
-                Err(_) => {
-                    // No image in clipboard - this is normal
-                }
+                None
+            }
+            Err(e) => {
+                error!("❌ Failed to save image entry: {}", e);
+                None
             }
         }
-
-        // Polling interval (80ms for good responsiveness)
-        sleep(Duration::from_millis(80)).await;
     }
 }
 
-/// Convert arboard ImageData to PNG bytes
-fn convert_image_data_to_png(img_data: &ImageData) -> Result<Vec<u8>> {
-    // Create image from raw RGBA data
-    let img = image::RgbaImage::from_raw(
-        img_data.width as u32,
-        img_data.height as u32,
-        img_data.bytes.to_vec(),
-    )
-    .ok_or_else(|| anyhow::anyhow!("Failed to create image from raw data"))?;
-
-    let dynamic_img = DynamicImage::ImageRgba8(img);
-
-    // Encode to PNG
-    let mut buf = Vec::new();
-    dynamic_img.write_to(&mut Cursor::new(&mut buf), ImageFormat::Png)?;
+/// Directory where captured images are stored
+fn images_dir() -> Result<PathBuf> {
+    let mut images_dir =
+        dirs::data_local_dir().ok_or_else(|| anyhow::anyhow!("Failed to get data directory"))?;
+    images_dir.push("clippit");
+    images_dir.push("images");
+    Ok(images_dir)
+}
 
-    // Reduced to trace to avoid log spam (runs every 80ms)
-    Ok(buf)
+/// Image ready to be saved
+struct OptimizedImage {
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    /// dHash of the original pixels, for near-duplicate detection
+    phash: u64,
+    /// QR/barcode payloads, decoded from the original pixels
+    barcode_text: Option<String>,
 }
 
-fn optimize_image(data: Vec<u8>, max_dimension: u32) -> Result<(Vec<u8>, u32, u32)> {
+/// Downscales images larger than `max_dimension`, re-encoding in the same format
+fn optimize_image(
+    data: Vec<u8>,
+    format: ImageFormat,
+    max_dimension: u32,
+    decode_barcodes: bool,
+    validator: &ContentValidator,
+) -> Result<OptimizedImage> {
     // Validate minimum size (avoid processing garbage data)
     if data.len() < 100 {
         return Err(anyhow::anyhow!(
@@ -247,8 +441,8 @@ fn optimize_image(data: Vec<u8>, max_dimension: u32) -> Result<(Vec<u8>, u32, u3
         ));
     }
 
-    // Try to load image with better error messages
-    let img = match image::load_from_memory(&data) {
+    // Decode with limits: oversized images fail before the pixels are allocated
+    let img = match validator.decode_image(&data) {
         Ok(img) => img,
         Err(e) => {
             // Log the error type for debugging
@@ -259,7 +453,19 @@ fn optimize_image(data: Vec<u8>, max_dimension: u32) -> Result<(Vec<u8>, u32, u3
 
     let original_width = img.width();
     let original_height = img.height();
-    let needs_resize = original_width > max_dimension || original_height > max_dimension;
+    let phash = phash::dhash(&img);
+    // Before resizing: small QR codes in big screenshots need every pixel
+    let barcode_text = if decode_barcodes {
+        barcode::payload_text(&barcode::decode(&img))
+    } else {
+        None
+    };
+    if let Some(text) = &barcode_text {
+        info!("🔳 Decoded {} QR/barcode payload(s)", text.lines().count());
+    }
+    // Animated GIF/WebP are kept as-is: resizing would drop the animation
+    let needs_resize = (original_width > max_dimension || original_height > max_dimension)
+        && !imaging::is_animated(&data, format);
 
     if needs_resize {
         info!(
@@ -273,30 +479,41 @@ fn optimize_image(data: Vec<u8>, max_dimension: u32) -> Result<(Vec<u8>, u32, u3
         );
         let final_width = resized.width();
         let final_height = resized.height();
-        let mut buf = Vec::new();
-        resized.write_to(&mut Cursor::new(&mut buf), ImageFormat::Png)?;
+        let buf = imaging::encode(&resized, format)?;
         info!(
             "✅ Image optimized: {} bytes -> {} bytes",
             data.len(),
             buf.len()
         );
-        Ok((buf, final_width, final_height))
+        Ok(OptimizedImage {
+            data: buf,
+            width: final_width,
+            height: final_height,
+            phash,
+            barcode_text,
+        })
     } else {
         info!(
             "✅ Image already optimal ({}x{})",
             original_width, original_height
         );
-        Ok((data, original_width, original_height))
+        Ok(OptimizedImage {
+            data,
+            width: original_width,
+            height: original_height,
+            phash,
+            barcode_text,
+        })
     }
 }
 
 /// Create a thumbnail (128x128) for preview purposes
-fn create_thumbnail(data: &[u8], size: u32) -> Result<Vec<u8>> {
+fn create_thumbnail(data: &[u8], size: u32, validator: &ContentValidator) -> Result<Vec<u8>> {
     if data.len() < 100 {
         return Err(anyhow::anyhow!("Image data too small"));
     }
 
-    let img = image::load_from_memory(data)?;
+    let img = validator.decode_image(data)?;
     let thumbnail = img.resize(size, size, image::imageops::FilterType::Lanczos3);
 
     let mut buf = Vec::new();
@@ -306,19 +523,21 @@ fn create_thumbnail(data: &[u8], size: u32) -> Result<Vec<u8>> {
 }
 
 /// Save image to disk and return the file path
-fn save_image_to_file(image_data: &[u8], hash: &str) -> Result<String> {
-    // Create images directory if it doesn't exist
-    let mut images_dir =
-        dirs::data_local_dir().ok_or_else(|| anyhow::anyhow!("Failed to get data directory"))?;
-    images_dir.push("clippit");
-    images_dir.push("images");
-
-    std::fs::create_dir_all(&images_dir)?;
+fn save_image_to_file(
+    images_dir: &Path,
+    image_data: &[u8],
+    hash: &str,
+    format: ImageFormat,
+) -> Result<String> {
+    // Create filename from hash, keeping the original format's extension
+    let filename = format!("{}.{}", hash, imaging::extension(format));
+    save_file(images_dir, &filename, image_data)
+}
 
-    // Create filename from hash
-    let filename = format!("{}.png", hash);
-    let mut file_path = images_dir.clone();
-    file_path.push(&filename);
+fn save_file(images_dir: &Path, filename: &str, image_data: &[u8]) -> Result<String> {
+    // Create images directory if it doesn't exist
+    std::fs::create_dir_all(images_dir)?;
+    let file_path = images_dir.join(filename);
 
     // Save image to file
     std::fs::write(&file_path, image_data)?;
@@ -328,36 +547,637 @@ fn save_image_to_file(image_data: &[u8], hash: &str) -> Result<String> {
 }
 
 /// Set clipboard content (used for copying entries back to clipboard)
-#[allow(dead_code)]
+///
+/// Images go back byte for byte under their stored MIME type, so JPEG, WebP,
+/// animated GIF and SVG stay what they were instead of becoming PNG.
 pub fn set_clipboard_content(entry: &ClipboardEntry) -> Result<()> {
-    let mut clipboard = Clipboard::new()?;
-
     match entry.content_type {
         clippit_core::ContentType::Text => {
             if let Some(text) = &entry.content_text {
-                clipboard.set_text(text)?;
+                Clipboard::new()?.set_text(text)?;
                 info!("✅ Set clipboard to text entry");
             }
         }
         clippit_core::ContentType::Image => {
-            if let Some(data) = &entry.content_data {
-                info!("📋 Setting clipboard to image ({} bytes)", data.len());
-
-                // Load image and convert to ImageData
-                let img = image::load_from_memory(data)?;
-                let rgba = img.to_rgba8();
+            let data = match (&entry.content_data, &entry.image_path) {
+                (Some(data), _) => data.clone(),
+                (None, Some(path)) => std::fs::read(path)?,
+                (None, None) => return Err(anyhow::anyhow!("Image entry has no data")),
+            };
+            let mime_type = image_mime_type(entry, &data)
+                .ok_or_else(|| anyhow::anyhow!("Unknown image format"))?;
+            info!(
+                "📋 Setting clipboard to {} image ({} bytes)",
+                mime_type,
+                data.len()
+            );
 
-                let img_data = ImageData {
+            if let Err(e) = copy_with_mime(&data, &mime_type) {
+                if imaging::is_svg_mime(&mime_type) {
+                    return Err(e);
+                }
+                // Without the clipboard tools the image still goes out, as PNG
+                warn!(
+                    "⚠️  Failed to copy as {}, falling back to PNG: {}",
+                    mime_type, e
+                );
+                let rgba = image::load_from_memory(&data)?.to_rgba8();
+                Clipboard::new()?.set_image(ImageData {
                     width: rgba.width() as usize,
                     height: rgba.height() as usize,
                     bytes: rgba.as_raw().into(),
-                };
-
-                clipboard.set_image(img_data)?;
-                info!("✅ Image copied to clipboard successfully");
+                })?;
             }
+            info!("✅ Image copied to clipboard successfully");
         }
     }
 
     Ok(())
 }
+
+/// MIME type an image entry goes back to the clipboard with
+///
+/// Entries stored before formats were kept have none; their bytes tell.
+fn image_mime_type(entry: &ClipboardEntry, data: &[u8]) -> Option<String> {
+    if let Some(mime_type) = &entry.mime_type {
+        return Some(mime_type.clone());
+    }
+    if imaging::is_svg(data) {
+        return Some(imaging::SVG_MIME_TYPE.to_string());
+    }
+    imaging::detect_format(data).map(|format| imaging::mime_type(format).to_string())
+}
+
+/// Puts `data` on the clipboard under `mime_type`
+///
+/// arboard only offers images as PNG, so this goes through the clipboard tools
+/// (wl-copy on Wayland, xclip on X11), which stay running as selection owners.
+fn copy_with_mime(data: &[u8], mime_type: &str) -> Result<()> {
+    let mut command = clipboard_tool(mime_type, std::env::var_os("WAYLAND_DISPLAY").is_some());
+    let tool = command.get_program().to_string_lossy().into_owned();
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", tool))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(data)?;
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("{} exited with {}", tool, status));
+    }
+    Ok(())
+}
+
+fn clipboard_tool(mime_type: &str, wayland: bool) -> Command {
+    if wayland {
+        let mut command = Command::new("wl-copy");
+        command.arg("--type").arg(mime_type);
+        command
+    } else {
+        let mut command = Command::new("xclip");
+        command
+            .args(["-selection", "clipboard", "-t"])
+            .arg(mime_type)
+            .arg("-i");
+        command
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard_source::fake::FakeSource;
+    use crate::clipboard_source::ClipboardSource;
+    use image::DynamicImage;
+
+    fn test_config() -> Config {
+        let mut config = Config::default();
+        config.features.enable_ocr = false;
+        config
+    }
+
+    /// Drives the pipeline the same way `start_monitor` does, minus the thread
+    fn run(source: &mut dyn ClipboardSource, pipeline: &mut CapturePipeline) -> Vec<i64> {
+        let config = test_config();
+        let mut added = Vec::new();
+        while let Some(snapshot) = source.next_change().unwrap() {
+            added.extend(pipeline.process(snapshot, &config));
+        }
+        added
+    }
+
+    fn pipeline(images_dir: &Path) -> (CapturePipeline, Arc<Mutex<HistoryManager>>) {
+        let history = Arc::new(Mutex::new(HistoryManager::new_in_memory(100).unwrap()));
+        let pipeline = CapturePipeline::new(Arc::clone(&history), images_dir.to_path_buf());
+        (pipeline, history)
+    }
+
+    #[test]
+    fn test_text_changes_are_captured() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut pipeline, history) = pipeline(dir.path());
+        let mut source =
+            FakeSource::new(vec![FakeSource::text("first"), FakeSource::text("second")]);
+
+        let added = run(&mut source, &mut pipeline);
+
+        assert_eq!(added.len(), 2);
+        let entries = history.lock().unwrap().get_recent(10).unwrap();
+        let texts: Vec<_> = entries
+            .iter()
+            .filter_map(|e| e.content_text.clone())
+            .collect();
+        assert!(texts.contains(&"first".to_string()));
+        assert!(texts.contains(&"second".to_string()));
+    }
+
+    #[test]
+    fn test_repeated_notification_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut pipeline, history) = pipeline(dir.path());
+        let mut source = FakeSource::new(vec![FakeSource::text("same"), FakeSource::text("same")]);
+
+        let added = run(&mut source, &mut pipeline);
+
+        assert_eq!(added.len(), 1);
+        assert_eq!(history.lock().unwrap().count().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_image_is_saved_with_thumbnail() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut pipeline, history) = pipeline(dir.path());
+        let mut source = FakeSource::new(vec![FakeSource::image(64, 32, [255, 0, 0, 255])]);
+
+        let added = run(&mut source, &mut pipeline);
+
+        assert_eq!(added.len(), 1);
+        let entry = history
+            .lock()
+            .unwrap()
+            .get_by_id(added[0])
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.image_width, Some(64));
+        assert_eq!(entry.image_height, Some(32));
+        assert!(entry.thumbnail_data.is_some());
+        let path = entry.image_path.unwrap();
+        assert!(Path::new(&path).starts_with(dir.path()));
+        assert!(Path::new(&path).exists());
+    }
+
+    #[test]
+    fn test_animated_gif_is_kept_intact() {
+        use image::codecs::gif::GifEncoder;
+        use image::{Delay, Frame, Rgba, RgbaImage};
+
+        let dir = tempfile::tempdir().unwrap();
+        let (mut pipeline, history) = pipeline(dir.path());
+
+        // Larger than max_image_dimension: a static image would be downscaled
+        let mut config = test_config();
+        config.general.max_image_dimension = 16;
+        let frames = [[255, 0, 0, 255], [0, 0, 255, 255]].map(|color| {
+            Frame::from_parts(
+                RgbaImage::from_pixel(48, 24, Rgba(color)),
+                0,
+                0,
+                Delay::from_numer_denom_ms(100, 1),
+            )
+        });
+        let mut gif = Vec::new();
+        GifEncoder::new(&mut gif).encode_frames(frames).unwrap();
+
+        let snapshot = FakeSource::encoded("image/gif", gif.clone());
+        let added = pipeline.process(snapshot, &config);
+
+        assert_eq!(added.len(), 1);
+        let entry = history
+            .lock()
+            .unwrap()
+            .get_by_id(added[0])
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.mime_type.as_deref(), Some("image/gif"));
+        assert_eq!(
+            (entry.image_width, entry.image_height),
+            (Some(48), Some(24))
+        );
+        assert_eq!(std::fs::read(entry.image_path.unwrap()).unwrap(), gif);
+
+        // Thumbnail is the first frame
+        let thumbnail = image::load_from_memory(&entry.thumbnail_data.unwrap()).unwrap();
+        assert_eq!(thumbnail.to_rgba8().get_pixel(0, 0).0, [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_svg_is_stored_as_document_with_raster_thumbnail() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut pipeline, history) = pipeline(dir.path());
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="400" height="200">
+            <rect width="400" height="200" fill="blue"/>
+        </svg>"#;
+        let mut source = FakeSource::new(vec![FakeSource::encoded("image/svg+xml", svg.to_vec())]);
+
+        let added = run(&mut source, &mut pipeline);
+
+        assert_eq!(added.len(), 1);
+        let entry = history
+            .lock()
+            .unwrap()
+            .get_by_id(added[0])
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.mime_type.as_deref(), Some("image/svg+xml"));
+        assert_eq!(
+            (entry.image_width, entry.image_height),
+            (Some(400), Some(200))
+        );
+        assert!(entry.image_phash.is_some());
+
+        let path = entry.image_path.unwrap();
+        assert!(path.ends_with(".svg"));
+        assert_eq!(std::fs::read(path).unwrap(), svg);
+
+        let thumbnail = image::load_from_memory(&entry.thumbnail_data.unwrap()).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (128, 64));
+        assert_eq!(thumbnail.to_rgba8().get_pixel(5, 5).0, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn test_jpeg_keeps_format_and_loses_exif() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut pipeline, history) = pipeline(dir.path());
+
+        // JPEG with an EXIF block carrying a GPS marker
+        let img = DynamicImage::new_rgb8(40, 30);
+        let jpeg = imaging::encode(&img, ImageFormat::Jpeg).unwrap();
+        let exif = b"\xff\xe1\x00\x15Exif\0\0MM\0\x2a\0\0\0\x08GPS\0\0";
+        let mut data = jpeg[..2].to_vec();
+        data.extend_from_slice(exif);
+        data.extend_from_slice(&jpeg[2..]);
+
+        let added = pipeline.process(FakeSource::encoded("image/jpeg", data), &test_config());
+
+        assert_eq!(added.len(), 1);
+        let entry = history
+            .lock()
+            .unwrap()
+            .get_by_id(added[0])
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.mime_type.as_deref(), Some("image/jpeg"));
+        let path = entry.image_path.unwrap();
+        assert!(path.ends_with(".jpg"));
+        let saved = std::fs::read(&path).unwrap();
+        assert_eq!(imaging::detect_format(&saved), Some(ImageFormat::Jpeg));
+        assert!(!saved.windows(3).any(|w| w == b"GPS"));
+    }
+
+    #[test]
+    fn test_image_limits_come_from_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut pipeline, history) = pipeline(dir.path());
+        let mut config = test_config();
+        config.general.max_image_size = 64;
+        config.general.max_image_dimension = 16;
+
+        let big = pipeline.process(FakeSource::image(32, 32, [1, 2, 3, 255]), &config);
+        assert!(big.is_empty());
+
+        config.general.max_image_size = Config::default().general.max_image_size;
+        let added = pipeline.process(FakeSource::image(32, 32, [1, 2, 3, 255]), &config);
+        assert_eq!(added.len(), 1);
+        let entry = history
+            .lock()
+            .unwrap()
+            .get_by_id(added[0])
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.image_width, Some(16));
+        assert_eq!(entry.mime_type.as_deref(), Some("image/png"));
+    }
+
+    #[test]
+    fn test_oversized_images_are_refused_before_decoding() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut pipeline, history) = pipeline(dir.path());
+        let mut config = test_config();
+        config.general.max_image_pixels = 1_000_000;
+
+        // A few KB on the wire, 16MB once decoded
+        let img = DynamicImage::new_rgba8(2000, 2000);
+        let bomb = imaging::encode(&img, ImageFormat::Png).unwrap();
+        assert!(bomb.len() < 100 * 1024);
+
+        let added = pipeline.process(FakeSource::encoded("image/png", bomb), &config);
+
+        assert!(added.is_empty());
+        assert_eq!(history.lock().unwrap().count().unwrap(), 0);
+        assert!(!dir.path().exists() || std::fs::read_dir(dir.path()).unwrap().count() == 0);
+    }
+
+    /// Tiny PNG whose IHDR claims `width`x`height` (the IDAT stays 8x8)
+    fn png_bomb(width: u32, height: u32) -> Vec<u8> {
+        use img_parts::png::{Png, PngChunk};
+        use img_parts::Bytes;
+
+        let small = imaging::encode(&DynamicImage::new_rgba8(8, 8), ImageFormat::Png).unwrap();
+        let mut png = Png::from_bytes(small.into()).unwrap();
+        let ihdr = png.chunk_by_type(*b"IHDR").unwrap().contents().to_vec();
+
+        let mut contents = Vec::with_capacity(ihdr.len());
+        contents.extend_from_slice(&width.to_be_bytes());
+        contents.extend_from_slice(&height.to_be_bytes());
+        contents.extend_from_slice(&ihdr[8..]);
+
+        png.chunks_mut()[0] = PngChunk::new(*b"IHDR", Bytes::from(contents));
+        png.encoder().bytes().to_vec()
+    }
+
+    #[test]
+    fn test_offered_bomb_reaches_the_pipeline_undecoded() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut pipeline, history) = pipeline(dir.path());
+
+        // Read the way the X11 and polling backends read an owner's targets
+        let bomb = png_bomb(100_000, 100_000);
+        let snapshot =
+            FakeSource::offered(&[("TARGETS", b""), ("image/png", &bomb), ("image/bmp", b"BM")]);
+        let image = snapshot.image.as_ref().unwrap();
+        assert_eq!(image.mime_type, "image/png");
+        assert_eq!(image.data, bomb);
+
+        // Refused by the header check: it claims 10 billion pixels
+        let added = pipeline.process(snapshot, &test_config());
+        assert!(added.is_empty());
+        assert_eq!(history.lock().unwrap().count().unwrap(), 0);
+        assert!(image_files(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn test_near_duplicate_screenshot_is_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut pipeline, history) = pipeline(dir.path());
+
+        let screenshot = image::RgbaImage::from_fn(200, 100, |x, y| {
+            let v = ((x + y) % 256) as u8;
+            image::Rgba([v, v / 2, 255 - v, 255])
+        });
+        let mut retaken = screenshot.clone();
+        retaken.put_pixel(5, 5, image::Rgba([255, 255, 255, 255]));
+
+        let to_snapshot = |img: image::RgbaImage| {
+            let png = imaging::encode(&DynamicImage::ImageRgba8(img), ImageFormat::Png).unwrap();
+            FakeSource::encoded("image/png", png)
+        };
+
+        let config = test_config();
+        let first = pipeline.process(to_snapshot(screenshot), &config);
+        let second = pipeline.process(to_snapshot(retaken), &config);
+
+        assert_eq!(first.len(), 1);
+        assert!(second.is_empty());
+        let entry = history
+            .lock()
+            .unwrap()
+            .get_by_id(first[0])
+            .unwrap()
+            .unwrap();
+        assert!(entry.image_phash.is_some());
+        // The skipped copy doesn't leave a file behind
+        assert_eq!(image_files(dir.path()), vec![entry.image_path.unwrap()]);
+    }
+
+    /// Paths of the files in the images dir
+    fn image_files(dir: &Path) -> Vec<String> {
+        let Ok(files) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        files
+            .map(|file| file.unwrap().path().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_dropped_image_leaves_no_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut pipeline, history) = pipeline(dir.path());
+        let mut config = test_config();
+        config.rules = vec![CaptureRule {
+            name: "no images".to_string(),
+            enabled: true,
+            pattern: None,
+            kind: Some("image".to_string()),
+            source_app: None,
+            min_size: None,
+            max_size: None,
+            drop: true,
+            rewrite: None,
+            tags: Vec::new(),
+            ttl_seconds: None,
+            skip_ocr: false,
+        }];
+
+        let added = pipeline.process(FakeSource::image(64, 32, [0, 255, 0, 255]), &config);
+
+        assert!(added.is_empty());
+        assert_eq!(history.lock().unwrap().count().unwrap(), 0);
+        assert!(image_files(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn test_exact_duplicate_keeps_shared_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut pipeline, history) = pipeline(dir.path());
+        let config = test_config();
+        let red = || FakeSource::image(64, 32, [255, 0, 0, 255]);
+
+        let first = pipeline.process(red(), &config);
+        pipeline.process(FakeSource::text("in between"), &config);
+        pipeline.process(FakeSource::image(32, 32, [0, 0, 255, 255]), &config);
+        // Same bytes, same file name: skipped as a duplicate of the first entry
+        assert!(pipeline.process(red(), &config).is_empty());
+
+        let entry = history
+            .lock()
+            .unwrap()
+            .get_by_id(first[0])
+            .unwrap()
+            .unwrap();
+        assert!(Path::new(&entry.image_path.unwrap()).exists());
+        assert_eq!(image_files(dir.path()).len(), 2);
+    }
+
+    #[cfg(feature = "barcode")]
+    #[test]
+    fn test_qr_code_payload_is_searchable() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut pipeline, history) = pipeline(dir.path());
+        let qr =
+            DynamicImage::ImageLuma8(crate::barcode::tests::qr_image("otpauth://totp/Clippit"));
+        let png = imaging::encode(&qr, ImageFormat::Png).unwrap();
+        let mut source = FakeSource::new(vec![FakeSource::encoded("image/png", png)]);
+
+        let added = run(&mut source, &mut pipeline);
+
+        assert_eq!(added.len(), 1);
+        let found = history.lock().unwrap().search("otpauth").unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(
+            found[0].barcode_text.as_deref(),
+            Some("otpauth://totp/Clippit")
+        );
+    }
+
+    #[test]
+    fn test_image_capture_disabled() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut pipeline, history) = pipeline(dir.path());
+        let mut config = test_config();
+        config.privacy.enable_image_capture = false;
+
+        let added = pipeline.process(FakeSource::image(8, 8, [0, 0, 0, 255]), &config);
+
+        assert!(added.is_empty());
+        assert_eq!(history.lock().unwrap().count().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_nothing_is_stored_while_paused() {
+        let dir = tempfile::tempdir().unwrap();
+        let images_dir = dir.path().join("images");
+        let (mut pipeline, history) = pipeline(&images_dir);
+        let config = test_config();
+        history.lock().unwrap().set_capture_paused(true);
+
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"/>"#;
+        assert!(pipeline
+            .process(FakeSource::image(64, 32, [0, 0, 0, 255]), &config)
+            .is_empty());
+        assert!(pipeline
+            .process(FakeSource::encoded("image/svg+xml", svg.to_vec()), &config)
+            .is_empty());
+        assert!(pipeline
+            .process(FakeSource::text("secret"), &config)
+            .is_empty());
+        assert!(!images_dir.exists());
+
+        // Copied again after resuming: not mistaken for the last capture
+        history.lock().unwrap().set_capture_paused(false);
+        assert_eq!(
+            pipeline.process(FakeSource::text("secret"), &config).len(),
+            1
+        );
+        assert_eq!(
+            pipeline
+                .process(FakeSource::image(64, 32, [0, 0, 0, 255]), &config)
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_snippets_paste_the_live_clipboard() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut pipeline, history) = pipeline(dir.path());
+        let config = test_config();
+        let snippet = history
+            .lock()
+            .unwrap()
+            .create_snippet("Pedido", "[{clipboard}]", None)
+            .unwrap();
+        let expand = || history.lock().unwrap().expand_snippet(snippet.id).unwrap();
+
+        pipeline.process(FakeSource::text("PED-1"), &config);
+        history.lock().unwrap().set_capture_paused(true);
+        pipeline.process(FakeSource::text("PED-2"), &config);
+        assert_eq!(expand().text, "[PED-2]");
+
+        history.lock().unwrap().set_capture_paused(false);
+        pipeline.process(FakeSource::image(4, 4, [0, 0, 0, 255]), &config);
+        assert_eq!(expand().text, "[]");
+    }
+
+    #[test]
+    fn test_images_go_back_with_their_mime_type() {
+        let mut entry = ClipboardEntry::new_image_with_dimensions("/i.jpg".into(), None, 4, 4);
+        let jpeg = imaging::encode(&DynamicImage::new_rgb8(4, 4), ImageFormat::Jpeg).unwrap();
+
+        // Older entries have no MIME type stored
+        assert_eq!(
+            image_mime_type(&entry, &jpeg).as_deref(),
+            Some("image/jpeg")
+        );
+        assert_eq!(
+            image_mime_type(&entry, b"<svg width=\"4\" height=\"4\"/>").as_deref(),
+            Some("image/svg+xml")
+        );
+        entry.mime_type = Some("image/webp".to_string());
+        assert_eq!(
+            image_mime_type(&entry, &jpeg).as_deref(),
+            Some("image/webp")
+        );
+
+        let args = |command: &Command| {
+            command
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+        let wayland = clipboard_tool("image/jpeg", true);
+        assert_eq!(wayland.get_program(), "wl-copy");
+        assert_eq!(args(&wayland), ["--type", "image/jpeg"]);
+        let x11 = clipboard_tool("image/svg+xml", false);
+        assert_eq!(x11.get_program(), "xclip");
+        assert_eq!(
+            args(&x11),
+            ["-selection", "clipboard", "-t", "image/svg+xml", "-i"]
+        );
+    }
+
+    #[test]
+    fn test_capture_rules_follow_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut pipeline, history) = pipeline(dir.path());
+        let mut config = test_config();
+        config.rules = vec![CaptureRule {
+            name: "terminal".to_string(),
+            enabled: true,
+            pattern: None,
+            kind: None,
+            source_app: Some("terminal".to_string()),
+            min_size: None,
+            max_size: None,
+            drop: true,
+            rewrite: None,
+            tags: Vec::new(),
+            ttl_seconds: None,
+            skip_ocr: false,
+        }];
+
+        let mut from_terminal = FakeSource::text("rm -rf build");
+        from_terminal.source_app = Some("Gnome-terminal".to_string());
+        let mut from_editor = FakeSource::text("notes");
+        from_editor.source_app = Some("Gedit".to_string());
+
+        assert!(pipeline.process(from_terminal.clone(), &config).is_empty());
+        let added = pipeline.process(from_editor, &config);
+        assert_eq!(added.len(), 1);
+        let entry = history
+            .lock()
+            .unwrap()
+            .get_by_id(added[0])
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.source_app.as_deref(), Some("Gedit"));
+
+        // Rules are reloaded with the config
+        config.rules.clear();
+        assert_eq!(pipeline.process(from_terminal, &config).len(), 1);
+    }
+}
@@ -0,0 +1,167 @@
+//! OCR engines
+//!
+//! Every engine returns the full text plus per-word boxes and confidence, so the
+//! rest of the daemon doesn't care which one is running. Engines are compiled
+//! in through cargo features and picked at startup with `ocr.engine`:
+//! - `tesseract` (feature `tesseract`): libtesseract + system language packs
+//! - `ocrs` (feature `ocrs`): pure Rust, only needs two `.rten` model files
+
+#[cfg(feature = "ocrs")]
+mod ocrs;
+#[cfg(feature = "tesseract")]
+mod tesseract;
+
+use anyhow::{anyhow, Result};
+use clippit_core::{Config, OcrWord};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+#[cfg(feature = "ocrs")]
+pub use self::ocrs::OcrsEngine;
+#[cfg(feature = "tesseract")]
+pub use self::tesseract::TesseractEngine;
+
+/// Text recognized in an image
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OcrResult {
+    /// Full text, lines separated by '\n'
+    pub text: String,
+    pub words: Vec<OcrWord>,
+}
+
+impl OcrResult {
+    /// Trimmed text, None when nothing was recognized
+    pub fn into_text(self) -> Option<String> {
+        let trimmed = self.text.trim();
+        (!trimmed.is_empty()).then(|| trimmed.to_string())
+    }
+}
+
+pub trait OcrEngine: Send + Sync {
+    /// Name used in `ocr.engine` and in logs
+    fn name(&self) -> &'static str;
+
+    fn recognize(&self, image_path: &Path) -> Result<OcrResult>;
+}
+
+/// Stand-in when no engine could be started; every job fails with the reason,
+/// so they can be retried with a backfill once the engine is fixed
+pub struct UnavailableEngine {
+    reason: String,
+}
+
+impl UnavailableEngine {
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+        }
+    }
+}
+
+impl OcrEngine for UnavailableEngine {
+    fn name(&self) -> &'static str {
+        "unavailable"
+    }
+
+    fn recognize(&self, _image_path: &Path) -> Result<OcrResult> {
+        Err(anyhow!("No OCR engine available: {}", self.reason))
+    }
+}
+
+/// Creates the engine selected by `ocr.engine`
+///
+/// "auto" prefers Tesseract and falls back to ocrs when libtesseract or the
+/// language packs are missing.
+pub fn create_engine(config: &Config) -> Result<Arc<dyn OcrEngine>> {
+    let engine: Arc<dyn OcrEngine> = match config.ocr.engine.as_str() {
+        "tesseract" => tesseract_engine(config)?,
+        "ocrs" => ocrs_engine(config)?,
+        "auto" => match tesseract_engine(config) {
+            Ok(engine) => engine,
+            Err(e) => {
+                warn!("⚠️ Tesseract unavailable ({}), trying ocrs", e);
+                ocrs_engine(config)?
+            }
+        },
+        other => {
+            return Err(anyhow!(
+                "Unknown OCR engine '{}'. Use \"auto\", \"tesseract\" or \"ocrs\"",
+                other
+            ))
+        }
+    };
+
+    info!("🔍 OCR engine: {}", engine.name());
+    Ok(engine)
+}
+
+#[cfg(feature = "tesseract")]
+fn tesseract_engine(config: &Config) -> Result<Arc<dyn OcrEngine>> {
+    Ok(Arc::new(TesseractEngine::new(&config.ocr.languages)?))
+}
+
+#[cfg(not(feature = "tesseract"))]
+fn tesseract_engine(_config: &Config) -> Result<Arc<dyn OcrEngine>> {
+    Err(anyhow!(
+        "clippit-daemon was built without the 'tesseract' feature"
+    ))
+}
+
+#[cfg(feature = "ocrs")]
+fn ocrs_engine(config: &Config) -> Result<Arc<dyn OcrEngine>> {
+    Ok(Arc::new(OcrsEngine::new(&model_dir(config)?)?))
+}
+
+#[cfg(not(feature = "ocrs"))]
+fn ocrs_engine(_config: &Config) -> Result<Arc<dyn OcrEngine>> {
+    Err(anyhow!(
+        "clippit-daemon was built without the 'ocrs' feature"
+    ))
+}
+
+/// `ocr.model_dir`, or ~/.local/share/clippit/ocr-models
+#[cfg_attr(not(feature = "ocrs"), allow(dead_code))]
+fn model_dir(config: &Config) -> Result<PathBuf> {
+    if let Some(dir) = &config.ocr.model_dir {
+        return Ok(PathBuf::from(dir));
+    }
+
+    let mut dir = dirs::data_local_dir().ok_or_else(|| anyhow!("Failed to get data directory"))?;
+    dir.push("clippit");
+    dir.push("ocr-models");
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_engine_is_rejected() {
+        let mut config = Config::default();
+        config.ocr.engine = "paddle".to_string();
+        let err = create_engine(&config).err().unwrap();
+        assert!(err.to_string().contains("paddle"));
+    }
+
+    #[test]
+    fn test_model_dir_from_config() {
+        let mut config = Config::default();
+        config.ocr.model_dir = Some("/opt/models".to_string());
+        assert_eq!(model_dir(&config).unwrap(), PathBuf::from("/opt/models"));
+
+        config.ocr.model_dir = None;
+        assert!(model_dir(&config).unwrap().ends_with("clippit/ocr-models"));
+    }
+
+    #[test]
+    fn test_result_text_is_trimmed() {
+        let result = OcrResult {
+            text: "  hello\n".to_string(),
+            words: Vec::new(),
+        };
+        assert_eq!(result.into_text().as_deref(), Some("hello"));
+        assert_eq!(OcrResult::default().into_text(), None);
+    }
+}
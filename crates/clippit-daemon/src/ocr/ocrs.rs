@@ -0,0 +1,92 @@
+use anyhow::{anyhow, Context, Result};
+use clippit_core::{BoundingBox, OcrWord};
+use ocrs::{ImageSource, OcrEngine as Ocrs, OcrEngineParams, TextItem};
+use rten::Model;
+use std::path::Path;
+use tracing::{info, warn};
+
+use super::{OcrEngine, OcrResult};
+
+const DETECTION_MODEL: &str = "text-detection.rten";
+const RECOGNITION_MODEL: &str = "text-recognition.rten";
+
+/// Pure Rust OCR (no system libraries), Latin script only
+///
+/// The models are downloaded once from the ocrs project into `ocr.model_dir`.
+/// ocrs doesn't report per-word confidence, so words carry `confidence: None`.
+pub struct OcrsEngine {
+    engine: Ocrs,
+}
+
+impl OcrsEngine {
+    pub fn new(model_dir: &Path) -> Result<Self> {
+        let load = |name: &str| {
+            let path = model_dir.join(name);
+            Model::load_file(&path).with_context(|| format!("Failed to load ocrs model {:?}", path))
+        };
+
+        let engine = Ocrs::new(OcrEngineParams {
+            detection_model: Some(load(DETECTION_MODEL)?),
+            recognition_model: Some(load(RECOGNITION_MODEL)?),
+            ..Default::default()
+        })?;
+
+        Ok(Self { engine })
+    }
+}
+
+impl OcrEngine for OcrsEngine {
+    fn name(&self) -> &'static str {
+        "ocrs"
+    }
+
+    fn recognize(&self, image_path: &Path) -> Result<OcrResult> {
+        info!("🔍 Starting OCR for: {:?}", image_path);
+
+        if !image_path.exists() {
+            warn!("⚠️ Image file not found: {:?}", image_path);
+            return Ok(OcrResult::default());
+        }
+
+        let img = image::open(image_path)?.into_rgb8();
+        let source = ImageSource::from_bytes(img.as_raw(), img.dimensions())
+            .map_err(|e| anyhow!("Invalid image for OCR: {:?}", e))?;
+        let input = self.engine.prepare_input(source)?;
+
+        let word_rects = self.engine.detect_words(&input)?;
+        let line_rects = self.engine.find_text_lines(&input, &word_rects);
+        let lines = self.engine.recognize_text(&input, &line_rects)?;
+
+        let mut text_lines = Vec::new();
+        let mut words = Vec::new();
+        for line in lines.iter().flatten() {
+            text_lines.push(line.to_string());
+
+            for word in line.words() {
+                let rect = word.bounding_rect();
+                let coord = |value: f64| value.max(0.0).round() as u32;
+                words.push(OcrWord {
+                    text: word.to_string(),
+                    bbox: BoundingBox {
+                        x: coord(f64::from(rect.left())),
+                        y: coord(f64::from(rect.top())),
+                        width: coord(f64::from(rect.width())),
+                        height: coord(f64::from(rect.height())),
+                    },
+                    confidence: None,
+                });
+            }
+        }
+
+        info!(
+            "✅ OCR extracted {} lines ({} words)",
+            text_lines.len(),
+            words.len()
+        );
+
+        Ok(OcrResult {
+            text: text_lines.join("\n"),
+            words,
+        })
+    }
+}
@@ -0,0 +1,141 @@
+use anyhow::{anyhow, Result};
+use clippit_core::{BoundingBox, OcrWord};
+use std::path::Path;
+use tesseract::Tesseract;
+use tracing::{error, info, warn};
+
+use super::{OcrEngine, OcrResult};
+
+/// TSV level of word rows (1 = page, 2 = block, 3 = paragraph, 4 = line)
+const TSV_WORD_LEVEL: &str = "5";
+
+pub struct TesseractEngine {
+    languages: String, // "por+eng"
+}
+
+impl TesseractEngine {
+    /// Checks that libtesseract loads with the requested language packs
+    pub fn new(languages: &str) -> Result<Self> {
+        Tesseract::new(None, Some(languages))
+            .map_err(|e| anyhow!("Failed to initialize Tesseract ({}): {}", languages, e))?;
+
+        Ok(Self {
+            languages: languages.to_string(),
+        })
+    }
+}
+
+impl OcrEngine for TesseractEngine {
+    fn name(&self) -> &'static str {
+        "tesseract"
+    }
+
+    /// Processa OCR em imagem e retorna texto extraído
+    fn recognize(&self, image_path: &Path) -> Result<OcrResult> {
+        info!("🔍 Starting OCR for: {:?}", image_path);
+
+        // Verificar se arquivo existe
+        if !image_path.exists() {
+            warn!("⚠️ Image file not found: {:?}", image_path);
+            return Ok(OcrResult::default());
+        }
+
+        let image_path = image_path
+            .to_str()
+            .ok_or_else(|| anyhow!("Image path is not valid UTF-8: {:?}", image_path))?;
+
+        // Inicializar Tesseract (a instância não é Sync, uma por imagem)
+        let tesseract = Tesseract::new(None, Some(&self.languages)).map_err(|e| {
+            error!("❌ Failed to initialize Tesseract: {}", e);
+            anyhow!(e)
+        })?;
+
+        // Configurar imagem e reconhecer uma vez para texto e caixas
+        let mut tesseract = tesseract
+            .set_image(image_path)
+            .map_err(|e| {
+                error!("❌ Failed to set image: {}", e);
+                anyhow!(e)
+            })?
+            .recognize()
+            .map_err(|e| {
+                error!("❌ OCR failed: {}", e);
+                anyhow!(e)
+            })?;
+
+        // Extrair texto
+        let text = tesseract.get_text()?;
+        let words = parse_tsv_words(&tesseract.get_tsv_text(0)?);
+        info!(
+            "✅ OCR extracted {} characters ({} words)",
+            text.trim().len(),
+            words.len()
+        );
+
+        Ok(OcrResult { text, words })
+    }
+}
+
+/// Parses word rows of Tesseract's TSV output
+///
+/// Columns: level page_num block_num par_num line_num word_num left top width
+/// height conf text
+fn parse_tsv_words(tsv: &str) -> Vec<OcrWord> {
+    tsv.lines()
+        .filter_map(|line| {
+            let columns: Vec<&str> = line.split('\t').collect();
+            if columns.len() < 12 || columns[0] != TSV_WORD_LEVEL {
+                return None;
+            }
+
+            let text = columns[11].trim();
+            if text.is_empty() {
+                return None;
+            }
+
+            let number = |i: usize| columns[i].parse::<u32>().ok();
+            let confidence = columns[10].parse::<f32>().ok().filter(|c| *c >= 0.0);
+
+            Some(OcrWord {
+                text: text.to_string(),
+                bbox: BoundingBox {
+                    x: number(6)?,
+                    y: number(7)?,
+                    width: number(8)?,
+                    height: number(9)?,
+                },
+                confidence: confidence.map(|c| (c / 100.0).min(1.0)),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tsv_words() {
+        let tsv = "1\t1\t0\t0\t0\t0\t0\t0\t640\t480\t-1\t\n\
+                   4\t1\t1\t1\t1\t0\t10\t20\t200\t30\t-1\t\n\
+                   5\t1\t1\t1\t1\t1\t10\t20\t80\t30\t96.5\tOlá\n\
+                   5\t1\t1\t1\t1\t2\t100\t20\t110\t30\t42\tmundo\n\
+                   5\t1\t1\t1\t1\t3\t220\t20\t5\t30\t95\t \n";
+
+        let words = parse_tsv_words(tsv);
+
+        assert_eq!(words.len(), 2);
+        assert_eq!(words[0].text, "Olá");
+        assert_eq!(
+            words[0].bbox,
+            BoundingBox {
+                x: 10,
+                y: 20,
+                width: 80,
+                height: 30
+            }
+        );
+        assert_eq!(words[0].confidence, Some(0.965));
+        assert_eq!(words[1].confidence, Some(0.42));
+    }
+}
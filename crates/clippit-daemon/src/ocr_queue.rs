@@ -0,0 +1,389 @@
+//! Persistent OCR job queue
+//!
+//! Captured images are queued in the `ocr_jobs` table instead of spawning OCR
+//! right away, so pending work survives a daemon restart. A single worker task
+//! drains the queue, running at most `ocr.max_concurrent_jobs` recognitions at a
+//! time and failing jobs that take longer than `ocr.timeout_seconds`.
+
+use anyhow::Result;
+use clippit_core::{Config, HistoryManager, OcrJob, OcrJobStatus, OcrQueueStats};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{Notify, Semaphore};
+use tokio::task::JoinSet;
+use tracing::{error, info, warn};
+
+use crate::ocr::{self, OcrEngine, OcrResult, UnavailableEngine};
+
+/// Jobs fetched from the database per round
+const BATCH_SIZE: usize = 32;
+
+/// How often an idle worker re-reads the config (e.g. OCR enabled in the dashboard)
+const IDLE_RECHECK: Duration = Duration::from_secs(30);
+
+pub struct OcrQueue {
+    history_manager: Arc<Mutex<HistoryManager>>,
+    engine: Arc<dyn OcrEngine>,
+    slots: Arc<Semaphore>,
+    wakeup: Notify,
+}
+
+impl OcrQueue {
+    pub fn new(
+        history_manager: Arc<Mutex<HistoryManager>>,
+        engine: Arc<dyn OcrEngine>,
+        max_concurrent_jobs: usize,
+    ) -> Self {
+        Self {
+            history_manager,
+            engine,
+            slots: Arc::new(Semaphore::new(max_concurrent_jobs.max(1))),
+            wakeup: Notify::new(),
+        }
+    }
+
+    /// Queue using the engine selected by `ocr.engine`, sized from `ocr.max_concurrent_jobs`
+    ///
+    /// If no engine can start, jobs are still queued and fail with the reason.
+    pub fn from_config(history_manager: Arc<Mutex<HistoryManager>>, config: &Config) -> Self {
+        let engine = ocr::create_engine(config).unwrap_or_else(|e| {
+            error!("❌ OCR engine unavailable: {}", e);
+            Arc::new(UnavailableEngine::new(e.to_string()))
+        });
+        Self::new(history_manager, engine, config.ocr.max_concurrent_jobs)
+    }
+
+    /// Queues a freshly captured image and wakes the worker
+    pub fn enqueue(&self, entry_id: i64) -> Result<()> {
+        self.history_manager
+            .lock()
+            .unwrap()
+            .enqueue_ocr_job(entry_id)?;
+        self.wakeup.notify_one();
+        Ok(())
+    }
+
+    /// Queues existing images without OCR text, returning how many were queued
+    pub fn backfill(&self, include_failed: bool) -> Result<usize> {
+        let queued = self
+            .history_manager
+            .lock()
+            .unwrap()
+            .enqueue_ocr_backfill(include_failed)?;
+        info!("🔍 OCR backfill queued {} image(s)", queued);
+        self.wakeup.notify_one();
+        Ok(queued)
+    }
+
+    pub fn stats(&self) -> Result<OcrQueueStats> {
+        self.history_manager.lock().unwrap().ocr_queue_stats()
+    }
+
+    /// Worker loop: drains pending jobs while OCR is enabled, then sleeps until woken
+    pub async fn run(self: Arc<Self>) {
+        match self
+            .history_manager
+            .lock()
+            .unwrap()
+            .reset_running_ocr_jobs()
+        {
+            Ok(0) => {}
+            Ok(reset) => info!("🔁 Resuming {} interrupted OCR job(s)", reset),
+            Err(e) => error!("❌ Failed to reset interrupted OCR jobs: {}", e),
+        }
+
+        loop {
+            // Load config each round (to respect runtime changes)
+            let config = Config::load().unwrap_or_default();
+
+            if config.features.enable_ocr {
+                match self.process_pending(&config).await {
+                    Ok(0) => {}
+                    Ok(_) => continue,
+                    Err(e) => error!("❌ OCR queue error: {}", e),
+                }
+            }
+
+            let _ = tokio::time::timeout(IDLE_RECHECK, self.wakeup.notified()).await;
+        }
+    }
+
+    /// Runs one batch of pending jobs and waits for it, returning how many ran
+    pub async fn process_pending(&self, config: &Config) -> Result<usize> {
+        let jobs = self
+            .history_manager
+            .lock()
+            .unwrap()
+            .pending_ocr_jobs(BATCH_SIZE)?;
+        let timeout = Duration::from_secs(config.ocr.timeout_seconds.max(1));
+        let mut tasks = JoinSet::new();
+
+        for job in jobs {
+            let permit = Arc::clone(&self.slots).acquire_owned().await?;
+            self.history_manager.lock().unwrap().set_ocr_job_status(
+                job.entry_id,
+                OcrJobStatus::Running,
+                None,
+            )?;
+
+            let engine = Arc::clone(&self.engine);
+            let history_manager = Arc::clone(&self.history_manager);
+
+            tasks.spawn(async move {
+                info!("🔍 Running OCR for entry {}", job.entry_id);
+                let image_path = job.image_path.clone();
+
+                // Tesseract can't be interrupted: the permit stays with the blocking
+                // call, so a timed-out job keeps its slot until it really finishes
+                let work = tokio::task::spawn_blocking(move || {
+                    let _permit = permit;
+                    engine.recognize(Path::new(&image_path))
+                });
+
+                let outcome = match tokio::time::timeout(timeout, work).await {
+                    Ok(Ok(result)) => result,
+                    Ok(Err(e)) => Err(anyhow::anyhow!("OCR task panicked: {}", e)),
+                    Err(_) => Err(anyhow::anyhow!("timed out after {:?}", timeout)),
+                };
+                finish_job(&history_manager, &job, outcome);
+            });
+        }
+
+        let count = tasks.len();
+        while tasks.join_next().await.is_some() {}
+        Ok(count)
+    }
+}
+
+fn finish_job(
+    history_manager: &Arc<Mutex<HistoryManager>>,
+    job: &OcrJob,
+    outcome: Result<OcrResult>,
+) {
+    let manager = history_manager.lock().unwrap();
+
+    let saved = match outcome {
+        Ok(mut result) => {
+            // Word boxes are kept even without text, so a re-run clears stale ones
+            let words = std::mem::take(&mut result.words);
+            manager
+                .save_ocr_words(job.entry_id, &words)
+                .and_then(|_| match result.into_text() {
+                    Some(text) => {
+                        manager.update_ocr_text(job.entry_id, &text)?;
+                        info!(
+                            "✅ OCR text saved for entry {} ({} words)",
+                            job.entry_id,
+                            words.len()
+                        );
+                        Ok(())
+                    }
+                    None => {
+                        info!("ℹ️ No text found in image {}", job.entry_id);
+                        Ok(())
+                    }
+                })
+                .and_then(|_| manager.set_ocr_job_status(job.entry_id, OcrJobStatus::Done, None))
+        }
+        Err(e) => {
+            warn!(
+                "⚠️ OCR failed for entry {} (attempt {}): {}",
+                job.entry_id,
+                job.attempts + 1,
+                e
+            );
+            manager.set_ocr_job_status(job.entry_id, OcrJobStatus::Failed, Some(&e.to_string()))
+        }
+    };
+
+    if let Err(e) = saved {
+        error!(
+            "❌ Failed to update OCR job for entry {}: {}",
+            job.entry_id, e
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clippit_core::{BoundingBox, ClipboardEntry, OcrWord};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Engine answering with a closure
+    struct FakeEngine<F>(F);
+
+    impl<F> OcrEngine for FakeEngine<F>
+    where
+        F: Fn(&Path) -> Result<String> + Send + Sync,
+    {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn recognize(&self, image_path: &Path) -> Result<OcrResult> {
+            Ok(OcrResult {
+                text: (self.0)(image_path)?,
+                words: Vec::new(),
+            })
+        }
+    }
+
+    fn engine<F>(f: F) -> Arc<dyn OcrEngine>
+    where
+        F: Fn(&Path) -> Result<String> + Send + Sync + 'static,
+    {
+        Arc::new(FakeEngine(f))
+    }
+
+    fn history_with_images(count: usize) -> (Arc<Mutex<HistoryManager>>, Vec<i64>) {
+        let history = Arc::new(Mutex::new(HistoryManager::new_in_memory(100).unwrap()));
+        let ids = (0..count)
+            .map(|i| {
+                let entry = ClipboardEntry::new_image(format!("/tmp/ocr-{}.png", i), None);
+                history.lock().unwrap().add_entry(entry).unwrap().unwrap()
+            })
+            .collect();
+        (history, ids)
+    }
+
+    fn status(history: &Arc<Mutex<HistoryManager>>, id: i64) -> Option<OcrJobStatus> {
+        history.lock().unwrap().get_ocr_job_status(id).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_queued_image_gets_ocr_text() {
+        let (history, ids) = history_with_images(1);
+        let engine = engine(|path: &Path| Ok(format!("text from {}\n", path.display())));
+        let queue = OcrQueue::new(Arc::clone(&history), engine, 1);
+
+        queue.enqueue(ids[0]).unwrap();
+        assert_eq!(status(&history, ids[0]), Some(OcrJobStatus::Pending));
+        assert_eq!(queue.process_pending(&Config::default()).await.unwrap(), 1);
+
+        assert_eq!(status(&history, ids[0]), Some(OcrJobStatus::Done));
+        let entry = history.lock().unwrap().get_by_id(ids[0]).unwrap().unwrap();
+        assert_eq!(entry.ocr_text.as_deref(), Some("text from /tmp/ocr-0.png"));
+    }
+
+    #[tokio::test]
+    async fn test_slow_ocr_times_out_as_failed() {
+        let (history, ids) = history_with_images(1);
+        let engine = engine(|_: &Path| {
+            std::thread::sleep(Duration::from_millis(1500));
+            Ok("late".to_string())
+        });
+        let queue = OcrQueue::new(Arc::clone(&history), engine, 1);
+        let mut config = Config::default();
+        config.ocr.timeout_seconds = 1;
+
+        queue.enqueue(ids[0]).unwrap();
+        queue.process_pending(&config).await.unwrap();
+
+        assert_eq!(status(&history, ids[0]), Some(OcrJobStatus::Failed));
+        let entry = history.lock().unwrap().get_by_id(ids[0]).unwrap().unwrap();
+        assert_eq!(entry.ocr_text, None);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_is_limited() {
+        let (history, ids) = history_with_images(6);
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (running_clone, peak_clone) = (Arc::clone(&running), Arc::clone(&peak));
+        let engine = engine(move |_: &Path| {
+            let now = running_clone.fetch_add(1, Ordering::SeqCst) + 1;
+            peak_clone.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(50));
+            running_clone.fetch_sub(1, Ordering::SeqCst);
+            Ok(String::new())
+        });
+        let queue = OcrQueue::new(Arc::clone(&history), engine, 2);
+
+        for &id in &ids {
+            queue.enqueue(id).unwrap();
+        }
+        assert_eq!(queue.process_pending(&Config::default()).await.unwrap(), 6);
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert_eq!(queue.stats().unwrap().done, 6);
+    }
+
+    #[tokio::test]
+    async fn test_backfill_and_restart_resume() {
+        let (history, ids) = history_with_images(2);
+        let queue = OcrQueue::new(
+            Arc::clone(&history),
+            engine(|_: &Path| Ok(String::new())),
+            1,
+        );
+
+        // Nothing queued at capture time (e.g. OCR was disabled)
+        assert_eq!(queue.backfill(false).unwrap(), 2);
+        assert_eq!(queue.backfill(false).unwrap(), 0);
+
+        // Simulate a daemon killed mid-job
+        history
+            .lock()
+            .unwrap()
+            .set_ocr_job_status(ids[0], OcrJobStatus::Running, None)
+            .unwrap();
+        history.lock().unwrap().reset_running_ocr_jobs().unwrap();
+
+        assert_eq!(queue.process_pending(&Config::default()).await.unwrap(), 2);
+        assert_eq!(queue.stats().unwrap().done, 2);
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_engine_fails_jobs() {
+        let (history, ids) = history_with_images(1);
+        let engine = Arc::new(UnavailableEngine::new("no tessdata"));
+        let queue = OcrQueue::new(Arc::clone(&history), engine, 1);
+
+        queue.enqueue(ids[0]).unwrap();
+        queue.process_pending(&Config::default()).await.unwrap();
+
+        assert_eq!(status(&history, ids[0]), Some(OcrJobStatus::Failed));
+    }
+
+    #[tokio::test]
+    async fn test_word_boxes_are_stored() {
+        struct WordsEngine;
+
+        impl OcrEngine for WordsEngine {
+            fn name(&self) -> &'static str {
+                "words"
+            }
+
+            fn recognize(&self, _image_path: &Path) -> Result<OcrResult> {
+                let word = |text: &str, x| OcrWord {
+                    text: text.to_string(),
+                    bbox: BoundingBox::new(x, 0, 40, 20),
+                    confidence: Some(0.9),
+                };
+                Ok(OcrResult {
+                    text: "Total 42".to_string(),
+                    words: vec![word("Total", 0), word("42", 100)],
+                })
+            }
+        }
+
+        let (history, ids) = history_with_images(1);
+        let queue = OcrQueue::new(Arc::clone(&history), Arc::new(WordsEngine), 1);
+
+        queue.enqueue(ids[0]).unwrap();
+        queue.process_pending(&Config::default()).await.unwrap();
+
+        let manager = history.lock().unwrap();
+        assert_eq!(manager.get_ocr_words(ids[0]).unwrap().len(), 2);
+        let region = BoundingBox::new(90, 0, 60, 20);
+        assert_eq!(
+            manager
+                .ocr_text_in_region(ids[0], &region)
+                .unwrap()
+                .as_deref(),
+            Some("42")
+        );
+    }
+}
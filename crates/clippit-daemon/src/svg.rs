@@ -0,0 +1,106 @@
+//! SVG rasterization for thumbnails and near-duplicate hashing
+//!
+//! The SVG document itself is stored untouched; only a bounded raster of it is
+//! produced here, so a huge `width`/`height` can't blow up memory.
+
+use anyhow::{anyhow, Result};
+use image::DynamicImage;
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg::{fontdb, ImageHrefResolver, Options, Tree};
+use std::sync::{Arc, OnceLock};
+
+/// Parsed SVG with its declared size
+pub struct SvgDocument {
+    tree: Tree,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// System fonts are loaded once; scanning them on every capture is slow
+fn fonts() -> Arc<fontdb::Database> {
+    static FONTS: OnceLock<Arc<fontdb::Database>> = OnceLock::new();
+    FONTS
+        .get_or_init(|| {
+            let mut db = fontdb::Database::new();
+            db.load_system_fonts();
+            Arc::new(db)
+        })
+        .clone()
+}
+
+pub fn parse(data: &[u8]) -> Result<SvgDocument> {
+    let options = Options {
+        // Clipboard content must not make us read local files (`<image href="/home/...">`)
+        image_href_resolver: ImageHrefResolver {
+            resolve_data: ImageHrefResolver::default_data_resolver(),
+            resolve_string: Box::new(|_, _| None),
+        },
+        fontdb: fonts(),
+        ..Options::default()
+    };
+
+    let tree = Tree::from_data(data, &options).map_err(|e| anyhow!("Invalid SVG: {}", e))?;
+    let size = tree.size();
+
+    Ok(SvgDocument {
+        width: size.width().ceil() as u32,
+        height: size.height().ceil() as u32,
+        tree,
+    })
+}
+
+impl SvgDocument {
+    /// Renders the document scaled to fit in `max_size` x `max_size`
+    pub fn render(&self, max_size: u32) -> Result<DynamicImage> {
+        let size = self.tree.size();
+        let scale = (max_size as f32 / size.width().max(size.height())).min(1.0);
+        let width = ((size.width() * scale).ceil() as u32).max(1);
+        let height = ((size.height() * scale).ceil() as u32).max(1);
+
+        let mut pixmap = Pixmap::new(width, height)
+            .ok_or_else(|| anyhow!("Invalid SVG render size {}x{}", width, height))?;
+        resvg::render(
+            &self.tree,
+            Transform::from_scale(scale, scale),
+            &mut pixmap.as_mut(),
+        );
+
+        // tiny-skia works with premultiplied alpha; the PNG encoder converts back
+        let png = pixmap
+            .encode_png()
+            .map_err(|e| anyhow!("Failed to encode SVG render: {}", e))?;
+        Ok(image::load_from_memory(&png)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_fits_in_bounds() {
+        let svg = parse(
+            br#"<svg xmlns="http://www.w3.org/2000/svg" width="1000" height="500">
+                <rect width="500" height="500" fill="red"/>
+            </svg>"#,
+        )
+        .unwrap();
+        assert_eq!((svg.width, svg.height), (1000, 500));
+
+        let img = svg.render(128).unwrap().to_rgba8();
+        assert_eq!(img.dimensions(), (128, 64));
+        assert_eq!(img.get_pixel(10, 10).0, [255, 0, 0, 255]);
+        assert_eq!(img.get_pixel(120, 10).0[3], 0);
+    }
+
+    #[test]
+    fn test_local_files_are_not_loaded() {
+        let svg = parse(
+            br#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="8" height="8">
+                <image width="8" height="8" xlink:href="/etc/hostname"/>
+            </svg>"#,
+        )
+        .unwrap();
+        assert!(!svg.tree.root().has_children());
+    }
+}
@@ -0,0 +1,47 @@
+//! Fixtures shared by the frontend tests (D-Bus, HTTP)
+
+use clippit_ipc::{ErrorCode, HistoryEntry, IpcMessage, IpcResponse};
+use std::sync::{Arc, Mutex};
+
+use crate::Handler;
+
+pub use clippit_ipc::test_support::text_entry;
+
+/// Fake handler that records every message it gets
+///
+/// Lists two entries (with thumbnails, so frontends can be checked to leave
+/// them out), searches find the query itself, entry lookups are `NotFound`,
+/// additions get id 5, and everything else is `Ok`.
+pub fn recording_handler() -> (Handler, Arc<Mutex<Vec<IpcMessage>>>) {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::clone(&received);
+    let handler: Handler = Arc::new(move |message| {
+        let response = match &message {
+            IpcMessage::QueryHistoryMetadata { .. } => IpcResponse::HistoryMetadataResponse {
+                entries: vec![with_thumbnail(2, "hello"), with_thumbnail(1, "world")],
+            },
+            IpcMessage::SearchHistoryWithLimit { query, .. } => {
+                IpcResponse::SearchHistoryWithLimitResponse {
+                    entries: vec![with_thumbnail(3, query)],
+                }
+            }
+            IpcMessage::GetEntryData { id, .. } => IpcResponse::error(
+                ErrorCode::NotFound,
+                format!("Entry with id {} not found", id),
+            ),
+            IpcMessage::AddEntry { .. } => IpcResponse::EntryAddedResponse { id: Some(5) },
+            IpcMessage::GetCaptureState => IpcResponse::CaptureStateResponse { paused: false },
+            _ => IpcResponse::Ok,
+        };
+        log.lock().unwrap().push(message);
+        response
+    });
+    (handler, received)
+}
+
+fn with_thumbnail(id: i64, text: &str) -> HistoryEntry {
+    HistoryEntry {
+        thumbnail_data: Some(vec![1, 2, 3]),
+        ..text_entry(id, text)
+    }
+}
@@ -1,5 +1,5 @@
 use adw::prelude::*;
-use clippit_core::{Config, HistoryManager};
+use clippit_core::Config;
 use gtk::prelude::*;
 use libadwaita as adw;
 use rust_i18n::t;
@@ -133,19 +133,10 @@ pub fn create_page() -> gtk::Widget {
     clear_button.connect_clicked(move |_| {
         eprintln!("🗑️ Limpando histórico completo...");
 
-        // Get database path
-        let mut db_path = dirs::data_local_dir().unwrap_or_else(|| std::path::PathBuf::from("."));
-        db_path.push("clippit");
-        std::fs::create_dir_all(&db_path).ok();
-        db_path.push("history.db");
-
-        // Clear history
-        if let Ok(history) = HistoryManager::new(db_path, 100) {
-            if let Err(e) = history.clear() {
-                eprintln!("❌ Erro ao limpar histórico: {}", e);
-            } else {
-                eprintln!("✅ Histórico limpo com sucesso!");
-            }
+        // O daemon apaga (e avisa o popup aberto)
+        match clippit_ipc::IpcClient::clear_history() {
+            Ok(deleted) => eprintln!("✅ Histórico limpo com sucesso! ({} itens)", deleted),
+            Err(e) => eprintln!("❌ Erro ao limpar histórico: {}", e),
         }
     });
 
@@ -319,6 +310,39 @@ pub fn create_page() -> gtk::Widget {
     ocr_lang_row.add_suffix(&lang_dropdown);
     ocr_group.add(&ocr_lang_row);
 
+    // OCR backfill row (existing images without text)
+    let ocr_backfill_row = adw::ActionRow::new();
+    ocr_backfill_row.set_title("Processar imagens existentes");
+    ocr_backfill_row.set_subtitle(&ocr_status_subtitle());
+
+    let backfill_icon = gtk::Image::from_icon_name("view-refresh-symbolic");
+    ocr_backfill_row.add_prefix(&backfill_icon);
+
+    let backfill_button = gtk::Button::with_label("Processar");
+    backfill_button.set_valign(gtk::Align::Center);
+
+    let backfill_row_clone = ocr_backfill_row.clone();
+    backfill_button.connect_clicked(move |_| {
+        // Falhas anteriores também são tentadas de novo
+        match clippit_ipc::IpcClient::backfill_ocr(true) {
+            Ok(queued) => {
+                eprintln!("✅ {} imagens enfileiradas para OCR", queued);
+                backfill_row_clone.set_subtitle(&format!(
+                    "{} imagens enfileiradas · {}",
+                    queued,
+                    ocr_status_subtitle()
+                ));
+            }
+            Err(e) => {
+                eprintln!("❌ Erro ao enfileirar OCR: {}", e);
+                backfill_row_clone.set_subtitle("Daemon não está rodando");
+            }
+        }
+    });
+
+    ocr_backfill_row.add_suffix(&backfill_button);
+    ocr_group.add(&ocr_backfill_row);
+
     page.add(&ocr_group);
 
     // No need for save button - auto-save enabled
@@ -331,3 +355,16 @@ pub fn create_page() -> gtk::Widget {
 
     scrolled.upcast()
 }
+
+/// Resumo da fila de OCR do daemon
+fn ocr_status_subtitle() -> String {
+    match clippit_ipc::IpcClient::get_ocr_status() {
+        Ok(status) => format!(
+            "{} pendentes, {} concluídas, {} com falha",
+            status.pending + status.running,
+            status.done,
+            status.failed
+        ),
+        Err(_) => "Extrair texto de imagens capturadas antes do OCR".to_string(),
+    }
+}
@@ -3,6 +3,7 @@ mod general;
 mod hotkeys;
 mod privacy;
 mod search;
+mod snippets;
 mod theme;
 
 use gtk::prelude::*;
@@ -37,6 +38,7 @@ pub fn create_content() -> gtk::Widget {
             "applications-graphics-symbolic",
         ),
         (t!("menu.privacy").to_string(), "security-high-symbolic"),
+        ("Snippets".to_string(), "accessories-text-editor-symbolic"),
     ];
 
     for (label, icon) in items {
@@ -71,6 +73,7 @@ pub fn create_content() -> gtk::Widget {
     stack.add_named(&autocomplete::create_page(), Some("3"));
     stack.add_named(&theme::create_page(), Some("4"));
     stack.add_named(&privacy::create_page(), Some("5"));
+    stack.add_named(&snippets::create_page(), Some("6"));
 
     // Connect sidebar selection to stack
     let stack_clone = stack.clone();
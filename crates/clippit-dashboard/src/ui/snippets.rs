@@ -0,0 +1,243 @@
+use adw::prelude::*;
+use clippit_core::Config;
+use clippit_ipc::{IpcClient, SnippetInfo};
+use gtk::prelude::*;
+use libadwaita as adw;
+
+pub fn create_page() -> gtk::Widget {
+    let config = Config::load().unwrap_or_default();
+
+    // Create scrolled window
+    let scrolled = gtk::ScrolledWindow::new();
+    scrolled.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+    scrolled.set_vexpand(true);
+
+    let page = adw::PreferencesPage::new();
+
+    // ========== SEÇÃO: BIBLIOTECA ==========
+    let group = adw::PreferencesGroup::new();
+    group.set_title("Snippets");
+    group.set_description(Some(
+        "Textos reutilizáveis, escolhidos na aba Snippets do popup. \
+         Use {date}, {time}, {clipboard} e {cursor} no conteúdo.",
+    ));
+
+    let add_button = gtk::Button::from_icon_name("list-add-symbolic");
+    add_button.set_valign(gtk::Align::Center);
+    add_button.add_css_class("flat");
+    add_button.set_tooltip_text(Some("Novo snippet"));
+    group.set_header_suffix(Some(&add_button));
+
+    let list_box = gtk::ListBox::new();
+    list_box.add_css_class("boxed-list");
+    list_box.set_selection_mode(gtk::SelectionMode::None);
+    group.add(&list_box);
+    reload_list(&list_box);
+
+    let list_box_for_add = list_box.clone();
+    add_button.connect_clicked(move |btn| {
+        show_editor(btn.upcast_ref(), None, &list_box_for_add);
+    });
+
+    page.add(&group);
+
+    // ========== SEÇÃO: COLAR ==========
+    let paste_group = adw::PreferencesGroup::new();
+    paste_group.set_title("Ao escolher um snippet");
+    paste_group.set_margin_top(12);
+
+    let paste_row = adw::ActionRow::new();
+    paste_row.set_title("Colar automaticamente");
+    paste_row.set_subtitle(
+        "Cola no app em foco e posiciona o cursor em {cursor} \
+         (requer xdotool no X11 ou wtype no Wayland)",
+    );
+
+    let paste_switch = gtk::Switch::new();
+    paste_switch.set_active(config.snippets.paste_on_select);
+    paste_switch.set_valign(gtk::Align::Center);
+
+    // Auto-save on toggle
+    paste_switch.connect_state_set(|_, state| {
+        if let Ok(mut cfg) = Config::load() {
+            cfg.snippets.paste_on_select = state;
+            if let Err(e) = cfg.save() {
+                eprintln!("❌ Erro ao salvar: {}", e);
+            } else {
+                eprintln!("✅ Colar snippets automaticamente: {}", state);
+            }
+        }
+        gtk::glib::Propagation::Proceed
+    });
+
+    paste_row.set_activatable_widget(Some(&paste_switch));
+    paste_row.add_suffix(&paste_switch);
+    paste_group.add(&paste_row);
+    page.add(&paste_group);
+
+    page.set_margin_start(12);
+    page.set_margin_end(12);
+    page.set_margin_top(12);
+    page.set_margin_bottom(12);
+
+    scrolled.set_child(Some(&page));
+
+    scrolled.upcast()
+}
+
+/// Rebuilds the rows from the daemon
+fn reload_list(list_box: &gtk::ListBox) {
+    while let Some(child) = list_box.first_child() {
+        list_box.remove(&child);
+    }
+
+    let snippets = match IpcClient::list_snippets() {
+        Ok(snippets) => snippets,
+        Err(e) => {
+            eprintln!("❌ Erro ao carregar snippets: {}", e);
+            let error_row = adw::ActionRow::new();
+            error_row.set_title("Não foi possível carregar os snippets");
+            error_row.set_subtitle("Verifique se o daemon está rodando");
+            error_row.add_prefix(&gtk::Image::from_icon_name("dialog-warning-symbolic"));
+            list_box.append(&error_row);
+            return;
+        }
+    };
+
+    if snippets.is_empty() {
+        let empty_row = adw::ActionRow::new();
+        empty_row.set_title("Nenhum snippet");
+        empty_row.set_subtitle("Clique em + para criar o primeiro");
+        empty_row.add_prefix(&gtk::Image::from_icon_name("dialog-information-symbolic"));
+        list_box.append(&empty_row);
+        return;
+    }
+
+    for snippet in snippets {
+        list_box.append(&snippet_row(snippet, list_box));
+    }
+}
+
+fn snippet_row(snippet: SnippetInfo, list_box: &gtk::ListBox) -> adw::ActionRow {
+    let row = adw::ActionRow::new();
+    row.set_title(&gtk::glib::markup_escape_text(&snippet.title));
+    let first_line = snippet.body.lines().next().unwrap_or_default();
+    let subtitle = match &snippet.keyword {
+        Some(keyword) => format!("{} · {}", keyword, first_line),
+        None => first_line.to_string(),
+    };
+    row.set_subtitle(&gtk::glib::markup_escape_text(&subtitle));
+    row.add_prefix(&gtk::Image::from_icon_name(
+        "accessories-text-editor-symbolic",
+    ));
+
+    let delete_button = gtk::Button::from_icon_name("user-trash-symbolic");
+    delete_button.set_valign(gtk::Align::Center);
+    delete_button.add_css_class("flat");
+    delete_button.set_tooltip_text(Some("Excluir"));
+    let id = snippet.id;
+    let list_box_for_delete = list_box.clone();
+    delete_button.connect_clicked(move |_| match IpcClient::delete_snippet(id) {
+        Ok(()) => {
+            eprintln!("✅ Snippet {} excluído", id);
+            reload_list(&list_box_for_delete);
+        }
+        Err(e) => eprintln!("❌ Erro ao excluir snippet: {}", e),
+    });
+    row.add_suffix(&delete_button);
+
+    let list_box_for_edit = list_box.clone();
+    row.set_activatable(true);
+    row.connect_activated(move |row| {
+        show_editor(row.upcast_ref(), Some(&snippet), &list_box_for_edit);
+    });
+    row
+}
+
+/// Dialog to create (`snippet` = None) or edit a snippet
+fn show_editor(anchor: &gtk::Widget, snippet: Option<&SnippetInfo>, list_box: &gtk::ListBox) {
+    let title_entry = gtk::Entry::new();
+    title_entry.set_placeholder_text(Some("Título"));
+
+    let keyword_entry = gtk::Entry::new();
+    keyword_entry.set_placeholder_text(Some("Palavra-chave (opcional, ex: ;assinatura)"));
+
+    let body_view = gtk::TextView::new();
+    body_view.set_wrap_mode(gtk::WrapMode::WordChar);
+    body_view.set_top_margin(6);
+    body_view.set_bottom_margin(6);
+    body_view.set_left_margin(6);
+    body_view.set_right_margin(6);
+
+    if let Some(snippet) = snippet {
+        title_entry.set_text(&snippet.title);
+        keyword_entry.set_text(snippet.keyword.as_deref().unwrap_or_default());
+        body_view.buffer().set_text(&snippet.body);
+    }
+
+    let body_scrolled = gtk::ScrolledWindow::new();
+    body_scrolled.set_child(Some(&body_view));
+    body_scrolled.set_min_content_height(160);
+    body_scrolled.set_min_content_width(360);
+    body_scrolled.add_css_class("card");
+
+    let form = gtk::Box::new(gtk::Orientation::Vertical, 8);
+    form.append(&title_entry);
+    form.append(&keyword_entry);
+    form.append(&body_scrolled);
+
+    let parent_window = anchor.root().and_downcast::<gtk::Window>();
+    let heading = if snippet.is_some() {
+        "Editar snippet"
+    } else {
+        "Novo snippet"
+    };
+    let dialog = adw::MessageDialog::new(parent_window.as_ref(), Some(heading), None);
+    dialog.set_extra_child(Some(&form));
+    dialog.add_response("cancel", "Cancelar");
+    dialog.add_response("save", "Salvar");
+    dialog.set_response_appearance("save", adw::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
+
+    // Title is required
+    dialog.set_response_enabled("save", !title_entry.text().trim().is_empty());
+    let dialog_for_title = dialog.clone();
+    title_entry.connect_changed(move |entry| {
+        dialog_for_title.set_response_enabled("save", !entry.text().trim().is_empty());
+    });
+
+    let id = snippet.map(|snippet| snippet.id);
+    let list_box = list_box.clone();
+    let parent_for_error = parent_window.clone();
+    dialog.connect_response(Some("save"), move |_, _| {
+        let buffer = body_view.buffer();
+        let body = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+        let title = title_entry.text();
+        let keyword = keyword_entry.text();
+        let keyword = Some(keyword.as_str()).filter(|k| !k.trim().is_empty());
+
+        let result = match id {
+            Some(id) => IpcClient::update_snippet(id, &title, &body, keyword),
+            None => IpcClient::create_snippet(&title, &body, keyword),
+        };
+        match result {
+            Ok(saved) => {
+                eprintln!("✅ Snippet '{}' salvo", saved.title);
+                reload_list(&list_box);
+            }
+            Err(e) => {
+                eprintln!("❌ Erro ao salvar snippet: {}", e);
+                let error_dialog = adw::MessageDialog::new(
+                    parent_for_error.as_ref(),
+                    Some("Erro ao salvar snippet"),
+                    Some(&e.to_string()),
+                );
+                error_dialog.add_response("ok", "OK");
+                error_dialog.present();
+            }
+        }
+    });
+
+    dialog.present();
+}
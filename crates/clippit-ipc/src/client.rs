@@ -1,20 +1,81 @@
 use anyhow::{Context, Result};
 use interprocess::local_socket::prelude::LocalSocketStream;
 use interprocess::local_socket::traits::Stream;
-use interprocess::local_socket::{GenericNamespaced, ToNsName};
 use std::io::{BufRead, BufReader, Write};
 
-use crate::protocol::{IpcMessage, IpcResponse, SOCKET_PATH};
+use crate::error::IpcError;
+use crate::handshake::{self, Negotiated};
+use crate::protocol::{capability, ErrorCode, IpcMessage, IpcResponse, PROTOCOL_VERSION};
+use crate::session::IpcSession;
+use crate::socket::{socket_name, socket_path};
+use crate::subscription::EventStream;
+
+/// Image bytes asked for per `GetImageChunk`: small enough that other requests
+/// on the connection get through in between
+const IMAGE_CHUNK_SIZE: u64 = 1024 * 1024;
 
 pub struct IpcClient;
 
 impl IpcClient {
+    /// Sends a message over the process-wide persistent session
     pub fn send_message(message: IpcMessage) -> Result<IpcResponse> {
-        let name = SOCKET_PATH.to_ns_name::<GenericNamespaced>()?;
+        IpcSession::shared().request(message)
+    }
+
+    /// Sends a message on a connection of its own (protocol 0 style)
+    pub fn send_once(message: IpcMessage) -> Result<IpcResponse> {
+        Self::send_once_to(socket_path(), &message)
+    }
+
+    pub(crate) fn send_once_to(socket_path: &str, message: &IpcMessage) -> Result<IpcResponse> {
+        let response_line = Self::round_trip(socket_path, message)?;
+        if response_line.trim().is_empty() {
+            return Err(anyhow::anyhow!(
+                "clippit-daemon closed the connection without answering (older daemon?)"
+            ));
+        }
+        Self::check_supported(Self::decode_response(&response_line)?)
+    }
+
+    pub(crate) fn decode_response(line: &str) -> Result<IpcResponse> {
+        serde_json::from_str(line).map_err(|e| match handshake::variant_name(line) {
+            Some(name) => anyhow::anyhow!(
+                "Unknown response '{}' from clippit-daemon (this client speaks protocol {}); \
+                 update Clippit",
+                name,
+                PROTOCOL_VERSION
+            ),
+            None => anyhow::anyhow!("Failed to deserialize response: {}", e),
+        })
+    }
+
+    /// Turns `Unsupported` into an error that says which side to update
+    pub(crate) fn check_supported(response: IpcResponse) -> Result<IpcResponse> {
+        match response {
+            IpcResponse::Unsupported {
+                message,
+                protocol_version,
+            } => Err(IpcError::new(
+                ErrorCode::Unsupported,
+                format!(
+                    "clippit-daemon (protocol {}) doesn't support '{}' \
+                     (this client speaks protocol {}); update clippit-daemon",
+                    protocol_version, message, PROTOCOL_VERSION
+                ),
+            )
+            .into()),
+            response => Ok(response),
+        }
+    }
+
+    /// Sends one message and returns the raw response line (empty if the
+    /// daemon hung up without answering)
+    fn round_trip(socket_path: &str, message: &IpcMessage) -> Result<String> {
+        let name = socket_name(socket_path)?;
         let mut stream = LocalSocketStream::connect(name)
             .context("Failed to connect to daemon. Is clippit-daemon running?")?;
 
-        let message_json = serde_json::to_string(&message)?;
+        let message_json = serde_json::to_string(message)?;
         writeln!(stream, "{}", message_json)?;
         stream.flush()?;
 
@@ -22,10 +83,43 @@ impl IpcClient {
         let mut response_line = String::new();
         reader.read_line(&mut response_line)?;
 
-        let response: IpcResponse =
-            serde_json::from_str(&response_line).context("Failed to deserialize response")?;
+        Ok(response_line)
+    }
 
-        Ok(response)
+    /// Handshake: protocol version and capabilities shared with the daemon
+    ///
+    /// Daemons from before the handshake drop the connection on `Hello`; they
+    /// are reported as protocol 0 with no optional capabilities.
+    pub fn hello(client: &str) -> Result<Negotiated> {
+        Self::hello_at(socket_path(), client)
+    }
+
+    pub(crate) fn hello_at(socket_path: &str, client: &str) -> Result<Negotiated> {
+        let response_line = Self::round_trip(
+            socket_path,
+            &IpcMessage::Hello {
+                client: client.to_string(),
+                protocol_version: PROTOCOL_VERSION,
+                capabilities: capability::ALL.iter().map(|c| c.to_string()).collect(),
+            },
+        )?;
+        if response_line.trim().is_empty() {
+            return Ok(Negotiated::legacy());
+        }
+
+        match serde_json::from_str(&response_line).context("Failed to deserialize response")? {
+            IpcResponse::HelloResponse {
+                server,
+                protocol_version,
+                capabilities,
+            } => Ok(Negotiated {
+                peer: server,
+                protocol_version,
+                capabilities,
+            }),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
+            _ => Err(anyhow::anyhow!("Unexpected response to hello")),
+        }
     }
 
     pub fn ping() -> Result<()> {
@@ -38,7 +132,7 @@ impl IpcClient {
     pub fn query_history(limit: usize) -> Result<Vec<crate::protocol::HistoryEntry>> {
         match Self::send_message(IpcMessage::QueryHistory { limit })? {
             IpcResponse::HistoryResponse { entries } => Ok(entries),
-            IpcResponse::Error { message } => Err(anyhow::anyhow!("Server error: {}", message)),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
             _ => Err(anyhow::anyhow!("Unexpected response")),
         }
     }
@@ -47,7 +141,7 @@ impl IpcClient {
     pub fn query_history_metadata(limit: usize) -> Result<Vec<crate::protocol::HistoryEntry>> {
         match Self::send_message(IpcMessage::QueryHistoryMetadata { limit, offset: 0 })? {
             IpcResponse::HistoryMetadataResponse { entries } => Ok(entries),
-            IpcResponse::Error { message } => Err(anyhow::anyhow!("Server error: {}", message)),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
             _ => Err(anyhow::anyhow!("Unexpected response")),
         }
     }
@@ -59,7 +153,7 @@ impl IpcClient {
     ) -> Result<Vec<crate::protocol::HistoryEntry>> {
         match Self::send_message(IpcMessage::QueryHistoryMetadata { limit, offset })? {
             IpcResponse::HistoryMetadataResponse { entries } => Ok(entries),
-            IpcResponse::Error { message } => Err(anyhow::anyhow!("Server error: {}", message)),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
             _ => Err(anyhow::anyhow!("Unexpected response")),
         }
     }
@@ -68,7 +162,7 @@ impl IpcClient {
     pub fn search_history(query: String) -> Result<Vec<crate::protocol::HistoryEntry>> {
         match Self::send_message(IpcMessage::SearchHistory { query })? {
             IpcResponse::SearchHistoryResponse { entries } => Ok(entries),
-            IpcResponse::Error { message } => Err(anyhow::anyhow!("Server error: {}", message)),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
             _ => Err(anyhow::anyhow!("Unexpected response")),
         }
     }
@@ -80,16 +174,69 @@ impl IpcClient {
     ) -> Result<Vec<crate::protocol::HistoryEntry>> {
         match Self::send_message(IpcMessage::SearchHistoryWithLimit { query, limit })? {
             IpcResponse::SearchHistoryWithLimitResponse { entries } => Ok(entries),
-            IpcResponse::Error { message } => Err(anyhow::anyhow!("Server error: {}", message)),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
             _ => Err(anyhow::anyhow!("Unexpected response")),
         }
     }
 
     /// Get full data for a specific entry by ID (loads image data on-demand)
+    ///
+    /// With `image-chunks`, the image comes in `IMAGE_CHUNK_SIZE` pieces after
+    /// the metadata instead of inside one huge response.
     pub fn get_entry_data(id: i64) -> Result<crate::protocol::HistoryEntry> {
-        match Self::send_message(IpcMessage::GetEntryData { id })? {
-            IpcResponse::EntryDataResponse { entry } => Ok(entry),
-            IpcResponse::Error { message } => Err(anyhow::anyhow!("Server error: {}", message)),
+        let chunked = IpcSession::shared()
+            .supports(capability::IMAGE_CHUNKS)
+            .unwrap_or(false);
+        let mut entry = match Self::send_message(IpcMessage::GetEntryData {
+            id,
+            skip_image_data: chunked,
+        })? {
+            IpcResponse::EntryDataResponse { entry } => entry,
+            error @ IpcResponse::Error { .. } => return Err(IpcError::from_response(error)),
+            _ => return Err(anyhow::anyhow!("Unexpected response")),
+        };
+
+        if chunked
+            && matches!(entry.content_type, crate::protocol::ContentType::Image)
+            && entry.content_data.is_none()
+        {
+            let data = Self::get_image_data(id)?;
+            entry.content_data = (!data.is_empty()).then_some(data);
+        }
+        Ok(entry)
+    }
+
+    /// Bytes of image entry `id`, fetched in chunks (needs `image-chunks`)
+    pub fn get_image_data(id: i64) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        loop {
+            let (chunk, total_size) = Self::get_image_chunk(id, data.len() as u64)?;
+            if data.is_empty() {
+                data.reserve_exact(total_size as usize);
+            }
+            if chunk.is_empty() && (data.len() as u64) < total_size {
+                return Err(anyhow::anyhow!(
+                    "Image {} ended at {} of {} bytes",
+                    id,
+                    data.len(),
+                    total_size
+                ));
+            }
+            data.extend_from_slice(&chunk);
+            if data.len() as u64 >= total_size {
+                return Ok(data);
+            }
+        }
+    }
+
+    fn get_image_chunk(id: i64, offset: u64) -> Result<(Vec<u8>, u64)> {
+        match Self::send_message(IpcMessage::GetImageChunk {
+            id,
+            offset,
+            length: IMAGE_CHUNK_SIZE,
+        })? {
+            IpcResponse::ImageChunkResponse { data, total_size } => Ok((data, total_size)),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
             _ => Err(anyhow::anyhow!("Unexpected response")),
         }
     }
@@ -97,7 +244,296 @@ impl IpcClient {
     pub fn select_item(id: i64) -> Result<()> {
         match Self::send_message(IpcMessage::SelectItem { id })? {
             IpcResponse::Ok => Ok(()),
-            IpcResponse::Error { message } => Err(anyhow::anyhow!("Server error: {}", message)),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
+            _ => Err(anyhow::anyhow!("Unexpected response")),
+        }
+    }
+
+    // ========== HISTORY EDITING ==========
+
+    pub fn delete_entry(id: i64) -> Result<()> {
+        Self::expect_ok(IpcMessage::DeleteEntry { id })
+    }
+
+    /// Deletes several entries; returns the ids that existed
+    pub fn delete_entries(ids: Vec<i64>) -> Result<Vec<i64>> {
+        match Self::send_message(IpcMessage::DeleteEntries { ids })? {
+            IpcResponse::EntriesDeletedResponse { ids } => Ok(ids),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
+            _ => Err(anyhow::anyhow!("Unexpected response")),
+        }
+    }
+
+    /// Adds a text entry as if it had been copied; returns its id, or None when
+    /// it wasn't stored (capture paused, duplicate, dropped by a rule)
+    pub fn add_entry(text: &str, place_on_clipboard: bool) -> Result<Option<i64>> {
+        match Self::send_message(IpcMessage::AddEntry {
+            text: text.to_string(),
+            place_on_clipboard,
+        })? {
+            IpcResponse::EntryAddedResponse { id } => Ok(id),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
+            _ => Err(anyhow::anyhow!("Unexpected response")),
+        }
+    }
+
+    /// Deletes the whole history; returns how many entries were deleted
+    pub fn clear_history() -> Result<usize> {
+        match Self::send_message(IpcMessage::ClearHistory)? {
+            IpcResponse::HistoryClearedResponse { deleted } => Ok(deleted),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
+            _ => Err(anyhow::anyhow!("Unexpected response")),
+        }
+    }
+
+    /// Replaces the text of a text entry; returns the updated entry
+    pub fn update_entry_text(id: i64, text: &str) -> Result<crate::protocol::HistoryEntry> {
+        match Self::send_message(IpcMessage::UpdateEntryText {
+            id,
+            text: text.to_string(),
+        })? {
+            IpcResponse::EntryDataResponse { entry } => Ok(entry),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
+            _ => Err(anyhow::anyhow!("Unexpected response")),
+        }
+    }
+
+    pub fn count_entries() -> Result<usize> {
+        match Self::send_message(IpcMessage::CountEntries)? {
+            IpcResponse::CountResponse { count } => Ok(count),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
+            _ => Err(anyhow::anyhow!("Unexpected response")),
+        }
+    }
+
+    pub fn get_stats() -> Result<crate::protocol::HistoryStats> {
+        match Self::send_message(IpcMessage::GetStats)? {
+            IpcResponse::StatsResponse { stats } => Ok(stats),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
+            _ => Err(anyhow::anyhow!("Unexpected response")),
+        }
+    }
+
+    /// Find images visually similar to an entry (closest first)
+    pub fn find_similar_images(
+        id: i64,
+        max_distance: Option<u32>,
+        limit: usize,
+    ) -> Result<Vec<crate::protocol::SimilarImage>> {
+        match Self::send_message(IpcMessage::FindSimilarImages {
+            id,
+            max_distance,
+            limit,
+        })? {
+            IpcResponse::SimilarImagesResponse { images } => Ok(images),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
+            _ => Err(anyhow::anyhow!("Unexpected response")),
+        }
+    }
+
+    /// Queue OCR for existing images that have no text yet; returns how many were queued
+    pub fn backfill_ocr(include_failed: bool) -> Result<usize> {
+        match Self::send_message(IpcMessage::BackfillOcr { include_failed })? {
+            IpcResponse::OcrBackfillResponse { queued } => Ok(queued),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
+            _ => Err(anyhow::anyhow!("Unexpected response")),
+        }
+    }
+
+    /// Get the number of pending/running/done/failed OCR jobs
+    pub fn get_ocr_status() -> Result<crate::protocol::OcrQueueStatus> {
+        match Self::send_message(IpcMessage::GetOcrStatus)? {
+            IpcResponse::OcrStatusResponse { status } => Ok(status),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
+            _ => Err(anyhow::anyhow!("Unexpected response")),
+        }
+    }
+
+    /// Get the OCR text inside a region of an image (None = image has no word boxes)
+    pub fn get_ocr_region_text(
+        id: i64,
+        region: crate::protocol::OcrRegion,
+    ) -> Result<Option<String>> {
+        match Self::send_message(IpcMessage::GetOcrRegionText { id, region })? {
+            IpcResponse::OcrRegionTextResponse { text } => Ok(text),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
+            _ => Err(anyhow::anyhow!("Unexpected response")),
+        }
+    }
+
+    /// Render a text entry as a QR code, returned as SVG
+    pub fn generate_qr_code(id: i64, ec_level: crate::protocol::QrEcLevel) -> Result<String> {
+        match Self::send_message(IpcMessage::GenerateQrCode { id, ec_level })? {
+            IpcResponse::QrCodeResponse { svg, .. } => Ok(svg),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
+            _ => Err(anyhow::anyhow!("Unexpected response")),
+        }
+    }
+
+    /// Text of entry `id` after `transform` (see `clippit_core::Transform`);
+    /// with `place_on_clipboard` the daemon also copies the result
+    pub fn transform_text(id: i64, transform: &str, place_on_clipboard: bool) -> Result<String> {
+        match Self::send_message(IpcMessage::TransformText {
+            id,
+            transform: transform.to_string(),
+            place_on_clipboard,
+        })? {
+            IpcResponse::TransformedTextResponse { text } => Ok(text),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
+            _ => Err(anyhow::anyhow!("Unexpected response")),
+        }
+    }
+
+    /// What the capture rules would do to `text`, without saving it
+    pub fn test_rules(
+        text: &str,
+        source_app: Option<&str>,
+    ) -> Result<crate::protocol::RuleTestResult> {
+        match Self::send_message(IpcMessage::TestRules {
+            text: text.to_string(),
+            source_app: source_app.map(str::to_string),
+        })? {
+            IpcResponse::RuleTestResponse { result } => Ok(result),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
+            _ => Err(anyhow::anyhow!("Unexpected response")),
+        }
+    }
+
+    // ========== TAG METHODS ==========
+
+    /// All tags with their entry counts, sorted by name
+    pub fn list_tags() -> Result<Vec<crate::protocol::TagInfo>> {
+        match Self::send_message(IpcMessage::ListTags)? {
+            IpcResponse::TagsResponse { tags } => Ok(tags),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
+            _ => Err(anyhow::anyhow!("Unexpected response")),
+        }
+    }
+
+    pub fn create_tag(name: &str) -> Result<crate::protocol::TagInfo> {
+        Self::expect_tag(IpcMessage::CreateTag {
+            name: name.to_string(),
+        })
+    }
+
+    pub fn rename_tag(id: i64, name: &str) -> Result<crate::protocol::TagInfo> {
+        Self::expect_tag(IpcMessage::RenameTag {
+            id,
+            name: name.to_string(),
+        })
+    }
+
+    pub fn delete_tag(id: i64) -> Result<()> {
+        Self::expect_ok(IpcMessage::DeleteTag { id })
+    }
+
+    pub fn add_entry_tag(id: i64, tag: &str) -> Result<()> {
+        Self::expect_ok(IpcMessage::AddEntryTag {
+            id,
+            tag: tag.to_string(),
+        })
+    }
+
+    pub fn remove_entry_tag(id: i64, tag: &str) -> Result<()> {
+        Self::expect_ok(IpcMessage::RemoveEntryTag {
+            id,
+            tag: tag.to_string(),
+        })
+    }
+
+    pub fn set_entry_tags(id: i64, tags: Vec<String>) -> Result<()> {
+        Self::expect_ok(IpcMessage::SetEntryTags { id, tags })
+    }
+
+    // ========== SNIPPET METHODS ==========
+
+    /// All snippets, sorted by title
+    pub fn list_snippets() -> Result<Vec<crate::protocol::SnippetInfo>> {
+        match Self::send_message(IpcMessage::ListSnippets)? {
+            IpcResponse::SnippetsResponse { snippets } => Ok(snippets),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
+            _ => Err(anyhow::anyhow!("Unexpected response")),
+        }
+    }
+
+    pub fn create_snippet(
+        title: &str,
+        body: &str,
+        keyword: Option<&str>,
+    ) -> Result<crate::protocol::SnippetInfo> {
+        Self::expect_snippet(IpcMessage::CreateSnippet {
+            title: title.to_string(),
+            body: body.to_string(),
+            keyword: keyword.map(str::to_string),
+        })
+    }
+
+    pub fn update_snippet(
+        id: i64,
+        title: &str,
+        body: &str,
+        keyword: Option<&str>,
+    ) -> Result<crate::protocol::SnippetInfo> {
+        Self::expect_snippet(IpcMessage::UpdateSnippet {
+            id,
+            title: title.to_string(),
+            body: body.to_string(),
+            keyword: keyword.map(str::to_string),
+        })
+    }
+
+    pub fn delete_snippet(id: i64) -> Result<()> {
+        Self::expect_ok(IpcMessage::DeleteSnippet { id })
+    }
+
+    /// Expanded text and the char index of `{cursor}`
+    pub fn expand_snippet(id: i64) -> Result<(String, Option<usize>)> {
+        match Self::send_message(IpcMessage::ExpandSnippet { id })? {
+            IpcResponse::ExpandedSnippetResponse { text, cursor } => Ok((text, cursor)),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
+            _ => Err(anyhow::anyhow!("Unexpected response")),
+        }
+    }
+
+    fn expect_snippet(message: IpcMessage) -> Result<crate::protocol::SnippetInfo> {
+        match Self::send_message(message)? {
+            IpcResponse::SnippetResponse { snippet } => Ok(snippet),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
+            _ => Err(anyhow::anyhow!("Unexpected response")),
+        }
+    }
+
+    fn expect_tag(message: IpcMessage) -> Result<crate::protocol::TagInfo> {
+        match Self::send_message(message)? {
+            IpcResponse::TagResponse { tag } => Ok(tag),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
+            _ => Err(anyhow::anyhow!("Unexpected response")),
+        }
+    }
+
+    fn expect_ok(message: IpcMessage) -> Result<()> {
+        match Self::send_message(message)? {
+            IpcResponse::Ok => Ok(()),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
+            _ => Err(anyhow::anyhow!("Unexpected response")),
+        }
+    }
+
+    // ========== EVENTS ==========
+
+    /// Opens an event stream (see `EventStream`); empty `topics` = everything
+    pub fn subscribe(topics: Vec<crate::protocol::EventTopic>) -> Result<EventStream> {
+        EventStream::connect(socket_path(), topics)
+    }
+
+    pub fn set_capture_paused(paused: bool) -> Result<()> {
+        Self::expect_ok(IpcMessage::SetCapturePaused { paused })
+    }
+
+    pub fn get_capture_state() -> Result<bool> {
+        match Self::send_message(IpcMessage::GetCaptureState)? {
+            IpcResponse::CaptureStateResponse { paused } => Ok(paused),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
             _ => Err(anyhow::anyhow!("Unexpected response")),
         }
     }
@@ -116,7 +552,7 @@ impl IpcClient {
             max_results,
         })? {
             IpcResponse::AutocompleteSuggestions { suggestions, .. } => Ok(suggestions),
-            IpcResponse::Error { message } => Err(anyhow::anyhow!("Server error: {}", message)),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
             _ => Err(anyhow::anyhow!("Unexpected response")),
         }
     }
@@ -129,7 +565,7 @@ impl IpcClient {
         })? {
             IpcResponse::SuggestionAccepted => Ok(()),
             IpcResponse::Ok => Ok(()),
-            IpcResponse::Error { message } => Err(anyhow::anyhow!("Server error: {}", message)),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
             _ => Err(anyhow::anyhow!("Unexpected response")),
         }
     }
@@ -0,0 +1,51 @@
+//! Errors the daemon answered with, for callers that look at the code
+
+use thiserror::Error;
+
+use crate::protocol::{ErrorCode, IpcResponse};
+
+/// An `IpcResponse::Error`, as returned (inside `anyhow::Error`) by `IpcClient`
+///
+/// `err.downcast_ref::<IpcError>()` gives the code back.
+#[derive(Debug, Clone, Error)]
+#[error("Server error: {message}")]
+pub struct IpcError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub details: Option<String>,
+}
+
+impl IpcError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    /// The error carried by an `IpcResponse::Error`
+    pub(crate) fn from_response(response: IpcResponse) -> anyhow::Error {
+        match response {
+            IpcResponse::Error {
+                code,
+                message,
+                details,
+            } => IpcError {
+                code,
+                message,
+                details,
+            }
+            .into(),
+            other => anyhow::anyhow!("Unexpected response: {:?}", other),
+        }
+    }
+
+    /// The `IpcError` somewhere in `error`'s chain; None for local failures
+    /// (daemon not running, connection lost...)
+    pub fn find(error: &anyhow::Error) -> Option<&IpcError> {
+        error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<IpcError>())
+    }
+}
@@ -0,0 +1,172 @@
+//! How messages are laid out on a connection
+//!
+//! Connections start with one JSON per line, which is what old clients speak
+//! and what `socat` shows when debugging. Once both sides announced `msgpack`,
+//! a client may send `UseFraming { framing: MessagePack }`; after the `Ok`,
+//! every frame is a big-endian u32 length followed by that many bytes of
+//! MessagePack. Byte fields (`content_data`, image chunks) then travel as
+//! binaries instead of JSON arrays of numbers, about 4x smaller.
+
+use anyhow::{anyhow, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::io::BufRead;
+
+use crate::protocol::Framing;
+
+/// Frames above this are refused (a corrupt length, most likely)
+pub const MAX_FRAME_SIZE: usize = 512 * 1024 * 1024;
+
+/// One frame, ready to be written
+pub fn encode<T: Serialize>(framing: Framing, value: &T) -> Result<Vec<u8>> {
+    match framing {
+        Framing::Json => {
+            let mut json = serde_json::to_vec(value)?;
+            json.push(b'\n');
+            Ok(json)
+        }
+        Framing::MessagePack => {
+            // Structs as maps, so `#[serde(default)]` fields keep working
+            let payload = rmp_serde::to_vec_named(value)?;
+            let length = u32::try_from(payload.len())
+                .ok()
+                .filter(|length| *length as usize <= MAX_FRAME_SIZE)
+                .ok_or_else(|| anyhow!("Frame too large: {} bytes", payload.len()))?;
+            let mut frame = Vec::with_capacity(4 + payload.len());
+            frame.extend_from_slice(&length.to_be_bytes());
+            frame.extend_from_slice(&payload);
+            Ok(frame)
+        }
+    }
+}
+
+pub fn decode<T: DeserializeOwned>(framing: Framing, frame: &[u8]) -> Result<T> {
+    match framing {
+        Framing::Json => Ok(serde_json::from_slice(frame)?),
+        Framing::MessagePack => Ok(rmp_serde::from_slice(frame)?),
+    }
+}
+
+/// The frame as a JSON line, for code that inspects messages as JSON
+/// (request ids, names of unknown messages)
+pub fn to_json(framing: Framing, frame: &[u8]) -> Result<String> {
+    match framing {
+        Framing::Json => Ok(String::from_utf8_lossy(frame).into_owned()),
+        Framing::MessagePack => {
+            let value: Value =
+                rmp_serde::from_slice(frame).context("Malformed MessagePack frame")?;
+            Ok(value.to_string())
+        }
+    }
+}
+
+/// Next frame (a JSON line, newline included); None at EOF
+pub fn read_frame(reader: &mut impl BufRead, framing: Framing) -> std::io::Result<Option<Vec<u8>>> {
+    match framing {
+        Framing::Json => {
+            let mut line = Vec::new();
+            match reader.read_until(b'\n', &mut line)? {
+                0 => Ok(None),
+                _ => Ok(Some(line)),
+            }
+        }
+        Framing::MessagePack => {
+            let mut length = [0u8; 4];
+            match reader.read_exact(&mut length) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(e),
+            }
+            let length = frame_length(length)?;
+            let mut frame = vec![0; length];
+            reader.read_exact(&mut frame)?;
+            Ok(Some(frame))
+        }
+    }
+}
+
+/// Payload size from a MessagePack frame header
+pub(crate) fn frame_length(header: [u8; 4]) -> std::io::Result<usize> {
+    let length = u32::from_be_bytes(header) as usize;
+    if length > MAX_FRAME_SIZE {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Frame of {} bytes is over the limit", length),
+        ));
+    }
+    Ok(length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{IpcResponse, Reply};
+    use crate::test_support::image_entry;
+
+    #[test]
+    fn test_round_trip_in_both_framings() {
+        let data: Vec<u8> = (0..=255).cycle().take(10_000).collect();
+        let reply = Reply {
+            id: 9,
+            response: IpcResponse::EntryDataResponse {
+                entry: image_entry(3, data.clone()),
+            },
+        };
+
+        let json = encode(Framing::Json, &reply).unwrap();
+        let msgpack = encode(Framing::MessagePack, &reply).unwrap();
+        // Bytes as a binary, not as an array of numbers
+        assert!(msgpack.len() < data.len() + 500);
+        assert!(json.len() > 3 * data.len());
+
+        let mut stream = std::io::Cursor::new([msgpack.clone(), msgpack].concat());
+        for _ in 0..2 {
+            let frame = read_frame(&mut stream, Framing::MessagePack)
+                .unwrap()
+                .unwrap();
+            let reply: Reply = decode(Framing::MessagePack, &frame).unwrap();
+            assert_eq!(reply.id, 9);
+            assert!(matches!(
+                reply.response,
+                IpcResponse::EntryDataResponse { entry } if entry.content_data == Some(data.clone())
+            ));
+        }
+        assert!(read_frame(&mut stream, Framing::MessagePack)
+            .unwrap()
+            .is_none());
+
+        // JSON byte arrays from older peers still decode
+        let frame = read_frame(&mut std::io::Cursor::new(json), Framing::Json)
+            .unwrap()
+            .unwrap();
+        let reply: Reply = decode(Framing::Json, &frame).unwrap();
+        assert!(matches!(
+            reply.response,
+            IpcResponse::EntryDataResponse { entry } if entry.content_data == Some(data)
+        ));
+    }
+
+    #[test]
+    fn test_messagepack_as_json() {
+        let frame = encode(Framing::MessagePack, &IpcResponse::Pong).unwrap();
+        assert_eq!(
+            to_json(Framing::MessagePack, &frame[4..]).unwrap(),
+            r#""Pong""#
+        );
+
+        let frame = encode(
+            Framing::MessagePack,
+            &IpcResponse::CaptureStateResponse { paused: true },
+        )
+        .unwrap();
+        assert_eq!(
+            to_json(Framing::MessagePack, &frame[4..]).unwrap(),
+            r#"{"CaptureStateResponse":{"paused":true}}"#
+        );
+
+        // A corrupt length is refused before allocating anything
+        let mut stream = std::io::Cursor::new(vec![0xff, 0xff, 0xff, 0xff, 0]);
+        assert!(read_frame(&mut stream, Framing::MessagePack).is_err());
+    }
+}
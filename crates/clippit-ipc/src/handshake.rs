@@ -0,0 +1,139 @@
+//! `Hello` handshake and handling of messages the other side doesn't know
+//!
+//! `Hello` is optional: clients from before the handshake (protocol 0) keep
+//! sending one message per connection and still work. Newer clients start with
+//! it to learn what the daemon supports before relying on it, e.g. `multiplex`
+//! before keeping a connection open for `Request`/`Reply` (see `session`).
+
+use serde_json::Value;
+
+use crate::protocol::{IpcResponse, PROTOCOL_VERSION};
+
+/// What both sides agreed on in the handshake
+#[derive(Debug, Clone, PartialEq)]
+pub struct Negotiated {
+    pub peer: String,
+    pub protocol_version: u32,
+    pub capabilities: Vec<String>,
+}
+
+impl Negotiated {
+    /// Daemon from before the handshake: no optional features can be assumed
+    pub fn legacy() -> Self {
+        Self {
+            peer: "clippit-daemon (protocol 0)".to_string(),
+            protocol_version: 0,
+            capabilities: Vec::new(),
+        }
+    }
+
+    pub fn supports(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+}
+
+/// Daemon side of `Hello`: common version and capabilities
+pub fn answer_hello(
+    server: &str,
+    client_version: u32,
+    client_capabilities: &[String],
+    server_capabilities: &[&str],
+) -> IpcResponse {
+    IpcResponse::HelloResponse {
+        server: server.to_string(),
+        protocol_version: client_version.min(PROTOCOL_VERSION),
+        capabilities: negotiate_capabilities(server_capabilities, client_capabilities),
+    }
+}
+
+/// Capabilities announced by both sides, in `ours` order
+pub fn negotiate_capabilities(ours: &[&str], theirs: &[String]) -> Vec<String> {
+    ours.iter()
+        .filter(|capability| theirs.iter().any(|c| c == *capability))
+        .map(|capability| capability.to_string())
+        .collect()
+}
+
+/// Answer for a line that doesn't parse as an `IpcMessage`
+pub fn unsupported(line: &str, error: &serde_json::Error) -> IpcResponse {
+    IpcResponse::Unsupported {
+        message: variant_name(line).unwrap_or_else(|| error.to_string()),
+        protocol_version: PROTOCOL_VERSION,
+    }
+}
+
+/// Name of an externally tagged enum value: `"Ping"` or `{"GetEntryData": {...}}`
+///
+/// None when the line isn't JSON shaped like a message at all.
+pub fn variant_name(line: &str) -> Option<String> {
+    match serde_json::from_str::<Value>(line).ok()? {
+        Value::String(name) => Some(name),
+        Value::Object(map) if map.len() == 1 => map.keys().next().cloned(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{capability, IpcMessage};
+
+    #[test]
+    fn test_hello_negotiation() {
+        let client_caps = vec![
+            capability::TAGS.to_string(),
+            "clipboard-sync".to_string(), // Newer than this daemon
+            capability::OCR.to_string(),
+        ];
+        let response = answer_hello(
+            "clippit-daemon",
+            PROTOCOL_VERSION + 3,
+            &client_caps,
+            &[capability::OCR, capability::TAGS, capability::SNIPPETS],
+        );
+
+        let IpcResponse::HelloResponse {
+            protocol_version,
+            capabilities,
+            ..
+        } = response
+        else {
+            panic!("expected HelloResponse, got {:?}", response);
+        };
+        assert_eq!(protocol_version, PROTOCOL_VERSION);
+        assert_eq!(capabilities, vec!["ocr", "tags"]);
+
+        let IpcResponse::HelloResponse {
+            protocol_version, ..
+        } = answer_hello("clippit-daemon", 0, &[], capability::ALL)
+        else {
+            panic!("expected HelloResponse");
+        };
+        assert_eq!(protocol_version, 0);
+    }
+
+    #[test]
+    fn test_unknown_messages_are_unsupported() {
+        for (line, name) in [
+            (r#"{"SyncClipboard":{"peer":"laptop"}}"#, "SyncClipboard"),
+            (r#""Shutdown""#, "Shutdown"),
+            // Known message with a field this daemon doesn't have a default for
+            (r#"{"GetEntryData":{"entry":1}}"#, "GetEntryData"),
+        ] {
+            let error = serde_json::from_str::<IpcMessage>(line).unwrap_err();
+            match unsupported(line, &error) {
+                IpcResponse::Unsupported {
+                    message,
+                    protocol_version,
+                } => {
+                    assert_eq!(message, name);
+                    assert_eq!(protocol_version, PROTOCOL_VERSION);
+                }
+                other => panic!("expected Unsupported, got {:?}", other),
+            }
+        }
+
+        assert_eq!(variant_name("not json"), None);
+        assert_eq!(variant_name("[1, 2]"), None);
+    }
+}
@@ -1,7 +1,24 @@
 pub mod client;
+pub mod error;
+pub mod framing;
+pub mod handshake;
 pub mod protocol;
 pub mod server;
+pub mod session;
+pub mod socket;
+pub mod subscription;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
 
 pub use client::IpcClient;
-pub use protocol::{ContentType, HistoryEntry, IpcMessage, IpcResponse};
-pub use server::IpcServer;
+pub use error::IpcError;
+pub use handshake::Negotiated;
+pub use protocol::{
+    ContentType, ErrorCode, EventTopic, Framing, HistoryEntry, HistoryStats, IpcEvent, IpcMessage,
+    IpcResponse, OcrQueueStatus, OcrRegion, QrEcLevel, RuleTestResult, SimilarImage, SnippetInfo,
+    TagInfo, TextKind,
+};
+pub use server::{IpcServer, ServerLimits, ShutdownHandle};
+pub use session::IpcSession;
+pub use socket::socket_path;
+pub use subscription::EventStream;
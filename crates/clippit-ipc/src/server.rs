@@ -1,80 +1,828 @@
-use anyhow::{Context, Result};
-use interprocess::local_socket::prelude::LocalSocketStream;
-use interprocess::local_socket::traits::Listener;
-use interprocess::local_socket::{GenericNamespaced, ListenerOptions, ToNsName};
-use std::io::{BufRead, BufReader, Write};
+use anyhow::{anyhow, Context, Result};
+use interprocess::local_socket::tokio::prelude::*;
+use interprocess::local_socket::tokio::{RecvHalf, SendHalf};
+use interprocess::local_socket::ListenerOptions;
+use serde_json::Value;
 use std::path::Path;
-use tracing::{error, info};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::{broadcast, watch, Mutex, Semaphore};
+use tokio::task::JoinSet;
+use tracing::{debug, error, info, warn};
 
-use crate::protocol::{IpcMessage, IpcResponse, SOCKET_PATH};
+use crate::framing;
+use crate::handshake;
+use crate::protocol::{
+    ErrorCode, EventTopic, Framing, IpcEvent, IpcMessage, IpcResponse, Reply, Request,
+    PROTOCOL_VERSION,
+};
+use crate::socket;
 
 pub type ServerCallback = Box<dyn Fn(IpcMessage) -> IpcResponse + Send + Sync>;
 
+/// Hands out a fresh receiver of every event, one per subscribed connection
+///
+/// The receiver is dropped as soon as its client disconnects.
+pub type EventSource = Box<dyn Fn() -> broadcast::Receiver<IpcEvent> + Send + Sync>;
+
+/// Capacity for the event channel behind an `EventSource`: a subscriber that
+/// falls this many events behind is disconnected
+pub const EVENT_BUFFER: usize = 256;
+
+/// How long `start` waits for running requests after a shutdown
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// Limits that keep one client from starving the others
+#[derive(Debug, Clone)]
+pub struct ServerLimits {
+    /// Max wait for the next message; idle connections are closed after it
+    pub read_timeout: Duration,
+    /// Max time to write one response
+    pub write_timeout: Duration,
+    /// Longest message accepted (line or frame), in bytes
+    pub max_message_size: usize,
+    /// Connections served at once; more wait to be accepted
+    pub max_connections: usize,
+    /// Requests running at once per connection; the connection isn't read
+    /// further until one finishes
+    pub max_in_flight: usize,
+}
+
+impl Default for ServerLimits {
+    fn default() -> Self {
+        Self {
+            read_timeout: Duration::from_secs(300),
+            write_timeout: Duration::from_secs(30),
+            max_message_size: 4 * 1024 * 1024,
+            max_connections: 128,
+            max_in_flight: 32,
+        }
+    }
+}
+
+/// Stops a running `IpcServer::start` from anywhere (signal handler, tests)
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl ShutdownHandle {
+    pub fn shutdown(&self) {
+        self.sender.send_replace(true);
+    }
+}
+
 pub struct IpcServer {
-    callback: ServerCallback,
+    callback: Arc<ServerCallback>,
+    events: Option<Arc<EventSource>>,
+    socket_path: String,
+    limits: ServerLimits,
+    shutdown: Arc<watch::Sender<bool>>,
 }
 
 impl IpcServer {
     pub fn new(callback: ServerCallback) -> Self {
-        Self { callback }
+        Self {
+            callback: Arc::new(callback),
+            events: None,
+            socket_path: socket::socket_path().to_string(),
+            limits: ServerLimits::default(),
+            shutdown: Arc::new(watch::channel(false).0),
+        }
+    }
+
+    /// Listens on another socket name (tests, second instances)
+    pub fn with_socket_path(mut self, socket_path: &str) -> Self {
+        self.socket_path = socket_path.to_string();
+        self
+    }
+
+    /// Enables `Subscribe`: each subscriber gets the events of its own receiver
+    pub fn with_event_source(mut self, source: EventSource) -> Self {
+        self.events = Some(Arc::new(source));
+        self
     }
 
+    pub fn with_limits(mut self, limits: ServerLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle {
+            sender: Arc::clone(&self.shutdown),
+        }
+    }
+
+    /// Serves clients until `ShutdownHandle::shutdown` is called
+    ///
+    /// Every connection runs on its own task and every request on the blocking
+    /// pool, so a slow request only holds up its own client.
     pub async fn start(&self) -> Result<()> {
-        // Remove existing socket if present
-        let socket_path = Path::new(SOCKET_PATH);
+        let socket_path = Path::new(&self.socket_path);
+        socket::prepare_socket_dir(socket_path)?;
+
+        // Leftover socket of a crashed daemon: remove it. A live one: don't
+        // take it over.
         if socket_path.exists() {
+            if std::os::unix::net::UnixStream::connect(socket_path).is_ok() {
+                return Err(anyhow!(
+                    "Another clippit-daemon is already listening on {}",
+                    self.socket_path
+                ));
+            }
             std::fs::remove_file(socket_path).context("Failed to remove existing socket file")?;
         }
 
-        let name = SOCKET_PATH.to_ns_name::<GenericNamespaced>()?;
         let listener = ListenerOptions::new()
-            .name(name)
-            .create_sync()
+            .name(socket::socket_name(&self.socket_path)?)
+            .create_tokio()
             .context("Failed to create socket listener")?;
+        socket::restrict_socket(socket_path)?;
+        let own_uid = socket::current_uid()?;
+
+        info!("IPC server listening on {}", self.socket_path);
 
-        info!("IPC server listening on {}", SOCKET_PATH);
+        let connection_slots = Arc::new(Semaphore::new(self.limits.max_connections));
+        let mut connections = JoinSet::new();
+        let mut shutdown = self.shutdown.subscribe();
 
         loop {
-            match listener.accept() {
-                Ok(stream) => {
-                    let callback = &self.callback;
-                    if let Err(e) = self.handle_connection(stream, callback) {
-                        error!("Error handling connection: {}", e);
+            // Reap finished connections
+            while connections.try_join_next().is_some() {}
+
+            let accepted = tokio::select! {
+                _ = wait_for_shutdown(&mut shutdown) => break,
+                slot = Arc::clone(&connection_slots).acquire_owned() => {
+                    let slot = slot?;
+                    tokio::select! {
+                        _ = wait_for_shutdown(&mut shutdown) => break,
+                        stream = listener.accept() => stream.map(|stream| (stream, slot)),
                     }
                 }
+            };
+
+            match accepted {
+                Ok((stream, slot)) => {
+                    if !peer_is_trusted(&stream, own_uid) {
+                        continue;
+                    }
+                    let connection = Connection {
+                        callback: Arc::clone(&self.callback),
+                        events: self.events.clone(),
+                        limits: self.limits.clone(),
+                        shutdown: self.shutdown.subscribe(),
+                    };
+                    connections.spawn(async move {
+                        if let Err(e) = connection.serve(stream).await {
+                            error!("Error handling connection: {}", e);
+                        }
+                        drop(slot);
+                    });
+                }
                 Err(e) => {
                     error!("Error accepting connection: {}", e);
                 }
             }
         }
+
+        info!(
+            "IPC server shutting down ({} connections open)",
+            connections.len()
+        );
+        drop(listener);
+        let finished = tokio::time::timeout(SHUTDOWN_GRACE, async {
+            while connections.join_next().await.is_some() {}
+        })
+        .await;
+        if finished.is_err() {
+            warn!(
+                "Requests still running after {:?}; dropping them",
+                SHUTDOWN_GRACE
+            );
+            connections.shutdown().await;
+        }
+        Ok(())
     }
+}
+
+/// Same user as the daemon; anyone else is dropped and logged for audit
+fn peer_is_trusted(stream: &LocalSocketStream, own_uid: u32) -> bool {
+    let creds = stream.peer_creds().ok();
+    let peer_uid = creds.as_ref().and_then(|creds| creds.euid());
+    if socket::peer_allowed(peer_uid, own_uid) {
+        return true;
+    }
+    warn!(
+        target: "clippit_ipc::audit",
+        peer_uid = ?peer_uid,
+        peer_pid = ?creds.as_ref().and_then(|creds| creds.pid()),
+        "Rejected IPC connection from another user"
+    );
+    false
+}
 
-    fn handle_connection(
+/// Resolves once shutdown was requested (never, if the server is gone)
+async fn wait_for_shutdown(shutdown: &mut watch::Receiver<bool>) {
+    while !*shutdown.borrow_and_update() {
+        if shutdown.changed().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+struct Connection {
+    callback: Arc<ServerCallback>,
+    events: Option<Arc<EventSource>>,
+    limits: ServerLimits,
+    shutdown: watch::Receiver<bool>,
+}
+
+impl Connection {
+    /// Serves every line of a connection until the client hangs up
+    ///
+    /// Bare messages are answered in place (one-shot clients). `Request`s run
+    /// concurrently, so a slow one doesn't hold back the ones behind it. A bare
+    /// `Subscribe` hands the connection over to `stream_events`.
+    async fn serve(mut self, stream: LocalSocketStream) -> Result<()> {
+        let (recv, send) = stream.split();
+        let writer = Arc::new(Mutex::new(send));
+        let mut reader = BufReader::new(recv);
+        let in_flight = Arc::new(Semaphore::new(self.limits.max_in_flight));
+        let mut requests = JoinSet::new();
+        let mut framing = Framing::Json;
+
+        loop {
+            while requests.try_join_next().is_some() {}
+
+            let read = tokio::select! {
+                _ = wait_for_shutdown(&mut self.shutdown) => break,
+                read = tokio::time::timeout(
+                    self.limits.read_timeout,
+                    read_message(&mut reader, self.limits.max_message_size, framing),
+                ) => read,
+            };
+            let line = match read {
+                Ok(Ok(Some(line))) => line,
+                Ok(Ok(None)) => break,
+                Ok(Err(ReadError::TooLarge)) => {
+                    warn!(
+                        "Closing connection: message over {} bytes",
+                        self.limits.max_message_size
+                    );
+                    let response = IpcResponse::error(
+                        ErrorCode::InvalidRequest,
+                        format!(
+                            "Message too large (max {} bytes)",
+                            self.limits.max_message_size
+                        ),
+                    );
+                    self.write(&writer, framing, &response).await?;
+                    break;
+                }
+                Ok(Err(ReadError::Io(e))) => return Err(e.into()),
+                Err(_) => {
+                    debug!("Closing idle connection");
+                    break;
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            // Handled here, so nothing is read in the old framing after the switch
+            if let Some((id, requested)) = framing_switch(&line) {
+                debug!("Connection switches to {:?}", requested);
+                match id {
+                    Some(id) => {
+                        let reply = Reply {
+                            id,
+                            response: IpcResponse::Ok,
+                        };
+                        write_frame(&writer, framing, &reply, self.limits.write_timeout).await?;
+                    }
+                    None => self.write(&writer, framing, &IpcResponse::Ok).await?,
+                }
+                framing = requested;
+                continue;
+            }
+
+            match request_id(&line) {
+                Some(id) => {
+                    // Backpressure: stop reading while too many requests run
+                    let permit = Arc::clone(&in_flight).acquire_owned().await?;
+                    let callback = Arc::clone(&self.callback);
+                    let writer = Arc::clone(&writer);
+                    let write_timeout = self.limits.write_timeout;
+                    requests.spawn(async move {
+                        let response =
+                            run_blocking(move || respond_to_request(&line, &callback)).await;
+                        let reply = Reply { id, response };
+                        if let Err(e) = write_frame(&writer, framing, &reply, write_timeout).await {
+                            debug!("Client left before reply {}: {}", id, e);
+                        }
+                        drop(permit);
+                    });
+                }
+                None => {
+                    if let Ok(IpcMessage::Subscribe { topics }) = serde_json::from_str(&line) {
+                        let Some(events) = &self.events else {
+                            let response = IpcResponse::Unsupported {
+                                message: "Subscribe".to_string(),
+                                protocol_version: PROTOCOL_VERSION,
+                            };
+                            self.write(&writer, framing, &response).await?;
+                            continue;
+                        };
+                        let events = events();
+                        return self
+                            .stream_events(reader, &writer, framing, events, &topics)
+                            .await;
+                    }
+                    let callback = Arc::clone(&self.callback);
+                    let response = run_blocking(move || respond(&line, &callback)).await;
+                    self.write(&writer, framing, &response).await?;
+                }
+            }
+        }
+
+        // Replies of requests already running still go out
+        while requests.join_next().await.is_some() {}
+        Ok(())
+    }
+
+    async fn write(
         &self,
-        mut stream: LocalSocketStream,
-        callback: &ServerCallback,
+        writer: &Mutex<SendHalf>,
+        framing: Framing,
+        response: &IpcResponse,
     ) -> Result<()> {
-        let mut line = String::new();
-        {
-            let mut reader = BufReader::new(&mut stream);
-            reader.read_line(&mut line)?;
+        write_frame(writer, framing, response, self.limits.write_timeout).await
+    }
+
+    /// Pushes events matching `topics` (all when empty) until the client leaves
+    ///
+    /// A subscriber that lags behind the channel capacity (`EVENT_BUFFER`) is
+    /// disconnected; it reconnects and reloads instead of making the daemon
+    /// queue forever.
+    async fn stream_events(
+        mut self,
+        mut reader: BufReader<RecvHalf>,
+        writer: &Mutex<SendHalf>,
+        framing: Framing,
+        mut events: broadcast::Receiver<IpcEvent>,
+        topics: &[EventTopic],
+    ) -> Result<()> {
+        info!("Client subscribed to {:?}", topics);
+        self.write(writer, framing, &IpcResponse::Subscribed)
+            .await?;
+
+        // Subscribers only listen; EOF (or anything else) on the read side ends it
+        let client_left = async move {
+            let _ = reader.read_u8().await;
+        };
+        tokio::pin!(client_left);
+
+        loop {
+            let event = tokio::select! {
+                _ = &mut client_left => break,
+                _ = wait_for_shutdown(&mut self.shutdown) => break,
+                event = events.recv() => match event {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        warn!("Subscriber missed {} events; disconnecting it", missed);
+                        break;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+            };
+            if !topics.is_empty() && !topics.contains(&event.topic()) {
+                continue;
+            }
+            if let Err(e) = self
+                .write(writer, framing, &IpcResponse::Event { event })
+                .await
+            {
+                debug!("Subscriber left: {}", e);
+                break;
+            }
         }
+        info!("Subscription closed");
+        Ok(())
+    }
+}
 
-        if line.is_empty() {
-            return Ok(());
+enum ReadError {
+    TooLarge,
+    Io(std::io::Error),
+}
+
+/// Next message as a JSON line (without reading past `max_size` bytes); None
+/// at EOF
+async fn read_message(
+    reader: &mut BufReader<RecvHalf>,
+    max_size: usize,
+    framing: Framing,
+) -> Result<Option<String>, ReadError> {
+    if framing == Framing::MessagePack {
+        let mut header = [0u8; 4];
+        match reader.read_exact(&mut header).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(ReadError::Io(e)),
+        }
+        let length = framing::frame_length(header).map_err(ReadError::Io)?;
+        if length > max_size {
+            return Err(ReadError::TooLarge);
         }
+        let mut frame = vec![0; length];
+        reader.read_exact(&mut frame).await.map_err(ReadError::Io)?;
+        let line = framing::to_json(framing, &frame)
+            .map_err(|e| ReadError::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))?;
+        return Ok(Some(line));
+    }
 
-        let message: IpcMessage =
-            serde_json::from_str(&line).context("Failed to deserialize message")?;
+    let mut line = Vec::new();
+    let read = reader
+        .take(max_size as u64 + 1)
+        .read_until(b'\n', &mut line)
+        .await
+        .map_err(ReadError::Io)?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if line.len() > max_size {
+        return Err(ReadError::TooLarge);
+    }
+    Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+}
 
-        info!("Received message: {:?}", message);
+async fn write_frame<T: serde::Serialize>(
+    writer: &Mutex<SendHalf>,
+    framing: Framing,
+    value: &T,
+    timeout: Duration,
+) -> Result<()> {
+    let frame = framing::encode(framing, value)?;
+    let mut writer = writer.lock().await;
+    tokio::time::timeout(timeout, async {
+        writer.write_all(&frame).await?;
+        writer.flush().await
+    })
+    .await
+    .map_err(|_| anyhow!("Write timed out after {:?}", timeout))??;
+    Ok(())
+}
 
-        let response = callback(message);
-        let response_json = serde_json::to_string(&response)?;
+/// Runs the (blocking) callback off the async workers
+async fn run_blocking(respond: impl FnOnce() -> IpcResponse + Send + 'static) -> IpcResponse {
+    tokio::task::spawn_blocking(respond)
+        .await
+        .unwrap_or_else(|e| {
+            IpcResponse::error(ErrorCode::Internal, format!("Request failed: {}", e))
+        })
+}
 
-        writeln!(stream, "{}", response_json)?;
-        stream.flush()?;
+/// `id` of a `Request` line; None for bare messages
+fn request_id(line: &str) -> Option<u64> {
+    match serde_json::from_str::<Value>(line).ok()? {
+        Value::Object(map) if map.contains_key("message") => map.get("id")?.as_u64(),
+        _ => None,
+    }
+}
 
-        Ok(())
+/// `id` (None for a bare message) and framing asked for by a `UseFraming` line
+fn framing_switch(line: &str) -> Option<(Option<u64>, Framing)> {
+    match serde_json::from_str::<Request>(line) {
+        Ok(Request {
+            id,
+            message: IpcMessage::UseFraming { framing },
+        }) => Some((Some(id), framing)),
+        Ok(_) => None,
+        Err(_) => match serde_json::from_str::<IpcMessage>(line).ok()? {
+            IpcMessage::UseFraming { framing } => Some((None, framing)),
+            _ => None,
+        },
+    }
+}
+
+fn respond_to_request(line: &str, callback: &ServerCallback) -> IpcResponse {
+    match serde_json::from_str::<Request>(line) {
+        Ok(Request {
+            message: IpcMessage::Subscribe { .. },
+            ..
+        }) => IpcResponse::error(
+            ErrorCode::InvalidRequest,
+            "Subscribe needs a connection of its own",
+        ),
+        Ok(request) => {
+            info!("Received request {}: {:?}", request.id, request.message);
+            callback(request.message)
+        }
+        Err(e) => {
+            // The envelope is fine (see request_id), so the message is the unknown part
+            let message = serde_json::from_str::<Value>(line)
+                .ok()
+                .and_then(|value| value.get("message").map(Value::to_string))
+                .unwrap_or_default();
+            let response = handshake::unsupported(&message, &e);
+            warn!("Unsupported request ({}): {:?}", e, response);
+            response
+        }
+    }
+}
+
+/// Runs the callback for one request line
+///
+/// Lines that don't parse (a client newer than this daemon, usually) get an
+/// `Unsupported` answer instead of a dropped connection.
+fn respond(line: &str, callback: &ServerCallback) -> IpcResponse {
+    match serde_json::from_str::<IpcMessage>(line) {
+        Ok(message) => {
+            info!("Received message: {:?}", message);
+            callback(message)
+        }
+        Err(e) => {
+            let response = handshake::unsupported(line, &e);
+            warn!("Unsupported message ({}): {:?}", e, response);
+            response
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::IpcClient;
+    use crate::session::IpcSession;
+    use std::io::{BufRead, Write};
+    use std::sync::Barrier;
+
+    fn callback() -> ServerCallback {
+        Box::new(|message| match message {
+            IpcMessage::Ping => IpcResponse::Pong,
+            _ => IpcResponse::Ok,
+        })
+    }
+
+    #[test]
+    fn test_respond_to_unknown_message() {
+        let callback = callback();
+
+        assert!(matches!(
+            respond("\"Ping\"\n", &callback),
+            IpcResponse::Pong
+        ));
+        assert!(matches!(
+            respond("{\"WatchFolder\":{\"path\":\"/tmp\"}}\n", &callback),
+            IpcResponse::Unsupported { message, .. } if message == "WatchFolder"
+        ));
+    }
+
+    #[test]
+    fn test_request_framing() {
+        let callback = callback();
+
+        let line = r#"{"id":7,"message":"Ping"}"#;
+        assert_eq!(request_id(line), Some(7));
+        assert!(matches!(
+            respond_to_request(line, &callback),
+            IpcResponse::Pong
+        ));
+
+        let line = r#"{"id":8,"message":{"WatchFolder":{"path":"/tmp"}}}"#;
+        assert_eq!(request_id(line), Some(8));
+        assert!(matches!(
+            respond_to_request(line, &callback),
+            IpcResponse::Unsupported { message, protocol_version }
+                if message == "WatchFolder" && protocol_version == PROTOCOL_VERSION
+        ));
+
+        // Subscribing takes a connection of its own
+        let line = r#"{"id":9,"message":{"Subscribe":{"topics":["History"]}}}"#;
+        assert!(matches!(
+            respond_to_request(line, &callback),
+            IpcResponse::Error {
+                code: ErrorCode::InvalidRequest,
+                ..
+            }
+        ));
+
+        // Bare messages aren't requests, even ones with an `id` field
+        assert_eq!(request_id(r#"{"GetEntryData":{"id":3}}"#), None);
+        assert_eq!(request_id(r#""Ping""#), None);
+    }
+
+    /// Server on a socket of its own, shut down on drop
+    struct TestServer {
+        _dir: tempfile::TempDir,
+        path: String,
+        shutdown: ShutdownHandle,
+        /// `GetEntryData { id: 0 }` meets the test here twice: once when it
+        /// starts, then again before it answers
+        gate: Arc<Barrier>,
+    }
+
+    impl Drop for TestServer {
+        fn drop(&mut self) {
+            self.shutdown.shutdown();
+        }
+    }
+
+    fn start_server(limits: ServerLimits) -> TestServer {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir
+            .path()
+            .join("clippit.sock")
+            .to_string_lossy()
+            .into_owned();
+        let gate = Arc::new(Barrier::new(2));
+        let server_gate = Arc::clone(&gate);
+        let server = IpcServer::new(Box::new(move |message| match message {
+            IpcMessage::Hello {
+                protocol_version,
+                capabilities,
+                ..
+            } => handshake::answer_hello(
+                "test-daemon",
+                protocol_version,
+                &capabilities,
+                crate::protocol::capability::ALL,
+            ),
+            IpcMessage::GetEntryData { id: 0, .. } => {
+                server_gate.wait();
+                server_gate.wait();
+                IpcResponse::Ok
+            }
+            IpcMessage::Ping => IpcResponse::Pong,
+            _ => IpcResponse::Ok,
+        }))
+        .with_socket_path(&path)
+        .with_limits(limits);
+        let shutdown = server.shutdown_handle();
+
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            runtime.block_on(server.start()).unwrap();
+        });
+        for _ in 0..250 {
+            if IpcClient::send_once_to(&path, &IpcMessage::Ping).is_ok() {
+                return TestServer {
+                    _dir: dir,
+                    path,
+                    shutdown,
+                    gate,
+                };
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        panic!("test server didn't start");
+    }
+
+    fn connect(path: &str) -> interprocess::local_socket::Stream {
+        use interprocess::local_socket::prelude::*;
+        LocalSocketStream::connect(socket::socket_name(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_slow_client_doesnt_stall_the_others() {
+        let server = start_server(ServerLimits::default());
+
+        // A one-shot client asking for a "large image" that takes until released
+        let slow_path = server.path.clone();
+        let slow = std::thread::spawn(move || {
+            IpcClient::send_once_to(
+                &slow_path,
+                &IpcMessage::GetEntryData {
+                    id: 0,
+                    skip_image_data: false,
+                },
+            )
+        });
+        server.gate.wait();
+
+        // Many clients at once, one-shot and multiplexed, all served meanwhile
+        let clients: Vec<_> = (0..40)
+            .map(|i| {
+                let path = server.path.clone();
+                std::thread::spawn(move || {
+                    if i % 2 == 0 {
+                        IpcClient::send_once_to(&path, &IpcMessage::Ping)
+                    } else {
+                        let session = IpcSession::with_socket_path("test", &path);
+                        (0..5)
+                            .map(|_| {
+                                session.request(IpcMessage::GetEntryData {
+                                    id: 10,
+                                    skip_image_data: false,
+                                })
+                            })
+                            .last()
+                            .unwrap()
+                    }
+                })
+            })
+            .collect();
+        for client in clients {
+            let result = client.join().unwrap();
+            assert!(result.is_ok(), "{:?}", result.err());
+        }
+        assert!(!slow.is_finished());
+
+        server.gate.wait();
+        assert!(slow.join().unwrap().is_ok());
+    }
+
+    #[test]
+    fn test_limits() {
+        let limits = ServerLimits {
+            read_timeout: Duration::from_millis(200),
+            max_message_size: 64,
+            ..ServerLimits::default()
+        };
+        let server = start_server(limits);
+        let path = &server.path;
+
+        // Oversized message: an error, then the connection is closed
+        let mut stream = connect(path);
+        writeln!(
+            stream,
+            "{{\"SearchHistory\":{{\"query\":\"{}\"}}}}",
+            "x".repeat(100)
+        )
+        .unwrap();
+        let mut reader = std::io::BufReader::new(&stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert!(line.contains("too large"), "{}", line);
+        line.clear();
+        assert_eq!(reader.read_line(&mut line).unwrap(), 0);
+
+        // Idle connection: closed by the server after the read timeout
+        let stream = connect(path);
+        let mut reader = std::io::BufReader::new(&stream);
+        assert_eq!(reader.read_line(&mut String::new()).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_socket_is_private_and_not_taken_over() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let server = start_server(ServerLimits::default());
+        let path = &server.path;
+        let mode = std::fs::metadata(path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // A second daemon on the same socket refuses to start
+        let second = IpcServer::new(Box::new(|_| IpcResponse::Ok)).with_socket_path(path);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let error = runtime.block_on(second.start()).unwrap_err();
+        assert!(error.to_string().contains("already listening"), "{}", error);
+        assert!(IpcClient::send_once_to(path, &IpcMessage::Ping).is_ok());
+    }
+
+    #[test]
+    fn test_shutdown_lets_running_requests_finish() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir
+            .path()
+            .join("clippit.sock")
+            .to_string_lossy()
+            .into_owned();
+        let gate = Arc::new(Barrier::new(2));
+        let server_gate = Arc::clone(&gate);
+        let server = IpcServer::new(Box::new(move |message| match message {
+            IpcMessage::Ping => IpcResponse::Pong,
+            _ => {
+                server_gate.wait();
+                server_gate.wait();
+                IpcResponse::Ok
+            }
+        }))
+        .with_socket_path(&path);
+        let shutdown = server.shutdown_handle();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let server = runtime.spawn(async move { server.start().await });
+        for _ in 0..250 {
+            if IpcClient::send_once_to(&path, &IpcMessage::Ping).is_ok() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+
+        let client_path = path.clone();
+        let client = std::thread::spawn(move || {
+            IpcClient::send_once_to(&client_path, &IpcMessage::GetCaptureState)
+        });
+        gate.wait();
+
+        // Stop while the request runs; it still gets its answer
+        shutdown.shutdown();
+        gate.wait();
+        runtime.block_on(server).unwrap().unwrap();
+        assert!(client.join().unwrap().is_ok());
+        // Nobody listens anymore
+        assert!(IpcClient::send_once_to(&path, &IpcMessage::Ping).is_err());
     }
 }
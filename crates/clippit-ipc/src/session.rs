@@ -0,0 +1,426 @@
+//! Persistent connection to the daemon, shared by many requests
+//!
+//! Each request goes out as a `Request` with a fresh id; a reader thread routes
+//! every `Reply` to the caller waiting for that id, so callers on different
+//! threads don't wait on each other. A dropped connection is reopened on the
+//! next request. Daemons without the `multiplex` capability get one connection
+//! per request, like before.
+//!
+//! When the daemon has `msgpack`, the connection switches to MessagePack frames
+//! right after connecting (see `framing`); set `CLIPPIT_IPC_JSON=1` to stay on
+//! JSON lines when debugging.
+
+use anyhow::{anyhow, Context, Result};
+use interprocess::local_socket::prelude::LocalSocketStream;
+use interprocess::local_socket::traits::Stream;
+use interprocess::local_socket::{RecvHalf, SendHalf};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::{BufReader, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tracing::warn;
+
+use crate::client::IpcClient;
+use crate::error::IpcError;
+use crate::framing;
+use crate::handshake::Negotiated;
+use crate::protocol::{capability, ErrorCode, Framing, IpcMessage, IpcResponse, Reply, Request};
+use crate::socket::{socket_name, socket_path};
+
+/// How long a request waits for its reply
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+type Pending = Arc<Mutex<HashMap<u64, mpsc::Sender<Result<IpcResponse>>>>>;
+
+pub struct IpcSession {
+    client: String,
+    socket_path: String,
+    next_id: AtomicU64,
+    connection: Mutex<Option<Arc<Connection>>>,
+}
+
+struct Connection {
+    writer: Option<Mutex<SendHalf>>, // None: daemon without `multiplex`
+    framing: Framing,
+    negotiated: Negotiated,
+    pending: Pending,
+    alive: Arc<AtomicBool>,
+}
+
+impl IpcSession {
+    /// Session on the daemon socket; connects on the first request
+    pub fn new(client: &str) -> Self {
+        Self::with_socket_path(client, socket_path())
+    }
+
+    pub fn with_socket_path(client: &str, socket_path: &str) -> Self {
+        Self {
+            client: client.to_string(),
+            socket_path: socket_path.to_string(),
+            next_id: AtomicU64::new(1),
+            connection: Mutex::new(None),
+        }
+    }
+
+    /// Session shared by the `IpcClient` helpers of this process
+    pub fn shared() -> &'static IpcSession {
+        static SHARED: OnceLock<IpcSession> = OnceLock::new();
+        SHARED.get_or_init(|| {
+            let program = std::env::args()
+                .next()
+                .and_then(|arg| {
+                    std::path::Path::new(&arg)
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                })
+                .unwrap_or_else(|| "clippit-client".to_string());
+            IpcSession::new(&program)
+        })
+    }
+
+    /// Whether the daemon agreed on `capability` (connects if needed)
+    pub fn supports(&self, capability: &str) -> Result<bool> {
+        Ok(self.connection()?.negotiated.supports(capability))
+    }
+
+    /// Sends a message and waits for its response
+    pub fn request(&self, message: IpcMessage) -> Result<IpcResponse> {
+        let connection = self.connection()?;
+        let Some(writer) = &connection.writer else {
+            let response = IpcClient::send_once_to(&self.socket_path, &message);
+            if response.is_err() {
+                // Negotiate again next time: the daemon may have been upgraded
+                connection.alive.store(false, Ordering::Relaxed);
+            }
+            return response;
+        };
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::channel();
+        connection.pending.lock().unwrap().insert(id, sender);
+
+        let frame = framing::encode(connection.framing, &Request { id, message })?;
+        let sent = {
+            let mut writer = writer.lock().unwrap();
+            writer.write_all(&frame).and_then(|_| writer.flush())
+        };
+        if let Err(e) = sent {
+            connection.pending.lock().unwrap().remove(&id);
+            connection.alive.store(false, Ordering::Relaxed);
+            return Err(anyhow!("Lost connection to clippit-daemon: {}", e));
+        }
+
+        match receiver.recv_timeout(REQUEST_TIMEOUT) {
+            Ok(response) => response,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                connection.pending.lock().unwrap().remove(&id);
+                Err(IpcError::new(
+                    ErrorCode::Timeout,
+                    format!("clippit-daemon didn't answer in {:?}", REQUEST_TIMEOUT),
+                )
+                .into())
+            }
+            // The reader thread dropped every pending request: connection closed
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(anyhow!(
+                "Lost connection to clippit-daemon before it answered"
+            )),
+        }
+    }
+
+    /// Current connection, (re)opened when there is none or it was closed
+    fn connection(&self) -> Result<Arc<Connection>> {
+        let mut current = self.connection.lock().unwrap();
+        if let Some(connection) = current.as_ref() {
+            if connection.alive.load(Ordering::Relaxed) {
+                return Ok(Arc::clone(connection));
+            }
+        }
+
+        let connection = Arc::new(self.connect()?);
+        *current = Some(Arc::clone(&connection));
+        Ok(connection)
+    }
+
+    fn connect(&self) -> Result<Connection> {
+        let pending: Pending = Arc::default();
+        let alive = Arc::new(AtomicBool::new(true));
+
+        let negotiated = IpcClient::hello_at(&self.socket_path, &self.client)?;
+        if !negotiated.supports(capability::MULTIPLEX) {
+            log_legacy_daemon(&negotiated);
+            return Ok(Connection {
+                writer: None,
+                framing: Framing::Json,
+                negotiated,
+                pending,
+                alive,
+            });
+        }
+
+        let name = socket_name(&self.socket_path)?;
+        let stream = LocalSocketStream::connect(name)
+            .context("Failed to connect to daemon. Is clippit-daemon running?")?;
+        let (recv, mut send) = stream.split();
+        let mut reader = BufReader::new(recv);
+
+        let framing = if negotiated.supports(capability::MSGPACK) && !json_forced() {
+            switch_framing(&mut reader, &mut send, Framing::MessagePack)?;
+            Framing::MessagePack
+        } else {
+            Framing::Json
+        };
+
+        let reader_pending = Arc::clone(&pending);
+        let reader_alive = Arc::clone(&alive);
+        std::thread::Builder::new()
+            .name("clippit-ipc-reader".to_string())
+            .spawn(move || read_replies(reader, framing, reader_pending, reader_alive))?;
+
+        Ok(Connection {
+            writer: Some(Mutex::new(send)),
+            framing,
+            negotiated,
+            pending,
+            alive,
+        })
+    }
+}
+
+/// `CLIPPIT_IPC_JSON=1`: keep JSON lines, readable in `strace`/`socat`
+fn json_forced() -> bool {
+    std::env::var("CLIPPIT_IPC_JSON").is_ok_and(|value| value == "1")
+}
+
+/// Asks the daemon for `framing` and waits for the `Ok` (still in JSON)
+fn switch_framing(
+    reader: &mut BufReader<RecvHalf>,
+    writer: &mut SendHalf,
+    framing: Framing,
+) -> Result<()> {
+    let request = Request {
+        id: 0,
+        message: IpcMessage::UseFraming { framing },
+    };
+    writer.write_all(&framing::encode(Framing::Json, &request)?)?;
+    writer.flush()?;
+
+    let frame = framing::read_frame(reader, Framing::Json)?
+        .ok_or_else(|| anyhow!("clippit-daemon closed the connection on UseFraming"))?;
+    match framing::decode::<Reply>(Framing::Json, &frame)? {
+        Reply {
+            id: 0,
+            response: IpcResponse::Ok,
+        } => Ok(()),
+        reply => Err(anyhow!("Unexpected response to UseFraming: {:?}", reply)),
+    }
+}
+
+fn log_legacy_daemon(negotiated: &Negotiated) {
+    warn!(
+        "⚠️ {} (protocol {}) has no persistent connections; using one connection per request",
+        negotiated.peer, negotiated.protocol_version
+    );
+}
+
+/// Routes each reply to the request waiting for it, until the connection closes
+fn read_replies(
+    mut reader: BufReader<RecvHalf>,
+    framing: Framing,
+    pending: Pending,
+    alive: Arc<AtomicBool>,
+) {
+    while let Ok(Some(frame)) = framing::read_frame(&mut reader, framing) {
+        let Some((id, response)) = parse_reply_frame(framing, &frame) else {
+            warn!("❌ Ignoring malformed reply from daemon");
+            continue;
+        };
+        if let Some(sender) = pending.lock().unwrap().remove(&id) {
+            // The caller may have timed out already
+            let _ = sender.send(response.and_then(IpcClient::check_supported));
+        }
+    }
+
+    alive.store(false, Ordering::Relaxed);
+    // Dropping the senders wakes every waiting request with an error
+    pending.lock().unwrap().clear();
+}
+
+/// `Reply` in either framing; MessagePack replies are decoded directly (image
+/// bytes stay binaries) and only go through JSON when they don't decode
+fn parse_reply_frame(framing: Framing, frame: &[u8]) -> Option<(u64, Result<IpcResponse>)> {
+    if framing == Framing::MessagePack {
+        if let Ok(Reply { id, response }) = framing::decode(framing, frame) {
+            return Some((id, Ok(response)));
+        }
+    }
+    parse_reply(&framing::to_json(framing, frame).ok()?)
+}
+
+/// `id` and response of a `Reply` line; the response fails alone when it is
+/// a variant this client doesn't know
+fn parse_reply(line: &str) -> Option<(u64, Result<IpcResponse>)> {
+    let mut value: Value = serde_json::from_str(line).ok()?;
+    let id = value.get("id")?.as_u64()?;
+    let response = value.get_mut("response")?.take();
+    Some((id, IpcClient::decode_response(&response.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::IpcServer;
+    use std::sync::Barrier;
+
+    /// Socket in a directory removed with the returned guard
+    fn socket_path() -> (tempfile::TempDir, String) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir
+            .path()
+            .join("clippit.sock")
+            .to_string_lossy()
+            .into_owned();
+        (dir, path)
+    }
+
+    /// Daemon stand-in: `SearchHistory { query: "slow" }` meets the test at
+    /// the returned barrier twice, when it starts and before it answers
+    fn start_server(socket_path: &str) -> Arc<Barrier> {
+        let gate = Arc::new(Barrier::new(2));
+        let server_gate = Arc::clone(&gate);
+        let server = IpcServer::new(Box::new(move |message| match message {
+            IpcMessage::Hello {
+                protocol_version,
+                capabilities,
+                ..
+            } => crate::handshake::answer_hello(
+                "test-daemon",
+                protocol_version,
+                &capabilities,
+                capability::ALL,
+            ),
+            IpcMessage::SearchHistory { query } => {
+                if query == "slow" {
+                    server_gate.wait();
+                    server_gate.wait();
+                }
+                IpcResponse::SearchHistoryResponse {
+                    entries: Vec::new(),
+                }
+            }
+            IpcMessage::GetImageChunk { offset, length, .. } => IpcResponse::ImageChunkResponse {
+                data: vec![7; length as usize],
+                total_size: offset + length,
+            },
+            IpcMessage::Ping => IpcResponse::Pong,
+            _ => IpcResponse::Ok,
+        }))
+        .with_socket_path(socket_path);
+
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            runtime.block_on(server.start()).unwrap();
+        });
+        gate
+    }
+
+    fn wait_for_server(session: &IpcSession) {
+        for _ in 0..250 {
+            if session.request(IpcMessage::Ping).is_ok() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        panic!("test server didn't start");
+    }
+
+    #[test]
+    fn test_out_of_order_replies_on_one_connection() {
+        let (_dir, path) = socket_path();
+        let session = Arc::new(IpcSession::with_socket_path("test", &path));
+        // Nothing listening yet: fails, and the next request connects again
+        assert!(session.request(IpcMessage::Ping).is_err());
+
+        let gate = start_server(&path);
+        wait_for_server(&session);
+        let first = session.connection().unwrap();
+        assert!(first.writer.is_some());
+
+        let slow_session = Arc::clone(&session);
+        let slow = std::thread::spawn(move || {
+            slow_session.request(IpcMessage::SearchHistory {
+                query: "slow".to_string(),
+            })
+        });
+        gate.wait();
+
+        // Sent after the slow search, answered while it still runs
+        assert!(matches!(
+            session.request(IpcMessage::Ping).unwrap(),
+            IpcResponse::Pong
+        ));
+        assert!(!slow.is_finished());
+        gate.wait();
+
+        assert!(matches!(
+            slow.join().unwrap().unwrap(),
+            IpcResponse::SearchHistoryResponse { .. }
+        ));
+        // Still the same connection
+        assert!(Arc::ptr_eq(&first, &session.connection().unwrap()));
+    }
+
+    #[test]
+    fn test_connection_switches_to_messagepack() {
+        let (_dir, path) = socket_path();
+        start_server(&path);
+        let session = IpcSession::with_socket_path("test", &path);
+        wait_for_server(&session);
+
+        let connection = session.connection().unwrap();
+        assert_eq!(connection.framing, Framing::MessagePack);
+        assert!(session.supports(capability::IMAGE_CHUNKS).unwrap());
+
+        let response = session
+            .request(IpcMessage::GetImageChunk {
+                id: 1,
+                offset: 0,
+                length: 300_000,
+            })
+            .unwrap();
+        assert!(matches!(
+            response,
+            IpcResponse::ImageChunkResponse { data, total_size }
+                if data.len() == 300_000 && total_size == 300_000
+        ));
+        // JSON one-shot clients on the same daemon are unaffected
+        let response = IpcClient::send_once_to(&path, &IpcMessage::Ping).unwrap();
+        assert!(matches!(response, IpcResponse::Pong));
+    }
+
+    #[test]
+    fn test_one_shot_messages_on_the_new_server() {
+        let (_dir, path) = socket_path();
+        start_server(&path);
+        wait_for_server(&IpcSession::with_socket_path("test", &path));
+
+        let response = IpcClient::send_once_to(&path, &IpcMessage::Ping).unwrap();
+        assert!(matches!(response, IpcResponse::Pong));
+    }
+
+    #[test]
+    fn test_parse_reply() {
+        let (id, response) = parse_reply(r#"{"id":4,"response":"Pong"}"#).unwrap();
+        assert_eq!(id, 4);
+        assert!(matches!(response.unwrap(), IpcResponse::Pong));
+
+        // Unknown response: only that request fails
+        let (id, response) = parse_reply(r#"{"id":5,"response":{"Teleported":{}}}"#).unwrap();
+        assert_eq!(id, 5);
+        assert!(response.unwrap_err().to_string().contains("Teleported"));
+
+        assert!(parse_reply("garbage").is_none());
+    }
+}
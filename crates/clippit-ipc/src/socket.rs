@@ -0,0 +1,159 @@
+//! Where the daemon socket lives and who may use it
+//!
+//! The socket is a file in `$XDG_RUNTIME_DIR/clippit/` (a directory only the
+//! user can enter) with mode 0600, unless `[advanced] ipc_socket` in the config
+//! points somewhere else. The daemon also checks the peer UID of every
+//! connection, so a socket in a shared directory isn't an open door either.
+
+use anyhow::{anyhow, Context, Result};
+use interprocess::local_socket::{GenericFilePath, Name, ToFsName};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const SOCKET_FILE: &str = "clippit.sock";
+
+/// Socket used by the daemon and every client of this process
+///
+/// Resolved once: `[advanced] ipc_socket` from the config, or
+/// `default_socket_path()`.
+pub fn socket_path() -> &'static str {
+    static PATH: OnceLock<String> = OnceLock::new();
+    PATH.get_or_init(|| {
+        configured_socket_path()
+            .unwrap_or_else(|| default_socket_path().to_string_lossy().into_owned())
+    })
+}
+
+/// `$XDG_RUNTIME_DIR/clippit/clippit.sock`; `/tmp/clippit-<uid>/` when the
+/// session has no runtime directory
+pub fn default_socket_path() -> PathBuf {
+    let dir = match dirs::runtime_dir() {
+        Some(runtime_dir) => runtime_dir.join("clippit"),
+        None => PathBuf::from(format!("/tmp/clippit-{}", current_uid().unwrap_or(0))),
+    };
+    dir.join(SOCKET_FILE)
+}
+
+/// Same file as `clippit_core::Config::config_path` (this crate can't depend
+/// on clippit-core)
+fn configured_socket_path() -> Option<String> {
+    let config_path = dirs::config_dir()?.join("clippit").join("config.toml");
+    socket_path_from_config(&std::fs::read_to_string(config_path).ok()?)
+}
+
+fn socket_path_from_config(contents: &str) -> Option<String> {
+    let config: toml::Value = toml::from_str(contents).ok()?;
+    let path = config.get("advanced")?.get("ipc_socket")?.as_str()?.trim();
+    (!path.is_empty()).then(|| path.to_string())
+}
+
+pub(crate) fn socket_name(path: &str) -> std::io::Result<Name<'_>> {
+    path.to_fs_name::<GenericFilePath>()
+}
+
+/// Creates the socket directory (0700) when missing
+///
+/// A directory that already exists must belong to this user and be closed to
+/// others, except for shared ones the config points at (like `/tmp`), where
+/// the socket's own mode and the peer check have to do.
+pub(crate) fn prepare_socket_dir(socket_path: &Path) -> Result<()> {
+    let Some(dir) = socket_path.parent() else {
+        return Ok(());
+    };
+    if !dir.exists() {
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)
+            .with_context(|| format!("Failed to create socket directory {}", dir.display()))?;
+        return Ok(());
+    }
+
+    let metadata = std::fs::metadata(dir)?;
+    let sticky = metadata.permissions().mode() & 0o1000 != 0;
+    if !sticky && metadata.uid() != current_uid()? {
+        return Err(anyhow!(
+            "Socket directory {} belongs to another user",
+            dir.display()
+        ));
+    }
+    if !sticky && metadata.permissions().mode() & 0o022 != 0 {
+        return Err(anyhow!(
+            "Socket directory {} is writable by other users",
+            dir.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Only the owner may connect
+pub(crate) fn restrict_socket(socket_path: &Path) -> Result<()> {
+    std::fs::set_permissions(socket_path, std::fs::Permissions::from_mode(0o600))
+        .with_context(|| format!("Failed to set permissions of {}", socket_path.display()))
+}
+
+/// Effective UID of this process (owner of `/proc/self`; Clippit is Linux-only)
+pub(crate) fn current_uid() -> std::io::Result<u32> {
+    Ok(std::fs::metadata("/proc/self")?.uid())
+}
+
+/// Whether a peer may talk to the daemon: same user, and known
+pub(crate) fn peer_allowed(peer_uid: Option<u32>, own_uid: u32) -> bool {
+    peer_uid == Some(own_uid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_socket_path_from_config() {
+        let config =
+            "[general]\nmax_history_items = 10\n\n[advanced]\nipc_socket = \"/run/x.sock\"\n";
+        assert_eq!(
+            socket_path_from_config(config).as_deref(),
+            Some("/run/x.sock")
+        );
+        assert_eq!(
+            socket_path_from_config("[advanced]\nlog_level = \"info\"\n"),
+            None
+        );
+        assert_eq!(
+            socket_path_from_config("[advanced]\nipc_socket = \" \"\n"),
+            None
+        );
+        assert_eq!(socket_path_from_config("not toml ["), None);
+    }
+
+    #[test]
+    fn test_peer_allowed() {
+        let uid = current_uid().unwrap();
+        assert!(peer_allowed(Some(uid), uid));
+        assert!(!peer_allowed(Some(uid + 1), uid));
+        // No credentials: refused
+        assert!(!peer_allowed(None, uid));
+    }
+
+    #[test]
+    fn test_prepare_socket_dir() {
+        let dir = std::env::temp_dir().join(format!("clippit-test-dir-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let socket = dir.join("run").join(SOCKET_FILE);
+
+        prepare_socket_dir(&socket).unwrap();
+        let mode = std::fs::metadata(socket.parent().unwrap())
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o700);
+        // Existing private directory: fine
+        prepare_socket_dir(&socket).unwrap();
+
+        // Group-writable directory: refused
+        std::fs::set_permissions(dir.join("run"), std::fs::Permissions::from_mode(0o770)).unwrap();
+        assert!(prepare_socket_dir(&socket).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,161 @@
+//! Live events from the daemon on a dedicated connection
+//!
+//! `IpcClient::subscribe` sends a bare `Subscribe`, waits for `Subscribed` and
+//! then yields one `IpcEvent` per line. The stream ends when the daemon goes
+//! away; reconnecting (and reloading whatever was missed) is up to the caller.
+
+use anyhow::{anyhow, Context, Result};
+use interprocess::local_socket::prelude::LocalSocketStream;
+use interprocess::local_socket::traits::Stream;
+use std::io::{BufRead, BufReader, Write};
+use tracing::debug;
+
+use crate::client::IpcClient;
+use crate::error::IpcError;
+use crate::protocol::{EventTopic, IpcEvent, IpcMessage, IpcResponse};
+use crate::socket::socket_name;
+
+pub struct EventStream {
+    reader: BufReader<LocalSocketStream>,
+}
+
+impl EventStream {
+    pub(crate) fn connect(socket_path: &str, topics: Vec<EventTopic>) -> Result<Self> {
+        let name = socket_name(socket_path)?;
+        let mut stream = LocalSocketStream::connect(name)
+            .context("Failed to connect to daemon. Is clippit-daemon running?")?;
+
+        let line = serde_json::to_string(&IpcMessage::Subscribe { topics })?;
+        writeln!(stream, "{}", line)?;
+        stream.flush()?;
+
+        let mut reader = BufReader::new(stream);
+        let mut response_line = String::new();
+        reader.read_line(&mut response_line)?;
+        if response_line.trim().is_empty() {
+            return Err(anyhow!(
+                "clippit-daemon closed the connection on Subscribe (older daemon?)"
+            ));
+        }
+
+        match IpcClient::check_supported(IpcClient::decode_response(&response_line)?)? {
+            IpcResponse::Subscribed => Ok(Self { reader }),
+            error @ IpcResponse::Error { .. } => Err(IpcError::from_response(error)),
+            _ => Err(anyhow!("Unexpected response to subscribe")),
+        }
+    }
+}
+
+impl Iterator for EventStream {
+    type Item = IpcEvent;
+
+    /// Next event; None once the daemon closed the connection
+    fn next(&mut self) -> Option<IpcEvent> {
+        loop {
+            let mut line = String::new();
+            match self.reader.read_line(&mut line) {
+                Ok(0) | Err(_) => return None,
+                Ok(_) => {}
+            }
+
+            match parse_event(&line) {
+                Some(event) => return Some(event),
+                // Newer daemon: skip events this client doesn't know
+                None => debug!("Ignoring unknown event: {}", line.trim()),
+            }
+        }
+    }
+}
+
+fn parse_event(line: &str) -> Option<IpcEvent> {
+    match serde_json::from_str(line).ok()? {
+        IpcResponse::Event { event } => Some(event),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::IpcServer;
+    use std::time::Duration;
+    use tokio::sync::broadcast;
+
+    #[test]
+    fn test_parse_event() {
+        assert!(matches!(
+            parse_event(r#"{"Event":{"event":{"EntryDeleted":{"ids":[1,2]}}}}"#),
+            Some(IpcEvent::EntryDeleted { ids }) if ids == vec![1, 2]
+        ));
+        assert!(parse_event(r#"{"Event":{"event":{"Teleported":{}}}}"#).is_none());
+        assert!(parse_event(r#""Pong""#).is_none());
+    }
+
+    /// Server publishing `events` on `path`, and a subscription to `topics`
+    fn subscribe(
+        path: &str,
+        events: &broadcast::Sender<IpcEvent>,
+        topics: Vec<EventTopic>,
+    ) -> EventStream {
+        let source = events.clone();
+        let server = IpcServer::new(Box::new(|_| IpcResponse::Ok))
+            .with_socket_path(path)
+            .with_event_source(Box::new(move || source.subscribe()));
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            runtime.block_on(server.start()).unwrap();
+        });
+
+        (0..50)
+            .find_map(|_| {
+                std::thread::sleep(Duration::from_millis(20));
+                EventStream::connect(path, topics.clone()).ok()
+            })
+            .expect("test server didn't start")
+    }
+
+    #[test]
+    fn test_subscriber_gets_events_of_its_topics() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir
+            .path()
+            .join("clippit.sock")
+            .to_string_lossy()
+            .into_owned();
+        let (events, _) = broadcast::channel(16);
+        let mut stream = subscribe(&path, &events, vec![EventTopic::Capture]);
+
+        events.send(IpcEvent::HistoryCleared).unwrap();
+        events
+            .send(IpcEvent::CaptureStateChanged { paused: true })
+            .unwrap();
+        // HistoryCleared is filtered out by the topic
+        assert!(matches!(
+            stream.next(),
+            Some(IpcEvent::CaptureStateChanged { paused: true })
+        ));
+    }
+
+    #[test]
+    fn test_receiver_is_dropped_when_subscriber_leaves() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir
+            .path()
+            .join("clippit.sock")
+            .to_string_lossy()
+            .into_owned();
+        let (events, _) = broadcast::channel(16);
+        let stream = subscribe(&path, &events, Vec::new());
+        assert_eq!(events.receiver_count(), 1);
+
+        // No event needed for the server to notice
+        drop(stream);
+        for _ in 0..250 {
+            if events.receiver_count() == 0 {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        panic!("subscription outlived its client");
+    }
+}
@@ -0,0 +1,38 @@
+//! `HistoryEntry` values for tests, here and in the crates that speak the
+//! protocol (enable the `test-support` feature from their dev-dependencies)
+
+use crate::protocol::{ContentType, HistoryEntry};
+
+/// Text entry with every optional field empty
+pub fn text_entry(id: i64, text: &str) -> HistoryEntry {
+    HistoryEntry {
+        id,
+        content_type: ContentType::Text,
+        content_text: Some(text.to_string()),
+        content_data: None,
+        image_path: None,
+        thumbnail_data: None,
+        image_width: None,
+        image_height: None,
+        ocr_text: None,
+        timestamp: chrono::Utc::now(),
+        mime_type: None,
+        barcode_text: None,
+        text_kind: None,
+        code_language: None,
+        tags: Vec::new(),
+    }
+}
+
+/// 10x10 PNG entry whose bytes (`data`) travel with it
+pub fn image_entry(id: i64, data: Vec<u8>) -> HistoryEntry {
+    HistoryEntry {
+        content_type: ContentType::Image,
+        content_text: None,
+        content_data: Some(data),
+        image_width: Some(10),
+        image_height: Some(10),
+        mime_type: Some("image/png".to_string()),
+        ..text_entry(id, "")
+    }
+}
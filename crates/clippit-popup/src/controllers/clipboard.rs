@@ -1,11 +1,13 @@
-use arboard::{Clipboard, ImageData};
+use crate::utils::error_message;
+use arboard::Clipboard;
 use clippit_core::Config;
 
 /// Copies an entry to the clipboard and shows a system notification
 ///
 /// This function:
 /// 1. Gets the full entry data via IPC
-/// 2. Copies the content to clipboard using arboard (Wayland-native)
+/// 2. Copies text with arboard (Wayland-native); images are copied by the
+///    daemon, which keeps their original format
 /// 3. Shows a system notification to user
 /// 4. Returns success status to allow caller to close the window immediately
 ///
@@ -106,61 +108,28 @@ pub fn copy_to_clipboard(entry_id: i64) -> bool {
                     }
                 }
                 clippit_ipc::ContentType::Image => {
-                    if let Some(image_path) = &entry.image_path {
-                        eprintln!("📸 Copying image from file: {}", image_path);
-
-                        // Load image from file
-                        match image::open(image_path) {
-                            Ok(img) => {
-                                let rgba = img.to_rgba8();
-                                let img_data = ImageData {
-                                    width: rgba.width() as usize,
-                                    height: rgba.height() as usize,
-                                    bytes: rgba.as_raw().into(),
-                                };
-
-                                match clipboard.set_image(img_data) {
-                                    Ok(_) => {
-                                        eprintln!(
-                                            "✅ Image copied to clipboard from file: {}",
-                                            image_path
-                                        );
-                                        show_notification(
-                                            "Clippit",
-                                            &format!(
-                                                "Imagem copiada ({}x{})",
-                                                rgba.width(),
-                                                rgba.height()
-                                            ),
-                                            show_notifications,
-                                        );
-                                        true
-                                    }
-                                    Err(e) => {
-                                        eprintln!("❌ Failed to copy image: {}", e);
-                                        show_notification(
-                                            "Erro",
-                                            &format!("Erro ao copiar imagem: {}", e),
-                                            show_notifications,
-                                        );
-                                        false
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                eprintln!("❌ Failed to load image: {}", e);
-                                show_notification(
-                                    "Erro",
-                                    &format!("Erro ao carregar imagem: {}", e),
-                                    show_notifications,
-                                );
-                                false
-                            }
+                    // The daemon puts the original bytes back under their MIME type,
+                    // keeping GIF animation, SVG and the JPEG/WebP encoding
+                    eprintln!("📸 Asking the daemon to copy image entry {}", entry_id);
+                    match clippit_ipc::IpcClient::select_item(entry_id) {
+                        Ok(()) => {
+                            eprintln!("✅ Image copied to clipboard with original format");
+                            show_notification(
+                                "Clippit",
+                                &format!(
+                                    "Imagem copiada ({}x{})",
+                                    entry.image_width.unwrap_or(0),
+                                    entry.image_height.unwrap_or(0)
+                                ),
+                                show_notifications,
+                            );
+                            true
+                        }
+                        Err(e) => {
+                            eprintln!("❌ Failed to copy image: {}", e);
+                            show_notification("Erro", &error_message(&e), show_notifications);
+                            false
                         }
-                    } else {
-                        eprintln!("❌ Image entry has no image_path!");
-                        show_notification("Erro", "Imagem sem caminho", show_notifications);
-                        false
                     }
                 }
             }
@@ -172,7 +141,7 @@ pub fn copy_to_clipboard(entry_id: i64) -> bool {
                 entry_id, e
             );
             eprintln!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-            show_notification("Erro", &format!("Erro: {}", e), show_notifications);
+            show_notification("Erro", &error_message(&e), show_notifications);
             false
         }
     };
@@ -190,6 +159,33 @@ pub fn copy_to_clipboard(entry_id: i64) -> bool {
     success
 }
 
+/// Copies text that isn't a history entry of its own (e.g. a decoded QR code)
+pub fn copy_text_to_clipboard(text: &str) -> bool {
+    let show_notifications = Config::load().unwrap_or_default().ui.show_notifications;
+
+    match Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text)) {
+        Ok(()) => {
+            eprintln!("✅ Text copied to clipboard: {} chars", text.len());
+            let preview: String = text.chars().take(80).collect();
+            show_notification(
+                "Clippit",
+                &format!("Copiado: {}", preview),
+                show_notifications,
+            );
+            true
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to copy text: {}", e);
+            show_notification(
+                "Erro",
+                &format!("Erro ao copiar texto: {}", e),
+                show_notifications,
+            );
+            false
+        }
+    }
+}
+
 /// Show a system notification using notify-send (reliable and blocking)
 fn show_notification(summary: &str, body: &str, enabled: bool) {
     if !enabled {
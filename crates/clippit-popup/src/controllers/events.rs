@@ -0,0 +1,50 @@
+use clippit_ipc::protocol::capability;
+use clippit_ipc::{EventTopic, IpcClient, IpcEvent};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Wait before subscribing again after the daemon went away
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+/// How often the GTK main loop picks up received events
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Calls `on_event` on the GTK main loop for each daemon event of `topics`
+///
+/// The subscription lives on a background thread and is reopened whenever the
+/// daemon restarts. Daemons without the `events` capability are left alone.
+pub fn listen_daemon_events(topics: Vec<EventTopic>, on_event: impl Fn(IpcEvent) + 'static) {
+    let (sender, receiver) = mpsc::channel();
+
+    std::thread::spawn(move || loop {
+        match IpcClient::hello(concat!("clippit-popup ", env!("CARGO_PKG_VERSION"))) {
+            Ok(negotiated) if !negotiated.supports(capability::EVENTS) => {
+                eprintln!("⚠️ {} has no live updates", negotiated.peer);
+                return;
+            }
+            Ok(_) => match IpcClient::subscribe(topics.clone()) {
+                Ok(stream) => {
+                    eprintln!("📡 Subscribed to daemon events {:?}", topics);
+                    for event in stream {
+                        // Popup closed: nobody is polling anymore
+                        if sender.send(event).is_err() {
+                            return;
+                        }
+                    }
+                    eprintln!("⚠️ Daemon event stream closed, reconnecting...");
+                }
+                Err(e) => eprintln!("❌ Failed to subscribe to daemon events: {}", e),
+            },
+            Err(e) => eprintln!("❌ Handshake with daemon failed: {}", e),
+        }
+        std::thread::sleep(RECONNECT_DELAY);
+    });
+
+    gtk::glib::timeout_add_local(POLL_INTERVAL, move || loop {
+        match receiver.try_recv() {
+            Ok(event) => on_event(event),
+            Err(mpsc::TryRecvError::Empty) => return gtk::glib::ControlFlow::Continue,
+            Err(mpsc::TryRecvError::Disconnected) => return gtk::glib::ControlFlow::Break,
+        }
+    });
+}
@@ -1,5 +1,7 @@
 pub mod clipboard;
+pub mod events;
 pub mod keyboard;
 
-pub use clipboard::copy_to_clipboard;
+pub use clipboard::{copy_text_to_clipboard, copy_to_clipboard};
+pub use events::listen_daemon_events;
 pub use keyboard::{setup_keyboard_navigation, setup_row_activation};
@@ -15,7 +15,8 @@ use models::{new_entry_map, new_search_content_map};
 use panic_handler::setup_panic_handler;
 use utils::{apply_theme, load_custom_css};
 use views::{
-    create_main_window, populate_history_list, setup_infinite_scroll, setup_search_filter,
+    create_main_window, populate_history_list, setup_infinite_scroll, setup_live_updates,
+    setup_search_filter,
 };
 
 const APP_ID: &str = "com.clippit.Clippit";
@@ -233,6 +234,16 @@ fn build_ui(app: &Application) {
             &entry_map_clone,
             &search_map_clone,
         );
+
+        // Keep the list in sync with the daemon while the popup is open
+        setup_live_updates(
+            &list_box_clone,
+            &search_entry_clone,
+            &window_clone,
+            &app_clone,
+            &entry_map_clone,
+            &search_map_clone,
+        );
     });
 }
 
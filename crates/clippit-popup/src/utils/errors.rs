@@ -0,0 +1,26 @@
+use clippit_ipc::{ErrorCode, IpcError};
+use rust_i18n::t;
+
+/// Message to show the user for a failed daemon request
+///
+/// Errors the daemon answered with are worded by their code; anything else
+/// (daemon not running, connection lost) is shown as is.
+pub fn error_message(error: &anyhow::Error) -> String {
+    let Some(ipc_error) = IpcError::find(error) else {
+        return t!("popup.errors.generic", message = error.to_string()).to_string();
+    };
+
+    match ipc_error.code {
+        ErrorCode::NotFound => t!("popup.errors.not_found"),
+        ErrorCode::InvalidRequest => {
+            t!("popup.errors.invalid_request", message = ipc_error.message)
+        }
+        ErrorCode::StorageFailure => t!("popup.errors.storage"),
+        ErrorCode::Busy => t!("popup.errors.busy"),
+        ErrorCode::Unauthorized => t!("popup.errors.unauthorized"),
+        ErrorCode::Unsupported => t!("popup.errors.unsupported"),
+        ErrorCode::Timeout => t!("popup.errors.timeout"),
+        ErrorCode::Internal => t!("popup.errors.generic", message = ipc_error.message),
+    }
+    .to_string()
+}
@@ -1,7 +1,9 @@
+pub mod errors;
 pub mod suggestions;
 pub mod theme;
 pub mod thumbnail;
 
+pub use errors::error_message;
 pub use suggestions::SuggestionEngine;
 pub use theme::{apply_theme, load_custom_css};
 pub use thumbnail::create_thumbnail;
@@ -27,6 +27,21 @@ pub fn load_custom_css() {
         image.preview-rounded {
             border-radius: 16px;
         }
+
+        /* Host de links copiados (sem favicon) */
+        label.link-chip {
+            padding: 2px 8px;
+            border-radius: 999px;
+            background-color: alpha(@accent_bg_color, 0.12);
+        }
+
+        /* Tags das entradas (clique filtra a lista) */
+        button.tag-chip {
+            min-height: 0;
+            padding: 1px 8px;
+            border-radius: 999px;
+            background-color: alpha(@accent_bg_color, 0.12);
+        }
         
         /* Skeleton loader styles - matches Adwaita design */
         .skeleton-thumb {
@@ -3,9 +3,10 @@ use gtk::prelude::*;
 use libadwaita as adw;
 use rust_i18n::t;
 
-use crate::controllers::copy_to_clipboard;
+use crate::controllers::{copy_text_to_clipboard, copy_to_clipboard};
+use crate::utils::error_message;
 
-/// Adds a delete button to a row that removes the entry from DB and UI
+/// Adds a delete button to a row that removes the entry (via the daemon) and its row
 pub fn add_delete_button(row: &adw::ActionRow, entry_id: i64, list_box: &gtk::ListBox) {
     let delete_button = gtk::Button::from_icon_name("user-trash-symbolic");
     delete_button.set_valign(gtk::Align::Center);
@@ -15,6 +16,7 @@ pub fn add_delete_button(row: &adw::ActionRow, entry_id: i64, list_box: &gtk::Li
 
     let delete_entry_id = entry_id;
     let list_box_for_delete = list_box.clone();
+    let row_for_error = row.clone();
 
     delete_button.connect_clicked(move |btn| {
         eprintln!(
@@ -22,28 +24,24 @@ pub fn add_delete_button(row: &adw::ActionRow, entry_id: i64, list_box: &gtk::Li
             delete_entry_id
         );
 
-        match clippit_core::HistoryManager::new(get_db_path(), 100) {
-            Ok(history) => match history.delete_by_id(delete_entry_id) {
-                Ok(_) => {
-                    eprintln!("✅ Entry {} deleted from database", delete_entry_id);
-
-                    if let Some(row) = btn.ancestor(gtk::ListBoxRow::static_type()) {
-                        if let Ok(list_box_row) = row.downcast::<gtk::ListBoxRow>() {
-                            list_box_for_delete.remove(&list_box_row);
-                            eprintln!("✅ Entry removed from UI");
-                        } else {
-                            eprintln!("❌ Failed to downcast to ListBoxRow");
-                        }
+        match clippit_ipc::IpcClient::delete_entry(delete_entry_id) {
+            Ok(()) => {
+                eprintln!("✅ Entry {} deleted from database", delete_entry_id);
+
+                if let Some(row) = btn.ancestor(gtk::ListBoxRow::static_type()) {
+                    if let Ok(list_box_row) = row.downcast::<gtk::ListBoxRow>() {
+                        list_box_for_delete.remove(&list_box_row);
+                        eprintln!("✅ Entry removed from UI");
                     } else {
-                        eprintln!("❌ Could not find parent ListBoxRow");
+                        eprintln!("❌ Failed to downcast to ListBoxRow");
                     }
+                } else {
+                    eprintln!("❌ Could not find parent ListBoxRow");
                 }
-                Err(e) => {
-                    eprintln!("❌ Failed to delete entry {}: {}", delete_entry_id, e);
-                }
-            },
+            }
             Err(e) => {
-                eprintln!("❌ Failed to create HistoryManager: {}", e);
+                eprintln!("❌ Failed to delete entry {}: {}", delete_entry_id, e);
+                row_for_error.set_subtitle(&gtk::glib::markup_escape_text(&error_message(&e)));
             }
         }
     });
@@ -83,10 +81,85 @@ pub fn add_copy_button(
     row.add_suffix(&copy_button);
 }
 
-fn get_db_path() -> std::path::PathBuf {
-    let mut path = dirs::data_local_dir().unwrap_or_else(|| std::path::PathBuf::from("."));
-    path.push("clippit");
-    std::fs::create_dir_all(&path).ok();
-    path.push("history.db");
-    path
+/// Adds a button that copies the decoded QR code/barcode value of an image
+///
+/// Images with several codes get a popover listing each value.
+pub fn add_decoded_value_button(
+    row: &adw::ActionRow,
+    entry: &clippit_ipc::HistoryEntry,
+    window: &adw::ApplicationWindow,
+    app: &gtk::Application,
+) {
+    let Some(barcode_text) = &entry.barcode_text else {
+        return;
+    };
+    let values: Vec<String> = barcode_text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(str::to_string)
+        .collect();
+
+    let copy_value = {
+        let window = window.clone();
+        let app = app.clone();
+        move |value: &str| {
+            eprintln!("🔳 Copying decoded value ({} chars)", value.len());
+            if copy_text_to_clipboard(value) {
+                window.close();
+                app.quit();
+            }
+        }
+    };
+
+    match values.as_slice() {
+        [] => {}
+        [value] => {
+            let button = gtk::Button::from_icon_name("scanner-symbolic");
+            button.set_valign(gtk::Align::Center);
+            button.add_css_class("flat");
+            button.add_css_class("circular");
+            button.set_tooltip_text(Some(&format!("Copiar valor do código: {}", value)));
+
+            let value = value.clone();
+            button.connect_clicked(move |_| copy_value(&value));
+            row.add_suffix(&button);
+        }
+        values => {
+            let list = gtk::Box::new(gtk::Orientation::Vertical, 2);
+            let popover = gtk::Popover::new();
+
+            for value in values {
+                let label = gtk::Label::new(Some(value));
+                label.set_xalign(0.0);
+                label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+                label.set_max_width_chars(50);
+
+                let item = gtk::Button::new();
+                item.set_child(Some(&label));
+                item.add_css_class("flat");
+
+                let value = value.clone();
+                let copy_value = copy_value.clone();
+                let popover = popover.clone();
+                item.connect_clicked(move |_| {
+                    popover.popdown();
+                    copy_value(&value);
+                });
+                list.append(&item);
+            }
+            popover.set_child(Some(&list));
+
+            let button = gtk::MenuButton::new();
+            button.set_icon_name("scanner-symbolic");
+            button.set_valign(gtk::Align::Center);
+            button.add_css_class("flat");
+            button.add_css_class("circular");
+            button.set_tooltip_text(Some(&format!(
+                "Copiar valor do código ({} códigos)",
+                values.len()
+            )));
+            button.set_popover(Some(&popover));
+            row.add_suffix(&button);
+        }
+    }
 }
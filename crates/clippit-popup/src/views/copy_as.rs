@@ -0,0 +1,131 @@
+use adw::prelude::*;
+use clippit_core::Transform;
+use libadwaita as adw;
+use rust_i18n::t;
+use std::borrow::Cow;
+
+use crate::controllers::copy_text_to_clipboard;
+
+/// Characters of the transformed text shown under each action
+const PREVIEW_CHARS: usize = 48;
+
+/// Adds a "Copiar como…" menu with the transforms that apply to a text entry
+///
+/// The list is built when the menu opens, so rows don't pay for running every
+/// transform on long texts.
+pub fn add_copy_as_button(
+    row: &adw::ActionRow,
+    entry: &clippit_ipc::HistoryEntry,
+    window: &adw::ApplicationWindow,
+    app: &gtk::Application,
+) {
+    let Some(text) = entry.content_text.clone().filter(|t| !t.is_empty()) else {
+        return;
+    };
+
+    let popover = gtk::Popover::new();
+    let window = window.clone();
+    let app = app.clone();
+    popover.connect_show(move |popover| {
+        if popover.child().is_none() {
+            popover.set_child(Some(&transform_list(popover, &text, &window, &app)));
+        }
+    });
+
+    let button = gtk::MenuButton::new();
+    button.set_icon_name("document-edit-symbolic");
+    button.set_valign(gtk::Align::Center);
+    button.add_css_class("flat");
+    button.add_css_class("circular");
+    button.set_tooltip_text(Some(&t!("popup.copy_as.tooltip")));
+    button.set_popover(Some(&popover));
+    row.add_suffix(&button);
+}
+
+fn transform_list(
+    popover: &gtk::Popover,
+    text: &str,
+    window: &adw::ApplicationWindow,
+    app: &gtk::Application,
+) -> gtk::Box {
+    let list = gtk::Box::new(gtk::Orientation::Vertical, 2);
+
+    let results = Transform::apply_all(text);
+    if results.is_empty() {
+        let empty = gtk::Label::new(Some(&t!("popup.copy_as.empty")));
+        empty.add_css_class("dim-label");
+        empty.set_margin_top(6);
+        empty.set_margin_bottom(6);
+        list.append(&empty);
+        return list;
+    }
+
+    for (transform, transformed) in results {
+        let title = gtk::Label::new(Some(&transform_label(transform)));
+        title.set_xalign(0.0);
+
+        let preview = gtk::Label::new(Some(&preview_line(&transformed)));
+        preview.set_xalign(0.0);
+        preview.set_ellipsize(gtk::pango::EllipsizeMode::End);
+        preview.set_max_width_chars(PREVIEW_CHARS as i32);
+        preview.add_css_class("caption");
+        preview.add_css_class("dim-label");
+
+        let content = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        content.append(&title);
+        content.append(&preview);
+
+        let item = gtk::Button::new();
+        item.set_child(Some(&content));
+        item.add_css_class("flat");
+
+        let popover = popover.clone();
+        let window = window.clone();
+        let app = app.clone();
+        item.connect_clicked(move |_| {
+            popover.popdown();
+            eprintln!(
+                "✨ Copying as {} ({} chars)",
+                transform.id(),
+                transformed.len()
+            );
+            if copy_text_to_clipboard(&transformed) {
+                window.close();
+                app.quit();
+            }
+        });
+        list.append(&item);
+    }
+
+    list
+}
+
+fn transform_label(transform: Transform) -> Cow<'static, str> {
+    match transform {
+        Transform::Upper => t!("popup.copy_as.upper"),
+        Transform::Lower => t!("popup.copy_as.lower"),
+        Transform::Title => t!("popup.copy_as.title"),
+        Transform::Trim => t!("popup.copy_as.trim"),
+        Transform::CollapseWhitespace => t!("popup.copy_as.collapse_whitespace"),
+        Transform::NormalizeLineEndings => t!("popup.copy_as.normalize_line_endings"),
+        Transform::JsonPretty => t!("popup.copy_as.json_pretty"),
+        Transform::JsonMinify => t!("popup.copy_as.json_minify"),
+        Transform::Base64Encode => t!("popup.copy_as.base64_encode"),
+        Transform::Base64Decode => t!("popup.copy_as.base64_decode"),
+        Transform::UrlEncode => t!("popup.copy_as.url_encode"),
+        Transform::UrlDecode => t!("popup.copy_as.url_decode"),
+        Transform::StringLiteral => t!("popup.copy_as.string_literal"),
+        Transform::MarkdownCodeBlock => t!("popup.copy_as.markdown_code_block"),
+    }
+}
+
+/// First line of the result, with a marker when there is more
+fn preview_line(text: &str) -> String {
+    let mut lines = text.lines();
+    let first = lines.next().unwrap_or("");
+    if lines.next().is_some() {
+        format!("{} ⏎…", first)
+    } else {
+        first.to_string()
+    }
+}
@@ -1,24 +1,52 @@
-use crate::utils::create_thumbnail;
+use crate::utils::{create_thumbnail, error_message};
+use adw::prelude::*;
+use clippit_ipc::{HistoryEntry, IpcClient, OcrRegion};
+use gdk_pixbuf::prelude::*;
+use gdk_pixbuf::{InterpType, Pixbuf, PixbufAnimation, PixbufAnimationIter};
+use gtk::glib;
 use gtk::prelude::*;
 use libadwaita as adw;
-use std::cell::RefCell;
+use rust_i18n::t;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
+use std::time::{Duration, SystemTime};
+
+const PREVIEW_SIZE: i32 = 512;
+
+/// Max size of the image shown for region selection
+const REGION_SELECT_SIZE: i32 = 800;
 
 /// Adds a hover preview popover to an image row
-/// Shows a larger 512px preview when hovering over the thumbnail
-pub fn add_image_hover_preview(row: &adw::ActionRow, data: &[u8]) {
+/// Shows a larger 512px preview when hovering over the thumbnail.
+/// Animated GIFs play while the popover is open and SVGs are rendered at full
+/// preview size from the original file (the thumbnail is only 128px).
+pub fn add_image_hover_preview(row: &adw::ActionRow, data: &[u8], entry: &HistoryEntry) {
     let data_clone = data.to_vec();
+    let entry_clone = entry.clone();
     let row_clone = row.clone();
 
     let hover = gtk::EventControllerMotion::new();
     let popover: Rc<RefCell<Option<gtk::Popover>>> = Rc::new(RefCell::new(None));
+    let animation: Rc<RefCell<Option<PreviewAnimation>>> = Rc::new(RefCell::new(None));
     let popover_clone = popover.clone();
+    let animation_clone = animation.clone();
 
     hover.connect_enter(move |_, _, _| {
         // Create preview popover if not exists
         if popover_clone.borrow().is_none() {
-            if let Ok(preview_pixbuf) = create_thumbnail(&data_clone, 512) {
-                let preview_image = gtk::Image::from_pixbuf(Some(&preview_pixbuf));
+            let (preview_image, preview_animation) = match load_animation(&entry_clone) {
+                Some(anim) => {
+                    let image = gtk::Image::from_pixbuf(Some(&scale_to_preview(&anim.pixbuf())));
+                    (Some(image), Some(anim))
+                }
+                None => (
+                    load_preview(&entry_clone, &data_clone)
+                        .map(|pixbuf| gtk::Image::from_pixbuf(Some(&pixbuf))),
+                    None,
+                ),
+            };
+
+            if let Some(preview_image) = preview_image {
                 preview_image.set_margin_top(8);
                 preview_image.set_margin_bottom(8);
                 preview_image.set_margin_start(8);
@@ -33,6 +61,11 @@ pub fn add_image_hover_preview(row: &adw::ActionRow, data: &[u8]) {
                 new_popover.set_can_focus(false);
 
                 *popover_clone.borrow_mut() = Some(new_popover);
+                *animation_clone.borrow_mut() = preview_animation.map(|iter| PreviewAnimation {
+                    image: preview_image,
+                    iter,
+                    timer: Rc::new(RefCell::new(None)),
+                });
             }
         }
 
@@ -40,15 +73,284 @@ pub fn add_image_hover_preview(row: &adw::ActionRow, data: &[u8]) {
         if let Some(ref pop) = *popover_clone.borrow() {
             pop.popup();
         }
+        if let Some(ref anim) = *animation_clone.borrow() {
+            anim.play();
+        }
     });
 
     let popover_clone2 = popover.clone();
+    let animation_clone2 = animation.clone();
     hover.connect_leave(move |_| {
         // Hide popover
         if let Some(ref pop) = *popover_clone2.borrow() {
             pop.popdown();
         }
+        // Stop animating frames nobody can see
+        if let Some(ref anim) = *animation_clone2.borrow() {
+            anim.stop();
+        }
     });
 
     row.add_controller(hover);
 }
+
+/// GIF playing inside the preview popover
+struct PreviewAnimation {
+    image: gtk::Image,
+    iter: PixbufAnimationIter,
+    timer: Rc<RefCell<Option<glib::SourceId>>>,
+}
+
+impl PreviewAnimation {
+    fn play(&self) {
+        if self.timer.borrow().is_none() {
+            schedule_frame(self.image.clone(), self.iter.clone(), self.timer.clone());
+        }
+    }
+
+    fn stop(&self) {
+        if let Some(source) = self.timer.borrow_mut().take() {
+            source.remove();
+        }
+    }
+}
+
+/// Shows the next frame once the current one's delay has passed
+fn schedule_frame(
+    image: gtk::Image,
+    iter: PixbufAnimationIter,
+    timer: Rc<RefCell<Option<glib::SourceId>>>,
+) {
+    // None = last frame of a non-looping GIF
+    let Some(delay) = iter.delay_time() else {
+        *timer.borrow_mut() = None;
+        return;
+    };
+
+    let timer_clone = timer.clone();
+    let source = glib::timeout_add_local_once(delay.max(Duration::from_millis(20)), move || {
+        if iter.advance(SystemTime::now()) {
+            image.set_from_pixbuf(Some(&scale_to_preview(&iter.pixbuf())));
+        }
+        schedule_frame(image, iter, timer_clone);
+    });
+    *timer.borrow_mut() = Some(source);
+}
+
+/// Loads an animated GIF from disk; static GIFs and other formats return None
+fn load_animation(entry: &HistoryEntry) -> Option<PixbufAnimationIter> {
+    if entry.mime_type.as_deref() != Some("image/gif") {
+        return None;
+    }
+
+    let animation = PixbufAnimation::from_file(entry.image_path.as_ref()?).ok()?;
+    if animation.is_static_image() {
+        return None;
+    }
+
+    Some(animation.iter(Some(SystemTime::now())))
+}
+
+/// Static preview: SVGs are rasterized again at preview size, everything else
+/// is scaled from the data the row already has
+fn load_preview(entry: &HistoryEntry, data: &[u8]) -> Option<Pixbuf> {
+    if entry.mime_type.as_deref() == Some("image/svg+xml") {
+        if let Some(path) = &entry.image_path {
+            match Pixbuf::from_file_at_scale(path, PREVIEW_SIZE, PREVIEW_SIZE, true) {
+                Ok(pixbuf) => return Some(pixbuf),
+                Err(e) => eprintln!("⚠️  Failed to render SVG preview: {}", e),
+            }
+        }
+    }
+
+    create_thumbnail(data, PREVIEW_SIZE as u32).ok()
+}
+
+/// Fits a frame in the preview size, keeping the aspect ratio
+fn scale_to_preview(pixbuf: &Pixbuf) -> Pixbuf {
+    let (width, height) = (pixbuf.width(), pixbuf.height());
+    if width <= PREVIEW_SIZE && height <= PREVIEW_SIZE {
+        return pixbuf.clone();
+    }
+
+    let scale = PREVIEW_SIZE as f64 / width.max(height) as f64;
+    let new_width = ((width as f64 * scale) as i32).max(1);
+    let new_height = ((height as f64 * scale) as i32).max(1);
+    pixbuf
+        .scale_simple(new_width, new_height, InterpType::Bilinear)
+        .unwrap_or_else(|| pixbuf.clone())
+}
+
+/// Adds a button to image rows with OCR text that opens the image in a popover
+/// where a rectangle can be dragged to copy only the text inside it (e.g. one
+/// cell of a table screenshot)
+pub fn add_region_select(row: &adw::ActionRow, entry: &HistoryEntry) {
+    let has_text = entry
+        .ocr_text
+        .as_ref()
+        .is_some_and(|text| !text.trim().is_empty());
+    let Some(image_path) = entry.image_path.clone().filter(|_| has_text) else {
+        return;
+    };
+
+    let button = gtk::Button::from_icon_name("edit-select-all-symbolic");
+    button.set_valign(gtk::Align::Center);
+    button.add_css_class("flat");
+    button.add_css_class("circular");
+    button.set_tooltip_text(Some(&t!("popup.region_select.tooltip")));
+
+    let entry_id = entry.id;
+    let image_width = entry.image_width;
+    button.connect_clicked(move |btn| {
+        let pixbuf = match Pixbuf::from_file_at_scale(
+            &image_path,
+            REGION_SELECT_SIZE,
+            REGION_SELECT_SIZE,
+            true,
+        ) {
+            Ok(pixbuf) => pixbuf,
+            Err(e) => {
+                eprintln!("⚠️  Failed to load image for region selection: {}", e);
+                return;
+            }
+        };
+
+        // Displayed pixels -> original image pixels (OCR boxes use the original)
+        let scale = image_width.unwrap_or(pixbuf.width() as u32) as f64 / pixbuf.width() as f64;
+        let popover = region_select_popover(entry_id, &pixbuf, scale);
+        popover.set_parent(btn);
+        popover.connect_closed(|popover| popover.unparent());
+        popover.popup();
+    });
+
+    row.add_suffix(&button);
+}
+
+/// Selection rectangle in displayed pixels: start point and drag offset
+#[derive(Clone, Copy, Default)]
+struct Selection {
+    x: f64,
+    y: f64,
+    dx: f64,
+    dy: f64,
+}
+
+impl Selection {
+    /// Normalized (x, y, width, height), whatever the drag direction
+    fn rect(&self) -> (f64, f64, f64, f64) {
+        (
+            self.x.min(self.x + self.dx),
+            self.y.min(self.y + self.dy),
+            self.dx.abs(),
+            self.dy.abs(),
+        )
+    }
+}
+
+fn region_select_popover(entry_id: i64, pixbuf: &Pixbuf, scale: f64) -> gtk::Popover {
+    let hint = gtk::Label::new(Some(&t!("popup.region_select.hint")));
+    hint.add_css_class("dim-label");
+
+    let picture = gtk::Picture::for_pixbuf(pixbuf);
+    picture.set_can_shrink(false);
+    picture.set_halign(gtk::Align::Start);
+    picture.set_valign(gtk::Align::Start);
+
+    let selection: Rc<Cell<Option<Selection>>> = Rc::new(Cell::new(None));
+
+    let canvas = gtk::DrawingArea::new();
+    let selection_draw = selection.clone();
+    canvas.set_draw_func(move |_, cr, _, _| {
+        if let Some(selection) = selection_draw.get() {
+            let (x, y, width, height) = selection.rect();
+            cr.rectangle(x, y, width, height);
+            cr.set_source_rgba(0.21, 0.52, 0.89, 0.25);
+            let _ = cr.fill_preserve();
+            cr.set_source_rgba(0.21, 0.52, 0.89, 1.0);
+            cr.set_line_width(1.5);
+            let _ = cr.stroke();
+        }
+    });
+
+    let overlay = gtk::Overlay::new();
+    overlay.set_child(Some(&picture));
+    overlay.add_overlay(&canvas);
+    overlay.add_css_class("preview-rounded");
+
+    let status = gtk::Label::new(None);
+    status.set_ellipsize(gtk::pango::EllipsizeMode::End);
+    status.set_max_width_chars(60);
+
+    let drag = gtk::GestureDrag::new();
+
+    let selection_begin = selection.clone();
+    let canvas_begin = canvas.clone();
+    drag.connect_drag_begin(move |_, x, y| {
+        selection_begin.set(Some(Selection {
+            x,
+            y,
+            ..Default::default()
+        }));
+        canvas_begin.queue_draw();
+    });
+
+    let selection_update = selection.clone();
+    let canvas_update = canvas.clone();
+    drag.connect_drag_update(move |_, dx, dy| {
+        if let Some(current) = selection_update.get() {
+            selection_update.set(Some(Selection { dx, dy, ..current }));
+            canvas_update.queue_draw();
+        }
+    });
+
+    let status_end = status.clone();
+    let canvas_end = canvas.clone();
+    drag.connect_drag_end(move |_, dx, dy| {
+        let Some(current) = selection.get() else {
+            return;
+        };
+        let (x, y, width, height) = Selection { dx, dy, ..current }.rect();
+        if width < 3.0 || height < 3.0 {
+            return; // A click, not a selection
+        }
+
+        let region = OcrRegion {
+            x: (x.max(0.0) * scale) as u32,
+            y: (y.max(0.0) * scale) as u32,
+            width: (width * scale).ceil() as u32,
+            height: (height * scale).ceil() as u32,
+        };
+
+        match IpcClient::get_ocr_region_text(entry_id, region) {
+            Ok(Some(text)) if !text.is_empty() => {
+                canvas_end.clipboard().set_text(&text);
+                eprintln!("✅ Copied {} chars from image region", text.len());
+                status_end.set_text(&t!(
+                    "popup.region_select.copied",
+                    text = text.replace('\n', " ")
+                ));
+            }
+            Ok(Some(_)) => status_end.set_text(&t!("popup.region_select.no_text")),
+            Ok(None) => status_end.set_text(&t!("popup.region_select.no_positions")),
+            Err(e) => {
+                eprintln!("❌ Failed to get region text: {}", e);
+                status_end.set_text(&error_message(&e));
+            }
+        }
+    });
+    canvas.add_controller(drag);
+
+    let content = gtk::Box::new(gtk::Orientation::Vertical, 6);
+    content.set_margin_top(8);
+    content.set_margin_bottom(8);
+    content.set_margin_start(8);
+    content.set_margin_end(8);
+    content.append(&hint);
+    content.append(&overlay);
+    content.append(&status);
+
+    let popover = gtk::Popover::new();
+    popover.set_child(Some(&content));
+    popover.set_position(gtk::PositionType::Left);
+    popover
+}
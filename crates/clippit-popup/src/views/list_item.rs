@@ -1,6 +1,10 @@
-use crate::utils::create_thumbnail;
-use crate::views::buttons::{add_copy_button, add_delete_button};
-use crate::views::image_preview::add_image_hover_preview;
+use crate::utils::{create_thumbnail, error_message};
+use crate::views::buttons::{add_copy_button, add_decoded_value_button, add_delete_button};
+use crate::views::copy_as::add_copy_as_button;
+use crate::views::image_preview::{add_image_hover_preview, add_region_select};
+use crate::views::qr_code::add_qr_code_button;
+use crate::views::tags::add_tag_editor;
+use crate::views::text_kind::add_text_kind_prefix;
 use adw::prelude::*;
 use clippit_ipc::IpcClient;
 use gtk::prelude::*;
@@ -11,11 +15,95 @@ use std::rc::Rc;
 
 // Estrutura para gerenciar o estado de carregamento
 pub struct LoadMoreState {
-    pub items_loaded: usize,
     pub is_loading: bool,
     pub has_more: bool,
 }
 
+/// Widget name of the row of entry `id` (live updates find rows by it)
+pub fn entry_row_name(id: i64) -> String {
+    format!("entry-{}", id)
+}
+
+/// Entry id of a row built by `create_history_row` (or a search result)
+pub fn row_entry_id(row: &gtk::ListBoxRow) -> Option<i64> {
+    row.widget_name().strip_prefix("entry-")?.parse().ok()
+}
+
+/// Builds the complete row of a history entry: preview, tags and buttons
+pub fn create_history_row(
+    entry: &clippit_ipc::HistoryEntry,
+    list_box: &gtk::ListBox,
+    window: &adw::ApplicationWindow,
+    app: &gtk::Application,
+) -> adw::ActionRow {
+    let row = adw::ActionRow::new();
+    row.set_activatable(true); // 🔥 Tornar a linha clicável
+    row.set_widget_name(&entry_row_name(entry.id));
+
+    // Format content and add prefix based on type
+    match entry.content_type {
+        clippit_ipc::ContentType::Text => {
+            create_text_row(&row, entry);
+        }
+        clippit_ipc::ContentType::Image => {
+            create_image_row(&row, entry);
+        }
+    }
+
+    row.set_subtitle(&entry.timestamp.format("%d/%m/%Y %H:%M:%S").to_string());
+
+    // Add tag chips and editor
+    add_tag_editor(&row, entry);
+
+    // Add delete button
+    add_delete_button(&row, entry.id, list_box);
+
+    // Add decoded QR code/barcode value button (images only)
+    add_decoded_value_button(&row, entry, window, app);
+
+    // Add QR code button (text only)
+    add_qr_code_button(&row, entry, window, app);
+
+    // Add "Copy as…" transforms (text only)
+    add_copy_as_button(&row, entry, window, app);
+
+    // Add copy button
+    add_copy_button(&row, entry.id, window, app);
+
+    row
+}
+
+/// Search content (title + subtitle) used for filtering
+fn row_search_content(row: &adw::ActionRow) -> String {
+    let title_text = row.title().to_string();
+    let subtitle_text = row.subtitle().map(|s| s.to_string()).unwrap_or_default();
+    format!("{} {}", title_text, subtitle_text)
+}
+
+/// Rebuilds the index → id/search maps from the rows, after rows were
+/// inserted or removed anywhere but the end
+pub fn rebuild_row_maps(
+    list_box: &gtk::ListBox,
+    entry_map: &Rc<RefCell<std::collections::HashMap<i32, i64>>>,
+    search_map: &Rc<RefCell<std::collections::HashMap<i32, String>>>,
+) {
+    let mut entry_map = entry_map.borrow_mut();
+    let mut search_map = search_map.borrow_mut();
+    entry_map.clear();
+    search_map.clear();
+
+    let mut index = 0;
+    while let Some(row) = list_box.row_at_index(index) {
+        if let Some(id) = row_entry_id(&row) {
+            entry_map.insert(index, id);
+        }
+        if let Some(action_row) = row.downcast_ref::<adw::ActionRow>() {
+            search_map.insert(index, row_search_content(action_row));
+        }
+        index += 1;
+    }
+}
+
 /// Creates action rows for history entries (text and images)
 ///
 /// Returns: (list_box, entry_map, search_map)
@@ -39,36 +127,13 @@ pub fn populate_history_list(
                     "📋 Entry {}: id={}, type={:?}",
                     index, entry.id, entry.content_type
                 );
-                let row = adw::ActionRow::new();
-                row.set_activatable(true); // 🔥 Tornar a linha clicável
-
-                // Format content and add prefix based on type
-                match entry.content_type {
-                    clippit_ipc::ContentType::Text => {
-                        create_text_row(&row, entry);
-                    }
-                    clippit_ipc::ContentType::Image => {
-                        create_image_row(&row, entry);
-                    }
-                }
-
-                row.set_subtitle(&entry.timestamp.format("%d/%m/%Y %H:%M:%S").to_string());
+                let row = create_history_row(entry, list_box, window, app);
 
                 // Store entry ID mapping for keyboard navigation
-                let entry_id = entry.id;
-                entry_map.borrow_mut().insert(index as i32, entry_id);
+                entry_map.borrow_mut().insert(index as i32, entry.id);
 
                 // Store search content (title + subtitle) for filtering
-                let title_text = row.title().to_string();
-                let subtitle_text = row.subtitle().map(|s| s.to_string()).unwrap_or_default();
-                let search_content = format!("{} {}", title_text, subtitle_text);
-                search_map.borrow_mut().insert(index as i32, search_content);
-
-                // Add delete button
-                add_delete_button(&row, entry_id, list_box);
-
-                // Add copy button
-                add_copy_button(&row, entry_id, window, app);
+                search_map.borrow_mut().insert(index as i32, row_search_content(&row));
 
                 list_box.append(&row);
             }
@@ -83,7 +148,7 @@ pub fn populate_history_list(
             eprintln!("❌ Failed to query history: {}", e);
             let empty_row = adw::ActionRow::new();
             empty_row.set_title("Erro ao carregar histórico");
-            empty_row.set_subtitle(&format!("Erro: {}", e));
+            empty_row.set_subtitle(&error_message(&e));
             list_box.append(&empty_row);
         }
     }
@@ -111,6 +176,9 @@ fn create_text_row(row: &adw::ActionRow, entry: &clippit_ipc::HistoryEntry) {
     // Escape HTML/XML special characters to avoid markup parsing errors
     let escaped_content = gtk::glib::markup_escape_text(&content);
     row.set_title(&escaped_content);
+
+    // Kind icon, color swatch or link host
+    add_text_kind_prefix(row, entry);
 }
 
 fn create_image_row(row: &adw::ActionRow, entry: &clippit_ipc::HistoryEntry) {
@@ -226,7 +294,10 @@ fn create_image_row(row: &adw::ActionRow, entry: &clippit_ipc::HistoryEntry) {
                 eprintln!("✅ Thumbnail created for entry {}", entry.id);
 
                 // Add hover preview with larger image (512px)
-                add_image_hover_preview(row, data);
+                add_image_hover_preview(row, data, entry);
+
+                // Copy the OCR text of a selected area
+                add_region_select(row, entry);
             }
             Err(e) => {
                 eprintln!("⚠️  Failed to create thumbnail: {}", e);
@@ -252,7 +323,6 @@ pub fn setup_infinite_scroll(
     search_map: &Rc<RefCell<std::collections::HashMap<i32, String>>>,
 ) {
     let load_state = Rc::new(RefCell::new(LoadMoreState {
-        items_loaded: 30, // Já carregamos 30 inicialmente
         is_loading: false,
         has_more: true,
     }));
@@ -276,9 +346,11 @@ pub fn setup_infinite_scroll(
 
             if !state.is_loading && state.has_more {
                 state.is_loading = true;
-                let offset = state.items_loaded;
                 drop(state); // Libera o borrow
 
+                // Rows already shown (live updates add and remove rows at any time)
+                let offset = list_box_clone.observe_children().n_items() as usize;
+
                 eprintln!("📜 Loading more items from offset {}...", offset);
 
                 // Carregar mais 20 itens
@@ -294,40 +366,23 @@ pub fn setup_infinite_scroll(
                                 list_box_clone.observe_children().n_items() as usize;
 
                             for (i, entry) in entries.iter().enumerate() {
-                                let row = adw::ActionRow::new();
-                                row.set_activatable(true); // 🔥 Tornar a linha clicável
-
-                                match entry.content_type {
-                                    clippit_ipc::ContentType::Text => {
-                                        create_text_row(&row, entry);
-                                    }
-                                    clippit_ipc::ContentType::Image => {
-                                        create_image_row(&row, entry);
-                                    }
-                                }
-
-                                row.set_subtitle(
-                                    &entry.timestamp.format("%d/%m/%Y %H:%M:%S").to_string(),
+                                let row = create_history_row(
+                                    entry,
+                                    &list_box_clone,
+                                    &window_clone,
+                                    &app_clone,
                                 );
 
                                 let index = (current_count + i) as i32;
                                 entry_map_clone.borrow_mut().insert(index, entry.id);
-
-                                let title_text = row.title().to_string();
-                                let subtitle_text =
-                                    row.subtitle().map(|s| s.to_string()).unwrap_or_default();
-                                let search_content = format!("{} {}", title_text, subtitle_text);
-                                search_map_clone.borrow_mut().insert(index, search_content);
-
-                                add_delete_button(&row, entry.id, &list_box_clone);
-                                add_copy_button(&row, entry.id, &window_clone, &app_clone);
+                                search_map_clone
+                                    .borrow_mut()
+                                    .insert(index, row_search_content(&row));
 
                                 list_box_clone.append(&row);
                             }
 
-                            let mut state = load_state_clone.borrow_mut();
-                            state.items_loaded += entries.len();
-                            state.is_loading = false;
+                            load_state_clone.borrow_mut().is_loading = false;
                         }
                     }
                     Err(e) => {
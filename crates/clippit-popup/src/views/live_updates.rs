@@ -0,0 +1,98 @@
+use clippit_ipc::{EventTopic, HistoryEntry, IpcEvent};
+use gtk::prelude::*;
+use libadwaita as adw;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::controllers::listen_daemon_events;
+use crate::views::list_item::{create_history_row, rebuild_row_maps, row_entry_id};
+
+/// Keeps the history list in sync with the daemon while the popup is open
+///
+/// New entries show up at the top (only while the list shows the history, not
+/// search results), changed entries are rebuilt in place and deleted ones go
+/// away. The selection stays on the row it was on.
+pub fn setup_live_updates(
+    list_box: &gtk::ListBox,
+    search_entry: &gtk::SearchEntry,
+    window: &adw::ApplicationWindow,
+    app: &gtk::Application,
+    entry_map: &Rc<RefCell<HashMap<i32, i64>>>,
+    search_map: &Rc<RefCell<HashMap<i32, String>>>,
+) {
+    let list_box = list_box.clone();
+    let search_entry = search_entry.clone();
+    let window = window.clone();
+    let app = app.clone();
+    let entry_map = entry_map.clone();
+    let search_map = search_map.clone();
+
+    listen_daemon_events(vec![EventTopic::History], move |event| {
+        match event {
+            IpcEvent::EntryAdded { entry } => {
+                if !search_entry.text().is_empty() || find_row(&list_box, entry.id).is_some() {
+                    return;
+                }
+                eprintln!("📥 New entry {} added to the list", entry.id);
+                list_box.prepend(&create_history_row(&entry, &list_box, &window, &app));
+            }
+            IpcEvent::EntryUpdated { entry } => {
+                replace_row(&list_box, &entry, &window, &app);
+            }
+            IpcEvent::EntryDeleted { ids } => {
+                for id in ids {
+                    // The delete button may have removed it already
+                    if let Some(row) = find_row(&list_box, id) {
+                        list_box.remove(&row);
+                    }
+                }
+            }
+            IpcEvent::HistoryCleared => {
+                let mut index = 0;
+                while let Some(row) = list_box.row_at_index(index) {
+                    if row_entry_id(&row).is_some() {
+                        list_box.remove(&row);
+                    } else {
+                        index += 1;
+                    }
+                }
+            }
+            IpcEvent::CaptureStateChanged { .. } => return,
+        }
+        rebuild_row_maps(&list_box, &entry_map, &search_map);
+    });
+}
+
+fn find_row(list_box: &gtk::ListBox, id: i64) -> Option<gtk::ListBoxRow> {
+    let mut index = 0;
+    while let Some(row) = list_box.row_at_index(index) {
+        if row_entry_id(&row) == Some(id) {
+            return Some(row);
+        }
+        index += 1;
+    }
+    None
+}
+
+/// Rebuilds the row of `entry` at the same position (OCR text, tags...)
+fn replace_row(
+    list_box: &gtk::ListBox,
+    entry: &HistoryEntry,
+    window: &adw::ApplicationWindow,
+    app: &gtk::Application,
+) {
+    let Some(old_row) = find_row(list_box, entry.id) else {
+        return;
+    };
+    let was_selected = old_row.is_selected();
+    let index = old_row.index();
+
+    let row = create_history_row(entry, list_box, window, app);
+    list_box.remove(&old_row);
+    list_box.insert(&row, index);
+    if was_selected {
+        list_box.select_row(Some(&row));
+    }
+    eprintln!("🔄 Entry {} updated in the list", entry.id);
+}
@@ -1,13 +1,20 @@
 pub mod autocomplete_popup;
 pub mod buttons;
+pub mod copy_as;
 pub mod floating_autocomplete;
 pub mod image_preview;
 pub mod list_item;
+pub mod live_updates;
+pub mod qr_code;
 pub mod search;
+pub mod snippets;
 pub mod suggestions_popover;
+pub mod tags;
+pub mod text_kind;
 pub mod window;
 
 pub use list_item::{populate_history_list, setup_infinite_scroll};
+pub use live_updates::setup_live_updates;
 pub use search::setup_search_filter;
 pub use suggestions_popover::SuggestionsPopover;
 pub use window::create_main_window;
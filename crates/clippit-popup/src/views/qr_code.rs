@@ -0,0 +1,137 @@
+use adw::prelude::*;
+use clippit_core::qr::MAX_QR_TEXT_BYTES;
+use clippit_core::{QrEcLevel, QrImage};
+use gtk::{gdk, glib};
+use libadwaita as adw;
+use rust_i18n::t;
+
+/// Approximate size of the code on screen, in pixels
+const QR_DISPLAY_SIZE: usize = 360;
+
+/// Adds a button that shows a text entry as a QR code (to scan with a phone)
+pub fn add_qr_code_button(
+    row: &adw::ActionRow,
+    entry: &clippit_ipc::HistoryEntry,
+    window: &adw::ApplicationWindow,
+    app: &gtk::Application,
+) {
+    let Some(text) = entry.content_text.clone().filter(|t| !t.trim().is_empty()) else {
+        return;
+    };
+
+    let button = gtk::Button::from_icon_name("phone-symbolic");
+    button.set_valign(gtk::Align::Center);
+    button.add_css_class("flat");
+    button.add_css_class("circular");
+    button.set_tooltip_text(Some(&t!("popup.qr_code.tooltip")));
+
+    let window = window.clone();
+    let app = app.clone();
+    button.connect_clicked(move |_| {
+        eprintln!("🔳 Showing QR code ({} bytes)", text.len());
+        show_qr_window(&app, &text);
+
+        // The popup would auto-close behind the new window anyway
+        window.close();
+    });
+
+    row.add_suffix(&button);
+}
+
+/// Window with the QR code and an error correction level selector
+fn show_qr_window(app: &gtk::Application, text: &str) {
+    let picture = gtk::Picture::new();
+    picture.set_size_request(QR_DISPLAY_SIZE as i32, QR_DISPLAY_SIZE as i32);
+    picture.set_can_shrink(false);
+
+    let info = gtk::Label::new(None);
+    info.set_wrap(true);
+    info.set_justify(gtk::Justification::Center);
+
+    let levels: Vec<String> = QrEcLevel::ALL
+        .iter()
+        .map(|level| {
+            t!(
+                "popup.qr_code.level",
+                level = format!("{:?}", level),
+                percent = level.recovery_percent()
+            )
+            .to_string()
+        })
+        .collect();
+    let level_names: Vec<&str> = levels.iter().map(String::as_str).collect();
+    let level_dropdown = gtk::DropDown::from_strings(&level_names);
+    level_dropdown.set_tooltip_text(Some(&t!("popup.qr_code.level_hint")));
+    let default_index = QrEcLevel::ALL
+        .iter()
+        .position(|level| *level == QrEcLevel::default())
+        .unwrap_or(0);
+    level_dropdown.set_selected(default_index as u32);
+
+    let content = gtk::Box::new(gtk::Orientation::Vertical, 12);
+    content.set_margin_top(12);
+    content.set_margin_bottom(18);
+    content.set_margin_start(18);
+    content.set_margin_end(18);
+    content.append(&picture);
+    content.append(&info);
+    content.append(&level_dropdown);
+
+    let toolbar = gtk::Box::new(gtk::Orientation::Vertical, 0);
+    toolbar.append(&adw::HeaderBar::new());
+    toolbar.append(&content);
+
+    let qr_window = adw::ApplicationWindow::builder()
+        .application(app)
+        .title(t!("popup.qr_code.title"))
+        .resizable(false)
+        .content(&toolbar)
+        .build();
+
+    let render = {
+        let text = text.to_string();
+        let picture = picture.clone();
+        let info = info.clone();
+        move |level: QrEcLevel| match render_qr(&text, level) {
+            Ok((texture, modules)) => {
+                picture.set_paintable(Some(&texture));
+                info.set_text(&t!(
+                    "popup.qr_code.info",
+                    chars = text.chars().count(),
+                    modules = modules
+                ));
+            }
+            Err(e) => {
+                eprintln!("❌ Failed to render QR code: {}", e);
+                picture.set_paintable(None::<&gdk::Paintable>);
+                info.set_text(&if text.len() > MAX_QR_TEXT_BYTES {
+                    t!(
+                        "popup.qr_code.too_long",
+                        bytes = text.len(),
+                        max = MAX_QR_TEXT_BYTES
+                    )
+                } else {
+                    t!("popup.qr_code.error", message = e.to_string())
+                });
+            }
+        }
+    };
+
+    render(QrEcLevel::default());
+    level_dropdown.connect_selected_notify(move |dropdown| {
+        if let Some(level) = QrEcLevel::ALL.get(dropdown.selected() as usize) {
+            render(*level);
+        }
+    });
+
+    qr_window.present();
+}
+
+/// PNG scaled to roughly `QR_DISPLAY_SIZE`, with whole pixels per module
+fn render_qr(text: &str, level: QrEcLevel) -> anyhow::Result<(gdk::Texture, usize)> {
+    let qr = QrImage::generate(text, level)?;
+    let scale = (QR_DISPLAY_SIZE / (qr.width() + 8)).max(2) as u32;
+    let png = qr.to_png(scale)?;
+    let texture = gdk::Texture::from_bytes(&glib::Bytes::from(&png))?;
+    Ok((texture, qr.width()))
+}
@@ -6,7 +6,12 @@ use std::rc::Rc;
 
 use crate::models::SearchContentMap;
 use crate::utils::{create_thumbnail, SuggestionEngine};
-use crate::views::buttons::{add_copy_button, add_delete_button};
+use crate::views::buttons::{add_copy_button, add_decoded_value_button, add_delete_button};
+use crate::views::copy_as::add_copy_as_button;
+use crate::views::list_item::entry_row_name;
+use crate::views::qr_code::add_qr_code_button;
+use crate::views::tags::add_tag_editor;
+use crate::views::text_kind::add_text_kind_prefix;
 use crate::views::SuggestionsPopover;
 use clippit_core::Config;
 use clippit_ipc::IpcClient;
@@ -123,6 +128,7 @@ pub fn setup_search_filter(
                         );
                         let row = adw::ActionRow::new();
                         row.set_activatable(true); // 🔥 Tornar a linha clicável
+                        row.set_widget_name(&entry_row_name(hist_entry.id));
 
                         // Format based on type
                         match hist_entry.content_type {
@@ -147,6 +153,7 @@ pub fn setup_search_filter(
 
                                 let escaped_content = gtk::glib::markup_escape_text(&content);
                                 row.set_title(&escaped_content);
+                                add_text_kind_prefix(&row, hist_entry);
                             }
                             clippit_ipc::ContentType::Image => {
                                 let thumbnail_source = hist_entry
@@ -210,7 +217,11 @@ pub fn setup_search_filter(
                             .insert(index as i32, format!("{} {}", title_text, subtitle_text));
 
                         // Add buttons
+                        add_tag_editor(&row, hist_entry);
                         add_delete_button(&row, hist_entry.id, &list_box_clone);
+                        add_decoded_value_button(&row, hist_entry, &window_clone, &app_clone);
+                        add_qr_code_button(&row, hist_entry, &window_clone, &app_clone);
+                        add_copy_as_button(&row, hist_entry, &window_clone, &app_clone);
                         add_copy_button(&row, hist_entry.id, &window_clone, &app_clone);
 
                         list_box_clone.append(&row);
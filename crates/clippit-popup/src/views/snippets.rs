@@ -0,0 +1,197 @@
+use adw::prelude::*;
+use clippit_core::{Config, ExpandedSnippet};
+use clippit_ipc::{IpcClient, SnippetInfo};
+use libadwaita as adw;
+use rust_i18n::t;
+use std::collections::HashMap;
+use std::process::Command;
+use std::rc::Rc;
+
+use crate::controllers::copy_text_to_clipboard;
+use crate::utils::error_message;
+
+/// Characters of the body shown under the title
+const PREVIEW_CHARS: usize = 64;
+
+/// Builds the "Snippets" tab: a list of snippets filtered by the search entry
+///
+/// Choosing a snippet expands its placeholders in the daemon, copies the result
+/// and, with `[snippets] paste_on_select`, pastes it into the focused app.
+pub fn create_snippets_page(
+    app: &gtk::Application,
+    search_entry: &gtk::SearchEntry,
+) -> gtk::Widget {
+    let list_box = gtk::ListBox::new();
+    list_box.add_css_class("boxed-list");
+    list_box.set_selection_mode(gtk::SelectionMode::Single);
+    list_box.set_activate_on_single_click(true);
+
+    let snippets = match IpcClient::list_snippets() {
+        Ok(snippets) => snippets,
+        Err(e) => {
+            eprintln!("❌ Failed to list snippets: {}", e);
+            Vec::new()
+        }
+    };
+
+    let placeholder = adw::StatusPage::new();
+    placeholder.set_icon_name(Some("accessories-text-editor-symbolic"));
+    placeholder.set_title(&t!("popup.snippets.empty_title"));
+    placeholder.set_description(Some(&t!("popup.snippets.empty_description")));
+    list_box.set_placeholder(Some(&placeholder));
+
+    // Row index -> snippet shown in it
+    let mut snippet_map = HashMap::new();
+    for snippet in snippets {
+        let row = snippet_row(&snippet);
+        list_box.append(&row);
+        snippet_map.insert(row.index(), snippet);
+    }
+    let snippet_map = Rc::new(snippet_map);
+
+    let search_entry_for_filter = search_entry.clone();
+    let snippet_map_for_filter = Rc::clone(&snippet_map);
+    list_box.set_filter_func(move |row| {
+        let query = search_entry_for_filter.text().to_lowercase();
+        let query = query.trim();
+        query.is_empty()
+            || snippet_map_for_filter
+                .get(&row.index())
+                .is_some_and(|snippet| matches_query(snippet, query))
+    });
+    let list_box_for_search = list_box.clone();
+    search_entry.connect_search_changed(move |_| list_box_for_search.invalidate_filter());
+
+    let app = app.clone();
+    list_box.connect_row_activated(move |list_box, row| {
+        let Some(id) = snippet_map.get(&row.index()).map(|snippet| snippet.id) else {
+            return;
+        };
+        let expanded = match IpcClient::expand_snippet(id) {
+            Ok((text, cursor)) => ExpandedSnippet { text, cursor },
+            Err(e) => {
+                eprintln!("❌ Failed to expand snippet {}: {}", id, e);
+                show_row_error(row, &e);
+                return;
+            }
+        };
+        eprintln!("📝 Snippet {} expanded ({} chars)", id, expanded.text.len());
+
+        if !copy_text_to_clipboard(&expanded.text) {
+            return;
+        }
+        if Config::load().unwrap_or_default().snippets.paste_on_select {
+            // The text is already copied; stay open so the failure is seen
+            if let Err(e) = paste_after_close(expanded.chars_after_cursor()) {
+                eprintln!("❌ Failed to paste snippet: {}", e);
+                show_row_error(row, &e.into());
+                return;
+            }
+        }
+        if let Some(window) = list_box.root().and_downcast::<gtk::Window>() {
+            window.close();
+        }
+        app.quit();
+    });
+
+    let scrolled = gtk::ScrolledWindow::new();
+    scrolled.set_child(Some(&list_box));
+    scrolled.set_vexpand(true);
+    scrolled.set_margin_start(12);
+    scrolled.set_margin_end(12);
+    scrolled.set_margin_bottom(12);
+    scrolled.upcast()
+}
+
+fn snippet_row(snippet: &SnippetInfo) -> adw::ActionRow {
+    let row = adw::ActionRow::new();
+    row.set_title(&gtk::glib::markup_escape_text(&snippet.title));
+    row.set_subtitle(&gtk::glib::markup_escape_text(&preview_line(&snippet.body)));
+    row.set_activatable(true);
+
+    if let Some(keyword) = &snippet.keyword {
+        let label = gtk::Label::new(Some(keyword));
+        label.add_css_class("caption");
+        label.add_css_class("dim-label");
+        row.add_suffix(&label);
+    }
+    row
+}
+
+/// Whether the title, keyword or body contains `query` (already lowercase)
+fn matches_query(snippet: &SnippetInfo, query: &str) -> bool {
+    [
+        snippet.title.as_str(),
+        snippet.keyword.as_deref().unwrap_or_default(),
+        snippet.body.as_str(),
+    ]
+    .iter()
+    .any(|field| field.to_lowercase().contains(query))
+}
+
+/// Replaces the row's preview with the error, worded for the user
+fn show_row_error(row: &gtk::ListBoxRow, error: &anyhow::Error) {
+    if let Some(row) = row.downcast_ref::<adw::ActionRow>() {
+        row.set_subtitle(&gtk::glib::markup_escape_text(&error_message(error)));
+    }
+}
+
+/// Body flattened to one line and cut to `PREVIEW_CHARS`
+fn preview_line(body: &str) -> String {
+    let flat = body.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flat.chars().count() > PREVIEW_CHARS {
+        format!("{}…", flat.chars().take(PREVIEW_CHARS).collect::<String>())
+    } else {
+        flat
+    }
+}
+
+/// Sends Ctrl+V to the app that gets the focus back once the popup is gone,
+/// then moves the caret to `{cursor}`
+///
+/// Runs detached because the popup quits right after this.
+fn paste_after_close(left_presses: usize) -> std::io::Result<()> {
+    let wayland = std::env::var("WAYLAND_DISPLAY").is_ok();
+    let mut script = String::from("sleep 0.15; ");
+    if wayland {
+        script.push_str("wtype -M ctrl -k v -m ctrl");
+        for _ in 0..left_presses {
+            script.push_str(" -k Left");
+        }
+    } else {
+        script.push_str("xdotool key --clearmodifiers ctrl+v");
+        if left_presses > 0 {
+            script.push_str(&format!("; xdotool key --repeat {} Left", left_presses));
+        }
+    }
+
+    Command::new("sh").arg("-c").arg(&script).spawn()?;
+    eprintln!("⌨️ Pasting snippet ({} chars after cursor)", left_presses);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_line() {
+        assert_eq!(preview_line("Linha 1\n\n  Linha 2"), "Linha 1 Linha 2");
+        assert!(preview_line(&"x".repeat(100)).ends_with('…'));
+    }
+
+    #[test]
+    fn test_matches_query() {
+        let snippet = SnippetInfo {
+            id: 7,
+            title: "Assinatura".to_string(),
+            body: "Att,\nEquipe Clippit".to_string(),
+            keyword: Some(";sig".to_string()),
+            updated_at: Default::default(),
+        };
+        assert!(matches_query(&snippet, "assin"));
+        assert!(matches_query(&snippet, ";sig"));
+        assert!(matches_query(&snippet, "equipe"));
+        assert!(!matches_query(&snippet, "7"));
+    }
+}
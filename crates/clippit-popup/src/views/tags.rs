@@ -0,0 +1,250 @@
+use adw::prelude::*;
+use clippit_ipc::{HistoryEntry, IpcClient};
+use libadwaita as adw;
+use rust_i18n::t;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use crate::utils::error_message;
+
+/// Chips shown in a row; the rest becomes "+N"
+const MAX_CHIPS: usize = 3;
+
+/// Adds the tag chips of an entry and the tag editor menu
+///
+/// Clicking a chip filters the list by that tag (`win.filter-tag`).
+pub fn add_tag_editor(row: &adw::ActionRow, entry: &HistoryEntry) {
+    let tags = Rc::new(RefCell::new(entry.tags.clone()));
+
+    let chips = gtk::Box::new(gtk::Orientation::Horizontal, 4);
+    chips.set_valign(gtk::Align::Center);
+    refresh_chips(&chips, &tags.borrow());
+    row.add_suffix(&chips);
+
+    let popover = gtk::Popover::new();
+    let entry_id = entry.id;
+    popover.connect_show(move |popover| {
+        // Rebuilt on every open: tags may have been created from another row
+        popover.set_child(Some(&editor(entry_id, &tags, &chips)));
+    });
+
+    let button = gtk::MenuButton::new();
+    button.set_icon_name("bookmark-new-symbolic");
+    button.set_valign(gtk::Align::Center);
+    button.add_css_class("flat");
+    button.add_css_class("circular");
+    button.set_tooltip_text(Some(&t!("popup.tags.tooltip")));
+    button.set_popover(Some(&popover));
+    row.add_suffix(&button);
+}
+
+/// Search text that filters by `tag` (quoted when it has spaces or quotes;
+/// `"` and `\` are escaped inside the quotes)
+pub fn tag_query(tag: &str) -> String {
+    if tag.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+        let escaped = tag.replace('\\', "\\\\").replace('"', "\\\"");
+        format!("tag:\"{}\"", escaped)
+    } else {
+        format!("tag:{}", tag)
+    }
+}
+
+fn refresh_chips(chips: &gtk::Box, tags: &[String]) {
+    while let Some(child) = chips.first_child() {
+        chips.remove(&child);
+    }
+
+    for tag in tags.iter().take(MAX_CHIPS) {
+        let chip = gtk::Button::with_label(tag);
+        chip.add_css_class("flat");
+        chip.add_css_class("caption");
+        chip.add_css_class("tag-chip");
+        chip.set_tooltip_text(Some(&t!("popup.tags.filter_by", tag = tag)));
+
+        let tag = tag.clone();
+        chip.connect_clicked(move |chip| {
+            if let Err(e) = chip.activate_action("win.filter-tag", Some(&tag.to_variant())) {
+                eprintln!("❌ Failed to filter by tag '{}': {}", tag, e);
+            }
+        });
+        chips.append(&chip);
+    }
+
+    if tags.len() > MAX_CHIPS {
+        let more = gtk::Label::new(Some(&format!("+{}", tags.len() - MAX_CHIPS)));
+        more.add_css_class("caption");
+        more.add_css_class("dim-label");
+        more.set_tooltip_text(Some(&tags[MAX_CHIPS..].join(", ")));
+        chips.append(&more);
+    }
+}
+
+/// Checkboxes for every known tag plus a field to create a new one
+fn editor(entry_id: i64, tags: &Rc<RefCell<Vec<String>>>, chips: &gtk::Box) -> gtk::Box {
+    let content = gtk::Box::new(gtk::Orientation::Vertical, 6);
+    content.set_margin_top(6);
+    content.set_margin_bottom(6);
+    content.set_margin_start(6);
+    content.set_margin_end(6);
+
+    // Failures are shown under the field until the next change succeeds
+    let status = gtk::Label::new(None);
+    status.add_css_class("caption");
+    status.add_css_class("error");
+    status.set_wrap(true);
+    status.set_max_width_chars(32);
+    status.set_xalign(0.0);
+    status.set_visible(false);
+
+    let list = gtk::Box::new(gtk::Orientation::Vertical, 2);
+    match IpcClient::list_tags() {
+        Ok(known) => {
+            for tag in known {
+                list.append(&tag_check(entry_id, &tag.name, tags, chips, &status));
+            }
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to list tags: {}", e);
+            show_error(&status, &e);
+        }
+    }
+
+    let scrolled = gtk::ScrolledWindow::new();
+    scrolled.set_child(Some(&list));
+    scrolled.set_propagate_natural_height(true);
+    scrolled.set_max_content_height(240);
+    scrolled.set_hscrollbar_policy(gtk::PolicyType::Never);
+    content.append(&scrolled);
+
+    let new_tag = gtk::Entry::new();
+    new_tag.set_placeholder_text(Some(&t!("popup.tags.new_tag")));
+    let tags = tags.clone();
+    let chips = chips.clone();
+    let status_for_field = status.clone();
+    new_tag.connect_activate(move |field| {
+        let name = field
+            .text()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        field.set_text("");
+        if name.is_empty() {
+            return;
+        }
+
+        // Existing tag: checking its box adds it
+        let mut child = list.first_child();
+        while let Some(widget) = child {
+            if let Some(check) = widget.downcast_ref::<gtk::CheckButton>() {
+                if check
+                    .label()
+                    .is_some_and(|label| label.eq_ignore_ascii_case(&name))
+                {
+                    check.set_active(true);
+                    return;
+                }
+            }
+            child = widget.next_sibling();
+        }
+
+        match IpcClient::add_entry_tag(entry_id, &name) {
+            Ok(()) => {
+                eprintln!("🏷️ Entry {} tagged '{}'", entry_id, name);
+                status_for_field.set_visible(false);
+                push_tag(&tags, &name);
+                refresh_chips(&chips, &tags.borrow());
+                list.append(&tag_check(
+                    entry_id,
+                    &name,
+                    &tags,
+                    &chips,
+                    &status_for_field,
+                ));
+            }
+            Err(e) => {
+                eprintln!("❌ Failed to tag entry {}: {}", entry_id, e);
+                show_error(&status_for_field, &e);
+            }
+        }
+    });
+    content.append(&new_tag);
+    content.append(&status);
+
+    content
+}
+
+fn tag_check(
+    entry_id: i64,
+    name: &str,
+    tags: &Rc<RefCell<Vec<String>>>,
+    chips: &gtk::Box,
+    status: &gtk::Label,
+) -> gtk::CheckButton {
+    let check = gtk::CheckButton::with_label(name);
+    check.set_active(
+        tags.borrow()
+            .iter()
+            .any(|tag| tag.eq_ignore_ascii_case(name)),
+    );
+
+    let name = name.to_string();
+    let tags = tags.clone();
+    let chips = chips.clone();
+    let status = status.clone();
+    // Set while a failed change is undone, so the undo isn't sent too
+    let reverting = Rc::new(Cell::new(false));
+    check.connect_toggled(move |check| {
+        if reverting.get() {
+            return;
+        }
+        let result = if check.is_active() {
+            IpcClient::add_entry_tag(entry_id, &name)
+        } else {
+            IpcClient::remove_entry_tag(entry_id, &name)
+        };
+        if let Err(e) = result {
+            eprintln!("❌ Failed to update tags of entry {}: {}", entry_id, e);
+            show_error(&status, &e);
+            reverting.set(true);
+            check.set_active(!check.is_active());
+            reverting.set(false);
+            return;
+        }
+        status.set_visible(false);
+
+        if check.is_active() {
+            push_tag(&tags, &name);
+        } else {
+            tags.borrow_mut()
+                .retain(|tag| !tag.eq_ignore_ascii_case(&name));
+        }
+        refresh_chips(&chips, &tags.borrow());
+    });
+    check
+}
+
+fn show_error(status: &gtk::Label, error: &anyhow::Error) {
+    status.set_text(&error_message(error));
+    status.set_visible(true);
+}
+
+fn push_tag(tags: &Rc<RefCell<Vec<String>>>, name: &str) {
+    let mut tags = tags.borrow_mut();
+    if !tags.iter().any(|tag| tag.eq_ignore_ascii_case(name)) {
+        tags.push(name.to_string());
+        tags.sort_by_key(|tag| tag.to_lowercase());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_query() {
+        assert_eq!(tag_query("sql"), "tag:sql");
+        assert_eq!(tag_query("Cliente X"), "tag:\"Cliente X\"");
+        assert_eq!(tag_query(r#"Ana "B" \ C"#), r#"tag:"Ana \"B\" \\ C""#);
+        assert_eq!(tag_query(r#"a"b"#), r#"tag:"a\"b""#);
+    }
+}
@@ -0,0 +1,154 @@
+use adw::prelude::*;
+use clippit_ipc::{HistoryEntry, TextKind};
+use gtk::gdk;
+use libadwaita as adw;
+use rust_i18n::t;
+use std::borrow::Cow;
+
+/// Size of the color swatch shown for color entries
+const SWATCH_SIZE: i32 = 24;
+
+/// Adds the kind icon (or a color swatch) and, for links, a chip with the host
+pub fn add_text_kind_prefix(row: &adw::ActionRow, entry: &HistoryEntry) {
+    let Some(kind) = entry.text_kind else {
+        return;
+    };
+    let text = entry.content_text.as_deref().unwrap_or("").trim();
+
+    let color = (kind == TextKind::Color)
+        .then(|| gdk::RGBA::parse(text).ok())
+        .flatten();
+    match color {
+        Some(color) => row.add_prefix(&color_swatch(color)),
+        None => {
+            let Some(icon_name) = icon_name(kind) else {
+                return;
+            };
+            let icon = gtk::Image::from_icon_name(icon_name);
+            icon.set_tooltip_text(Some(&kind_label(kind, entry.code_language.as_deref())));
+            icon.add_css_class("dim-label");
+            row.add_prefix(&icon);
+        }
+    }
+
+    if kind == TextKind::Url {
+        if let Some(host) = url_host(text) {
+            row.add_prefix(&link_chip(host));
+        }
+    }
+}
+
+/// Plain text keeps the row as it was
+fn icon_name(kind: TextKind) -> Option<&'static str> {
+    match kind {
+        TextKind::Plain => None,
+        TextKind::Url => Some("web-browser-symbolic"),
+        TextKind::Email => Some("mail-unread-symbolic"),
+        TextKind::Phone => Some("call-start-symbolic"),
+        TextKind::Path => Some("folder-symbolic"),
+        TextKind::Color => Some("color-select-symbolic"),
+        TextKind::Json => Some("text-x-generic-symbolic"),
+        TextKind::Code => Some("utilities-terminal-symbolic"),
+        TextKind::Number => Some("accessories-calculator-symbolic"),
+    }
+}
+
+fn kind_label(kind: TextKind, code_language: Option<&str>) -> Cow<'static, str> {
+    match (kind, code_language) {
+        (TextKind::Code, Some(language)) => {
+            t!("popup.text_kind.code_language", language = language)
+        }
+        (TextKind::Code, None) => t!("popup.text_kind.code"),
+        (TextKind::Plain, _) => t!("popup.text_kind.plain"),
+        (TextKind::Url, _) => t!("popup.text_kind.url"),
+        (TextKind::Email, _) => t!("popup.text_kind.email"),
+        (TextKind::Phone, _) => t!("popup.text_kind.phone"),
+        (TextKind::Path, _) => t!("popup.text_kind.path"),
+        (TextKind::Color, _) => t!("popup.text_kind.color"),
+        (TextKind::Json, _) => t!("popup.text_kind.json"),
+        (TextKind::Number, _) => t!("popup.text_kind.number"),
+    }
+}
+
+/// Rounded square filled with the copied color
+fn color_swatch(color: gdk::RGBA) -> gtk::DrawingArea {
+    let swatch = gtk::DrawingArea::new();
+    swatch.set_content_width(SWATCH_SIZE);
+    swatch.set_content_height(SWATCH_SIZE);
+    swatch.set_valign(gtk::Align::Center);
+    swatch.set_tooltip_text(Some(&t!("popup.text_kind.color")));
+
+    swatch.set_draw_func(move |_, cr, width, height| {
+        let (w, h) = (width as f64, height as f64);
+        let radius = 4.0;
+        cr.new_sub_path();
+        cr.arc(w - radius, radius, radius, -90f64.to_radians(), 0.0);
+        cr.arc(w - radius, h - radius, radius, 0.0, 90f64.to_radians());
+        cr.arc(
+            radius,
+            h - radius,
+            radius,
+            90f64.to_radians(),
+            180f64.to_radians(),
+        );
+        cr.arc(
+            radius,
+            radius,
+            radius,
+            180f64.to_radians(),
+            270f64.to_radians(),
+        );
+        cr.close_path();
+
+        cr.set_source_rgba(
+            color.red() as f64,
+            color.green() as f64,
+            color.blue() as f64,
+            color.alpha() as f64,
+        );
+        let _ = cr.fill_preserve();
+
+        // Border keeps white/transparent colors visible
+        cr.set_source_rgba(0.5, 0.5, 0.5, 0.6);
+        cr.set_line_width(1.0);
+        let _ = cr.stroke();
+    });
+
+    swatch
+}
+
+/// Small pill with the link host (no favicon: nothing is fetched)
+fn link_chip(host: &str) -> gtk::Label {
+    let chip = gtk::Label::new(Some(host));
+    chip.set_valign(gtk::Align::Center);
+    chip.set_ellipsize(gtk::pango::EllipsizeMode::End);
+    chip.set_max_width_chars(24);
+    chip.add_css_class("caption");
+    chip.add_css_class("accent");
+    chip.add_css_class("link-chip");
+    chip
+}
+
+/// Host of a URL, without scheme, credentials, port or path
+fn url_host(url: &str) -> Option<&str> {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = host.split(':').next()?;
+    (!host.is_empty()).then_some(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_host() {
+        assert_eq!(
+            url_host("https://user:pw@docs.rs:443/clippit?q=1"),
+            Some("docs.rs")
+        );
+        assert_eq!(url_host("www.example.com/path"), Some("www.example.com"));
+        assert_eq!(url_host("https://"), None);
+    }
+}
@@ -1,3 +1,5 @@
+use clippit_ipc::protocol::{capability, PROTOCOL_VERSION};
+use clippit_ipc::{EventTopic, IpcClient, IpcEvent};
 use gtk::prelude::*;
 use gtk::{ScrolledWindow, SearchEntry};
 use libadwaita as adw;
@@ -5,6 +7,10 @@ use rust_i18n::t;
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use crate::controllers::listen_daemon_events;
+use crate::views::snippets::create_snippets_page;
+use crate::views::tags::tag_query;
+
 /// Creates the main popup window with list and search
 ///
 /// Returns: (window, list_box, scrolled, search_entry)
@@ -49,8 +55,24 @@ pub fn create_main_window(
     header_box.set_margin_bottom(12); // ✅ Padding igual ao topo
     header_box.append(&search_entry);
 
+    // Abas: histórico e snippets (a busca filtra a aba visível)
+    let stack = gtk::Stack::new();
+    stack.set_transition_type(gtk::StackTransitionType::Crossfade);
+    stack.add_titled(&scrolled, Some("history"), "Histórico");
+    if daemon_supports(capability::SNIPPETS) {
+        stack.add_titled(&create_snippets_page(app, &search_entry), Some("snippets"), "Snippets");
+    }
+
+    let switcher = gtk::StackSwitcher::new();
+    switcher.set_stack(Some(&stack));
+    header_box.append(&switcher);
+
+    if daemon_supports(capability::EVENTS) {
+        header_box.append(&create_capture_toggle());
+    }
+
     main_box.append(&header_box);
-    main_box.append(&scrolled);
+    main_box.append(&stack);
 
     // Create main window (no toast overlay - using system notifications)
     let window = adw::ApplicationWindow::builder()
@@ -61,6 +83,18 @@ pub fn create_main_window(
         .content(&main_box)
         .build();
 
+    // Tag chips filter the list (see views::tags)
+    let filter_tag =
+        gtk::gio::SimpleAction::new("filter-tag", Some(gtk::glib::VariantTy::STRING));
+    let search_entry_for_tag = search_entry.clone();
+    filter_tag.connect_activate(move |_, parameter| {
+        if let Some(tag) = parameter.and_then(|parameter| parameter.get::<String>()) {
+            search_entry_for_tag.set_text(&tag_query(&tag));
+            search_entry_for_tag.set_position(-1);
+        }
+    });
+    window.add_action(&filter_tag);
+
     // Auto-close on focus loss with intelligent delay (retorna timeout_id para passar ao search_filter)
     let close_timeout_id = setup_auto_close(&window, &search_entry);
 
@@ -69,6 +103,64 @@ pub fn create_main_window(
     (window, list_box, scrolled, search_entry, close_timeout_id)
 }
 
+/// Handshake with the daemon; an older daemon hides what it can't serve
+fn daemon_supports(capability: &str) -> bool {
+    match IpcClient::hello(concat!("clippit-popup ", env!("CARGO_PKG_VERSION"))) {
+        Ok(negotiated) => {
+            if negotiated.protocol_version < PROTOCOL_VERSION {
+                eprintln!(
+                    "⚠️ {} speaks protocol {} (popup: {}) - update clippit-daemon",
+                    negotiated.peer, negotiated.protocol_version, PROTOCOL_VERSION
+                );
+            }
+            negotiated.supports(capability)
+        }
+        Err(e) => {
+            eprintln!("❌ Handshake with daemon failed: {}", e);
+            true
+        }
+    }
+}
+
+/// Pauses/resumes clipboard capture in the daemon; follows changes made
+/// elsewhere (another popup, the tray) through the event stream
+fn create_capture_toggle() -> gtk::ToggleButton {
+    let toggle = gtk::ToggleButton::new();
+    toggle.set_icon_name("media-playback-pause-symbolic");
+    toggle.set_valign(gtk::Align::Center);
+    toggle.add_css_class("flat");
+    toggle.set_active(IpcClient::get_capture_state().unwrap_or(false));
+    update_capture_tooltip(&toggle);
+
+    toggle.connect_toggled(|toggle| {
+        let paused = toggle.is_active();
+        update_capture_tooltip(toggle);
+        match IpcClient::set_capture_paused(paused) {
+            Ok(()) if paused => eprintln!("⏸️ Captura pausada"),
+            Ok(()) => eprintln!("▶️ Captura retomada"),
+            Err(e) => eprintln!("❌ Falha ao alterar captura: {}", e),
+        }
+    });
+
+    let toggle_for_events = toggle.clone();
+    listen_daemon_events(vec![EventTopic::Capture], move |event| {
+        if let IpcEvent::CaptureStateChanged { paused } = event {
+            // Same state: set_active doesn't emit `toggled`, so no loop
+            toggle_for_events.set_active(paused);
+        }
+    });
+
+    toggle
+}
+
+fn update_capture_tooltip(toggle: &gtk::ToggleButton) {
+    toggle.set_tooltip_text(Some(if toggle.is_active() {
+        "Captura pausada - clique para retomar"
+    } else {
+        "Pausar captura do clipboard"
+    }));
+}
+
 fn setup_auto_close(window: &adw::ApplicationWindow, search_entry: &SearchEntry) -> Rc<RefCell<Option<gtk::glib::SourceId>>> {
     let window_for_focus = window.clone();
     let search_entry_for_focus = search_entry.clone();
@@ -86,7 +86,7 @@ echo ""
 
 # Build release
 echo "📦 Building Clippit in release mode..."
-cargo build --release
+cargo build --release --features clippit-daemon/tesseract
 
 if [ $? -ne 0 ]; then
     echo "❌ Build failed!"
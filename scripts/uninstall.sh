@@ -71,9 +71,13 @@ else
 fi
 
 # Remove socket if exists
-if [ -S /tmp/clippit.sock ]; then
+SOCKET_PATH="$XDG_RUNTIME_DIR/clippit/clippit.sock"
+if [ -z "$XDG_RUNTIME_DIR" ]; then
+    SOCKET_PATH="/tmp/clippit-$(id -u)/clippit.sock"
+fi
+if [ -S "$SOCKET_PATH" ]; then
     echo "🗑️  Removing IPC socket..."
-    rm /tmp/clippit.sock
+    rm "$SOCKET_PATH"
 fi
 
 echo ""
@@ -97,7 +97,7 @@ echo ""
 
 # Compilar tudo
 echo "🏗️  Compilando em modo release..."
-cargo build --release
+cargo build --release --features clippit-daemon/tesseract
 
 if [ $? -ne 0 ]; then
     echo "❌ Erro na compilação!"